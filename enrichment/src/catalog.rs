@@ -0,0 +1,346 @@
+use crate::score::{self, ApkScorer, Scorer};
+use crate::units::{Apk, Measures, Percent, Sek};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use systemet::Product;
+
+/// Basen charges this much on top of the shelf price...
+pub const BASEN_MARKUP: f64 = 1.25;
+/// ...and rounds up to a multiple of this.
+pub const BASEN_ROUNDING: f64 = 5.0;
+
+/// The categories shown on the page, in display order.
+pub const CATEGORIES: [Category; 5] = [
+    Category::Beer,
+    Category::Wine,
+    Category::Cider,
+    Category::Liquor,
+    Category::Other,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Category {
+    #[serde(rename = "Öl")]
+    Beer,
+    #[serde(rename = "Vin")]
+    Wine,
+    #[serde(rename = "Cider")]
+    Cider,
+    #[serde(rename = "Sprit")]
+    Liquor,
+    #[serde(rename = "Annat")]
+    Other,
+}
+
+impl Category {
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Beer => "Öl",
+            Category::Wine => "Vin",
+            Category::Cider => "Cider",
+            Category::Liquor => "Sprit",
+            Category::Other => "Annat",
+        }
+    }
+
+    /// An ASCII name for machine consumption, like in MQTT topics.
+    pub fn slug(self) -> &'static str {
+        match self {
+            Category::Beer => "beer",
+            Category::Wine => "wine",
+            Category::Cider => "cider",
+            Category::Liquor => "liquor",
+            Category::Other => "other",
+        }
+    }
+
+    /// Looks up a category by its [`Category::slug`].
+    pub fn from_slug(slug: &str) -> Option<Category> {
+        CATEGORIES
+            .iter()
+            .copied()
+            .find(|category| category.slug() == slug)
+    }
+
+    /// Looks up a category by its name, ignoring case.
+    pub fn from_name(name: &str) -> Option<Category> {
+        let name = name.to_lowercase();
+        CATEGORIES
+            .iter()
+            .copied()
+            .find(|category| category.name().to_lowercase() == name)
+    }
+}
+
+/// Which assortments are shown, by Systembolaget's codes, like `FS` for the fixed range.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssortmentRules {
+    /// Only these, if any are given
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl Default for AssortmentRules {
+    /// Excludes local and small-scale products (BS) and order-only products (TSLS).
+    fn default() -> AssortmentRules {
+        AssortmentRules {
+            include: Vec::new(),
+            exclude: vec!["BS".to_string(), "TSLS".to_string()],
+        }
+    }
+}
+
+impl AssortmentRules {
+    pub fn allows(&self, drink: &Product) -> bool {
+        let assortment = drink.assortment.as_deref().unwrap_or("");
+        (self.include.is_empty() || self.include.iter().any(|code| code == assortment))
+            && !self.exclude.iter().any(|code| code == assortment)
+    }
+}
+
+/// Products grouped by category, each group sorted by descending APK.
+#[derive(Default, Serialize)]
+#[serde(transparent)]
+pub struct Catalog {
+    drinks: HashMap<Category, Vec<Product>>,
+    /// Where each product is in `drinks`, by id and product number
+    #[serde(skip)]
+    ids: HashMap<String, (Category, usize)>,
+}
+
+impl Catalog {
+    /// Filters, categorizes and sorts a raw product list by APK.
+    pub fn build(products: Vec<Product>) -> Catalog {
+        Catalog::build_with(products, &ApkScorer)
+    }
+
+    /// Filters, categorizes and sorts a raw product list by `scorer`.
+    pub fn build_with(products: Vec<Product>, scorer: &dyn Scorer) -> Catalog {
+        Catalog::build_with_rules(products, scorer, &AssortmentRules::default())
+    }
+
+    /// Like [`Catalog::build_with`], showing the assortments that `rules` allow.
+    pub fn build_with_rules(
+        products: Vec<Product>,
+        scorer: &dyn Scorer,
+        rules: &AssortmentRules,
+    ) -> Catalog {
+        let mut drinks: HashMap<Category, Vec<Product>> =
+            CATEGORIES.iter().map(|&c| (c, Vec::new())).collect();
+        products
+            .into_iter()
+            .filter(|drink| is_listed_by(rules, drink))
+            .for_each(|drink| drinks.get_mut(&categorize(&drink)).unwrap().push(drink));
+        for category in drinks.values_mut() {
+            category.sort_by(|d1, d2| score::compare(scorer, d1, d2));
+        }
+        // The first product with an id or number wins, like a search in category order would
+        let mut ids = HashMap::new();
+        for &category in &CATEGORIES {
+            for (i, drink) in drinks[&category].iter().enumerate() {
+                ids.entry(id(drink).to_string()).or_insert((category, i));
+                if let Some(number) = number(drink) {
+                    ids.entry(number.to_string()).or_insert((category, i));
+                }
+            }
+        }
+        Catalog { drinks, ids }
+    }
+
+    pub fn get(&self, category: Category) -> &[Product] {
+        self.drinks.get(&category).map_or(&[], Vec::as_slice)
+    }
+
+    /// All products in the catalog, in category order.
+    pub fn products(&self) -> impl Iterator<Item = &Product> {
+        CATEGORIES.iter().flat_map(move |&c| self.get(c).iter())
+    }
+
+    pub fn len(&self) -> usize {
+        self.drinks.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Finds a product by id or product number.
+    pub fn find(&self, id: &str) -> Option<&Product> {
+        let &(category, i) = self.ids.get(id)?;
+        self.get(category).get(i)
+    }
+
+    /// Products whose name contains `query`, ignoring case, best first.
+    pub fn search<'a>(&'a self, query: &str) -> Vec<&'a Product> {
+        let query = query.to_lowercase();
+        let mut matches: Vec<_> = self
+            .products()
+            .filter(|drink| name(drink).to_lowercase().contains(&query))
+            .collect();
+        matches.sort_by(|d1, d2| apk_comparator(d1, d2));
+        matches
+    }
+}
+
+/// Whether a product should be shown at all. Excludes non-alcoholic and free drinks, local and
+/// small-scale products (BS), order-only products (TSLS) and products that are completely out of
+/// stock.
+pub fn is_listed(drink: &Product) -> bool {
+    is_listed_by(&AssortmentRules::default(), drink)
+}
+
+/// Like [`is_listed`], but with the assortments that `rules` allow.
+pub fn is_listed_by(rules: &AssortmentRules, drink: &Product) -> bool {
+    drink.abv() > Percent(0.0)
+        && drink.shelf_price() > Sek(0.0)
+        && rules.allows(drink)
+        && !drink.is_completely_out_of_stock
+}
+
+pub fn categorize(drink: &Product) -> Category {
+    match drink.category.as_deref().unwrap_or("Other") {
+        "Röda viner" | "Vita viner" | "Mousserande viner" | "Roséviner" | "Aperitif & dessert" => {
+            Category::Wine
+        }
+        "Öl" => Category::Beer,
+        "Cider och blanddrycker" => match drink.sub_category.as_deref().unwrap_or("Other") {
+            "Cider" => Category::Cider,
+            _ => Category::Other,
+        },
+        "Sprit" => Category::Liquor,
+        _ => Category::Other,
+    }
+}
+
+pub fn id(drink: &Product) -> &str {
+    &drink.product_id
+}
+
+/// The product number shown on systembolaget.se, if it has one.
+pub fn number(drink: &Product) -> Option<&str> {
+    drink.product_number.as_deref()
+}
+
+pub fn name(drink: &Product) -> &str {
+    &drink.product_name_bold
+}
+
+/// Where the product is from, like `Belgien`.
+pub fn country(drink: &Product) -> Option<&str> {
+    drink
+        .country
+        .as_deref()
+        .filter(|country| !country.is_empty())
+}
+
+/// The day the product is (or was) first sold, as `YYYY-MM-DD`.
+pub fn sell_start(drink: &Product) -> Option<&str> {
+    drink.sell_start_date.get(..10)
+}
+
+/// Whether the product can be bought right now. Products that are completely out of stock aren't
+/// listed at all, but temporarily sold out ones are.
+pub fn in_stock(drink: &Product) -> bool {
+    !drink.is_completely_out_of_stock && !drink.is_temporary_out_of_stock
+}
+
+/// Grams of sugar per liter, where Systembolaget has said.
+pub fn sugar(drink: &Product) -> Option<f64> {
+    drink
+        .sugar_content
+        .filter(|sugar| sugar.is_finite() && *sugar >= 0.0)
+}
+
+pub fn apk(drink: &Product) -> Apk {
+    drink.pure_alcohol() / drink.price_with_deposit()
+}
+
+/// Whether the drink comes in a bag-in-box.
+pub fn is_box(drink: &Product) -> bool {
+    drink.bottle_text_short.to_lowercase().contains("box")
+}
+
+/// The price of 75 cl, a bottle of wine, including deposit.
+pub fn price_per_75cl(drink: &Product) -> Sek {
+    Sek(drink.price_with_deposit().0 / drink.volume().0 * 750.0)
+}
+
+/// The price of a liter, including deposit.
+pub fn price_per_liter(drink: &Product) -> Sek {
+    Sek(drink.price_with_deposit().0 / drink.volume().0 * 1000.0)
+}
+
+/// The average APK of the boxed wines, for comparing bottles against.
+pub fn box_apk(catalog: &Catalog) -> Option<Apk> {
+    let boxes: Vec<Apk> = catalog
+        .get(Category::Wine)
+        .iter()
+        .filter(|drink| is_box(drink))
+        .map(apk)
+        .collect();
+    if boxes.is_empty() {
+        None
+    } else {
+        Some(Apk(
+            boxes.iter().map(|apk| apk.0).sum::<f64>() / boxes.len() as f64
+        ))
+    }
+}
+
+/// What the drink would have cost in Basen.
+pub fn basen_price(drink: &Product) -> Sek {
+    Sek((drink.shelf_price() * BASEN_MARKUP / BASEN_ROUNDING)
+        .0
+        .ceil())
+        * BASEN_ROUNDING
+}
+
+pub fn basen_apk(drink: &Product) -> Apk {
+    drink.pure_alcohol() / basen_price(drink)
+}
+
+/// What the lists can be sorted by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    Apk,
+    BasenApk,
+    Price,
+    Abv,
+    Volume,
+    LiterPrice,
+    Name,
+}
+
+impl SortKey {
+    /// Orders `d1` first if it has less of the key. Numbers that can't be compared are equal.
+    pub fn compare(self, d1: &Product, d2: &Product) -> Ordering {
+        let numbers = |value: fn(&Product) -> f64| {
+            value(d1).partial_cmp(&value(d2)).unwrap_or(Ordering::Equal)
+        };
+        match self {
+            SortKey::Apk => numbers(|drink| apk(drink).0),
+            SortKey::BasenApk => numbers(|drink| basen_apk(drink).0),
+            SortKey::Price => numbers(|drink| drink.price_with_deposit().0),
+            SortKey::Abv => numbers(|drink| drink.abv().0),
+            SortKey::Volume => numbers(|drink| drink.volume().0),
+            SortKey::LiterPrice => numbers(|drink| price_per_liter(drink).0),
+            SortKey::Name => name(d1).to_lowercase().cmp(&name(d2).to_lowercase()),
+        }
+    }
+
+    /// Whether more is better, so that lists sorted by the key go from the most, unless asked
+    /// otherwise.
+    pub fn descending(self) -> bool {
+        match self {
+            SortKey::Apk | SortKey::BasenApk | SortKey::Abv | SortKey::Volume => true,
+            SortKey::Price | SortKey::LiterPrice | SortKey::Name => false,
+        }
+    }
+}
+
+/// Orders the best APK first.
+pub fn apk_comparator(d1: &Product, d2: &Product) -> Ordering {
+    SortKey::Apk.compare(d2, d1)
+}
@@ -0,0 +1,70 @@
+use crate::catalog::{self, Catalog, Category, CATEGORIES};
+use crate::units::{Measures, Sek};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::fmt;
+use systemet::Product;
+
+/// Enough of one product to reach a number of standard drinks.
+#[derive(Clone, Debug, Serialize)]
+pub struct Purchase<'a> {
+    pub category: Category,
+    #[serde(skip)]
+    pub drink: &'a Product,
+    /// How many to buy
+    pub units: u32,
+    pub price: Sek,
+    /// All of them together, at least the target
+    pub standard_drinks: f64,
+}
+
+/// Reaching a number of standard drinks would take more of a product than can be counted, like
+/// when it has no alcohol.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutOfReach;
+
+impl fmt::Display for OutOfReach {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "too many to buy")
+    }
+}
+
+impl std::error::Error for OutOfReach {}
+
+/// What it takes of `drink` to reach `standard_drinks`.
+pub fn purchase(drink: &Product, standard_drinks: f64) -> Result<Purchase, OutOfReach> {
+    let per_unit = drink.pure_alcohol().standard_drinks();
+    let units = (standard_drinks / per_unit).ceil().max(1.0);
+    if units.is_nan() || units > u32::MAX as f64 {
+        return Err(OutOfReach);
+    }
+    let units = units as u32;
+    Ok(Purchase {
+        category: catalog::categorize(drink),
+        drink,
+        units,
+        price: drink.price_with_deposit() * units as f64,
+        standard_drinks: per_unit * units as f64,
+    })
+}
+
+/// The cheapest way to reach `standard_drinks` in each category, buying just one product. Whole
+/// bottles and cans only, so the best APK isn't always the cheapest. Products it can't be reached
+/// with are left out.
+pub fn cheapest(catalog: &Catalog, standard_drinks: f64) -> Vec<Purchase> {
+    CATEGORIES
+        .iter()
+        .filter_map(|&category| {
+            catalog
+                .get(category)
+                .iter()
+                .filter_map(|drink| purchase(drink, standard_drinks).ok())
+                .min_by(|p1, p2| {
+                    p1.price
+                        .partial_cmp(&p2.price)
+                        .unwrap_or(Ordering::Equal)
+                        .then(p1.units.cmp(&p2.units))
+                })
+        })
+        .collect()
+}
@@ -0,0 +1,107 @@
+use crate::catalog::{self, Catalog, Category};
+use crate::units::{Apk, Measures, Sek};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use systemet::Product;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PriceChange {
+    pub id: String,
+    pub old_price: Sek,
+    pub new_price: Sek,
+    pub old_apk: Apk,
+    pub new_apk: Apk,
+}
+
+impl PriceChange {
+    pub fn apk_delta(&self) -> f64 {
+        self.new_apk.0 - self.old_apk.0
+    }
+}
+
+/// The difference between two catalogs, keyed by product id.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Diff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub price_changes: Vec<PriceChange>,
+    /// Products that were temporarily out of stock and now aren't
+    pub restocked: Vec<String>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.price_changes.is_empty()
+            && self.restocked.is_empty()
+    }
+
+    /// The `n` price changes that moved APK the most, in either direction.
+    pub fn top_changes(&self, n: usize) -> Vec<&PriceChange> {
+        let mut changes: Vec<_> = self.price_changes.iter().collect();
+        changes.sort_by(|a, b| {
+            b.apk_delta()
+                .abs()
+                .partial_cmp(&a.apk_delta().abs())
+                .unwrap_or(Ordering::Equal)
+        });
+        changes.truncate(n);
+        changes
+    }
+}
+
+pub fn diff(old: &Catalog, new: &Catalog) -> Diff {
+    let old_products: HashMap<&str, &Product> =
+        old.products().map(|p| (catalog::id(p), p)).collect();
+    let new_products: HashMap<&str, &Product> =
+        new.products().map(|p| (catalog::id(p), p)).collect();
+
+    let mut diff = Diff::default();
+    for (id, new_product) in &new_products {
+        match old_products.get(id) {
+            None => diff.added.push(id.to_string()),
+            Some(old_product) => {
+                if old_product.shelf_price() != new_product.shelf_price() {
+                    diff.price_changes.push(PriceChange {
+                        id: id.to_string(),
+                        old_price: old_product.shelf_price(),
+                        new_price: new_product.shelf_price(),
+                        old_apk: catalog::apk(old_product),
+                        new_apk: catalog::apk(new_product),
+                    });
+                }
+                if !catalog::in_stock(old_product) && catalog::in_stock(new_product) {
+                    diff.restocked.push(id.to_string());
+                }
+            }
+        }
+    }
+    diff.removed = old_products
+        .keys()
+        .filter(|id| !new_products.contains_key(*id))
+        .map(|id| id.to_string())
+        .collect();
+
+    diff.added.sort();
+    diff.removed.sort();
+    diff.restocked.sort();
+    diff.price_changes.sort_by(|a, b| a.id.cmp(&b.id));
+    diff
+}
+
+/// Products in the top `n` of `category` in `new` that weren't there in `old`.
+pub fn entered_top<'a>(
+    old: &Catalog,
+    new: &'a Catalog,
+    category: Category,
+    n: usize,
+) -> Vec<&'a Product> {
+    let old_top: Vec<&str> = old.get(category).iter().take(n).map(catalog::id).collect();
+    new.get(category)
+        .iter()
+        .take(n)
+        .filter(|drink| !old_top.contains(&catalog::id(drink)))
+        .collect()
+}
@@ -0,0 +1,7 @@
+//! Categorization, scoring and diffing of the Systembolaget catalog, without any IO.
+
+pub mod catalog;
+pub mod cost;
+pub mod diff;
+pub mod score;
+pub mod units;
@@ -0,0 +1,96 @@
+use crate::catalog;
+use crate::units::Measures;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::sync::Arc;
+use systemet::Product;
+
+/// A way of ranking products. Higher scores are better.
+pub trait Scorer: Send + Sync {
+    /// Used to refer to the scorer from templates, e.g. `drink | score(by="apk")`.
+    fn name(&self) -> &str;
+    fn score(&self, drink: &Product) -> f64;
+}
+
+pub struct ApkScorer;
+
+impl Scorer for ApkScorer {
+    fn name(&self) -> &str {
+        "apk"
+    }
+
+    fn score(&self, drink: &Product) -> f64 {
+        catalog::apk(drink).0
+    }
+}
+
+pub struct BasenApkScorer;
+
+impl Scorer for BasenApkScorer {
+    fn name(&self) -> &str {
+        "basen_apk"
+    }
+
+    fn score(&self, drink: &Product) -> f64 {
+        catalog::basen_apk(drink).0
+    }
+}
+
+/// Milliliters per krona, so that the cheapest per liter scores the highest, whatever its strength.
+/// For buying volume, like a base for punch or wine for cooking.
+pub struct LiterPriceScorer;
+
+impl Scorer for LiterPriceScorer {
+    fn name(&self) -> &str {
+        "literpris"
+    }
+
+    fn score(&self, drink: &Product) -> f64 {
+        1000.0 / catalog::price_per_liter(drink).0
+    }
+}
+
+pub fn default_scorers() -> Vec<Arc<dyn Scorer>> {
+    vec![
+        Arc::new(ApkScorer),
+        Arc::new(BasenApkScorer),
+        Arc::new(LiterPriceScorer),
+    ]
+}
+
+/// Kilocalories per gram of alcohol...
+pub const KCAL_PER_GRAM_ALCOHOL: f64 = 7.0;
+/// ...and of sugar
+pub const KCAL_PER_GRAM_SUGAR: f64 = 4.0;
+
+/// The facts panel of a product, per package.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Facts {
+    pub alcohol_grams: f64,
+    pub standard_drinks: f64,
+    /// Including deposit
+    pub kr_per_standard_drink: f64,
+    /// From the alcohol and, where it's known, the sugar. Other carbohydrates aren't counted, so
+    /// it's on the low side for beer.
+    pub kcal: f64,
+}
+
+pub fn facts(drink: &Product) -> Facts {
+    let alcohol = drink.pure_alcohol();
+    let standard_drinks = alcohol.standard_drinks();
+    let sugar_grams = catalog::sugar(drink).unwrap_or(0.0) * drink.volume().0 / 1000.0;
+    Facts {
+        alcohol_grams: alcohol.grams(),
+        standard_drinks,
+        kr_per_standard_drink: drink.price_with_deposit().0 / standard_drinks,
+        kcal: alcohol.grams() * KCAL_PER_GRAM_ALCOHOL + sugar_grams * KCAL_PER_GRAM_SUGAR,
+    }
+}
+
+/// Orders products by descending score.
+pub fn compare(scorer: &dyn Scorer, d1: &Product, d2: &Product) -> Ordering {
+    scorer
+        .score(d2)
+        .partial_cmp(&scorer.score(d1))
+        .unwrap_or(Ordering::Equal)
+}
@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, Div, Mul, Sub};
+use systemet::Product;
+
+/// An amount of money in Swedish kronor.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Sek(pub f64);
+
+/// A volume in milliliters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Ml(pub f64);
+
+/// Alcohol by volume, in percent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Percent(pub f64);
+
+/// Milliliters of pure alcohol per krona.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Apk(pub f64);
+
+impl Sek {
+    /// Rounds up to the nearest multiple of `increment` kronor.
+    pub fn round_up_to(self, increment: f64) -> Sek {
+        Sek((self.0 / increment).ceil() * increment)
+    }
+}
+
+impl Add for Sek {
+    type Output = Sek;
+    fn add(self, other: Sek) -> Sek {
+        Sek(self.0 + other.0)
+    }
+}
+
+impl Sub for Sek {
+    type Output = Sek;
+    fn sub(self, other: Sek) -> Sek {
+        Sek(self.0 - other.0)
+    }
+}
+
+impl Mul<f64> for Sek {
+    type Output = Sek;
+    fn mul(self, factor: f64) -> Sek {
+        Sek(self.0 * factor)
+    }
+}
+
+impl Div<f64> for Sek {
+    type Output = Sek;
+    fn div(self, divisor: f64) -> Sek {
+        Sek(self.0 / divisor)
+    }
+}
+
+impl Div for Sek {
+    type Output = f64;
+    fn div(self, other: Sek) -> f64 {
+        self.0 / other.0
+    }
+}
+
+impl Sum for Sek {
+    fn sum<I: Iterator<Item = Sek>>(iter: I) -> Sek {
+        Sek(iter.map(|s| s.0).sum())
+    }
+}
+
+/// Grams of alcohol in a Swedish standard drink ("standardglas")
+pub const STANDARD_DRINK_GRAMS: f64 = 12.0;
+/// Grams per ml of ethanol
+const ALCOHOL_DENSITY: f64 = 0.789;
+
+impl Ml {
+    /// What this much pure alcohol weighs, in grams.
+    pub fn grams(self) -> f64 {
+        self.0 * ALCOHOL_DENSITY
+    }
+
+    /// How many standard drinks this much pure alcohol is.
+    pub fn standard_drinks(self) -> f64 {
+        self.grams() / STANDARD_DRINK_GRAMS
+    }
+}
+
+impl Add for Ml {
+    type Output = Ml;
+    fn add(self, other: Ml) -> Ml {
+        Ml(self.0 + other.0)
+    }
+}
+
+impl Mul<f64> for Ml {
+    type Output = Ml;
+    fn mul(self, factor: f64) -> Ml {
+        Ml(self.0 * factor)
+    }
+}
+
+impl Div for Ml {
+    type Output = f64;
+    fn div(self, other: Ml) -> f64 {
+        self.0 / other.0
+    }
+}
+
+impl Div<Sek> for Ml {
+    type Output = Apk;
+    fn div(self, price: Sek) -> Apk {
+        Apk(self.0 / price.0)
+    }
+}
+
+impl Sum for Ml {
+    fn sum<I: Iterator<Item = Ml>>(iter: I) -> Ml {
+        Ml(iter.map(|m| m.0).sum())
+    }
+}
+
+/// The amount of pure alcohol in a volume.
+impl Mul<Ml> for Percent {
+    type Output = Ml;
+    fn mul(self, volume: Ml) -> Ml {
+        Ml(self.0 / 100.0 * volume.0)
+    }
+}
+
+impl fmt::Display for Sek {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.2} kr", self.0)
+    }
+}
+
+impl fmt::Display for Ml {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ml", self.0)
+    }
+}
+
+impl fmt::Display for Percent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+impl fmt::Display for Apk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.5}", self.0)
+    }
+}
+
+/// Typed accessors for the raw numbers in a [`Product`].
+pub trait Measures {
+    fn abv(&self) -> Percent;
+    fn volume(&self) -> Ml;
+    /// The shelf price, excluding deposit.
+    fn shelf_price(&self) -> Sek;
+    fn deposit(&self) -> Sek;
+
+    /// What you actually pay at the register.
+    fn price_with_deposit(&self) -> Sek {
+        self.shelf_price() + self.deposit()
+    }
+
+    fn pure_alcohol(&self) -> Ml {
+        self.abv() * self.volume()
+    }
+}
+
+impl Measures for Product {
+    fn abv(&self) -> Percent {
+        Percent(self.alcohol_percentage)
+    }
+
+    fn volume(&self) -> Ml {
+        Ml(self.volume)
+    }
+
+    fn shelf_price(&self) -> Sek {
+        Sek(self.price)
+    }
+
+    fn deposit(&self) -> Sek {
+        Sek(self.recycle_fee)
+    }
+}
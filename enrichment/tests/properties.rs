@@ -0,0 +1,108 @@
+use proptest::prelude::*;
+use serde_json::json;
+use systembolaget_enrichment::catalog::{self, Catalog, BASEN_ROUNDING, CATEGORIES};
+use systembolaget_enrichment::units::{Measures, Percent, Sek};
+use systemet::Product;
+
+const CATEGORY_NAMES: [&str; 7] = [
+    "Öl",
+    "Röda viner",
+    "Vita viner",
+    "Cider och blanddrycker",
+    "Sprit",
+    "Presentartiklar",
+    "Okänd",
+];
+const ASSORTMENTS: [&str; 4] = ["FS", "TSE", "BS", "TSLS"];
+
+fn product(
+    id: usize,
+    category: &str,
+    abv: f64,
+    volume: f64,
+    price: f64,
+    recycle_fee: f64,
+    assortment: &str,
+) -> Product {
+    let record = json!({
+        "ProductId": id.to_string(),
+        "ProductNumber": id.to_string(),
+        "ProductNameBold": "Produkt",
+        "Category": category,
+        "BottleTextShort": "Flaska",
+        "ProducerName": "Producent",
+        "Country": "Sverige",
+        "AlcoholPercentage": abv,
+        "Volume": volume,
+        "Price": price,
+        "RecycleFee": recycle_fee,
+        "Assortment": assortment,
+        "IsCompletelyOutOfStock": false,
+        "IsTemporaryOutOfStock": false,
+    });
+    serde_json::from_value(record).unwrap()
+}
+
+fn products() -> impl Strategy<Value = Vec<Product>> {
+    let record = (
+        0..CATEGORY_NAMES.len(),
+        prop_oneof![Just(0.0), 0.1..80.0f64],
+        1.0..5000.0f64,
+        prop_oneof![Just(0.0), 0.01..5000.0f64],
+        prop_oneof![Just(0.0), 0.5..2.0f64],
+        0..ASSORTMENTS.len(),
+    );
+    prop::collection::vec(record, 0..50).prop_map(|records| {
+        records
+            .into_iter()
+            .enumerate()
+            .map(|(id, (category, abv, volume, price, fee, assortment))| {
+                product(
+                    id,
+                    CATEGORY_NAMES[category],
+                    abv,
+                    volume,
+                    price,
+                    fee,
+                    ASSORTMENTS[assortment],
+                )
+            })
+            .collect()
+    })
+}
+
+proptest! {
+    #[test]
+    fn categories_are_sorted_by_descending_apk(products in products()) {
+        let catalog = Catalog::build(products);
+        for &category in CATEGORIES.iter() {
+            for pair in catalog.get(category).windows(2) {
+                prop_assert!(catalog::apk(&pair[0]) >= catalog::apk(&pair[1]));
+            }
+        }
+    }
+
+    #[test]
+    fn filtering_drops_free_and_alcohol_free_products(products in products()) {
+        let catalog = Catalog::build(products);
+        for drink in catalog.products() {
+            prop_assert!(drink.abv() > Percent(0.0));
+            prop_assert!(drink.shelf_price() > Sek(0.0));
+            prop_assert!(catalog::apk(drink).0.is_finite());
+        }
+    }
+
+    #[test]
+    fn filtering_keeps_every_listed_product(products in products()) {
+        let listed = products.iter().filter(|p| catalog::is_listed(p)).count();
+        prop_assert_eq!(Catalog::build(products).len(), listed);
+    }
+
+    #[test]
+    fn basen_price_is_rounded_up(price in 0.0..10000.0f64) {
+        let drink = product(0, "Öl", 5.0, 330.0, price, 1.0, "FS");
+        let basen_price = catalog::basen_price(&drink);
+        prop_assert!(basen_price >= Sek(price));
+        prop_assert_eq!(basen_price.0 % BASEN_ROUNDING, 0.0);
+    }
+}
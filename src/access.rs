@@ -0,0 +1,80 @@
+//! A line per request, with the method, path, status, how long it took and who asked, logged
+//! under the `access` target when `access_log` is on in the config.
+
+use crate::state::AppState;
+use std::net::{IpAddr, SocketAddr};
+use tracing::info;
+use warp::http::HeaderMap;
+use warp::log::{Info, Log};
+use warp::{Filter, Rejection};
+
+/// Where a request came from: the first address in `X-Forwarded-For`, as set by a reverse proxy,
+/// or else the peer's address.
+pub fn remote(headers: &HeaderMap, peer: Option<SocketAddr>) -> Option<String> {
+    headers
+        .get("x-forwarded-for")
+        .and_then(|forwarded| forwarded.to_str().ok())
+        .and_then(|forwarded| forwarded.split(',').next())
+        .map(|client| client.trim().to_string())
+        .filter(|client| !client.is_empty())
+        .or_else(|| peer.map(|peer| peer.ip().to_string()))
+}
+
+/// Who a request is from, for limits: the peer's address, or if that's one of the `trusted`
+/// proxies, the last address in `X-Forwarded-For` that isn't. Unlike [`remote`], it can't be
+/// made up by the client.
+pub fn client_address(
+    trusted: &[IpAddr],
+    headers: &HeaderMap,
+    peer: Option<SocketAddr>,
+) -> Option<IpAddr> {
+    let peer = peer?.ip();
+    if !trusted.contains(&peer) {
+        return Some(peer);
+    }
+    let forwarded: Vec<IpAddr> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|forwarded| forwarded.to_str().ok())
+        .flat_map(|forwarded| forwarded.split(','))
+        .filter_map(|address| address.trim().parse().ok())
+        .collect();
+    // The proxies append, so only the addresses after the first untrusted one are vouched for
+    Some(
+        forwarded
+            .into_iter()
+            .rev()
+            .find(|address| !trusted.contains(address))
+            .unwrap_or(peer),
+    )
+}
+
+/// [`client_address`] of each request, with the trusted proxies in the config.
+pub fn client(
+    state: &AppState,
+) -> impl Filter<Extract = (Option<IpAddr>,), Error = Rejection> + Clone {
+    let trusted = state.config.trusted_proxies.clone();
+    warp::header::headers_cloned()
+        .and(warp::addr::remote())
+        .map(move |headers: HeaderMap, peer: Option<SocketAddr>| {
+            client_address(&trusted, &headers, peer)
+        })
+}
+
+/// Logs each request if `enabled`.
+pub fn log(enabled: bool) -> Log<impl Fn(Info) + Clone + Send + Sync> {
+    warp::log::custom(move |request: Info| {
+        if enabled {
+            info!(
+                target: "access",
+                method = %request.method(),
+                path = request.path(),
+                status = request.status().as_u16(),
+                latency_ms = request.elapsed().as_millis() as u64,
+                remote = remote(request.request_headers(), request.remote_addr())
+                    .as_deref()
+                    .unwrap_or("-"),
+            );
+        }
+    })
+}
@@ -0,0 +1,64 @@
+//! Access to the `/admin` pages, by the bearer tokens in the config. Without any configured, the
+//! admin pages aren't served at all.
+
+use crate::config::{AdminConfig, AdminToken};
+use crate::signing;
+use crate::state::AppState;
+use futures::future;
+use secrecy::ExposeSecret;
+use warp::http::{header, StatusCode};
+use warp::path::FullPath;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// The admin token in an `Authorization: Bearer` header, if it's one of `config`'s.
+pub fn authorize<'a>(
+    config: &'a AdminConfig,
+    authorization: Option<&str>,
+) -> Option<&'a AdminToken> {
+    let token = authorization?.strip_prefix("Bearer ")?.trim();
+    // Comparing hashes, so the time taken doesn't tell how much of a token was right
+    let hash = signing::sha256(token.as_bytes());
+    config
+        .tokens
+        .iter()
+        .find(|issued| signing::sha256(issued.token.expose_secret().as_bytes()) == hash)
+}
+
+fn is_admin(path: &str) -> bool {
+    path == "/admin" || path.starts_with("/admin/")
+}
+
+/// Turns away requests for the admin pages without a valid admin token, leaving the rest, and
+/// authorized ones, to the routes after it.
+pub fn guard(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path::full()
+        .and(warp::header::optional::<String>("authorization"))
+        .and_then(move |path: FullPath, authorization: Option<String>| {
+            let response = match &state.config.admin {
+                _ if !is_admin(path.as_str()) => None,
+                Some(config) if authorize(config, authorization.as_deref()).is_some() => None,
+                Some(_) => Some(
+                    warp::reply::with_header(
+                        StatusCode::UNAUTHORIZED,
+                        header::WWW_AUTHENTICATE,
+                        "Bearer",
+                    )
+                    .into_response(),
+                ),
+                None => Some(StatusCode::NOT_FOUND.into_response()),
+            };
+            future::ready(response.ok_or_else(warp::reject::not_found))
+        })
+}
+
+/// The name of the admin token of a request that got past [`guard`], for the audit log.
+pub fn name(
+    state: AppState,
+) -> impl Filter<Extract = (Option<String>,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization").map(move |authorization: Option<String>| {
+        let config = state.config.admin.as_ref()?;
+        let token = authorize(config, authorization.as_deref())?;
+        Some(token.name.clone())
+    })
+}
@@ -0,0 +1,66 @@
+//! An optional age confirmation shown instead of the list until it's been answered, remembered in
+//! a cookie. Some hosts require it for anything about alcohol.
+
+use crate::back;
+use crate::render;
+use crate::state::AppState;
+use std::collections::HashMap;
+use tracing::error;
+use warp::http::{header, StatusCode};
+use warp::path::FullPath;
+use warp::reply::{html, Response};
+use warp::{Filter, Rejection, Reply};
+
+pub const COOKIE: &str = "age";
+/// A year, in seconds
+const MAX_AGE: u64 = 365 * 24 * 60 * 60;
+const MAX_FORM: u64 = 4 * 1024;
+
+fn interstitial(state: &AppState, back: &str) -> Response {
+    match render::render_age_gate(&state.tera(), back) {
+        Ok(page) => html(page).into_response(),
+        Err(err) => {
+            error!("{}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+fn confirm(form: HashMap<String, String>) -> Response {
+    let redirect =
+        warp::reply::with_header(StatusCode::SEE_OTHER, header::LOCATION, back::back(&form));
+    let cookie = format!("{}=ok; Path=/; Max-Age={}; SameSite=Lax", COOKIE, MAX_AGE);
+    warp::reply::with_header(redirect, header::SET_COOKIE, cookie).into_response()
+}
+
+/// Shows the confirmation for anything not handled before this, if the gate is enabled and hasn't
+/// been passed.
+pub fn gate(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path::full()
+        .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+        .and(warp::cookie::optional(COOKIE))
+        .and_then(
+            move |path: FullPath, query: String, cookie: Option<String>| {
+                let state = state.clone();
+                async move {
+                    if !state.config.age_gate || cookie.is_some() {
+                        return Err(warp::reject::not_found());
+                    }
+                    let back = match query.as_str() {
+                        "" => path.as_str().to_string(),
+                        query => format!("{}?{}", path.as_str(), query),
+                    };
+                    Ok(interstitial(&state, &back))
+                }
+            },
+        )
+}
+
+/// `POST /age` passes the gate.
+pub fn route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("age")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(MAX_FORM))
+        .and(warp::body::form())
+        .map(confirm)
+}
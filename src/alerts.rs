@@ -0,0 +1,103 @@
+//! Works out which refresh changes are worth alerting people about.
+
+use crate::catalog;
+use crate::notify::RefreshEvent;
+use crate::units::{Apk, Sek};
+use serde::Serialize;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Alert {
+    PriceDrop {
+        id: String,
+        name: String,
+        old_price: Sek,
+        new_price: Sek,
+        apk: Apk,
+    },
+    BackInStock {
+        id: String,
+        name: String,
+        apk: Apk,
+    },
+    /// A product started matching a saved search
+    SearchMatch {
+        id: String,
+        name: String,
+        apk: Apk,
+        /// Link to the search
+        search: String,
+    },
+}
+
+impl Alert {
+    pub fn product_id(&self) -> &str {
+        match self {
+            Alert::PriceDrop { id, .. }
+            | Alert::BackInStock { id, .. }
+            | Alert::SearchMatch { id, .. } => id,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        match self {
+            Alert::PriceDrop {
+                name,
+                old_price,
+                new_price,
+                apk,
+                ..
+            } => format!(
+                "{} har sänkt priset från {} till {} (APK {:.3})",
+                name, old_price, new_price, apk.0
+            ),
+            Alert::BackInStock { name, apk, .. } => {
+                format!("{} finns i lager igen (APK {:.3})", name, apk.0)
+            }
+            Alert::SearchMatch {
+                name, apk, search, ..
+            } => format!("{} matchar din sökning {} (APK {:.3})", name, search, apk.0),
+        }
+    }
+}
+
+/// Price drops and products back in stock. Products that are new to the catalog count as back in
+/// stock, since anyone watching them must have seen them before.
+pub fn alerts(event: &RefreshEvent) -> Vec<Alert> {
+    let catalog = &event.snapshot.catalog;
+    let restocked = event
+        .diff
+        .restocked
+        .iter()
+        .chain(event.diff.added.iter())
+        .filter_map(|id| catalog.find(id))
+        .map(|drink| Alert::BackInStock {
+            id: catalog::id(drink).to_string(),
+            name: catalog::name(drink).to_string(),
+            apk: catalog::apk(drink),
+        });
+    event
+        .diff
+        .price_changes
+        .iter()
+        .filter(|change| change.new_price < change.old_price)
+        .filter_map(|change| {
+            let drink = catalog.find(&change.id)?;
+            Some(Alert::PriceDrop {
+                id: change.id.clone(),
+                name: catalog::name(drink).to_string(),
+                old_price: change.old_price,
+                new_price: change.new_price,
+                apk: change.new_apk,
+            })
+        })
+        .chain(restocked)
+        .collect()
+}
+
+/// The alerts about any of `watched`.
+pub fn watched<'a>(alerts: &'a [Alert], watched: &'a [String]) -> impl Iterator<Item = &'a Alert> {
+    alerts
+        .iter()
+        .filter(move |alert| watched.iter().any(|id| id == alert.product_id()))
+}
@@ -0,0 +1,92 @@
+//! Allergen and ingredient tags, like gluten in beer or sulfites in wine. Systembolaget's API
+//! doesn't have them, so they're derived from the category, name and subcategory by the rules in
+//! the config. Shown on the product pages at `/produkt/{id}`, or `/product/{id}`, and their
+//! permalinks, see [`names::path`].
+
+use crate::catalog::{self, Category};
+use crate::config::AllergenConfig;
+use crate::names;
+use crate::render;
+use crate::state::AppState;
+use systemet::Product;
+use tracing::error;
+use warp::http::StatusCode;
+use warp::reply::{html, Response};
+use warp::{Filter, Rejection, Reply};
+
+/// The tag the gluten free filter looks for
+pub const GLUTEN: &str = "gluten";
+
+/// The text the rule words are looked for in, lowercase.
+fn text(drink: &Product) -> String {
+    format!(
+        "{} {}",
+        catalog::name(drink),
+        drink.sub_category.as_deref().unwrap_or("")
+    )
+    .to_lowercase()
+}
+
+fn applies(rule: &AllergenConfig, category: Category, text: &str) -> bool {
+    let has = |word: &String| text.contains(&word.to_lowercase());
+    rule.categories
+        .as_ref()
+        .map_or(true, |categories| categories.contains(&category))
+        && (rule.words.is_empty() || rule.words.iter().any(has))
+        && !rule.except.iter().any(has)
+}
+
+/// The tags of `drink` by `rules`, in the order of the rules.
+pub fn tags<'a>(rules: &'a [AllergenConfig], drink: &Product) -> Vec<&'a str> {
+    let category = catalog::categorize(drink);
+    let text = text(drink);
+    rules
+        .iter()
+        .filter(|rule| applies(rule, category, &text))
+        .map(|rule| rule.tag.as_str())
+        .collect()
+}
+
+/// Whether `drink` has `tag`, ignoring case.
+pub fn has(rules: &[AllergenConfig], drink: &Product, tag: &str) -> bool {
+    tags(rules, drink)
+        .iter()
+        .any(|other| other.eq_ignore_ascii_case(tag))
+}
+
+fn page(state: &AppState, id: &str) -> Response {
+    let snapshot = state.snapshot.read().unwrap().clone();
+    let catalog = match &snapshot {
+        Some(snapshot) => &snapshot.catalog,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let drink = match catalog.find(id) {
+        Some(drink) => drink,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let tags = tags(&state.config.allergens, drink);
+    let config = &state.config.names;
+    let name = names::display(config, drink);
+    let related = names::related(config, catalog, drink);
+    match render::render_product(&state.tera(), drink, &name, &tags, &related) {
+        Ok(page) => html(page).into_response(),
+        Err(err) => {
+            error!("{}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let by_id = {
+        let state = state.clone();
+        warp::path!("produkt" / String)
+            .or(warp::path!("product" / String))
+            .unify()
+            .map(move |id: String| page(&state, &id))
+    };
+    // The slug is just for people, so an outdated one still works
+    let permalink = warp::path!("produkt" / String / String)
+        .map(move |id: String, _: String| page(&state, &id));
+    by_id.or(permalink).unify()
+}
@@ -0,0 +1,53 @@
+//! Sanity checks of a fresh catalog against the current one, so that a broken API response doesn't
+//! replace a good list.
+
+use crate::catalog::{Catalog, CATEGORIES};
+use crate::units::Measures;
+use std::cmp::Ordering;
+
+/// The product count may shrink to this share of the current one...
+const MIN_COUNT_RATIO: f64 = 0.5;
+/// ...or grow to this many times it
+const MAX_COUNT_RATIO: f64 = 2.0;
+/// The median price may change by at most this factor
+const MAX_PRICE_RATIO: f64 = 1.5;
+
+fn median_price(catalog: &Catalog) -> Option<f64> {
+    let mut prices: Vec<f64> = catalog
+        .products()
+        .map(|drink| drink.price_with_deposit().0)
+        .collect();
+    if prices.is_empty() {
+        return None;
+    }
+    prices.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    Some(prices[prices.len() / 2])
+}
+
+/// What looks wrong about `next` compared to `current`, in Swedish. Empty if it looks fine.
+pub fn check(current: &Catalog, next: &Catalog) -> Vec<String> {
+    let mut problems = Vec::new();
+    let (before, after) = (current.len() as f64, next.len() as f64);
+    if after < before * MIN_COUNT_RATIO || after > before * MAX_COUNT_RATIO {
+        problems.push(format!(
+            "antalet produkter gick från {} till {}",
+            current.len(),
+            next.len()
+        ));
+    }
+    for &category in CATEGORIES.iter() {
+        if !current.get(category).is_empty() && next.get(category).is_empty() {
+            problems.push(format!("{} blev tom", category.name()));
+        }
+    }
+    if let (Some(before), Some(after)) = (median_price(current), median_price(next)) {
+        let ratio = after / before;
+        if ratio > MAX_PRICE_RATIO || ratio < 1.0 / MAX_PRICE_RATIO {
+            problems.push(format!(
+                "medianpriset gick från {:.2} kr till {:.2} kr",
+                before, after
+            ));
+        }
+    }
+    problems
+}
@@ -0,0 +1,367 @@
+//! A JSON API at `/api/products`, `/api/movers` and `/api/cost`, for the tokens issued in the
+//! config, plus `POST /api/products/lookup` for looking up many products at once. Each token's
+//! requests are counted per day in storage, and refused once it's over its quota. The counts are
+//! shown at `/admin/api`. Errors are answered with the envelope of [`crate::apierror`].
+
+use crate::apierror::{self, ApiError};
+use crate::catalog::{self, CATEGORIES};
+use crate::config::{ApiConfig, ApiToken, AssortmentConfig, DisplayConfig};
+use crate::cost;
+use crate::dates::{self, DAY};
+use crate::display::{self, Field};
+use crate::error::{Error, Result};
+use crate::movers;
+use crate::score;
+use crate::signing;
+use crate::state::AppState;
+use crate::status::unix_time;
+use crate::stock;
+use crate::storage::{self, Storage};
+use crate::stores;
+use crate::units::Measures;
+use crate::view::View;
+use futures::future;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use systemet::Product;
+use tracing::error;
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+const USAGE_KEY: &str = "api-usage.json";
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    /// The day `today` counts, as `YYYY-MM-DD`
+    pub day: String,
+    pub today: u64,
+    pub total: u64,
+    /// Requests refused for being over the quota
+    pub rejected: u64,
+}
+
+/// The usage of each token, by name.
+pub fn usage(storage: &dyn Storage) -> Result<HashMap<String, Usage>> {
+    Ok(storage::load_json(storage, USAGE_KEY)?.unwrap_or_default())
+}
+
+/// Counts a request by `token` at `now`, returning whether it's within the quota.
+pub fn record(storage: &dyn Storage, token: &ApiToken, now: SystemTime) -> Result<bool> {
+    let mut all = usage(storage)?;
+    let usage = all.entry(token.name.clone()).or_default();
+    let day = dates::date(now);
+    if usage.day != day {
+        usage.day = day;
+        usage.today = 0;
+    }
+    let allowed = token.quota.map_or(true, |quota| usage.today < quota);
+    if allowed {
+        usage.today += 1;
+        usage.total += 1;
+    } else {
+        usage.rejected += 1;
+    }
+    storage::save_json(storage, USAGE_KEY, &all)?;
+    Ok(allowed)
+}
+
+/// The token an `Authorization: Bearer` header is for, if any.
+pub fn authorize<'a>(config: &'a ApiConfig, authorization: Option<&str>) -> Option<&'a ApiToken> {
+    let token = authorization?.strip_prefix("Bearer ")?.trim();
+    // Comparing hashes, so the time taken doesn't tell how much of a token was right
+    let hash = signing::sha256(token.as_bytes());
+    config
+        .tokens
+        .iter()
+        .find(|issued| signing::sha256(issued.token.expose_secret().as_bytes()) == hash)
+}
+
+/// The most products a single lookup may ask for
+pub const MAX_LOOKUP: usize = 100;
+/// The most standard drinks `/api/cost` is asked about, more than any party needs
+pub const MAX_STANDARD_DRINKS: f64 = 10_000.0;
+
+/// `drink` as the API shows it, rounded like the pages, see [`display`].
+pub fn product(drink: &Product, icons: &HashMap<&str, String>, config: &DisplayConfig) -> Value {
+    let round = |field, value| display::round(config, field, value);
+    let facts = score::facts(drink);
+    json!({
+        "id": catalog::id(drink),
+        "name": catalog::name(drink),
+        "category": catalog::categorize(drink),
+        "icon": icons[catalog::categorize(drink).name()],
+        "apk": round(Field::Apk, catalog::apk(drink).0),
+        "price": round(Field::Price, drink.price_with_deposit().0),
+        "volume": drink.volume(),
+        "abv": round(Field::Abv, drink.abv().0),
+        "sugar": catalog::sugar(drink).map(|sugar| round(Field::Sugar, sugar)),
+        "facts": {
+            "alcohol_grams": round(Field::Grams, facts.alcohol_grams),
+            "standard_drinks": round(Field::StandardDrinks, facts.standard_drinks),
+            "kr_per_standard_drink": round(Field::UnitPrice, facts.kr_per_standard_drink),
+            "kcal": round(Field::Kcal, facts.kcal),
+        },
+    })
+}
+
+/// Whether the API shows `drink`, see [`AssortmentConfig`].
+fn allowed(state: &AppState, drink: &Product) -> bool {
+    state
+        .config
+        .assortment
+        .view(AssortmentConfig::API)
+        .map_or(true, |rules| rules.allows(drink))
+}
+
+/// The products in `view`, best first within each category.
+pub fn products(state: &AppState, view: &View) -> Value {
+    let snapshot = match state.snapshot.read().unwrap().clone() {
+        Some(snapshot) => snapshot,
+        None => return json!([]),
+    };
+    let drinks = view.apply(
+        &snapshot.catalog,
+        Some(&snapshot.index),
+        &[],
+        &state.config.allergens,
+    );
+    let drinks = stock::filter(state, view, drinks);
+    let icons = state.config.icons();
+    let products: Vec<Value> = CATEGORIES
+        .iter()
+        .flat_map(|category| drinks[category].iter())
+        .filter(|drink| allowed(state, drink))
+        .map(|drink| {
+            let mut product = product(drink, &icons, &state.config.display);
+            product["stock"] = json!(view.store.as_ref().and_then(|store| stock::level(
+                state,
+                store,
+                catalog::id(drink)
+            )));
+            product
+        })
+        .collect();
+    Value::Array(products)
+}
+
+/// The products with `ids`, by id, product number or barcode, in the same order, with their scores and
+/// where they rank in their category. The ids that aren't in the catalog are listed as `missing`.
+pub fn lookup(state: &AppState, ids: &[String]) -> Value {
+    let snapshot = match state.snapshot.read().unwrap().clone() {
+        Some(snapshot) => snapshot,
+        None => return json!({ "products": [], "missing": ids }),
+    };
+    let icons = state.config.icons();
+    let mut products = Vec::new();
+    let mut missing = Vec::new();
+    for id in ids {
+        match snapshot.find(id.trim()) {
+            Some(drink) if allowed(state, drink) => {
+                let category = catalog::categorize(drink);
+                let rank = snapshot
+                    .catalog
+                    .get(category)
+                    .iter()
+                    .position(|other| catalog::id(other) == catalog::id(drink))
+                    .map(|i| i + 1);
+                let config = &state.config.display;
+                let basen_apk = display::round(config, Field::Apk, catalog::basen_apk(drink).0);
+                let mut product = product(drink, &icons, config);
+                product["basen_apk"] = json!(basen_apk);
+                product["rank"] = json!(rank);
+                products.push(product);
+            }
+            _ => missing.push(id),
+        }
+    }
+    json!({ "products": products, "missing": missing })
+}
+
+/// The cheapest way to reach `standard_drinks` in each category, see [`cost::cheapest`].
+pub fn cheapest(state: &AppState, standard_drinks: f64) -> Value {
+    let snapshot = match state.snapshot.read().unwrap().clone() {
+        Some(snapshot) => snapshot,
+        None => return json!([]),
+    };
+    let purchases: Vec<Value> = cost::cheapest(&snapshot.catalog, standard_drinks)
+        .iter()
+        .map(|purchase| {
+            json!({
+                "category": purchase.category,
+                "id": catalog::id(purchase.drink),
+                "name": catalog::name(purchase.drink),
+                "units": purchase.units,
+                "price": display::round(&state.config.display, Field::Price, purchase.price.0),
+                "standard_drinks": display::round(
+                    &state.config.display,
+                    Field::StandardDrinks,
+                    purchase.standard_drinks,
+                ),
+            })
+        })
+        .collect();
+    Value::Array(purchases)
+}
+
+#[derive(Deserialize)]
+struct LookupRequest {
+    ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct CostQuery {
+    standard_drinks: Option<f64>,
+}
+
+/// Answers with `body` if `authorization` has a token that's within its quota, and there's a
+/// catalog.
+fn respond(
+    state: &AppState,
+    lock: &Mutex<()>,
+    authorization: Option<&str>,
+    body: impl FnOnce() -> Result<Value>,
+) -> std::result::Result<Response, Rejection> {
+    let config = state.config.api.as_ref().ok_or_else(ApiError::not_found)?;
+    let token = authorize(config, authorization).ok_or_else(ApiError::unauthorized)?;
+    if state.snapshot.read().unwrap().is_none() {
+        return Err(ApiError::not_ready().into());
+    }
+    let now = SystemTime::now();
+    let allowed = {
+        // Counting is a read and a write, which mustn't interleave with another request's
+        let _guard = lock.lock().unwrap();
+        record(&*state.storage, token, now).map_err(internal)?
+    };
+    if !allowed {
+        let reset = DAY - unix_time(now) % DAY;
+        return Err(ApiError::rate_limited(reset).into());
+    }
+    let body = body().map_err(internal)?;
+    Ok(warp::reply::json(&body).into_response())
+}
+
+fn internal(err: Error) -> ApiError {
+    error!("{}", err);
+    ApiError::internal()
+}
+
+/// Usage and quota of every issued token.
+pub fn admin(state: &AppState) -> Result<Value> {
+    let usage = usage(&*state.storage)?;
+    let tokens = state.config.api.as_ref().map_or(&[][..], |api| &api.tokens);
+    let stats: Map<String, Value> = tokens
+        .iter()
+        .map(|token| {
+            let usage = usage.get(&token.name).cloned().unwrap_or_default();
+            let stats = json!({
+                "quota": token.quota,
+                "day": usage.day,
+                "today": usage.today,
+                "total": usage.total,
+                "rejected": usage.rejected,
+            });
+            (token.name.clone(), stats)
+        })
+        .collect();
+    Ok(Value::Object(stats))
+}
+
+fn or_error(result: Result<Response>) -> Response {
+    result.unwrap_or_else(|err| {
+        error!("{}", err);
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    })
+}
+
+pub fn routes(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let lock = Arc::new(Mutex::new(()));
+    let products = {
+        let state = state.clone();
+        let lock = lock.clone();
+        warp::path!("api" / "products")
+            .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(warp::cookie::optional(stores::COOKIE))
+            .and_then(
+                move |query: String, authorization: Option<String>, store: Option<String>| {
+                    let mut view = View::from_query(&query);
+                    if view.store.is_none() {
+                        view.store = stores::default_with_stock(&state, store.as_deref());
+                    }
+                    let body = || Ok(products(&state, &view));
+                    future::ready(respond(&state, &lock, authorization.as_deref(), body))
+                },
+            )
+    };
+    let movers = {
+        let state = state.clone();
+        let lock = lock.clone();
+        warp::path!("api" / "movers")
+            .and(warp::header::optional::<String>("authorization"))
+            .and_then(move |authorization: Option<String>| {
+                let body = || Ok(serde_json::to_value(movers::current(&state)?)?);
+                future::ready(respond(&state, &lock, authorization.as_deref(), body))
+            })
+    };
+    let cost = {
+        let state = state.clone();
+        let lock = lock.clone();
+        warp::path!("api" / "cost")
+            .and(warp::query::<CostQuery>())
+            .and(warp::header::optional::<String>("authorization"))
+            .and_then(move |query: CostQuery, authorization: Option<String>| {
+                let result = match query.standard_drinks {
+                    Some(n) if n > 0.0 && n <= MAX_STANDARD_DRINKS => {
+                        let body = || Ok(cheapest(&state, n));
+                        respond(&state, &lock, authorization.as_deref(), body)
+                    }
+                    _ => {
+                        let message = format!(
+                            "standard_drinks måste vara ett positivt tal, högst {}",
+                            MAX_STANDARD_DRINKS
+                        );
+                        Err(ApiError::invalid(&message).into())
+                    }
+                };
+                future::ready(result)
+            })
+    };
+    let lookup = {
+        let state = state.clone();
+        warp::path!("api" / "products" / "lookup")
+            .and(warp::post())
+            .and(warp::body::content_length_limit(64 * 1024))
+            .and(warp::body::json())
+            .and(warp::header::optional::<String>("authorization"))
+            .and_then(
+                move |request: LookupRequest, authorization: Option<String>| {
+                    let result = if request.ids.len() > MAX_LOOKUP {
+                        let message = format!("Högst {} produkter åt gången", MAX_LOOKUP);
+                        Err(ApiError::too_large(&message).into())
+                    } else {
+                        let body = || Ok(lookup(&state, &request.ids));
+                        respond(&state, &lock, authorization.as_deref(), body)
+                    };
+                    future::ready(result)
+                },
+            )
+    };
+    let admin = warp::path!("admin" / "api").map(move || {
+        or_error(admin(&state).map(|stats| warp::reply::json(&stats).into_response()))
+    });
+    let reads = warp::get().and(
+        products
+            .or(movers)
+            .unify()
+            .or(cost)
+            .unify()
+            .or(admin)
+            .unify(),
+    );
+    lookup.or(reads).unify().recover(apierror::recover).unify()
+}
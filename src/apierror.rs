@@ -0,0 +1,133 @@
+//! The errors of the JSON API, as rejections that [`recover`] turns into a JSON envelope like
+//! `{"error": {"code": "rate_limited", "message": "...", "request_id": "..."}}`. The request id is
+//! also sent in the `X-Request-Id` header, and logged with the error, so a client's report can be
+//! found in the log.
+
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde_json::json;
+use tracing::info;
+use warp::body::BodyDeserializeError;
+use warp::http::{header, StatusCode};
+use warp::reject::{InvalidQuery, PayloadTooLarge, Reject};
+use warp::reply::Response;
+use warp::{Rejection, Reply};
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    /// Stable, for clients to match on
+    pub code: &'static str,
+    pub message: String,
+    /// Seconds until it's worth trying again
+    pub retry_after: Option<u64>,
+}
+
+impl Reject for ApiError {}
+
+impl From<ApiError> for Rejection {
+    fn from(err: ApiError) -> Rejection {
+        warp::reject::custom(err)
+    }
+}
+
+impl ApiError {
+    fn new(status: StatusCode, code: &'static str, message: &str) -> ApiError {
+        ApiError {
+            status,
+            code,
+            message: message.to_string(),
+            retry_after: None,
+        }
+    }
+
+    pub fn invalid(message: &str) -> ApiError {
+        ApiError::new(StatusCode::BAD_REQUEST, "invalid_request", message)
+    }
+
+    pub fn too_large(message: &str) -> ApiError {
+        ApiError::new(StatusCode::PAYLOAD_TOO_LARGE, "too_large", message)
+    }
+
+    pub fn unauthorized() -> ApiError {
+        ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "unauthorized",
+            "Ange en giltig token i Authorization: Bearer",
+        )
+    }
+
+    /// For the API when it isn't turned on.
+    pub fn not_found() -> ApiError {
+        ApiError::new(StatusCode::NOT_FOUND, "not_found", "API:t är inte påslaget")
+    }
+
+    /// Before the first refresh.
+    pub fn not_ready() -> ApiError {
+        ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "not_ready",
+            "Sortimentet har inte hämtats än",
+        )
+    }
+
+    pub fn rate_limited(retry_after: u64) -> ApiError {
+        ApiError {
+            retry_after: Some(retry_after),
+            ..ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "rate_limited",
+                "Dagens kvot är slut",
+            )
+        }
+    }
+
+    pub fn internal() -> ApiError {
+        ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal",
+            "Något gick fel",
+        )
+    }
+
+    fn respond(&self) -> Response {
+        let request_id: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(16)
+            .collect();
+        info!("API error {} ({}): {}", self.code, request_id, self.message);
+        let body = json!({
+            "error": {
+                "code": self.code,
+                "message": self.message,
+                "request_id": request_id,
+            }
+        });
+        let mut response =
+            warp::reply::with_status(warp::reply::json(&body), self.status).into_response();
+        let headers = response.headers_mut();
+        headers.insert(REQUEST_ID_HEADER, request_id.parse().unwrap());
+        if let Some(retry_after) = self.retry_after {
+            headers.insert(header::RETRY_AFTER, retry_after.into());
+        }
+        response
+    }
+}
+
+/// Answers API errors, and queries and bodies that couldn't be parsed, with the envelope. Other rejections
+/// are passed on, so that the routes after the API still get their turn.
+pub async fn recover(rejection: Rejection) -> Result<Response, Rejection> {
+    if let Some(err) = rejection.find::<ApiError>() {
+        Ok(err.respond())
+    } else if let Some(err) = rejection.find::<InvalidQuery>() {
+        Ok(ApiError::invalid(&err.to_string()).respond())
+    } else if let Some(err) = rejection.find::<BodyDeserializeError>() {
+        Ok(ApiError::invalid(&err.to_string()).respond())
+    } else if let Some(err) = rejection.find::<PayloadTooLarge>() {
+        Ok(ApiError::too_large(&err.to_string()).respond())
+    } else {
+        Err(rejection)
+    }
+}
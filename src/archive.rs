@@ -0,0 +1,51 @@
+//! Self-contained copies of the product list, as written by `apk snapshot export <file>`. Serving
+//! one with `apk snapshot import <file>` shows exactly the list it was taken from, which makes
+//! bugs in the rendering easy to reproduce.
+
+use crate::error::{Error, Result};
+use crate::status::unix_time;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+use systemet::Product;
+
+/// Bumped whenever the format changes in a way older versions can't read.
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Archive {
+    pub version: u32,
+    /// Unix time
+    pub exported_at: u64,
+    /// The version of apk that wrote the archive
+    pub generator: String,
+    pub products: Vec<Product>,
+}
+
+impl Archive {
+    pub fn new(products: Vec<Product>, at: SystemTime) -> Archive {
+        Archive {
+            version: VERSION,
+            exported_at: unix_time(at),
+            generator: format!("apk {}", env!("CARGO_PKG_VERSION")),
+            products,
+        }
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Archive> {
+        let archive: Archive = serde_json::from_slice(&fs::read(path)?)?;
+        if archive.version > VERSION {
+            return Err(Error::Config(format!(
+                "archive version {} is newer than the supported {}",
+                archive.version, VERSION
+            )));
+        }
+        Ok(archive)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
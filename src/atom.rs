@@ -0,0 +1,143 @@
+//! An Atom feed of the top of the list at `/feed.atom`, with an entry whenever a product enters the
+//! top [`TOP`] of its category, or one already there gets cheaper.
+
+use crate::catalog::{self, Catalog, CATEGORIES};
+use crate::dates;
+use crate::diff;
+use crate::error::Result;
+use crate::feed::{escape, Item};
+use crate::notify::{Notifier, RefreshEvent};
+use crate::state::AppState;
+use crate::status::unix_time;
+use crate::storage::{self, Storage};
+use async_trait::async_trait;
+use std::time::{Duration, UNIX_EPOCH};
+use tracing::error;
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+const ENTRIES_KEY: &str = "atom.json";
+/// How far down each category counts as the top
+pub const TOP: usize = 20;
+/// How many entries are kept and shown
+const MAX_ENTRIES: usize = 50;
+
+/// The entries for one refresh. There are none for the first one, since there's nothing to
+/// compare with.
+pub fn entries(event: &RefreshEvent) -> Vec<Item> {
+    let previous = match &event.previous {
+        Some(previous) => &previous.catalog,
+        None => return Vec::new(),
+    };
+    let at = unix_time(event.snapshot.updated_at);
+    let catalog = &event.snapshot.catalog;
+    let mut entries = Vec::new();
+    for &category in CATEGORIES.iter() {
+        for drink in diff::entered_top(previous, catalog, category, TOP) {
+            let title = format!(
+                "Ny i topp {}: {} (APK {:.3})",
+                TOP,
+                catalog::name(drink),
+                catalog::apk(drink).0
+            );
+            entries.push(Item::new(at, drink, title));
+        }
+    }
+    let top = |catalog: &Catalog, id: &str| {
+        CATEGORIES.iter().any(|&category| {
+            catalog
+                .get(category)
+                .iter()
+                .take(TOP)
+                .any(|drink| catalog::id(drink) == id)
+        })
+    };
+    for change in &event.diff.price_changes {
+        if change.new_price >= change.old_price
+            || !top(previous, &change.id)
+            || !top(catalog, &change.id)
+        {
+            continue;
+        }
+        if let Some(drink) = catalog.find(&change.id) {
+            let title = format!(
+                "Billigare: {}: {} → {}",
+                catalog::name(drink),
+                change.old_price,
+                change.new_price
+            );
+            entries.push(Item::new(at, drink, title));
+        }
+    }
+    entries
+}
+
+/// The stored entries, newest first.
+pub fn load(storage: &dyn Storage) -> Result<Vec<Item>> {
+    Ok(storage::load_json(storage, ENTRIES_KEY)?.unwrap_or_default())
+}
+
+/// Keeps the Atom entries of each refresh.
+pub struct AtomRecorder;
+
+#[async_trait]
+impl Notifier for AtomRecorder {
+    fn name(&self) -> &str {
+        "atom"
+    }
+
+    async fn notify(&self, state: &AppState, event: &RefreshEvent) -> Result<()> {
+        let mut entries = entries(event);
+        if entries.is_empty() {
+            return Ok(());
+        }
+        entries.extend(load(&*state.storage)?);
+        entries.truncate(MAX_ENTRIES);
+        storage::save_json(&*state.storage, ENTRIES_KEY, &entries)
+    }
+}
+
+fn timestamp(at: u64) -> String {
+    dates::timestamp(UNIX_EPOCH + Duration::from_secs(at))
+}
+
+/// An Atom document of `entries`.
+pub fn render(entries: &[Item]) -> String {
+    let updated = entries.first().map_or(0, |entry| entry.at);
+    let entries: String = entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "<entry><title>{}</title><link href=\"{}\"/><id>urn:apk:{}:{}</id><updated>{}</updated><category term=\"{}\"/></entry>",
+                escape(&entry.title),
+                escape(&entry.link),
+                escape(&entry.id),
+                entry.at,
+                timestamp(entry.at),
+                entry.category.slug()
+            )
+        })
+        .collect();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\"><title>APK – topp {}</title><id>urn:apk:top</id><updated>{}</updated><author><name>APK</name></author>{}</feed>\n",
+        TOP,
+        timestamp(updated),
+        entries
+    )
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("feed.atom").map(move || match load(&*state.storage) {
+        Ok(entries) => warp::reply::with_header(
+            render(&entries),
+            "Content-Type",
+            "application/atom+xml; charset=utf-8",
+        )
+        .into_response(),
+        Err(err) => {
+            error!("{}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    })
+}
@@ -0,0 +1,93 @@
+//! An append-only log of every time the snapshot being served is swapped, with what caused it,
+//! so operators can tell what the site was serving when. Shown at `/admin/audit`.
+
+use crate::diff::Diff;
+use crate::error::Result;
+use crate::refresh::Snapshot;
+use crate::state::AppState;
+use crate::status::unix_time;
+use crate::storage::{self, Storage};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+const AUDIT_KEY: &str = "audit.json";
+
+/// What swapped the snapshot.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Trigger {
+    /// The refresh job, or [`crate::server::ApkServer::update`]
+    Refresh,
+    /// A category shown on its own while refreshing one at a time
+    Stagger,
+    /// An admin, at `/admin/recategorize`
+    Recategorize,
+    /// Reloading the templates, see [`crate::reload`]
+    Reload,
+    /// Going back to the snapshot from before a refresh that looked wrong
+    Revert,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Unix timestamp of the swap
+    pub at: u64,
+    pub trigger: Trigger,
+    /// Who asked, for admin actions, by the name of their admin token, see [`crate::admin`]
+    pub by: Option<String>,
+    /// Where the products came from, see [`crate::source::ProductSource::name`]
+    pub source: String,
+    pub products: usize,
+    /// Of the new snapshot, see [`Snapshot::hash`]
+    pub hash: String,
+    pub added: usize,
+    pub removed: usize,
+    pub price_changes: usize,
+}
+
+impl Entry {
+    pub fn new(
+        trigger: Trigger,
+        by: Option<&str>,
+        source: String,
+        snapshot: &Snapshot,
+        diff: &Diff,
+    ) -> Entry {
+        Entry {
+            at: unix_time(snapshot.updated_at),
+            trigger,
+            by: by.map(str::to_string),
+            source,
+            products: snapshot.catalog.len(),
+            hash: snapshot.hash.clone(),
+            added: diff.added.len(),
+            removed: diff.removed.len(),
+            price_changes: diff.price_changes.len(),
+        }
+    }
+}
+
+/// Every entry, oldest first.
+pub fn entries(storage: &dyn Storage) -> Result<Vec<Entry>> {
+    Ok(storage::load_json(storage, AUDIT_KEY)?.unwrap_or_default())
+}
+
+/// Adds `entry` at the end. Callers must not append concurrently.
+pub fn append(storage: &dyn Storage, entry: Entry) -> Result<()> {
+    let mut entries = entries(storage)?;
+    entries.push(entry);
+    storage::save_json(storage, AUDIT_KEY, &entries)
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("admin" / "audit").map(move || match entries(&*state.storage) {
+        Ok(entries) => warp::reply::json(&entries).into_response(),
+        Err(err) => {
+            error!("{}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    })
+}
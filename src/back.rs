@@ -0,0 +1,21 @@
+//! Where to go after posting a form, given in its `back` field.
+
+use std::collections::HashMap;
+
+/// Whether `path` is on this site. Browsers read a backslash as a slash, so `/\evil.example`
+/// would go elsewhere just like `//evil.example`.
+pub fn is_local(path: &str) -> bool {
+    let mut chars = path.chars();
+    chars.next() == Some('/')
+        && !matches!(chars.next(), Some('/') | Some('\\'))
+        && !path.contains(|c: char| c == '\\' || c.is_control())
+}
+
+/// The `back` of `form` if it's on this site, or else the list, so the forms can't be used to
+/// redirect elsewhere.
+pub fn back(form: &HashMap<String, String>) -> String {
+    match form.get("back") {
+        Some(back) if is_local(back) => back.clone(),
+        _ => "/".to_string(),
+    }
+}
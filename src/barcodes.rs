@@ -0,0 +1,68 @@
+//! EAN barcodes of the products, so that scanning a bottle in the store finds it. They're taken
+//! from the upstream data where it has them, and from a file kept by the operator, named by
+//! `barcodes` in the config, with a line like `7310401000103,100103` per barcode. The second
+//! column is a product id or product number, and lines starting with `#` are comments.
+
+use crate::catalog::{self, Catalog};
+use crate::error::{Error, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// The fields upstream keeps barcodes in, on the products that have them.
+const UPSTREAM_FIELDS: [&str; 3] = ["Ean", "EAN", "Gtin"];
+
+/// Product ids by barcode.
+pub type Barcodes = HashMap<String, String>;
+
+/// Whether `code` looks like an EAN-8 or EAN-13.
+pub fn is_ean(code: &str) -> bool {
+    (code.len() == 8 || code.len() == 13) && code.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Reads a mapping file, see the module docs.
+pub fn read(path: impl AsRef<Path>) -> Result<Vec<(String, String)>> {
+    let path = path.as_ref();
+    fs::read_to_string(path)?
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| {
+            let mut columns = line.split(',').map(str::trim);
+            match (columns.next(), columns.next()) {
+                (Some(ean), Some(product)) if is_ean(ean) && !product.is_empty() => {
+                    Ok((ean.to_string(), product.to_string()))
+                }
+                _ => Err(Error::Config(format!(
+                    "{}:{}: expected an EAN and a product",
+                    path.display(),
+                    i + 1
+                ))),
+            }
+        })
+        .collect()
+}
+
+/// The barcodes of the products in `catalog`, from upstream and then from `mapping`, which wins
+/// where they disagree. Barcodes for products that aren't in the catalog are left out.
+pub fn build(catalog: &Catalog, mapping: &[(String, String)]) -> Result<Barcodes> {
+    let mut barcodes = Barcodes::new();
+    for drink in catalog.products() {
+        let fields = serde_json::to_value(drink)?;
+        for field in UPSTREAM_FIELDS.iter() {
+            if let Some(ean) = fields.get(field).and_then(Value::as_str) {
+                if is_ean(ean) {
+                    barcodes.insert(ean.to_string(), catalog::id(drink).to_string());
+                }
+            }
+        }
+    }
+    for (ean, product) in mapping {
+        if let Some(drink) = catalog.find(product) {
+            barcodes.insert(ean.clone(), catalog::id(drink).to_string());
+        }
+    }
+    Ok(barcodes)
+}
@@ -0,0 +1,48 @@
+//! `/buy/{id}`, redirecting to the product's page on systembolaget.se, so that the format of
+//! Systembolaget's URLs is only known here. The pages link there instead of to Systembolaget, and
+//! the bots use [`url`]. The redirects are counted for `/metrics`.
+
+use crate::catalog;
+use crate::state::AppState;
+use std::sync::atomic::Ordering;
+use systemet::Product;
+use warp::http::{header, StatusCode};
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// Where every product page at Systembolaget is
+pub const PREFIX: &str = "https://www.systembolaget.se/";
+
+/// The page at Systembolaget of the product with `number`.
+fn page(number: &str) -> String {
+    format!("{}{}/", PREFIX, number)
+}
+
+/// The page of `drink` at Systembolaget, by its product number if it has one.
+pub fn url(drink: &Product) -> String {
+    page(catalog::number(drink).unwrap_or_else(|| catalog::id(drink)))
+}
+
+/// Where to send someone buying the product with `id`. Products that aren't listed, like coming
+/// ones, are linked by their product number, which is all digits too.
+fn target(state: &AppState, id: &str) -> Option<String> {
+    let snapshot = state.snapshot.read().unwrap().clone();
+    match snapshot
+        .as_ref()
+        .and_then(|snapshot| snapshot.catalog.find(id))
+    {
+        Some(drink) => Some(url(drink)),
+        None if !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()) => Some(page(id)),
+        None => None,
+    }
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("buy" / String).map(move |id: String| match target(&state, &id) {
+        Some(target) => {
+            state.buys.fetch_add(1, Ordering::Relaxed);
+            warp::reply::with_header(StatusCode::FOUND, header::LOCATION, target).into_response()
+        }
+        None => StatusCode::NOT_FOUND.into_response(),
+    })
+}
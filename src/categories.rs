@@ -0,0 +1,61 @@
+//! A page for each category, like `/ol` for just the beer, to keep pages small and let people
+//! bookmark the one list they care about.
+
+use crate::catalog::{Category, CATEGORIES};
+use crate::session;
+use crate::shed;
+use crate::state::AppState;
+use crate::tried;
+use crate::view::{self, View};
+use warp::reply::Response;
+use warp::{Filter, Rejection};
+
+/// The path of the page of `category`.
+pub fn path(category: Category) -> &'static str {
+    match category {
+        Category::Beer => "/ol",
+        Category::Wine => "/vin",
+        Category::Cider => "/cider",
+        Category::Liquor => "/sprit",
+        Category::Other => "/annat",
+    }
+}
+
+/// The category whose page is at `/{segment}`.
+pub fn from_path(segment: &str) -> Option<Category> {
+    CATEGORIES
+        .iter()
+        .copied()
+        .find(|&category| path(category)[1..] == *segment)
+}
+
+/// The view of just `category`.
+pub fn view(category: Category) -> View {
+    View {
+        category: Some(category),
+        ..View::default()
+    }
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!(String)
+        .and_then(|segment: String| async move {
+            from_path(&segment).ok_or_else(warp::reject::not_found)
+        })
+        .and(warp::cookie::optional(tried::COOKIE))
+        .and(warp::cookie::optional(session::COOKIE))
+        .and_then(
+            move |category: Category, tried: Option<String>, session: Option<String>| {
+                let state = state.clone();
+                async move {
+                    let view = view(category);
+                    let shown = view.clone();
+                    let response = shed::run(&state, "categories", &view, move |state| {
+                        view::show(state, &shown, tried.as_deref(), session.as_deref())
+                    })
+                    .await;
+                    Ok::<_, Rejection>(response)
+                }
+            },
+        )
+}
@@ -0,0 +1,140 @@
+//! What changed in the last refresh that changed anything, at `/changes`: new products, products
+//! that are gone, and new prices. Quicker to read than going through the whole list again.
+
+use crate::catalog::{self, Category};
+use crate::dates;
+use crate::error::Result;
+use crate::notify::{Notifier, RefreshEvent};
+use crate::render;
+use crate::state::AppState;
+use crate::storage::{self, Storage};
+use crate::units::{Apk, Measures, Sek};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use systemet::Product;
+use tracing::error;
+use warp::http::StatusCode;
+use warp::reply::{html, Response};
+use warp::{Filter, Rejection, Reply};
+
+const CHANGES_KEY: &str = "changes.json";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Change {
+    pub id: String,
+    pub name: String,
+    pub category: Category,
+    pub price: Sek,
+    pub apk: Apk,
+    /// For price changes
+    pub old_price: Option<Sek>,
+    pub old_apk: Option<Apk>,
+}
+
+impl Change {
+    fn new(drink: &Product) -> Change {
+        Change {
+            id: catalog::id(drink).to_string(),
+            name: catalog::name(drink).to_string(),
+            category: catalog::categorize(drink),
+            price: drink.shelf_price(),
+            apk: catalog::apk(drink),
+            old_price: None,
+            old_apk: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Changes {
+    /// The date of the refresh, `YYYY-MM-DD`
+    pub date: String,
+    pub added: Vec<Change>,
+    pub removed: Vec<Change>,
+    /// Biggest change in APK first
+    pub price_changes: Vec<Change>,
+}
+
+/// The changes of one refresh, with the products looked up in the catalog they're in.
+pub fn changes(event: &RefreshEvent) -> Changes {
+    let catalog = &event.snapshot.catalog;
+    let added = event
+        .diff
+        .added
+        .iter()
+        .filter_map(|id| catalog.find(id))
+        .map(Change::new)
+        .collect();
+    let removed = match &event.previous {
+        Some(previous) => event
+            .diff
+            .removed
+            .iter()
+            .filter_map(|id| previous.catalog.find(id))
+            .map(Change::new)
+            .collect(),
+        None => Vec::new(),
+    };
+    let price_changes = event
+        .diff
+        .top_changes(event.diff.price_changes.len())
+        .into_iter()
+        .filter_map(|change| {
+            Some(Change {
+                old_price: Some(change.old_price),
+                old_apk: Some(change.old_apk),
+                ..Change::new(catalog.find(&change.id)?)
+            })
+        })
+        .collect();
+    Changes {
+        date: dates::date(event.snapshot.updated_at),
+        added,
+        removed,
+        price_changes,
+    }
+}
+
+/// The changes of the last refresh that had any.
+pub fn load(storage: &dyn Storage) -> Result<Changes> {
+    Ok(storage::load_json(storage, CHANGES_KEY)?.unwrap_or_default())
+}
+
+/// Keeps the changes of each refresh that changed anything.
+pub struct ChangesRecorder;
+
+#[async_trait]
+impl Notifier for ChangesRecorder {
+    fn name(&self) -> &str {
+        "changes"
+    }
+
+    async fn notify(&self, state: &AppState, event: &RefreshEvent) -> Result<()> {
+        let changes = changes(event);
+        // Restocks alone aren't worth replacing the last changes for
+        if changes.added.is_empty()
+            && changes.removed.is_empty()
+            && changes.price_changes.is_empty()
+        {
+            return Ok(());
+        }
+        storage::save_json(&*state.storage, CHANGES_KEY, &changes)
+    }
+}
+
+fn page(state: &AppState) -> Result<String> {
+    Ok(render::render_changes(
+        &state.tera(),
+        &load(&*state.storage)?,
+    )?)
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("changes").map(move || match page(&state) {
+        Ok(page) => html(page).into_response(),
+        Err(err) => {
+            error!("{}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    })
+}
@@ -0,0 +1,585 @@
+use crate::catalog::{AssortmentRules, Category, CATEGORIES};
+use crate::error::{Error, Result};
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_ENV_VAR: &str = "APK_CONFIG";
+
+/// Optional settings read from the TOML file named by `APK_CONFIG`. Everything has a default, so
+/// running without a config file works just like before.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Key for signing cookies, so they stay valid across restarts
+    pub cookie_secret: Option<SecretString>,
+    /// Ask visitors to confirm their age before showing the list
+    pub age_gate: bool,
+    /// Leave out Basen APK, what drinks had cost if sold in Basen, from the default scorers and the
+    /// pages
+    pub hide_basen: bool,
+    pub webhook: Option<WebhookConfig>,
+    /// More webhooks, each with its own settings
+    pub webhooks: Vec<WebhookConfig>,
+    pub discord: Option<DiscordConfig>,
+    pub telegram: Option<TelegramConfig>,
+    pub slack: Option<SlackConfig>,
+    pub email: Option<EmailConfig>,
+    pub push: Option<PushConfig>,
+    pub matrix: Option<MatrixConfig>,
+    pub mqtt: Option<MqttConfig>,
+    pub mastodon: Option<MastodonConfig>,
+    pub ntfy: Option<NtfyConfig>,
+    pub api: Option<ApiConfig>,
+    /// Who may use the `/admin` pages, see [`crate::admin`]
+    pub admin: Option<AdminConfig>,
+    pub venues: Vec<VenueConfig>,
+    pub images: Option<ImagesConfig>,
+    pub launch_plan: Option<LaunchPlanConfig>,
+    pub stores: Option<StoresConfig>,
+    /// A file of EAN barcodes and the products they're on, see [`crate::barcodes`]
+    pub barcodes: Option<PathBuf>,
+    /// Where to keep the price of each product at each refresh, see [`crate::prices`]
+    pub price_history: Option<PriceHistoryConfig>,
+    /// How allergen and ingredient tags are given to products, see [`crate::allergens`]
+    pub allergens: Vec<AllergenConfig>,
+    /// Curated views, linked from the list and served at `/view/{slug}`
+    pub presets: Vec<PresetConfig>,
+    pub crawl: CrawlConfig,
+    pub fallback: Option<FallbackConfig>,
+    /// Copy another instance instead of fetching from upstream
+    pub mirror: Option<MirrorConfig>,
+    /// Upload the exports of each refresh to object storage, see [`crate::publish`]
+    pub publish: Option<PublishConfig>,
+    /// Fetch and show one category at a time, so fresh beer doesn't wait for all the wine
+    pub stagger: bool,
+    /// Icons by category name, like `Öl = "🍺"`, replacing the default ones
+    pub icons: HashMap<String, String>,
+    pub load: LoadConfig,
+    pub stock: Option<StockConfig>,
+    pub log: LogConfig,
+    /// Log every request, see [`crate::access`]
+    pub access_log: bool,
+    /// Reverse proxies in front, whose `X-Forwarded-For` tells who a request is from, see
+    /// [`crate::access::client_address`]
+    pub trusted_proxies: Vec<IpAddr>,
+    pub assortment: AssortmentConfig,
+    /// How many decimals numbers are shown with, see [`crate::display`]
+    pub display: DisplayConfig,
+    /// How product names are cleaned up and made into slugs, see [`crate::names`]
+    pub names: NamesConfig,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Key used to sign the payload, see [`crate::webhook`].
+    pub secret: Option<SecretString>,
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// A Tera template for the body, instead of the JSON summary. The summary's fields and the
+    /// full `diff` are available in it.
+    pub template: Option<String>,
+    /// Defaults to `application/json`
+    pub content_type: Option<String>,
+    /// Only post when a new product has at least this APK, or one enters the top `top`
+    pub min_apk: Option<f64>,
+    /// Only post when a product enters the top this many of its category, or one beats `min_apk`
+    pub top: Option<usize>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct DiscordConfig {
+    /// The Discord webhook URL
+    pub url: String,
+    /// Only post about these categories. Defaults to all of them.
+    pub categories: Option<Vec<Category>>,
+    /// How many products must enter a top 10 before it's posted.
+    #[serde(default = "default_min_changes")]
+    pub min_changes: usize,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TelegramConfig {
+    /// The bot token from @BotFather
+    pub token: SecretString,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SlackConfig {
+    /// The app's signing secret, used to verify slash command requests
+    pub signing_secret: SecretString,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct EmailConfig {
+    pub smtp_host: String,
+    pub smtp_port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<SecretString>,
+    /// Sender, like `APK <apk@example.com>`
+    pub from: String,
+    /// Where the site is reachable, for unsubscribe links
+    pub base_url: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PushConfig {
+    /// The VAPID private key, a PEM file
+    pub private_key: PathBuf,
+    /// The matching public key, base64url encoded, as browsers want it
+    pub public_key: String,
+    /// Contact for push services, like `mailto:apk@example.com`
+    pub subject: String,
+    /// The hosts of the push services subscriptions may point at, subdomains included. Browsers'
+    /// own services by default.
+    #[serde(default = "default_push_services")]
+    pub services: Vec<String>,
+    /// Subscriptions kept at most, since each refresh posts to all of them. New ones are turned
+    /// away beyond that.
+    #[serde(default = "default_max_subscriptions")]
+    pub max_subscriptions: usize,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MatrixConfig {
+    /// Like `https://matrix.example.com`
+    pub homeserver: String,
+    /// Of the bot user, which must already have joined the room
+    pub access_token: SecretString,
+    /// The room id, like `!abcdef:example.com`
+    pub room: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MqttConfig {
+    /// The broker's host name
+    pub host: String,
+    #[serde(default = "default_mqtt_port")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<SecretString>,
+    /// Prepended to all topics
+    #[serde(default = "default_mqtt_prefix")]
+    pub prefix: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MastodonConfig {
+    /// Like `https://mastodon.social`
+    pub instance: String,
+    /// Needs the `write:statuses` scope
+    pub access_token: SecretString,
+    /// A Tera template for the toots, see [`crate::mastodon::DEFAULT_TEMPLATE`].
+    pub template: Option<String>,
+    /// Seconds to wait between toots. Anything more is skipped.
+    #[serde(default = "default_min_interval")]
+    pub min_interval: u64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct NtfyConfig {
+    /// The topic, like `https://ntfy.sh/my-apk-alerts`
+    pub url: String,
+    /// For topics that need authentication
+    pub token: Option<SecretString>,
+    /// Only alert about these product ids. Defaults to all price drops and restocks.
+    #[serde(default)]
+    pub watch: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ApiConfig {
+    pub tokens: Vec<ApiToken>,
+}
+
+/// A token issued to someone using the API.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ApiToken {
+    /// Who it was issued to, as shown in the usage stats
+    pub name: String,
+    pub token: SecretString,
+    /// Requests allowed per day, counted in UTC. Unlimited if not set.
+    pub quota: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AdminConfig {
+    pub tokens: Vec<AdminToken>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AdminToken {
+    /// Who it was issued to, as written to the audit log
+    pub name: String,
+    pub token: SecretString,
+}
+
+/// A pub or bar with its own list, served at `/v/{slug}` and at `/` for its `host`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct VenueConfig {
+    pub slug: String,
+    pub name: String,
+    /// Like `karhuset.apk.example.com`
+    pub host: Option<String>,
+    /// The shelf price is multiplied by this. There's no deposit at the bar.
+    #[serde(default = "default_markup")]
+    pub markup: f64,
+    /// Prices are rounded up to a multiple of this, like 5 kr
+    pub rounding: Option<f64>,
+    /// Product ids or numbers the venue doesn't serve
+    #[serde(default)]
+    pub blocklist: Vec<String>,
+    /// A glob of templates to use instead of the default ones
+    pub theme: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ImagesConfig {
+    /// Where resized images are kept
+    pub cache_dir: PathBuf,
+    /// The full size image of a product, with `{id}` replaced by its id
+    #[serde(default = "default_image_url")]
+    pub url: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LaunchPlanConfig {
+    /// A product list of upcoming launches, paged like the main one
+    pub url: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PriceHistoryConfig {
+    /// The SQLite database, created if missing
+    pub path: PathBuf,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StoresConfig {
+    /// A JSON list of stores, each with an id, a name and a city
+    pub url: String,
+}
+
+/// A secondary source of products, for when the API is down.
+#[derive(Clone, Debug, Deserialize)]
+pub struct FallbackConfig {
+    /// A JSON file with all products, like a nightly published dump
+    pub url: String,
+    /// Seconds the API must have been failing before the fallback is used
+    #[serde(default = "default_fallback_after")]
+    pub after: u64,
+}
+
+/// Which stores' stock to keep track of, see [`crate::stock`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct StockConfig {
+    /// The stock balance of a store, with `{store}` replaced by its id
+    pub url: String,
+    pub stores: Vec<StockStore>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StockStore {
+    pub id: String,
+    /// As shown in the store picker, like `Umeå Avion`
+    pub name: String,
+}
+
+/// A read-only copy of another instance, see [`crate::source::MirrorSource`]. Mirrors need no API
+/// key, and leave notifying and the bots to the primary.
+#[derive(Clone, Debug, Deserialize)]
+pub struct MirrorConfig {
+    /// The primary, like `https://apk.example.com`
+    pub url: String,
+}
+
+/// An S3-compatible bucket, see [`crate::publish`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct PublishConfig {
+    /// Like `https://s3.eu-north-1.amazonaws.com`. The bucket goes in the path.
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: SecretString,
+    /// Put before every key, like `apk/`
+    #[serde(default)]
+    pub prefix: String,
+}
+
+/// Limits on rendering views on demand, see [`crate::shed`].
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct LoadConfig {
+    /// Milliseconds a view may take to render before a stale page is served instead
+    pub timeout: u64,
+    /// Timeouts by route, like `categories = 2000`, overriding `timeout`
+    pub timeouts: HashMap<String, u64>,
+    /// Milliseconds a view should take to render. Once one takes longer, only stale pages are
+    /// served for `cooldown` seconds. No shedding if not set.
+    pub budget: Option<u64>,
+    pub cooldown: u64,
+    /// Views rendered at once at most, counting the ones given up on that are still running.
+    /// Stale pages are served for any more.
+    pub max_renders: usize,
+}
+
+impl Default for LoadConfig {
+    fn default() -> LoadConfig {
+        LoadConfig {
+            timeout: 10_000,
+            timeouts: HashMap::new(),
+            budget: None,
+            cooldown: 30,
+            max_renders: 8,
+        }
+    }
+}
+
+/// Which of Systembolaget's assortments are shown, like `exclude = ["BS", "TSLS"]`, which is the
+/// default. The rules under `views.api` and `views.html` apply to the API and the pages on top of
+/// that, so they can only leave out more, since the catalog is built with the base rules.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AssortmentConfig {
+    #[serde(flatten)]
+    pub rules: AssortmentRules,
+    pub views: HashMap<String, AssortmentRules>,
+}
+
+impl AssortmentConfig {
+    pub const API: &'static str = "api";
+    pub const HTML: &'static str = "html";
+
+    /// The extra rules for the view named `name`, if there are any.
+    pub fn view(&self, name: &str) -> Option<&AssortmentRules> {
+        self.views.get(name)
+    }
+}
+
+/// The decimals of each kind of number, like `apk = 3`, on the pages, in the API and in exports.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    /// Prices, shelf or Basen, in kronor
+    pub price: usize,
+    /// Prices per liter, per 75 cl or per standard drink
+    pub unit_price: usize,
+    pub apk: usize,
+    /// Alcohol by volume, in percent
+    pub abv: usize,
+    /// Grams per liter
+    pub sugar: usize,
+    pub standard_drinks: usize,
+    /// Grams of alcohol
+    pub grams: usize,
+    pub kcal: usize,
+    pub rating: usize,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> DisplayConfig {
+        DisplayConfig {
+            price: 2,
+            unit_price: 2,
+            apk: 5,
+            abv: 1,
+            sugar: 1,
+            standard_drinks: 1,
+            grams: 1,
+            kcal: 0,
+            rating: 1,
+        }
+    }
+}
+
+/// How product names are cleaned up, like `replace = { "Ipa" = "IPA" }`, for showing and for
+/// their slugs.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct NamesConfig {
+    /// Leave out years, so that the vintages of a wine share a name
+    pub strip_vintage: bool,
+    /// Write volumes the same way, like `33 cl` for `33CL`
+    pub volumes: bool,
+    /// Words replaced in every name, ignoring case, before the rest. Replacing with nothing leaves
+    /// the word out.
+    pub replace: BTreeMap<String, String>,
+    /// Slugs by product id, for when the one made from the name won't do
+    pub slugs: HashMap<String, String>,
+}
+
+impl Default for NamesConfig {
+    fn default() -> NamesConfig {
+        NamesConfig {
+            strip_vintage: true,
+            volumes: true,
+            replace: BTreeMap::new(),
+            slugs: HashMap::new(),
+        }
+    }
+}
+
+/// How much to log and how, see [`crate::logging`].
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    /// Like `info` or `apk=debug,warp=warn`. `APK_LOG` overrides it, and it defaults to `info`.
+    pub level: Option<String>,
+    /// One JSON object per line, for log aggregators, instead of plain text
+    pub json: bool,
+}
+
+/// Hints for search engines, see [`crate::crawl`].
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CrawlConfig {
+    /// Like `https://apk.example.com`, for absolute URLs in the sitemap and canonical links
+    pub base_url: Option<String>,
+    /// Path prefixes not to index, on top of the heavy and private ones like `/api`
+    pub noindex: Vec<String>,
+    /// Sitemap priorities by path, overriding the defaults
+    pub priorities: HashMap<String, f64>,
+}
+
+/// A named view, like "Fredagsöl" for beer at most 20 kr with at least 5%.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PresetConfig {
+    pub slug: String,
+    pub name: String,
+    /// The filters and sort as a query string, like `kategori=öl&maxpris=20&minalkohol=5`
+    pub query: String,
+}
+
+/// A tag, like `Gluten` or `Sulfiter`, given to the products it matches.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AllergenConfig {
+    pub tag: String,
+    /// Only products in these categories. Defaults to all of them.
+    pub categories: Option<Vec<Category>>,
+    /// Words in the name or subcategory that give the tag, ignoring case. If empty, every product
+    /// in the categories gets it.
+    #[serde(default)]
+    pub words: Vec<String>,
+    /// Words that rule the tag out, like `glutenfri`
+    #[serde(default)]
+    pub except: Vec<String>,
+}
+
+fn default_icon(category: Category) -> &'static str {
+    match category {
+        Category::Beer => "🍺",
+        Category::Wine => "🍷",
+        Category::Cider => "🍏",
+        Category::Liquor => "🥃",
+        Category::Other => "🍹",
+    }
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+fn default_min_changes() -> usize {
+    1
+}
+
+fn default_min_interval() -> u64 {
+    60 * 60
+}
+
+fn default_image_url() -> String {
+    "https://product-cdn.systembolaget.se/productimages/{id}/{id}_400.png".to_string()
+}
+
+fn default_max_subscriptions() -> usize {
+    10_000
+}
+
+fn default_push_services() -> Vec<String> {
+    [
+        "fcm.googleapis.com",
+        "push.services.mozilla.com",
+        "notify.windows.com",
+        "push.apple.com",
+    ]
+    .iter()
+    .map(|host| host.to_string())
+    .collect()
+}
+
+fn default_fallback_after() -> u64 {
+    6 * 60 * 60
+}
+
+fn default_markup() -> f64 {
+    1.0
+}
+
+fn default_mqtt_port() -> u16 {
+    1883
+}
+
+fn default_mqtt_prefix() -> String {
+    "apk".to_string()
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> Result<Config> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        toml::from_str(&contents)
+            .map_err(|err| Error::Config(format!("{}: {}", path.display(), err)))
+    }
+
+    /// Loads the file named by `APK_CONFIG`, or the defaults if it isn't set.
+    pub fn from_env() -> Result<Config> {
+        match env::var(CONFIG_ENV_VAR) {
+            Ok(path) => Config::load(path),
+            Err(_) => Ok(Config::default()),
+        }
+    }
+
+    /// Which of the optional parts are turned on, by name, so that templates can leave out what
+    /// isn't there.
+    pub fn features(&self) -> BTreeMap<&'static str, bool> {
+        let mut features = BTreeMap::new();
+        features.insert("age_gate", self.age_gate);
+        features.insert("api", self.api.is_some());
+        features.insert("basen", !self.hide_basen);
+        features.insert("email", self.email.is_some());
+        features.insert("history", self.price_history.is_some());
+        features.insert("images", self.images.is_some());
+        features.insert("launch_plan", self.launch_plan.is_some());
+        features.insert("presets", !self.presets.is_empty());
+        features.insert("push", self.push.is_some());
+        features.insert("slack", self.slack.is_some());
+        features.insert("stock", self.stock.is_some());
+        features.insert("stores", self.stores.is_some());
+        features.insert("venues", !self.venues.is_empty());
+        features
+    }
+
+    /// The icon of each category, by name.
+    pub fn icons(&self) -> HashMap<&'static str, String> {
+        CATEGORIES
+            .iter()
+            .map(|&category| {
+                let icon = self
+                    .icons
+                    .iter()
+                    .find(|(name, _)| Category::from_name(name) == Some(category))
+                    .map_or_else(
+                        || default_icon(category).to_string(),
+                        |(_, icon)| icon.clone(),
+                    );
+                (category.name(), icon)
+            })
+            .collect()
+    }
+}
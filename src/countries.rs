@@ -0,0 +1,98 @@
+//! Countries ranked by the median APK of their products, at `/countries`. Worked out at refresh
+//! time, so that the page is cheap.
+
+use crate::catalog::{self, Catalog};
+use crate::render;
+use crate::state::AppState;
+use crate::units::Apk;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use tracing::error;
+use warp::http::StatusCode;
+use warp::reply::{html, Response};
+use warp::{Filter, Rejection, Reply};
+
+/// Countries with fewer products than this aren't ranked, unless asked for
+pub const DEFAULT_MIN_PRODUCTS: usize = 5;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Country {
+    pub name: String,
+    pub products: usize,
+    pub median_apk: Apk,
+    /// The id and name of the best product
+    pub best_id: String,
+    pub best_name: String,
+}
+
+fn median(apks: &mut [f64]) -> f64 {
+    apks.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let middle = apks.len() / 2;
+    if apks.len() % 2 == 0 {
+        (apks[middle - 1] + apks[middle]) / 2.0
+    } else {
+        apks[middle]
+    }
+}
+
+/// Every country in `catalog`, best median APK first.
+pub fn leaderboard(catalog: &Catalog) -> Vec<Country> {
+    let mut by_country: HashMap<&str, Vec<_>> = HashMap::new();
+    for drink in catalog.products() {
+        if let Some(country) = catalog::country(drink) {
+            by_country.entry(country).or_default().push(drink);
+        }
+    }
+    let mut countries: Vec<Country> = by_country
+        .into_iter()
+        .map(|(name, mut drinks)| {
+            drinks.sort_by(|d1, d2| catalog::apk_comparator(d1, d2));
+            let mut apks: Vec<f64> = drinks.iter().map(|drink| catalog::apk(drink).0).collect();
+            Country {
+                name: name.to_string(),
+                products: drinks.len(),
+                median_apk: Apk(median(&mut apks)),
+                best_id: catalog::id(drinks[0]).to_string(),
+                best_name: catalog::name(drinks[0]).to_string(),
+            }
+        })
+        .collect();
+    countries.sort_by(|c1, c2| {
+        c2.median_apk
+            .partial_cmp(&c1.median_apk)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| c1.name.cmp(&c2.name))
+    });
+    countries
+}
+
+#[derive(Deserialize)]
+struct Query {
+    min: Option<usize>,
+}
+
+fn page(state: &AppState, query: Query) -> Response {
+    let min = query.min.unwrap_or(DEFAULT_MIN_PRODUCTS);
+    let snapshot = state.snapshot.read().unwrap().clone();
+    let countries: Vec<&Country> = snapshot.as_ref().map_or(Vec::new(), |snapshot| {
+        snapshot
+            .countries
+            .iter()
+            .filter(|country| country.products >= min)
+            .collect()
+    });
+    match render::render_countries(&state.tera(), &countries, min) {
+        Ok(page) => html(page).into_response(),
+        Err(err) => {
+            error!("{}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("countries")
+        .and(warp::query::<Query>())
+        .map(move |query| page(&state, query))
+}
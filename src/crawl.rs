@@ -0,0 +1,159 @@
+//! Hints for search engines, so they index the clean pages and leave the heavy ones alone:
+//! `X-Robots-Tag: noindex` on filtered views and private or expensive routes, canonical links from
+//! filtered views to their category page, `/robots.txt` and `/sitemap.xml`.
+
+use crate::catalog::CATEGORIES;
+use crate::config::CrawlConfig;
+use crate::state::AppState;
+use crate::view::View;
+use warp::http::header::{self, HeaderValue};
+use warp::path::FullPath;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// Never indexed, whatever the config says
+const NOINDEX: [&str; 12] = [
+    "/api", "/admin", "/metrics", "/grafana", "/kiosk", "/qr", "/img", "/s/", "/age", "/email",
+    "/buy", "/search",
+];
+
+/// The category page of a view of the list, which is all that's worth indexing.
+fn category_page(query: &str) -> View {
+    View {
+        category: View::from_query(query).category,
+        ..View::default()
+    }
+}
+
+/// Whether search engines should leave `path` with `query` out of their index.
+pub fn noindex(config: &CrawlConfig, path: &str, query: &str) -> bool {
+    (path == "/" && category_page(query).to_query() != query)
+        || NOINDEX.iter().any(|prefix| path.starts_with(prefix))
+        || config
+            .noindex
+            .iter()
+            .any(|prefix| path.starts_with(prefix.as_str()))
+}
+
+fn absolute(config: &CrawlConfig, link: &str) -> String {
+    match &config.base_url {
+        Some(base_url) => format!("{}{}", base_url.trim_end_matches('/'), link),
+        None => link.to_string(),
+    }
+}
+
+/// The category page of a filtered view of the list, for `rel="canonical"`.
+pub fn canonical(config: &CrawlConfig, query: &str) -> String {
+    absolute(config, &category_page(query).link())
+}
+
+/// Adds the hints to the responses of `filter`.
+pub fn hints<F, R>(
+    config: CrawlConfig,
+    filter: F,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone
+where
+    F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+    R: Reply,
+{
+    warp::path::full()
+        .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+        .and(filter)
+        .map(move |path: FullPath, query: String, reply: R| {
+            let mut response = reply.into_response();
+            let headers = response.headers_mut();
+            if noindex(&config, path.as_str(), &query) {
+                headers.insert("x-robots-tag", HeaderValue::from_static("noindex"));
+            }
+            if path.as_str() == "/" && category_page(&query).to_query() != query {
+                let link = format!("<{}>; rel=\"canonical\"", canonical(&config, &query));
+                if let Ok(link) = HeaderValue::from_str(&link) {
+                    headers.insert(header::LINK, link);
+                }
+            }
+            response
+        })
+}
+
+/// The pages worth indexing and their priorities.
+pub fn pages(state: &AppState) -> Vec<(String, f64)> {
+    let mut pages = vec![("/".to_string(), 1.0)];
+    for &category in CATEGORIES.iter() {
+        let view = View {
+            category: Some(category),
+            ..View::default()
+        };
+        pages.push((view.link(), 0.8));
+    }
+    for preset in &state.config.presets {
+        pages.push((format!("/view/{}", preset.slug), 0.6));
+    }
+    for path in &["/kommande", "/movers", "/changes", "/countries"] {
+        pages.push((path.to_string(), 0.5));
+    }
+    let config = &state.config.crawl;
+    pages
+        .into_iter()
+        .filter(|(path, _)| !noindex(config, path, ""))
+        .map(|(path, priority)| {
+            let priority = config.priorities.get(&path).copied().unwrap_or(priority);
+            (path, priority)
+        })
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn sitemap(state: &AppState) -> String {
+    let urls: String = pages(state)
+        .iter()
+        .map(|(path, priority)| {
+            format!(
+                "<url><loc>{}</loc><priority>{:.1}</priority></url>",
+                escape(&absolute(&state.config.crawl, path)),
+                priority
+            )
+        })
+        .collect();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">{}</urlset>\n",
+        urls
+    )
+}
+
+pub fn robots(state: &AppState) -> String {
+    let config = &state.config.crawl;
+    let mut robots = "User-agent: *\n".to_string();
+    for prefix in NOINDEX
+        .iter()
+        .copied()
+        .chain(config.noindex.iter().map(String::as_str))
+    {
+        robots.push_str(&format!("Disallow: {}\n", prefix));
+    }
+    if config.base_url.is_some() {
+        robots.push_str(&format!("Sitemap: {}\n", absolute(config, "/sitemap.xml")));
+    }
+    robots
+}
+
+pub fn routes(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let xml = {
+        let state = state.clone();
+        warp::path!("sitemap.xml").map(move || {
+            warp::reply::with_header(
+                sitemap(&state),
+                header::CONTENT_TYPE,
+                "application/xml; charset=utf-8",
+            )
+            .into_response()
+        })
+    };
+    let txt = warp::path!("robots.txt").map(move || robots(&state).into_response());
+    xml.or(txt).unify()
+}
@@ -0,0 +1,89 @@
+//! The few calendar calculations we need, in UTC, without pulling in a date library.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// In seconds
+pub const DAY: u64 = 24 * 60 * 60;
+
+/// Converts days since the epoch to (year, month, day), from Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// The inverse of [`civil_from_days`].
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// `time` as `YYYY-MM-DD`.
+pub fn date(time: SystemTime) -> String {
+    let (year, month, day) = civil_from_days((secs(time) / DAY) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// `time` as `YYYY-MM-DD HH:MM`, for people.
+pub fn date_time(time: SystemTime) -> String {
+    let secs = secs(time) % DAY;
+    format!("{} {:02}:{:02}", date(time), secs / 3600, secs / 60 % 60)
+}
+
+/// `time` in the basic ISO 8601 format used by iCal, like `20201015T120000Z`.
+pub fn basic_timestamp(time: SystemTime) -> String {
+    let secs = secs(time);
+    let (year, month, day) = civil_from_days((secs / DAY) as i64);
+    let secs = secs % DAY;
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// `time` as an RFC 3339 timestamp, like `2020-10-15T12:00:00Z`.
+pub fn timestamp(time: SystemTime) -> String {
+    let secs = secs(time) % DAY;
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        date(time),
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Parses the start of an RFC 3339 timestamp, like `2020-10-15T12:00:00.000Z`, to Unix seconds.
+/// Fractions and offsets are ignored.
+pub fn parse_timestamp(text: &str) -> Option<u64> {
+    let field = |range: std::ops::Range<usize>| text.get(range)?.parse::<u32>().ok();
+    let days = days_from_civil(field(0..4)? as i64, field(5..7)?, field(8..10)?);
+    let secs = field(11..13)? as i64 * 3600 + field(14..16)? as i64 * 60 + field(17..19)? as i64;
+    let total = days * DAY as i64 + secs;
+    if total < 0 {
+        None
+    } else {
+        Some(total as u64)
+    }
+}
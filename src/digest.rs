@@ -0,0 +1,37 @@
+//! The weekly digest of the best products in each category.
+
+use crate::catalog::{self, Catalog, CATEGORIES};
+use crate::text;
+use std::time::{Duration, SystemTime};
+use systemet::Product;
+
+pub const DIGEST_INTERVAL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+pub const DIGEST_TOP: usize = 5;
+/// How many products the email digest lists, across all categories
+pub const EMAIL_TOP: usize = 20;
+
+pub fn text(catalog: &Catalog) -> String {
+    let sections: Vec<String> = CATEGORIES
+        .iter()
+        .map(|&category| text::top(catalog, category, DIGEST_TOP))
+        .collect();
+    format!("Veckans APK!\n\n{}", sections.join("\n\n"))
+}
+
+/// The `n` products with the best APK, whatever their category.
+pub fn best(catalog: &Catalog, n: usize) -> Vec<&Product> {
+    let mut drinks: Vec<&Product> = catalog.products().collect();
+    drinks.sort_by(|d1, d2| catalog::apk_comparator(d1, d2));
+    drinks.truncate(n);
+    drinks
+}
+
+/// Whether a digest last sent at `last_sent` should be sent again.
+pub fn is_due(last_sent: Option<SystemTime>, now: SystemTime) -> bool {
+    match last_sent {
+        Some(last_sent) => now
+            .duration_since(last_sent)
+            .map_or(false, |elapsed| elapsed >= DIGEST_INTERVAL),
+        None => true,
+    }
+}
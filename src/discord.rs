@@ -0,0 +1,103 @@
+//! Posts an embed to a Discord webhook when the top 10 of a category changes.
+
+use crate::catalog::{self, Category, CATEGORIES};
+use crate::config::DiscordConfig;
+use crate::diff;
+use crate::error::Result;
+use crate::notify::{Notifier, RefreshEvent};
+use crate::state::AppState;
+use async_trait::async_trait;
+use serde::Serialize;
+
+const TOP: usize = 10;
+const COLOR: u32 = 0x002244;
+
+#[derive(Debug, Serialize)]
+pub struct Message {
+    pub embeds: Vec<Embed>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Embed {
+    pub title: String,
+    pub description: String,
+    pub color: u32,
+}
+
+pub struct DiscordNotifier {
+    client: reqwest::Client,
+    config: DiscordConfig,
+}
+
+impl DiscordNotifier {
+    pub fn new(config: DiscordConfig) -> DiscordNotifier {
+        DiscordNotifier {
+            client: reqwest::Client::new(),
+            config,
+        }
+    }
+
+    fn watches(&self, category: Category) -> bool {
+        self.config
+            .categories
+            .as_ref()
+            .map_or(true, |categories| categories.contains(&category))
+    }
+
+    /// An embed for each watched category whose top 10 changed enough, if any.
+    pub fn message(&self, event: &RefreshEvent) -> Option<Message> {
+        let previous = event.previous.as_ref()?;
+        let catalog = &event.snapshot.catalog;
+        let embeds: Vec<Embed> = CATEGORIES
+            .iter()
+            .filter(|&&category| self.watches(category))
+            .filter(|&&category| {
+                diff::entered_top(&previous.catalog, catalog, category, TOP).len()
+                    >= self.config.min_changes
+            })
+            .map(|&category| Embed {
+                title: format!("Ny topp {} för {}!", TOP, category.name()),
+                description: catalog
+                    .get(category)
+                    .iter()
+                    .take(TOP)
+                    .enumerate()
+                    .map(|(i, drink)| {
+                        format!(
+                            "{}. {} – {:.3}",
+                            i + 1,
+                            catalog::name(drink),
+                            catalog::apk(drink).0
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                color: COLOR,
+            })
+            .collect();
+        if embeds.is_empty() {
+            None
+        } else {
+            Some(Message { embeds })
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for DiscordNotifier {
+    fn name(&self) -> &str {
+        "discord"
+    }
+
+    async fn notify(&self, _: &AppState, event: &RefreshEvent) -> Result<()> {
+        if let Some(message) = self.message(event) {
+            self.client
+                .post(&self.config.url)
+                .json(&message)
+                .send()
+                .await?
+                .error_for_status()?;
+        }
+        Ok(())
+    }
+}
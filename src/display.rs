@@ -0,0 +1,81 @@
+//! How numbers are rounded for people, in one place, so that the pages, the API and the exports
+//! never disagree about a price. The decimals of each kind of number come from the config, see
+//! [`DisplayConfig`]. Templates get them through the `display` filter, like
+//! `{{drink | apk | display(field="apk")}}`.
+
+use crate::config::DisplayConfig;
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Field {
+    Price,
+    UnitPrice,
+    Apk,
+    Abv,
+    Sugar,
+    StandardDrinks,
+    Grams,
+    Kcal,
+    Rating,
+}
+
+impl Field {
+    /// The field by the name of its setting, like `unit_price`.
+    pub fn from_name(name: &str) -> Option<Field> {
+        match name {
+            "price" => Some(Field::Price),
+            "unit_price" => Some(Field::UnitPrice),
+            "apk" => Some(Field::Apk),
+            "abv" => Some(Field::Abv),
+            "sugar" => Some(Field::Sugar),
+            "standard_drinks" => Some(Field::StandardDrinks),
+            "grams" => Some(Field::Grams),
+            "kcal" => Some(Field::Kcal),
+            "rating" => Some(Field::Rating),
+            _ => None,
+        }
+    }
+}
+
+pub fn precision(config: &DisplayConfig, field: Field) -> usize {
+    match field {
+        Field::Price => config.price,
+        Field::UnitPrice => config.unit_price,
+        Field::Apk => config.apk,
+        Field::Abv => config.abv,
+        Field::Sugar => config.sugar,
+        Field::StandardDrinks => config.standard_drinks,
+        Field::Grams => config.grams,
+        Field::Kcal => config.kcal,
+        Field::Rating => config.rating,
+    }
+}
+
+/// `value` rounded to the decimals of `field`, half away from zero.
+pub fn round(config: &DisplayConfig, field: Field, value: f64) -> f64 {
+    let factor = 10_f64.powi(precision(config, field) as i32);
+    (value * factor).round() / factor
+}
+
+/// `value` with exactly the decimals of `field`.
+pub fn format(config: &DisplayConfig, field: Field, value: f64) -> String {
+    format!(
+        "{:.*}",
+        precision(config, field),
+        round(config, field, value)
+    )
+}
+
+/// The `display` filter, formatting a number as its `field` argument says.
+pub fn filter(
+    config: DisplayConfig,
+) -> impl Fn(&Value, &HashMap<String, Value>) -> tera::Result<Value> + Sync + Send {
+    move |value: &Value, args: &HashMap<String, Value>| {
+        let number: f64 = serde_json::from_value(value.clone())?;
+        let name = args.get("field").and_then(Value::as_str).unwrap_or("");
+        let field = Field::from_name(name)
+            .ok_or_else(|| tera::Error::msg(format!("No display field named {}", name)))?;
+        Ok(Value::String(format(&config, field, number)))
+    }
+}
@@ -0,0 +1,566 @@
+//! Email subscriptions: a weekly digest, plus alerts when a watched product gets cheaper and when
+//! something new matches a saved search. Subscribing and every change to a subscription wait for
+//! the address to confirm them through a signed link, so that nobody can sign up or change an
+//! address that isn't theirs.
+
+use crate::access;
+use crate::alerts;
+use crate::catalog::Catalog;
+use crate::config::EmailConfig;
+use crate::digest;
+use crate::error::{Error, Result};
+use crate::feed;
+use crate::limit::{self, Hourly};
+use crate::notify::{Notifier, RefreshEvent};
+use crate::render;
+use crate::searches::SavedSearch;
+use crate::server::Job;
+use crate::signing;
+use crate::state::AppState;
+use crate::status::unix_time;
+use crate::storage::{self, Storage};
+use crate::text;
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, Message, Tokio02Connector, Tokio02Transport};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::error;
+use warp::reply::{html, Response};
+use warp::{Filter, Rejection, Reply};
+
+const SUBSCRIBERS_KEY: &str = "email-subscribers.json";
+const LAST_DIGEST_KEY: &str = "email-last-digest.json";
+const PENDING_KEY: &str = "email-pending.json";
+const DIGEST_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// How long a confirmation link works, in seconds
+pub const CONFIRM_WITHIN: u64 = 2 * 24 * 60 * 60;
+/// Seconds before another confirmation mail may be sent to the same address
+pub const RESEND_AFTER: u64 = 60 * 60;
+/// The largest form accepted, in bytes
+const MAX_FORM: u64 = 16 * 1024;
+/// Changes waiting for confirmation at most. No more links are mailed while there are this many.
+pub const MAX_PENDING: usize = 1000;
+/// Confirmation links each client may ask for per hour, since they're mailed to anyone
+pub const MAX_ASKED_PER_HOUR: u32 = 5;
+
+fn secret() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .collect()
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Subscriber {
+    pub email: String,
+    /// Secret used in the unsubscribe link
+    pub token: String,
+    /// Product ids to send price alerts about
+    #[serde(default)]
+    pub watch: Vec<String>,
+    #[serde(default)]
+    pub searches: Vec<SavedSearch>,
+}
+
+impl Subscriber {
+    fn new(email: &str, watch: Vec<String>) -> Subscriber {
+        Subscriber {
+            email: email.to_string(),
+            token: secret(),
+            watch,
+            searches: Vec::new(),
+        }
+    }
+}
+
+pub fn subscribers(storage: &dyn Storage) -> Result<Vec<Subscriber>> {
+    Ok(storage::load_json(storage, SUBSCRIBERS_KEY)?.unwrap_or_default())
+}
+
+/// Adds a subscriber, or updates the watched products of an existing one.
+pub fn subscribe(storage: &dyn Storage, email: &str, watch: Vec<String>) -> Result<Subscriber> {
+    let mut subscribers = subscribers(storage)?;
+    let subscriber = match subscribers.iter_mut().find(|s| s.email == email) {
+        Some(subscriber) => {
+            subscriber.watch = watch;
+            subscriber.clone()
+        }
+        None => {
+            let subscriber = Subscriber::new(email, watch);
+            subscribers.push(subscriber.clone());
+            subscriber
+        }
+    };
+    storage::save_json(storage, SUBSCRIBERS_KEY, &subscribers)?;
+    Ok(subscriber)
+}
+
+/// Adds a saved search for `email`, subscribing them if they weren't already.
+pub fn save_search(storage: &dyn Storage, email: &str, search: SavedSearch) -> Result<Subscriber> {
+    let mut subscribers = subscribers(storage)?;
+    let index = match subscribers.iter().position(|s| s.email == email) {
+        Some(index) => index,
+        None => {
+            subscribers.push(Subscriber::new(email, Vec::new()));
+            subscribers.len() - 1
+        }
+    };
+    let subscriber = &mut subscribers[index];
+    if !subscriber.searches.contains(&search) {
+        subscriber.searches.push(search);
+    }
+    let subscriber = subscriber.clone();
+    storage::save_json(storage, SUBSCRIBERS_KEY, &subscribers)?;
+    Ok(subscriber)
+}
+
+/// A change to the subscription of an address.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    /// Subscribe, or replace the watched products
+    Watch(Vec<String>),
+    SaveSearch(SavedSearch),
+}
+
+/// A change waiting for its address to confirm it, see [`request`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Pending {
+    /// Secret in the confirmation link
+    pub id: String,
+    pub email: String,
+    pub change: Change,
+    /// When it was asked for, in seconds since the epoch
+    pub at: u64,
+}
+
+fn pending(storage: &dyn Storage, now: u64) -> Result<Vec<Pending>> {
+    let mut pending: Vec<Pending> = storage::load_json(storage, PENDING_KEY)?.unwrap_or_default();
+    pending.retain(|pending| now < pending.at + CONFIRM_WITHIN);
+    Ok(pending)
+}
+
+/// Keeps `change` for `email` until it's confirmed, giving what to mail a confirmation link about.
+/// Gives nothing if a link was mailed to the address less than [`RESEND_AFTER`] ago, or if
+/// [`MAX_PENDING`] changes are waiting already.
+pub fn request(
+    storage: &dyn Storage,
+    email: &str,
+    change: Change,
+    now: u64,
+) -> Result<Option<Pending>> {
+    let mut pending = pending(storage, now)?;
+    if pending
+        .iter()
+        .any(|pending| pending.email == email && now < pending.at + RESEND_AFTER)
+        || pending.len() >= MAX_PENDING
+    {
+        return Ok(None);
+    }
+    let new = Pending {
+        id: secret(),
+        email: email.to_string(),
+        change,
+        at: now,
+    };
+    pending.push(new.clone());
+    storage::save_json(storage, PENDING_KEY, &pending)?;
+    Ok(Some(new))
+}
+
+/// Makes the change with `id`, unless it has expired. Returns the change, if it was made.
+pub fn confirm(storage: &dyn Storage, id: &str, now: u64) -> Result<Option<Pending>> {
+    let mut pending = pending(storage, now)?;
+    let confirmed = match pending.iter().position(|pending| pending.id == id) {
+        Some(index) => pending.remove(index),
+        None => return Ok(None),
+    };
+    storage::save_json(storage, PENDING_KEY, &pending)?;
+    match &confirmed.change {
+        Change::Watch(watch) => subscribe(storage, &confirmed.email, watch.clone())?,
+        Change::SaveSearch(search) => save_search(storage, &confirmed.email, search.clone())?,
+    };
+    Ok(Some(confirmed))
+}
+
+/// Removes the subscriber with `token`. Returns whether there was one.
+pub fn unsubscribe(storage: &dyn Storage, token: &str) -> Result<bool> {
+    let mut subscribers = subscribers(storage)?;
+    let count = subscribers.len();
+    subscribers.retain(|s| s.token != token);
+    storage::save_json(storage, SUBSCRIBERS_KEY, &subscribers)?;
+    Ok(subscribers.len() != count)
+}
+
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio02Connector>,
+    from: Mailbox,
+    base_url: String,
+}
+
+impl Mailer {
+    pub fn new(config: &EmailConfig) -> Result<Mailer> {
+        let mut transport = AsyncSmtpTransport::<Tokio02Connector>::relay(&config.smtp_host)
+            .map_err(|err| Error::Config(format!("SMTP relay {}: {}", config.smtp_host, err)))?;
+        if let Some(port) = config.smtp_port {
+            transport = transport.port(port);
+        }
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            transport = transport.credentials(Credentials::new(
+                username.clone(),
+                password.expose_secret().clone(),
+            ));
+        }
+        Ok(Mailer {
+            transport: transport.build(),
+            from: config
+                .from
+                .parse()
+                .map_err(|err| Error::Config(format!("email from address: {}", err)))?,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    pub async fn send(&self, to: &Subscriber, subject: &str, body: &str) -> Result<()> {
+        let body = format!(
+            "{}\n\n--\nAvsluta prenumerationen: {}/email/unsubscribe?token={}",
+            body, self.base_url, to.token
+        );
+        self.deliver(&to.email, subject, body).await
+    }
+
+    /// Mails the link confirming `pending`, with its id signed by `key`.
+    pub async fn send_confirmation(&self, pending: &Pending, key: &[u8]) -> Result<()> {
+        let what = match pending.change {
+            Change::Watch(_) => "få veckans APK och prisvarningar via mejl",
+            Change::SaveSearch(_) => "få ett mejl när något nytt matchar en sökning",
+        };
+        let body = format!(
+            "Någon, förhoppningsvis du, vill {}. Bekräfta genom att gå till\n\
+             {}/email/confirm?id={}\n\n\
+             Har du inte bett om det kan du strunta i det här mejlet.",
+            what,
+            self.base_url,
+            signing::sign(key, &pending.id)
+        );
+        self.deliver(&pending.email, "Bekräfta din prenumeration på APK", body)
+            .await
+    }
+
+    async fn deliver(&self, to: &str, subject: &str, body: String) -> Result<()> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to
+                .parse()
+                .map_err(|err| Error::Config(format!("{}: {}", to, err)))?)
+            .subject(subject)
+            .body(body)
+            .map_err(Error::upstream)?;
+        self.transport
+            .send(message)
+            .await
+            .map_err(Error::upstream)?;
+        Ok(())
+    }
+}
+
+/// Sends price alerts to subscribers watching the products.
+pub struct EmailAlerts {
+    mailer: Arc<Mailer>,
+}
+
+impl EmailAlerts {
+    pub fn new(mailer: Arc<Mailer>) -> EmailAlerts {
+        EmailAlerts { mailer }
+    }
+}
+
+#[async_trait]
+impl Notifier for EmailAlerts {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    async fn notify(&self, state: &AppState, event: &RefreshEvent) -> Result<()> {
+        let alerts = alerts::alerts(event);
+        for subscriber in subscribers(&*state.storage)? {
+            let text: Vec<String> = alerts::watched(&alerts, &subscriber.watch)
+                .cloned()
+                .chain(
+                    subscriber
+                        .searches
+                        .iter()
+                        .flat_map(|search| search.alerts(event, &state.config.allergens)),
+                )
+                .map(|alert| alert.text())
+                .collect();
+            if text.is_empty() {
+                continue;
+            }
+            if let Err(err) = self
+                .mailer
+                .send(&subscriber, "Prisvarning från APK", &text.join("\n"))
+                .await
+            {
+                error!("Emailing {} failed: {}", subscriber.email, err);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The digest, with the best products overall and what the feed says changed after `since`.
+fn digest_text(state: &AppState, catalog: &Catalog, since: u64) -> Result<String> {
+    let best: Vec<String> = digest::best(catalog, digest::EMAIL_TOP)
+        .into_iter()
+        .map(text::drink_line)
+        .collect();
+    let mut changes: Vec<_> = feed::load(&*state.storage)?
+        .into_iter()
+        .filter(|item| item.at > since)
+        .collect();
+    // The feed is newest first
+    changes.reverse();
+    let changes: Vec<String> = changes.into_iter().map(|item| item.title).collect();
+    Ok(render::render_digest(&state.tera(), &best, &changes)?)
+}
+
+/// Sends the weekly digest to all subscribers.
+pub struct EmailDigest {
+    mailer: Arc<Mailer>,
+}
+
+impl EmailDigest {
+    pub fn new(mailer: Arc<Mailer>) -> EmailDigest {
+        EmailDigest { mailer }
+    }
+
+    async fn send_if_due(&self, state: &AppState) -> Result<()> {
+        let now = SystemTime::now();
+        let last_sent = storage::load_json::<u64>(&*state.storage, LAST_DIGEST_KEY)?
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+        if !digest::is_due(last_sent, now) {
+            return Ok(());
+        }
+        let snapshot = match state.snapshot.read().unwrap().clone() {
+            Some(snapshot) => snapshot,
+            None => return Ok(()),
+        };
+        let since = last_sent.unwrap_or(now - digest::DIGEST_INTERVAL);
+        let text = digest_text(state, &snapshot.catalog, unix_time(since))?;
+        for subscriber in subscribers(&*state.storage)? {
+            if let Err(err) = self.mailer.send(&subscriber, "Veckans APK", &text).await {
+                error!("Emailing {} failed: {}", subscriber.email, err);
+            }
+        }
+        let now = now.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        storage::save_json(&*state.storage, LAST_DIGEST_KEY, &now)
+    }
+}
+
+#[async_trait]
+impl Job for EmailDigest {
+    async fn run(self: Box<Self>, state: AppState) {
+        loop {
+            if let Err(err) = self.send_if_due(&state).await {
+                error!("Sending email digest failed: {}", err);
+            }
+            tokio::time::delay_for(DIGEST_CHECK_INTERVAL).await;
+        }
+    }
+}
+
+fn message(state: &AppState, text: &str) -> Response {
+    match render::render_message(&state.tera(), text) {
+        Ok(page) => html(page).into_response(),
+        Err(err) => {
+            error!("{}", err);
+            text.to_string().into_response()
+        }
+    }
+}
+
+/// Mails a link confirming `change` for `email`, unless one was mailed recently.
+async fn ask(state: &AppState, email: &str, change: Change) -> Result<Response> {
+    let mailer = state
+        .mailer
+        .as_ref()
+        .ok_or_else(|| Error::Config("email isn't configured".to_string()))?;
+    let now = unix_time(SystemTime::now());
+    let pending = {
+        let _saving = state.saving.lock().unwrap();
+        request(&*state.storage, email, change, now)?
+    };
+    if let Some(pending) = pending {
+        mailer
+            .send_confirmation(&pending, &state.cookie_key)
+            .await?;
+    }
+    Ok(message(
+        state,
+        "Kolla din mejl och bekräfta med länken där.",
+    ))
+}
+
+/// Whether `client` may ask for another confirmation link, counting it if so.
+fn may_ask(asked: &Hourly<Option<IpAddr>>, client: Option<IpAddr>) -> Option<Response> {
+    let now = unix_time(SystemTime::now());
+    if asked.allow(client, now) {
+        None
+    } else {
+        Some(limit::too_many(now))
+    }
+}
+
+async fn handle_subscribe(state: &AppState, form: HashMap<String, String>) -> Result<Response> {
+    let email = form.get("email").map_or("", |email| email.trim());
+    if email.parse::<Mailbox>().is_err() {
+        return Ok(message(state, "Det där ser inte ut som en mejladress."));
+    }
+    let watch = form
+        .get("watch")
+        .map(|watch| {
+            watch
+                .split(',')
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default();
+    ask(state, email, Change::Watch(watch)).await
+}
+
+async fn handle_save_search(state: &AppState, form: HashMap<String, String>) -> Result<Response> {
+    let email = form.get("email").map_or("", |email| email.trim());
+    if email.parse::<Mailbox>().is_err() {
+        return Ok(message(state, "Det där ser inte ut som en mejladress."));
+    }
+    let min_apk = form.get("min_apk").and_then(|apk| apk.trim().parse().ok());
+    let search = SavedSearch::new(form.get("query").map_or("", String::as_str), min_apk);
+    if search.view().is_default() && min_apk.is_none() {
+        return Ok(message(state, "Välj några filter att bevaka först."));
+    }
+    ask(state, email, Change::SaveSearch(search)).await
+}
+
+fn handle_confirm(state: &AppState, query: HashMap<String, String>) -> Result<Response> {
+    let id = query
+        .get("id")
+        .and_then(|id| signing::verify_signed(&state.cookie_key, id));
+    let confirmed = match id {
+        Some(id) => {
+            let _saving = state.saving.lock().unwrap();
+            confirm(&*state.storage, id, unix_time(SystemTime::now()))?
+        }
+        None => None,
+    };
+    Ok(match confirmed.map(|pending| pending.change) {
+        Some(Change::Watch(_)) => message(state, "Du får nu veckans APK via mejl."),
+        Some(Change::SaveSearch(_)) => {
+            message(state, "Du får ett mejl när något nytt matchar sökningen.")
+        }
+        None => message(
+            state,
+            "Länken fungerar inte längre, eller har redan använts.",
+        ),
+    })
+}
+
+fn handle_unsubscribe(state: &AppState, query: HashMap<String, String>) -> Result<Response> {
+    let token = query.get("token").map_or("", String::as_str);
+    let _saving = state.saving.lock().unwrap();
+    Ok(if unsubscribe(&*state.storage, token)? {
+        message(state, "Du får inte längre några mejl från APK.")
+    } else {
+        message(state, "Hittade ingen sådan prenumeration.")
+    })
+}
+
+fn enabled(state: AppState) -> impl Filter<Extract = (AppState,), Error = Rejection> + Clone {
+    warp::any().and_then(move || {
+        let state = state.clone();
+        async move {
+            if state.mailer.is_some() {
+                Ok(state)
+            } else {
+                Err(warp::reject::not_found())
+            }
+        }
+    })
+}
+
+fn respond(result: Result<Response>) -> Response {
+    result.unwrap_or_else(|err| {
+        error!("{}", err);
+        warp::reply::with_status(
+            "Något gick fel",
+            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+        )
+        .into_response()
+    })
+}
+
+pub fn routes(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let asked = Arc::new(Hourly::new(MAX_ASKED_PER_HOUR));
+    let subscribe = {
+        let asked = asked.clone();
+        warp::path!("email" / "subscribe")
+            .and(warp::post())
+            .and(enabled(state.clone()))
+            .and(access::client(&state))
+            .and(warp::body::content_length_limit(MAX_FORM))
+            .and(warp::body::form())
+            .and_then(move |state: AppState, client, form| {
+                let limited = may_ask(&asked, client);
+                async move {
+                    let response = match limited {
+                        Some(response) => response,
+                        None => respond(handle_subscribe(&state, form).await),
+                    };
+                    Ok::<_, Rejection>(response)
+                }
+            })
+    };
+    let search = warp::path!("email" / "search")
+        .and(warp::post())
+        .and(enabled(state.clone()))
+        .and(access::client(&state))
+        .and(warp::body::content_length_limit(MAX_FORM))
+        .and(warp::body::form())
+        .and_then(move |state: AppState, client, form| {
+            let limited = may_ask(&asked, client);
+            async move {
+                let response = match limited {
+                    Some(response) => response,
+                    None => respond(handle_save_search(&state, form).await),
+                };
+                Ok::<_, Rejection>(response)
+            }
+        });
+    let confirm = warp::path!("email" / "confirm")
+        .and(warp::get())
+        .and(enabled(state.clone()))
+        .and(warp::query())
+        .map(|state: AppState, query| respond(handle_confirm(&state, query)));
+    let unsubscribe = warp::path!("email" / "unsubscribe")
+        .and(warp::get())
+        .and(enabled(state))
+        .and(warp::query())
+        .map(|state: AppState, query| respond(handle_unsubscribe(&state, query)));
+    subscribe
+        .or(search)
+        .unify()
+        .or(confirm)
+        .unify()
+        .or(unsubscribe)
+        .unify()
+}
@@ -0,0 +1,73 @@
+use thiserror::Error;
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("upstream request failed: {0}")]
+    Upstream(#[source] BoxError),
+    #[error("couldn't parse upstream data: {0}")]
+    Parse(#[source] BoxError),
+    #[error("couldn't render template: {0}")]
+    Template(#[from] tera::Error),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("configuration error: {0}")]
+    Config(String),
+    #[error("couldn't make image: {0}")]
+    Image(#[source] BoxError),
+    #[error("suspicious catalog: {}", .0.join(", "))]
+    Anomaly(Vec<String>),
+    #[error("database error: {0}")]
+    Database(#[from] rusqlite::Error),
+    #[error("rendered page looks broken: {0}")]
+    Render(String),
+}
+
+impl Error {
+    /// Every [`Error::category`], in the order they're reported.
+    pub const CATEGORIES: [&'static str; 9] = [
+        "upstream", "parse", "template", "io", "config", "image", "anomaly", "database", "render",
+    ];
+
+    /// A short, stable name for the kind of error, for use in metrics and status reports.
+    pub fn category(&self) -> &'static str {
+        match self {
+            Error::Upstream(_) => "upstream",
+            Error::Parse(_) => "parse",
+            Error::Template(_) => "template",
+            Error::Io(_) => "io",
+            Error::Config(_) => "config",
+            Error::Image(_) => "image",
+            Error::Anomaly(_) => "anomaly",
+            Error::Database(_) => "database",
+            Error::Render(_) => "render",
+        }
+    }
+
+    pub fn upstream(err: impl Into<BoxError>) -> Error {
+        Error::Upstream(err.into())
+    }
+
+    pub fn image(err: impl Into<BoxError>) -> Error {
+        Error::Image(err.into())
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Error {
+        if err.is_decode() {
+            Error::Parse(err.into())
+        } else {
+            Error::Upstream(err.into())
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::Parse(err.into())
+    }
+}
@@ -0,0 +1,98 @@
+//! Starred products, kept in a signed cookie, shown at `/favorites`.
+
+use crate::render;
+use crate::signing;
+use crate::state::AppState;
+use std::collections::HashMap;
+use tracing::error;
+use warp::http::{header, StatusCode};
+use warp::reply::{html, Response};
+use warp::{Filter, Rejection, Reply};
+
+pub const COOKIE: &str = "favorites";
+const MAX_FAVORITES: usize = 100;
+/// A year, in seconds
+const MAX_AGE: u64 = 365 * 24 * 60 * 60;
+const MAX_FORM: u64 = 4 * 1024;
+
+/// The product ids in a favorites cookie, or none if it's missing or tampered with.
+pub fn parse(key: &[u8], cookie: Option<&str>) -> Vec<String> {
+    cookie
+        .and_then(|cookie| signing::verify_signed(key, cookie))
+        .map(|ids| {
+            ids.split(':')
+                .filter(|id| !id.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// A `Set-Cookie` value holding `ids`.
+pub fn cookie(key: &[u8], ids: &[String]) -> String {
+    format!(
+        "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax",
+        COOKIE,
+        signing::sign(key, &ids.join(":")),
+        MAX_AGE
+    )
+}
+
+fn page(state: &AppState, ids: &[String]) -> Response {
+    let snapshot = state.snapshot.read().unwrap().clone();
+    let drinks: Vec<_> = match &snapshot {
+        Some(snapshot) => ids
+            .iter()
+            .filter_map(|id| snapshot.catalog.find(id))
+            .collect(),
+        None => Vec::new(),
+    };
+    match render::render_favorites(&state.tera(), &drinks) {
+        Ok(page) => html(page).into_response(),
+        Err(err) => {
+            error!("{}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Stars or unstars the product in `form`, depending on its `action`.
+fn update(state: &AppState, current: Option<String>, form: HashMap<String, String>) -> Response {
+    let mut ids = parse(&state.cookie_key, current.as_deref());
+    let id = match form.get("id") {
+        // Separators would corrupt the cookie, and real ids are just digits anyway
+        Some(id) if !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric()) => id,
+        _ => return StatusCode::BAD_REQUEST.into_response(),
+    };
+    ids.retain(|starred| starred != id);
+    if form.get("action").map(String::as_str) != Some("remove") {
+        ids.insert(0, id.clone());
+        ids.truncate(MAX_FAVORITES);
+    }
+    let redirect = warp::reply::with_header(StatusCode::SEE_OTHER, header::LOCATION, "/favorites");
+    warp::reply::with_header(
+        redirect,
+        header::SET_COOKIE,
+        cookie(&state.cookie_key, &ids),
+    )
+    .into_response()
+}
+
+pub fn routes(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let show = {
+        let state = state.clone();
+        warp::path!("favorites")
+            .and(warp::get())
+            .and(warp::cookie::optional(COOKIE))
+            .map(move |cookie: Option<String>| {
+                page(&state, &parse(&state.cookie_key, cookie.as_deref()))
+            })
+    };
+    let update = warp::path!("favorites")
+        .and(warp::post())
+        .and(warp::cookie::optional(COOKIE))
+        .and(warp::body::content_length_limit(MAX_FORM))
+        .and(warp::body::form())
+        .map(move |cookie, form| update(&state, cookie, form));
+    show.or(update).unify()
+}
@@ -0,0 +1,176 @@
+//! RSS feeds of catalog changes, globally at `/feed.xml` and per category at
+//! `/feed/{category}.xml`.
+
+use crate::buy;
+use crate::catalog::{self, Category};
+use crate::error::Result;
+use crate::notify::{Notifier, RefreshEvent};
+use crate::state::AppState;
+use crate::status::unix_time;
+use crate::storage::{self, Storage};
+use async_trait::async_trait;
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, UNIX_EPOCH};
+use systemet::Product;
+use tracing::error;
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+const ITEMS_KEY: &str = "feed.json";
+/// How many items are kept, for all categories together
+const MAX_ITEMS: usize = 500;
+/// How many items a feed shows
+const FEED_ITEMS: usize = 50;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Item {
+    /// Unix timestamp of the refresh
+    pub at: u64,
+    pub category: Category,
+    pub id: String,
+    pub title: String,
+    pub link: String,
+}
+
+impl Item {
+    pub fn new(at: u64, drink: &Product, title: String) -> Item {
+        Item {
+            at,
+            category: catalog::categorize(drink),
+            id: catalog::id(drink).to_string(),
+            title,
+            link: buy::url(drink),
+        }
+    }
+}
+
+/// The feed items for one refresh.
+pub fn items(event: &RefreshEvent) -> Vec<Item> {
+    let at = unix_time(event.snapshot.updated_at);
+    let catalog = &event.snapshot.catalog;
+    let added = event.diff.added.iter().filter_map(|id| {
+        let drink = catalog.find(id)?;
+        let title = format!(
+            "Ny: {} (APK {:.3})",
+            catalog::name(drink),
+            catalog::apk(drink).0
+        );
+        Some(Item::new(at, drink, title))
+    });
+    let changed = event.diff.price_changes.iter().filter_map(|change| {
+        let drink = catalog.find(&change.id)?;
+        let title = format!(
+            "{}: {} → {}",
+            catalog::name(drink),
+            change.old_price,
+            change.new_price
+        );
+        Some(Item::new(at, drink, title))
+    });
+    let removed = event.diff.removed.iter().filter_map(|id| {
+        let drink = event.previous.as_ref()?.catalog.find(id)?;
+        Some(Item::new(
+            at,
+            drink,
+            format!("Utgått: {}", catalog::name(drink)),
+        ))
+    });
+    added.chain(changed).chain(removed).collect()
+}
+
+/// The stored items, newest first.
+pub fn load(storage: &dyn Storage) -> Result<Vec<Item>> {
+    Ok(storage::load_json(storage, ITEMS_KEY)?.unwrap_or_default())
+}
+
+/// Keeps the feed items of each refresh.
+pub struct FeedRecorder;
+
+#[async_trait]
+impl Notifier for FeedRecorder {
+    fn name(&self) -> &str {
+        "feed"
+    }
+
+    async fn notify(&self, state: &AppState, event: &RefreshEvent) -> Result<()> {
+        let mut items = items(event);
+        if items.is_empty() {
+            return Ok(());
+        }
+        items.extend(load(&*state.storage)?);
+        items.truncate(MAX_ITEMS);
+        storage::save_json(&*state.storage, ITEMS_KEY, &items)
+    }
+}
+
+/// Escapes `text` for XML.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// An RSS 2.0 document of the newest `items` in `category`, or in all categories.
+pub fn render(items: &[Item], category: Option<Category>) -> String {
+    let title = match category {
+        Some(category) => format!("APK – {}", category.name()),
+        None => "APK".to_string(),
+    };
+    let items: String = items
+        .iter()
+        .filter(|item| category.map_or(true, |category| item.category == category))
+        .take(FEED_ITEMS)
+        .map(|item| {
+            format!(
+                "<item><title>{}</title><link>{}</link><guid isPermaLink=\"false\">{}-{}</guid><pubDate>{}</pubDate><category>{}</category></item>",
+                escape(&item.title),
+                escape(&item.link),
+                escape(&item.id),
+                item.at,
+                httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_secs(item.at)),
+                item.category.name()
+            )
+        })
+        .collect();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\"><channel><title>{}</title><link>https://www.systembolaget.se/</link><description>Nya drycker och prisändringar</description>{}</channel></rss>\n",
+        escape(&title),
+        items
+    )
+}
+
+fn respond(state: &AppState, category: Option<Category>) -> Response {
+    match load(&*state.storage) {
+        Ok(items) => warp::reply::with_header(
+            render(&items, category),
+            "Content-Type",
+            "application/rss+xml; charset=utf-8",
+        )
+        .into_response(),
+        Err(err) => {
+            error!("{}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+pub fn routes(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let all = {
+        let state = state.clone();
+        warp::path!("feed.xml").map(move || respond(&state, None))
+    };
+    let category = warp::path!("feed" / String).and_then(move |file: String| {
+        let state = state.clone();
+        async move {
+            let name = percent_decode_str(file.trim_end_matches(".xml")).decode_utf8_lossy();
+            match Category::from_name(&name) {
+                Some(category) if file.ends_with(".xml") => Ok(respond(&state, Some(category))),
+                _ => Err(warp::reject::not_found()),
+            }
+        }
+    });
+    all.or(category).unify()
+}
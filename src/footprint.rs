@@ -0,0 +1,84 @@
+//! `/admin/snapshot`, how much the published snapshot and the pages rendered from it take up, and
+//! how often the rendered pages are used, for telling how much memory the server needs. The data in
+//! the snapshot is measured by the size of its JSON, which is close enough to what it takes in
+//! memory.
+
+use crate::catalog::CATEGORIES;
+use crate::error::Result;
+use crate::refresh::Snapshot;
+use crate::state::AppState;
+use serde_json::{json, Map, Value};
+use tracing::error;
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// How often visits to views were served a warm page, if there were any.
+fn rate(served: u64, missed: u64) -> Option<f64> {
+    match served + missed {
+        0 => None,
+        total => Some(served as f64 / total as f64),
+    }
+}
+
+fn snapshot(snapshot: &Snapshot) -> Result<Value> {
+    let categories: Map<String, Value> = CATEGORIES
+        .iter()
+        .map(|&category| {
+            let count = snapshot.catalog.get(category).len();
+            (category.slug().to_string(), count.into())
+        })
+        .collect();
+    let catalog = serde_json::to_vec(&snapshot.catalog)?.len();
+    let records = serde_json::to_vec(&snapshot.records)?.len();
+    let countries = serde_json::to_vec(&snapshot.countries)?.len();
+    let pages = snapshot.page.len()
+        + snapshot
+            .category_pages
+            .values()
+            .map(String::len)
+            .sum::<usize>();
+    Ok(json!({
+        "hash": snapshot.hash,
+        "products": snapshot.catalog.len(),
+        "categories": categories,
+        "bytes": {
+            "catalog": catalog,
+            "records": records,
+            "countries": countries,
+            "pages": pages,
+            "total": catalog + records + countries + pages,
+        },
+        "pages": 1 + snapshot.category_pages.len(),
+    }))
+}
+
+pub fn report(state: &AppState) -> Result<Value> {
+    let snapshot = match state.snapshot.read().unwrap().clone() {
+        Some(current) => snapshot(&current)?,
+        None => Value::Null,
+    };
+    let (pages, plain, gzip) = state.warm.sizes();
+    let (served, missed) = state.warm.hits();
+    Ok(json!({
+        "snapshot": snapshot,
+        "warm": {
+            "pages": pages,
+            "bytes": plain,
+            "gzip_bytes": gzip,
+            "served": served,
+            "missed": missed,
+            "hit_rate": rate(served, missed),
+        },
+    }))
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("admin" / "snapshot").map(move || match report(&state) {
+        Ok(report) => warp::reply::json(&report).into_response(),
+        Err(err) => {
+            error!("{}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    })
+}
@@ -0,0 +1,115 @@
+//! The Grafana simple JSON datasource API over the refresh history, under `/grafana`.
+
+use crate::catalog::{Category, CATEGORIES};
+use crate::dates;
+use crate::error::Result;
+use crate::history::{self, Point};
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use warp::http::StatusCode;
+use warp::reply::{json, Response};
+use warp::{Filter, Rejection, Reply};
+
+/// Queries are small, even with every category asked for
+const MAX_QUERY: u64 = 16 * 1024;
+
+#[derive(Deserialize)]
+pub struct Query {
+    pub range: Range,
+    pub targets: Vec<Target>,
+}
+
+#[derive(Deserialize)]
+pub struct Range {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Deserialize)]
+pub struct Target {
+    pub target: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Series {
+    pub target: String,
+    /// Pairs of value and Unix time in milliseconds
+    pub datapoints: Vec<(f64, u64)>,
+}
+
+/// The names of all series, like `best_apk.Öl`.
+pub fn targets() -> Vec<String> {
+    let mut targets: Vec<String> = CATEGORIES
+        .iter()
+        .map(|category| format!("best_apk.{}", category.name()))
+        .collect();
+    targets.extend(
+        CATEGORIES
+            .iter()
+            .map(|category| format!("products.{}", category.name())),
+    );
+    targets.push("products".to_string());
+    targets.push("refresh_errors".to_string());
+    targets
+}
+
+fn value(target: &str, point: &Point) -> Option<f64> {
+    let mut parts = target.splitn(2, '.');
+    match (parts.next()?, parts.next()) {
+        ("best_apk", Some(name)) => point.best.get(&Category::from_name(name)?).copied(),
+        ("products", Some(name)) => point
+            .products
+            .get(&Category::from_name(name)?)
+            .map(|&n| n as f64),
+        ("products", None) => Some(point.products.values().sum::<usize>() as f64),
+        ("refresh_errors", None) => Some(point.errors as f64),
+        _ => None,
+    }
+}
+
+/// The requested series, limited to the time range.
+pub fn query(history: &[Point], query: &Query) -> Vec<Series> {
+    let from = dates::parse_timestamp(&query.range.from).unwrap_or(0);
+    let to = dates::parse_timestamp(&query.range.to).unwrap_or(u64::MAX);
+    query
+        .targets
+        .iter()
+        .map(|target| Series {
+            target: target.target.clone(),
+            datapoints: history
+                .iter()
+                .filter(|point| point.at >= from && point.at <= to)
+                .filter_map(|point| Some((value(&target.target, point)?, point.at * 1000)))
+                .collect(),
+        })
+        .collect()
+}
+
+fn respond(result: Result<Vec<Series>>) -> Response {
+    match result {
+        Ok(series) => json(&series).into_response(),
+        Err(err) => {
+            error!("{}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+pub fn routes(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    // Grafana checks that the datasource is up with a plain GET
+    let health = warp::path!("grafana")
+        .and(warp::get())
+        .map(|| StatusCode::OK.into_response());
+    let search = warp::path!("grafana" / "search")
+        .and(warp::post())
+        .map(|| json(&targets()).into_response());
+    let query = warp::path!("grafana" / "query")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(MAX_QUERY))
+        .and(warp::body::json())
+        .map(move |body: Query| {
+            respond(history::load(&*state.storage).map(|history| self::query(&history, &body)))
+        });
+    health.or(search).unify().or(query).unify()
+}
@@ -0,0 +1,77 @@
+//! A time series of how the catalog and the refreshes have looked, one point per refresh.
+
+use crate::catalog::{self, Category, CATEGORIES};
+use crate::diff::PriceChange;
+use crate::error::Result;
+use crate::notify::{Notifier, RefreshEvent};
+use crate::state::AppState;
+use crate::status::unix_time;
+use crate::storage::{self, Storage};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const HISTORY_KEY: &str = "history.json";
+/// About two years of refreshes every other hour
+const MAX_POINTS: usize = 10_000;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Point {
+    /// Unix timestamp of the refresh
+    pub at: u64,
+    /// The best APK in each category
+    pub best: HashMap<Category, f64>,
+    /// Listed products in each category
+    pub products: HashMap<Category, usize>,
+    /// Failed refreshes so far
+    pub errors: u64,
+    /// The price changes of the refresh
+    #[serde(default)]
+    pub changes: Vec<PriceChange>,
+}
+
+impl Point {
+    pub fn new(state: &AppState, event: &RefreshEvent) -> Point {
+        let catalog = &event.snapshot.catalog;
+        Point {
+            at: unix_time(event.snapshot.updated_at),
+            best: CATEGORIES
+                .iter()
+                .filter_map(|&category| {
+                    let best = catalog.get(category).first()?;
+                    Some((category, catalog::apk(best).0))
+                })
+                .collect(),
+            products: CATEGORIES
+                .iter()
+                .map(|&category| (category, catalog.get(category).len()))
+                .collect(),
+            errors: state.status.read().unwrap().errors.values().sum(),
+            changes: event.diff.price_changes.clone(),
+        }
+    }
+}
+
+/// All points, oldest first.
+pub fn load(storage: &dyn Storage) -> Result<Vec<Point>> {
+    Ok(storage::load_json(storage, HISTORY_KEY)?.unwrap_or_default())
+}
+
+/// Adds a point to the history of each refresh.
+pub struct HistoryRecorder;
+
+#[async_trait]
+impl Notifier for HistoryRecorder {
+    fn name(&self) -> &str {
+        "history"
+    }
+
+    async fn notify(&self, state: &AppState, event: &RefreshEvent) -> Result<()> {
+        let mut history = load(&*state.storage)?;
+        history.push(Point::new(state, event));
+        if history.len() > MAX_POINTS {
+            history.drain(..history.len() - MAX_POINTS);
+        }
+        storage::save_json(&*state.storage, HISTORY_KEY, &history)
+    }
+}
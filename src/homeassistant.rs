@@ -0,0 +1,45 @@
+//! A JSON endpoint at `/homeassistant` shaped for Home Assistant's REST sensors, e.g. with
+//! `value_template: "{{ value_json.best.beer.apk }}"`.
+
+use crate::catalog::{self, CATEGORIES};
+use crate::state::AppState;
+use crate::status::unix_time;
+use crate::units::Measures;
+use serde_json::{json, Map, Value};
+use std::time::SystemTime;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// The best product per category, keyed by [`crate::catalog::Category::slug`], and how old the
+/// data is.
+pub fn sensors(state: &AppState, now: SystemTime) -> Value {
+    let snapshot = match state.snapshot.read().unwrap().clone() {
+        Some(snapshot) => snapshot,
+        None => return json!({ "best": {}, "last_update": null, "last_update_age": null }),
+    };
+    let catalog = &snapshot.catalog;
+    let best: Map<String, Value> = CATEGORIES
+        .iter()
+        .filter_map(|&category| {
+            let drink = catalog.get(category).first()?;
+            let sensor = json!({
+                "id": catalog::id(drink),
+                "name": catalog::name(drink),
+                "apk": (catalog::apk(drink).0 * 1000.0).round() / 1000.0,
+                "price": drink.price_with_deposit(),
+            });
+            Some((category.slug().to_string(), sensor))
+        })
+        .collect();
+    let updated_at = unix_time(snapshot.updated_at);
+    json!({
+        "best": best,
+        "last_update": updated_at,
+        "last_update_age": unix_time(now).saturating_sub(updated_at),
+    })
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("homeassistant")
+        .map(move || warp::reply::json(&sensors(&state, SystemTime::now())).into_response())
+}
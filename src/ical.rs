@@ -0,0 +1,86 @@
+//! An iCal feed of upcoming launches at `/releases.ics`, one all-day event per launch day.
+
+use crate::catalog::{self, Catalog};
+use crate::dates;
+use crate::state::AppState;
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+use systemet::Product;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// The longest line allowed by RFC 5545, in bytes
+const LINE_LENGTH: usize = 75;
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Folds a content line to at most 75 bytes per line, without splitting characters.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > LINE_LENGTH {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+/// Products launching after `today` (`YYYY-MM-DD`), by launch day.
+pub fn launches<'a>(catalog: &'a Catalog, today: &str) -> BTreeMap<&'a str, Vec<&'a Product>> {
+    let mut launches: BTreeMap<&str, Vec<&Product>> = BTreeMap::new();
+    for drink in catalog.products() {
+        match catalog::sell_start(drink) {
+            Some(day) if day > today => launches.entry(day).or_default().push(drink),
+            _ => {}
+        }
+    }
+    launches
+}
+
+/// A calendar of the launches after `now`.
+pub fn calendar(catalog: &Catalog, now: SystemTime) -> String {
+    let stamp = dates::basic_timestamp(now);
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//apk//releases//SV".to_string(),
+        "X-WR-CALNAME:Systembolagets släpp".to_string(),
+    ];
+    for (day, drinks) in launches(catalog, &dates::date(now)) {
+        let names: Vec<&str> = drinks.iter().map(|&drink| catalog::name(drink)).collect();
+        lines.extend(vec![
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}@apk", day),
+            format!("DTSTAMP:{}", stamp),
+            format!("DTSTART;VALUE=DATE:{}", day.replace('-', "")),
+            format!("SUMMARY:{} nya drycker på Systemet", drinks.len()),
+            format!("DESCRIPTION:{}", escape(&names.join("\n"))),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("releases.ics").map(move || {
+        let snapshot = state.snapshot.read().unwrap().clone();
+        let empty = Catalog::default();
+        let catalog = snapshot
+            .as_ref()
+            .map_or(&empty, |snapshot| &snapshot.catalog);
+        let body = calendar(catalog, SystemTime::now());
+        warp::reply::with_header(body, "Content-Type", "text/calendar; charset=utf-8")
+            .into_response()
+    })
+}
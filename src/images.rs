@@ -0,0 +1,125 @@
+//! Product images from Systembolaget's CDN at `/img/{id}`, resized to `?w=` pixels wide and
+//! cached on disk, so lists can show small images without loading the full size ones.
+
+use crate::config::ImagesConfig;
+use crate::error::{Error, Result};
+use crate::state::AppState;
+use crate::storage::{FileStorage, Storage};
+use image::imageops::FilterType;
+use image::ImageOutputFormat;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::error;
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// Widths are rounded up to a multiple of this, so the cache doesn't get one image per pixel
+const WIDTH_STEP: u32 = 20;
+const MAX_WIDTH: u32 = 400;
+const DEFAULT_WIDTH: u32 = 120;
+
+#[derive(Deserialize)]
+struct Query {
+    w: Option<u32>,
+}
+
+/// The width actually used when `requested` is asked for.
+pub fn width(requested: Option<u32>) -> u32 {
+    let width = requested.unwrap_or(DEFAULT_WIDTH).max(1).min(MAX_WIDTH);
+    (width + WIDTH_STEP - 1) / WIDTH_STEP * WIDTH_STEP
+}
+
+/// Where the full size image of product `id` is.
+pub fn source_url(config: &ImagesConfig, id: &str) -> String {
+    config.url.replace("{id}", id)
+}
+
+/// `image` scaled down to `width` pixels wide, as a PNG. Smaller images are only re-encoded.
+pub fn thumbnail(image: &[u8], width: u32) -> Result<Vec<u8>> {
+    let mut image = image::load_from_memory(image).map_err(Error::image)?;
+    if image.width() > width {
+        image = image.resize(width, u32::MAX, FilterType::Triangle);
+    }
+    let mut png = Vec::new();
+    image
+        .write_to(&mut png, ImageOutputFormat::Png)
+        .map_err(Error::image)?;
+    Ok(png)
+}
+
+fn png(png: Vec<u8>) -> Response {
+    let reply = warp::reply::with_header(png, "Content-Type", "image/png");
+    warp::reply::with_header(reply, "Cache-Control", "public, max-age=604800").into_response()
+}
+
+async fn respond(
+    state: &AppState,
+    client: &reqwest::Client,
+    cache: Option<&FileStorage>,
+    id: &str,
+    width: u32,
+) -> Result<Response> {
+    let config = match &state.config.images {
+        Some(config) => config,
+        None => return Ok(StatusCode::NOT_FOUND.into_response()),
+    };
+    // Only products in the catalog, so the cache can't be filled with anything else
+    let known = state
+        .snapshot
+        .read()
+        .unwrap()
+        .as_ref()
+        .map_or(false, |snapshot| snapshot.catalog.find(id).is_some());
+    if !known || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+
+    let key = format!("{}-{}.png", id, width);
+    if let Some(cached) = cache.map(|cache| cache.load(&key)).transpose()?.flatten() {
+        return Ok(png(cached));
+    }
+    let response = client.get(&source_url(config, id)).send().await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    let image = response.error_for_status()?.bytes().await?;
+    // Decoding and resizing take long enough to hold up other requests
+    let thumbnail = tokio::task::spawn_blocking(move || thumbnail(&image, width))
+        .await
+        .map_err(Error::image)??;
+    if let Some(cache) = cache {
+        cache.save(&key, &thumbnail)?;
+    }
+    Ok(png(thumbnail))
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let client = Arc::new(reqwest::Client::new());
+    // Without a cache, images are still served, just resized every time
+    let cache = state.config.images.as_ref().and_then(|config| {
+        FileStorage::new(&config.cache_dir)
+            .map_err(|err| error!("Can't cache images: {}", err))
+            .ok()
+            .map(Arc::new)
+    });
+    warp::path!("img" / String)
+        .and(warp::query::<Query>())
+        .and_then(move |id: String, query: Query| {
+            let state = state.clone();
+            let client = client.clone();
+            let cache = cache.clone();
+            async move {
+                if state.config.images.is_none() {
+                    return Err(warp::reject::not_found());
+                }
+                let response = respond(&state, &client, cache.as_deref(), &id, width(query.w))
+                    .await
+                    .unwrap_or_else(|err| {
+                        error!("{}", err);
+                        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                    });
+                Ok(response)
+            }
+        })
+}
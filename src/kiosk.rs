@@ -0,0 +1,86 @@
+//! A full screen leaderboard for a screen behind the bar, at `/kiosk?category=beer&rotate=30s`.
+//! With `rotate`, the page moves on to the next category by itself; without it, it stays and
+//! reloads now and then to pick up new prices.
+
+use crate::catalog::{Category, CATEGORIES};
+use crate::render;
+use crate::state::AppState;
+use serde::Deserialize;
+use tracing::error;
+use warp::http::StatusCode;
+use warp::reply::{html, Response};
+use warp::{Filter, Rejection, Reply};
+
+const TOP: usize = 10;
+/// In seconds, when not rotating
+const RELOAD_INTERVAL: u64 = 300;
+const MIN_ROTATE: u64 = 5;
+const MAX_ROTATE: u64 = 3600;
+
+#[derive(Deserialize)]
+pub struct Query {
+    category: Option<String>,
+    rotate: Option<String>,
+}
+
+/// Parses durations like `30s`, `2m` or just `30`, in seconds.
+pub fn parse_duration(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let (number, unit) = match text.chars().last()? {
+        's' => (&text[..text.len() - 1], 1),
+        'm' => (&text[..text.len() - 1], 60),
+        _ => (text, 1),
+    };
+    Some(number.parse::<u64>().ok()? * unit)
+}
+
+/// The category shown after `category`, wrapping around.
+pub fn next(category: Category) -> Category {
+    let index = CATEGORIES.iter().position(|&c| c == category).unwrap_or(0);
+    CATEGORIES[(index + 1) % CATEGORIES.len()]
+}
+
+fn page(state: &AppState, query: Query) -> Response {
+    let category = match query.category.as_deref().map(Category::from_slug) {
+        Some(Some(category)) => category,
+        Some(None) => return StatusCode::BAD_REQUEST.into_response(),
+        None => CATEGORIES[0],
+    };
+    let rotate = match query.rotate.as_deref().map(parse_duration) {
+        Some(Some(rotate)) => Some(rotate.max(MIN_ROTATE).min(MAX_ROTATE)),
+        Some(None) => return StatusCode::BAD_REQUEST.into_response(),
+        None => None,
+    };
+    let (refresh, next) = match rotate {
+        Some(rotate) => (
+            rotate,
+            format!(
+                "/kiosk?category={}&rotate={}s",
+                next(category).slug(),
+                rotate
+            ),
+        ),
+        None => (
+            RELOAD_INTERVAL,
+            format!("/kiosk?category={}", category.slug()),
+        ),
+    };
+    let snapshot = state.snapshot.read().unwrap().clone();
+    let drinks = snapshot.as_ref().map_or(&[][..], |snapshot| {
+        let drinks = snapshot.catalog.get(category);
+        &drinks[..drinks.len().min(TOP)]
+    });
+    match render::render_kiosk(&state.tera(), category, drinks, refresh, &next) {
+        Ok(page) => html(page).into_response(),
+        Err(err) => {
+            error!("{}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("kiosk")
+        .and(warp::query::<Query>())
+        .map(move |query| page(&state, query))
+}
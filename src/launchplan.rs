@@ -0,0 +1,99 @@
+//! Upcoming launches from Systembolaget's launch plan, shown with their announced prices and
+//! projected APK at `/kommande`, so release day can be planned for.
+
+use crate::catalog;
+use crate::config::LaunchPlanConfig;
+use crate::dates;
+use crate::error::Result;
+use crate::notify::{Notifier, RefreshEvent};
+use crate::render;
+use crate::source::{HttpSource, ProductSource};
+use crate::state::AppState;
+use crate::units::{Measures, Percent, Sek};
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use systemet::Product;
+use tracing::error;
+use warp::http::StatusCode;
+use warp::reply::{html, Response};
+use warp::{Filter, Rejection, Reply};
+
+/// The upcoming products, as of the last refresh
+pub type SharedLaunchPlan = Arc<RwLock<Vec<Product>>>;
+
+/// The products launching after `today` (`YYYY-MM-DD`), by launch day, best APK first. Unlike the
+/// list, this includes products that aren't in stock, since none of them are yet.
+pub fn coming<'a>(products: &'a [Product], today: &str) -> Vec<(&'a str, Vec<&'a Product>)> {
+    let mut days: BTreeMap<&str, Vec<&Product>> = BTreeMap::new();
+    for drink in products {
+        match catalog::sell_start(drink) {
+            Some(day)
+                if day > today && drink.abv() > Percent(0.0) && drink.shelf_price() > Sek(0.0) =>
+            {
+                days.entry(day).or_default().push(drink)
+            }
+            _ => {}
+        }
+    }
+    days.into_iter()
+        .map(|(day, mut drinks)| {
+            drinks.sort_by(|d1, d2| catalog::apk_comparator(d1, d2));
+            (day, drinks)
+        })
+        .collect()
+}
+
+/// Fetches the launch plan after each refresh.
+pub struct LaunchPlanFetcher {
+    source: HttpSource,
+}
+
+impl LaunchPlanFetcher {
+    pub fn new(config: &LaunchPlanConfig) -> LaunchPlanFetcher {
+        LaunchPlanFetcher {
+            source: HttpSource::new(config.url.clone()),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for LaunchPlanFetcher {
+    fn name(&self) -> &str {
+        "launch-plan"
+    }
+
+    async fn notify(&self, state: &AppState, event: &RefreshEvent) -> Result<()> {
+        let products = self.source.fetch_products().await?;
+        let today = dates::date(event.snapshot.updated_at);
+        // Only what's still to come, so the plan doesn't grow forever
+        let upcoming = products
+            .into_iter()
+            .filter(|drink| catalog::sell_start(drink).map_or(false, |day| day > today.as_str()))
+            .collect();
+        *state.launch_plan.write().unwrap() = upcoming;
+        Ok(())
+    }
+}
+
+fn page(state: &AppState) -> Response {
+    let products = state.launch_plan.read().unwrap().clone();
+    let updated_at = state
+        .snapshot
+        .read()
+        .unwrap()
+        .as_ref()
+        .map(|snapshot| snapshot.updated_at);
+    let today = updated_at.map(dates::date).unwrap_or_default();
+    match render::render_coming(&state.tera(), &coming(&products, &today)) {
+        Ok(page) => html(page).into_response(),
+        Err(err) => {
+            error!("{}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("kommande").map(move || page(&state))
+}
@@ -0,0 +1,88 @@
+pub mod access;
+pub mod admin;
+pub mod agegate;
+pub mod alerts;
+pub mod allergens;
+pub mod anomaly;
+pub mod api;
+pub mod apierror;
+pub mod archive;
+pub mod atom;
+pub mod audit;
+pub mod back;
+pub mod barcodes;
+pub mod buy;
+pub mod categories;
+pub mod changes;
+pub mod config;
+pub mod countries;
+pub mod crawl;
+pub mod dates;
+pub mod digest;
+pub mod discord;
+pub mod display;
+pub mod email;
+pub mod error;
+pub mod favorites;
+pub mod feed;
+pub mod footprint;
+pub mod grafana;
+pub mod history;
+pub mod homeassistant;
+pub mod ical;
+pub mod images;
+pub mod kiosk;
+pub mod launchplan;
+pub mod limit;
+pub mod logging;
+pub mod mastodon;
+pub mod matrix;
+pub mod metrics;
+pub mod movers;
+pub mod mqtt;
+pub mod names;
+pub mod normalize;
+pub mod notify;
+pub mod ntfy;
+pub mod pagecache;
+pub mod prefs;
+pub mod presets;
+pub mod prices;
+pub mod productcache;
+pub mod publish;
+pub mod push;
+pub mod qr;
+pub mod ratings;
+pub mod records;
+pub mod refresh;
+pub mod reload;
+pub mod render;
+pub mod search;
+pub mod searches;
+pub mod server;
+pub mod session;
+pub mod shed;
+pub mod shopping;
+pub mod shortlink;
+pub mod signing;
+pub mod site;
+pub mod slack;
+pub mod source;
+pub mod state;
+pub mod status;
+pub mod stock;
+pub mod storage;
+pub mod stores;
+pub mod sync;
+pub mod telegram;
+pub mod text;
+pub mod tried;
+pub mod venue;
+pub mod view;
+pub mod warm;
+pub mod webhook;
+
+pub use systembolaget_enrichment::{catalog, cost, diff, score, units};
+
+pub use error::{Error, Result};
+pub use server::ApkServer;
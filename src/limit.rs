@@ -0,0 +1,58 @@
+//! Limits on how much each client may do per hour, for what costs storage or mail, like making
+//! short links. Clients are told apart by [`crate::access::client_address`].
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use warp::http::{header, StatusCode};
+use warp::reply::Response;
+use warp::Reply;
+
+/// Keys counted per hour at most. Beyond that, new ones are turned away until the next hour.
+const MAX_KEYS: usize = 10_000;
+
+/// How many times each key, usually a client, has done something this hour.
+pub struct Hourly<K> {
+    max: u32,
+    counts: Mutex<(u64, HashMap<K, u32>)>,
+}
+
+impl<K: Eq + Hash> Hourly<K> {
+    /// Allowing `max` per key and hour.
+    pub fn new(max: u32) -> Hourly<K> {
+        Hourly {
+            max,
+            counts: Mutex::new((0, HashMap::new())),
+        }
+    }
+
+    /// Counts one more for `key` at `now`, in seconds since the epoch, if it's within the limit.
+    pub fn allow(&self, key: K, now: u64) -> bool {
+        let mut counts = self.counts.lock().unwrap();
+        let hour = now / 3600;
+        if counts.0 != hour {
+            *counts = (hour, HashMap::new());
+        }
+        let by_key = &mut counts.1;
+        if by_key.len() >= MAX_KEYS && !by_key.contains_key(&key) {
+            return false;
+        }
+        let count = by_key.entry(key).or_insert(0);
+        if *count >= self.max {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+}
+
+/// A `429 Too Many Requests`, to try again next hour.
+pub fn too_many(now: u64) -> Response {
+    let retry_after = (3600 - now % 3600).to_string();
+    warp::reply::with_header(
+        StatusCode::TOO_MANY_REQUESTS,
+        header::RETRY_AFTER,
+        retry_after,
+    )
+    .into_response()
+}
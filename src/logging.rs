@@ -0,0 +1,29 @@
+//! Logging through `tracing`, as plain text or as JSON for log aggregators. The level comes from
+//! `APK_LOG`, or else the config, and takes the same directives as `RUST_LOG`.
+
+use crate::config::LogConfig;
+use crate::error::{Error, Result};
+use std::env;
+use tracing_subscriber::EnvFilter;
+
+pub const LOG_ENV_VAR: &str = "APK_LOG";
+const DEFAULT_LEVEL: &str = "info";
+
+/// Starts logging as `config` says. Must only be called once.
+pub fn init(config: &LogConfig) -> Result<()> {
+    let level = env::var(LOG_ENV_VAR)
+        .ok()
+        .or_else(|| config.level.clone())
+        .unwrap_or_else(|| DEFAULT_LEVEL.to_string());
+    let filter = EnvFilter::try_new(&level)
+        .map_err(|err| Error::Config(format!("bad log level {}: {}", level, err)))?;
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    let result = if config.json {
+        builder.json().try_init()
+    } else {
+        builder.try_init()
+    };
+    result.map_err(|err| Error::Config(format!("logging is already set up: {}", err)))
+}
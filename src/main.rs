@@ -1,179 +1,244 @@
-use serde_json::{json, Value};
+use apk::archive::Archive;
+use apk::catalog::{Category, CATEGORIES};
+use apk::config::{self, Config};
+use apk::discord::DiscordNotifier;
+use apk::email::{EmailAlerts, EmailDigest, Mailer};
+use apk::logging;
+use apk::mastodon::MastodonNotifier;
+use apk::matrix::{MatrixBot, MatrixClient, MatrixNotifier};
+use apk::ntfy::NtfyNotifier;
+use apk::push::WebPushAlerts;
+use apk::server::{ApkServer, ApkServerBuilder, DEFAULT_ADDR};
+use apk::site;
+use apk::source::{ArchiveSource, MirrorSource, ProductSource};
+use apk::storage::FileStorage;
+use apk::telegram::TelegramBot;
+use apk::text;
+use apk::webhook::WebhookNotifier;
+use apk::{Error, Result};
+use clap::{Args, Parser, Subcommand};
 use std::env;
-use std::sync::{Arc, RwLock};
-use systemet::{Product, Systemet};
-use tera::{Context, Tera};
-use warp::{reply::html, Filter};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use systemet::Systemet;
+use tracing::info;
 
-const TEMPLATE_GLOB: &str = "templates/*";
-const TEMPLATE: &str = "apk.html";
 const KEY_ENV_VAR: &str = "APK_API_KEY";
-const PORT_ENV_VAR: &str = "APK_PORT";
-const ADDR_ENV_VAR: &str = "APK_ADDR";
-const DEFAULT_PORT: u16 = 3030;
-const DEFAULT_ADDR: [u8; 4] = [127, 0, 0, 1];
-/// In seconds
-const UPDATE_INTERVAL: u64 = 7200;
-const RETRY_INTERVAL: u64 = 5;
 
-async fn render(tera: &Tera, systemet: &Systemet) -> Result<String, Box<dyn std::error::Error>> {
-    eprintln!("Fetching list of products...");
-    let products = systemet.get_all_products().await?;
-
-    eprintln!("Categorizing products...");
-    let mut wines = Vec::new();
-    let mut beers = Vec::new();
-    let mut ciders = Vec::new();
-    let mut liquors = Vec::new();
-    let mut others = Vec::new();
+/// The best alcohol per krona at Systembolaget.
+/// Serves the list when no command is given.
+#[derive(Parser)]
+#[clap(version, args_conflicts_with_subcommands = true)]
+struct Cli {
+    /// The config file
+    #[clap(long, global = true, env = config::CONFIG_ENV_VAR)]
+    config: Option<PathBuf>,
+    #[clap(flatten)]
+    serve: ServeArgs,
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
 
-    products
-        .into_iter()
-        .filter(|drink| {
-            drink.alcohol_percentage > 0.0
-                && !drink.assortment.as_ref().unwrap().eq("BS")
-                && !drink.assortment.as_ref().unwrap().eq("TSLS")
-                && !drink.is_completely_out_of_stock
-        })
-        .for_each(
-            |drink| match &drink.category.as_ref().unwrap_or(&"Other".to_string()) as &str {
-                "Röda viner" | "Vita viner" | "Mousserande viner" | "Roséviner"
-                | "Aperitif & dessert" => wines.push(drink),
-                "Öl" => beers.push(drink),
-                "Cider och blanddrycker" => {
-                    match &drink.sub_category.as_ref().unwrap_or(&"Other".to_string()) as &str {
-                        "Cider" => ciders.push(drink),
-                        _ => others.push(drink),
-                    }
-                }
-                "Sprit" => liquors.push(drink),
-                _ => others.push(drink),
-            },
-        );
-    eprintln!("Sorting...");
-    wines.sort_by(apk_comparator);
-    beers.sort_by(apk_comparator);
-    ciders.sort_by(apk_comparator);
-    liquors.sort_by(apk_comparator);
-    others.sort_by(apk_comparator);
-    let drinks = json!({
-        "Öl": beers,
-        "Vin": wines,
-        "Cider": ciders,
-        "Sprit": liquors,
-        "Annat": others,
-    });
-    eprintln!("Rendering...");
-    let mut context = Context::new();
-    context.insert("drinks", &drinks);
-    match tera.render(TEMPLATE, &context) {
-        Ok(body) => Ok(body),
-        Err(err) => {
-            eprintln!("{:?}", err);
-            Err(Box::new(err))
-        }
-    }
+#[derive(Subcommand)]
+enum Command {
+    /// Serves the list, refreshing it regularly. The default.
+    Serve(ServeArgs),
+    /// Fetches the list once and writes the pages and exports to a directory, for static hosting
+    Render {
+        #[clap(long)]
+        out: PathBuf,
+    },
+    /// Fetches the list once and prints the best of each category as a table
+    Dump {
+        /// Only this category, like öl or sprit
+        #[clap(long, parse(try_from_str = parse_category))]
+        category: Option<Category>,
+        #[clap(long, default_value = "20")]
+        top: usize,
+    },
+    /// Saves the current products to a file, or serves the products in one
+    Snapshot {
+        #[clap(subcommand)]
+        command: SnapshotCommand,
+    },
 }
 
-pub fn format_float(
-    value: &serde_json::Value,
-    args: &std::collections::HashMap<String, Value>,
-) -> tera::Result<serde_json::Value> {
-    let number: f64 = serde_json::from_value(value.clone())?;
-    let precision = serde_json::from_value(args.get("precision").unwrap().to_owned())?;
-    Ok(serde_json::to_value(format!("{:.*}", precision, number))?)
+#[derive(Subcommand)]
+enum SnapshotCommand {
+    Export {
+        file: PathBuf,
+    },
+    /// Serves the products in `file` instead of the live list, without needing an API key
+    Import {
+        file: PathBuf,
+        #[clap(flatten)]
+        serve: ServeArgs,
+    },
 }
 
-pub fn apk_filter(
-    value: &serde_json::Value,
-    _: &std::collections::HashMap<String, Value>,
-) -> tera::Result<serde_json::Value> {
-    let drink: Product = serde_json::from_value(value.clone())?;
-    Ok(serde_json::to_value(apk(&drink))?)
+#[derive(Args)]
+struct ServeArgs {
+    #[clap(long, env = "APK_PORT", default_value_t = DEFAULT_ADDR.1)]
+    port: u16,
+    #[clap(long, env = "APK_ADDR")]
+    addr: Option<IpAddr>,
+    /// Where to keep state across restarts. In memory if not given.
+    #[clap(long, env = "APK_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+    /// Seconds between refreshes
+    #[clap(long, env = "APK_UPDATE_INTERVAL")]
+    interval: Option<u64>,
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let key = env::var(KEY_ENV_VAR)?;
-    let systemet = Systemet::new(key);
-    let mut tera = Tera::new(TEMPLATE_GLOB)?;
-    tera.register_filter("apk", apk_filter);
-    tera.register_filter("format_float", format_float);
-    let page = Arc::new(RwLock::new("".to_string()));
-    let page2 = page.clone();
+fn parse_category(name: &str) -> std::result::Result<Category, String> {
+    Category::from_name(name)
+        .or_else(|| Category::from_slug(name))
+        .ok_or_else(|| format!("no category called {}", name))
+}
 
-    tokio::spawn(async move {
-        let page = page.clone();
-        let systemet = systemet.clone();
-        loop {
-            let delay;
-            eprintln!("Updating APK list...");
-            match render(&tera, &systemet).await {
-                Ok(body) => {
-                    let mut page = page.write().unwrap();
-                    *page = body;
-                    delay = UPDATE_INTERVAL;
-                    eprintln!("Succesfully updated APK list");
-                }
-                Err(err) => {
-                    delay = RETRY_INTERVAL;
-                    eprintln!("{:?}", err);
-                }
-            }
-            tokio::time::delay_for(std::time::Duration::new(delay, 0)).await;
-        }
-    });
+fn api_key() -> Result<String> {
+    env::var(KEY_ENV_VAR).map_err(|_| Error::Config(format!("{} must be set", KEY_ENV_VAR)))
+}
 
-    let routes = warp::get()
-        .map(move || page2.clone())
-        .map(|page: Arc<RwLock<String>>| html((*page).read().unwrap().to_string()));
+/// A server of the live list, or of the primary's when mirroring, which needs no API key.
+fn live(config: &Config) -> Result<ApkServerBuilder> {
+    Ok(match &config.mirror {
+        Some(mirror) => ApkServer::builder().source(MirrorSource::new(&mirror.url)),
+        None => ApkServer::builder().source(Systemet::new(api_key()?)),
+    })
+}
 
-    let port = env::var(PORT_ENV_VAR)
-        .ok()
-        .and_then(|n| n.parse().ok())
-        .unwrap_or(DEFAULT_PORT);
-    let addr = env::var(ADDR_ENV_VAR)
-        .ok()
-        .and_then(|a| a.parse().ok())
-        .unwrap_or(DEFAULT_ADDR.into());
-    let sock_addr = std::net::SocketAddr::new(addr, port);
-    println!("Listening on {}...", sock_addr);
-    Ok(warp::serve(routes).run(sock_addr).await)
+/// A server that's only updated once, by hand, so that nothing it does outlives the command.
+fn one_off(config: Config) -> Result<ApkServer> {
+    live(&config)?
+        .config(Config {
+            price_history: None,
+            ..config
+        })
+        .build()
 }
 
-fn apk(drink: &Product) -> f64 {
-    drink.alcohol_percentage * drink.volume / (drink.price + drink.recycle_fee)
+async fn render(config: Config, out: &Path) -> Result<()> {
+    let server = one_off(config)?;
+    server.update().await?;
+    let snapshot = server.state().snapshot.read().unwrap().clone();
+    if let Some(snapshot) = snapshot {
+        info!("Writing pages to {}...", out.display());
+        site::write(server.state(), &snapshot, out)?;
+    }
+    Ok(())
 }
 
-fn basen_apk(drink: &Product) -> f64 {
-    basen_price(drink) * drink.volume / (drink.price + drink.recycle_fee)
+async fn dump(config: Config, category: Option<Category>, top: usize) -> Result<()> {
+    let display = config.display.clone();
+    let server = one_off(config)?;
+    server.update().await?;
+    let snapshot = server.state().snapshot.read().unwrap().clone();
+    if let Some(snapshot) = snapshot {
+        let categories = match category {
+            Some(category) => vec![category],
+            None => CATEGORIES.to_vec(),
+        };
+        for category in categories {
+            println!(
+                "{}\n",
+                text::table(&snapshot.catalog, category, top, &display)
+            );
+        }
+    }
+    Ok(())
 }
 
-fn basen_price(drink: &Product) -> f64 {
-    (drink.price * 1.25 / 5.0).ceil() * 5.0
+fn bind(builder: ApkServerBuilder, args: ServeArgs) -> Result<ApkServerBuilder> {
+    let addr = args.addr.unwrap_or_else(|| IpAddr::from(DEFAULT_ADDR.0));
+    let mut builder = builder.bind(SocketAddr::new(addr, args.port));
+    if let Some(dir) = args.data_dir {
+        builder = builder.storage(FileStorage::new(dir)?);
+    }
+    if let Some(interval) = args.interval {
+        builder = builder.interval(interval);
+    }
+    Ok(builder)
 }
 
-fn apk_comparator(d1: &Product, d2: &Product) -> std::cmp::Ordering {
-    if apk(d1) < apk(d2) {
-        std::cmp::Ordering::Greater
-    } else if apk(d1) > apk(d2) {
-        std::cmp::Ordering::Less
-    } else {
-        std::cmp::Ordering::Equal
+async fn serve(builder: ApkServerBuilder, config: Config, args: ServeArgs) -> Result<()> {
+    let mut builder = bind(builder, args)?;
+    if config.mirror.is_some() {
+        // The primary notifies and runs the bots
+        return builder.config(config).build()?.run().await;
+    }
+    for webhook in config.webhook.iter().chain(&config.webhooks) {
+        builder = builder.notifier(WebhookNotifier::new(webhook.clone()));
+    }
+    if let Some(discord) = config.discord.clone() {
+        builder = builder.notifier(DiscordNotifier::new(discord));
+    }
+    if let Some(telegram) = config.telegram.clone() {
+        builder = builder.job(TelegramBot::new(telegram));
+    }
+    if let Some(email) = &config.email {
+        let mailer = Arc::new(Mailer::new(email)?);
+        builder = builder
+            .mailer(mailer.clone())
+            .notifier(EmailAlerts::new(mailer.clone()))
+            .job(EmailDigest::new(mailer));
+    }
+    if let Some(matrix) = config.matrix.clone() {
+        let client = Arc::new(MatrixClient::new(matrix));
+        builder = builder
+            .notifier(MatrixNotifier::new(client.clone()))
+            .job(MatrixBot::new(client));
+    }
+    if let Some(mastodon) = config.mastodon.clone() {
+        builder = builder.notifier(MastodonNotifier::new(mastodon));
+    }
+    if let Some(mqtt) = &config.mqtt {
+        let (notifier, connection) = apk::mqtt::connect(mqtt);
+        builder = builder.notifier(notifier).job(connection);
     }
+    if let Some(ntfy) = config.ntfy.clone() {
+        builder = builder.notifier(NtfyNotifier::new(ntfy));
+    }
+    if let Some(push) = &config.push {
+        builder = builder.notifier(WebPushAlerts::new(push)?);
+    }
+    builder.config(config).build()?.run().await
 }
 
-//async fn make_list(
-//    tera: Tera,
-//    drinks: Arc<RwLock<Drinks>>,
-//) -> Result<impl warp::Reply, warp::Rejection> {
-//    let mut context = Context::new();
-//    context.insert("drinks", &drinks.read().unwrap().beers);
-//    match tera.render(TEMPLATE, &context) {
-//        Ok(body) => Ok(Response::builder().body(body)),
-//        Err(err) => {
-//            eprintln!("{:?}", err);
-//            Ok(err.to_string())
-//        }
-//    }
-//}
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let config = match &cli.config {
+        Some(path) => {
+            // Where reloading looks for it, see apk::reload
+            env::set_var(config::CONFIG_ENV_VAR, path);
+            Config::load(path)?
+        }
+        None => Config::default(),
+    };
+    logging::init(&config.log)?;
+    match cli.command {
+        None => serve(live(&config)?, config, cli.serve).await,
+        Some(Command::Serve(args)) => serve(live(&config)?, config, args).await,
+        Some(Command::Render { out }) => render(config, &out).await,
+        Some(Command::Dump { category, top }) => dump(config, category, top).await,
+        Some(Command::Snapshot { command }) => match command {
+            SnapshotCommand::Export { file } => {
+                let products = Systemet::new(api_key()?).fetch_products().await?;
+                info!(
+                    "Writing {} products to {}...",
+                    products.len(),
+                    file.display()
+                );
+                Archive::new(products, SystemTime::now()).write(file)
+            }
+            SnapshotCommand::Import { file, serve: args } => {
+                // Nothing in an old file is news, and the bots belong to the live server
+                let builder = ApkServer::builder().source(ArchiveSource::new(file));
+                bind(builder, args)?.config(config).build()?.run().await
+            }
+        },
+    }
+}
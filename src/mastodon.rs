@@ -0,0 +1,181 @@
+//! Toots when a product beats the best APK ever seen, or when the #1 of a category changes.
+
+use crate::buy;
+use crate::catalog::{self, Category, CATEGORIES};
+use crate::config::MastodonConfig;
+use crate::error::Result;
+use crate::notify::{Notifier, RefreshEvent};
+use crate::state::AppState;
+use crate::storage;
+use crate::units::Measures;
+use async_trait::async_trait;
+use secrecy::ExposeSecret;
+use serde::Serialize;
+use serde_json::json;
+use std::cmp::Ordering;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use systemet::Product;
+use tera::{Context, Tera};
+use tracing::warn;
+
+const RECORD_KEY: &str = "mastodon-record.json";
+pub const DEFAULT_TEMPLATE: &str = "{% if kind == \"record\" %}Nytt APK-rekord!{% else %}Ny etta bland {{ category }}!{% endif %} \
+    {{ name }}, {{ apk | round(precision=3) }} ml alkohol per krona för {{ price }} kr. {{ link }}";
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    /// The best APK ever seen, in any category
+    Record,
+    /// A new #1 in a category
+    Top,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Post {
+    pub kind: Kind,
+    pub category: &'static str,
+    pub id: String,
+    pub name: String,
+    pub apk: f64,
+    pub price: f64,
+    pub link: String,
+}
+
+impl Post {
+    fn new(kind: Kind, category: Category, drink: &Product) -> Post {
+        Post {
+            kind,
+            category: category.name(),
+            id: catalog::id(drink).to_string(),
+            name: catalog::name(drink).to_string(),
+            apk: catalog::apk(drink).0,
+            price: drink.price_with_deposit().0,
+            link: buy::url(drink),
+        }
+    }
+}
+
+/// What to toot about after a refresh, given the best APK seen before it. A new record is only
+/// tooted once, not again as a new #1.
+pub fn posts(event: &RefreshEvent, record: Option<f64>) -> Vec<Post> {
+    let catalog = &event.snapshot.catalog;
+    let mut posts = Vec::new();
+    // By APK alone, whatever the categories are scored by
+    let best = CATEGORIES
+        .iter()
+        .flat_map(|&category| {
+            catalog
+                .get(category)
+                .iter()
+                .map(move |drink| (category, drink))
+        })
+        .max_by(|(_, d1), (_, d2)| {
+            let (apk1, apk2) = (catalog::apk(d1).0, catalog::apk(d2).0);
+            apk1.partial_cmp(&apk2).unwrap_or(Ordering::Equal)
+        });
+    if let Some((category, drink)) = best {
+        if record.map_or(false, |record| catalog::apk(drink).0 > record) {
+            posts.push(Post::new(Kind::Record, category, drink));
+        }
+    }
+    let previous = match &event.previous {
+        Some(previous) => &previous.catalog,
+        None => return posts,
+    };
+    for &category in CATEGORIES.iter() {
+        let old = previous.get(category).first().map(catalog::id);
+        if let Some(drink) = catalog.get(category).first() {
+            let new = catalog::id(drink);
+            if old.map_or(false, |old| old != new) && !posts.iter().any(|post| post.id == new) {
+                posts.push(Post::new(Kind::Top, category, drink));
+            }
+        }
+    }
+    posts
+}
+
+pub struct MastodonNotifier {
+    client: reqwest::Client,
+    config: MastodonConfig,
+    last_post: Mutex<Option<Instant>>,
+}
+
+impl MastodonNotifier {
+    pub fn new(config: MastodonConfig) -> MastodonNotifier {
+        MastodonNotifier {
+            client: reqwest::Client::new(),
+            config,
+            last_post: Mutex::new(None),
+        }
+    }
+
+    /// Whether enough time has passed since the last toot, marking now as the last one if so.
+    fn may_post(&self) -> bool {
+        let mut last_post = self.last_post.lock().unwrap();
+        let min_interval = Duration::from_secs(self.config.min_interval);
+        if last_post.map_or(false, |last| last.elapsed() < min_interval) {
+            return false;
+        }
+        *last_post = Some(Instant::now());
+        true
+    }
+
+    pub fn text(&self, post: &Post) -> Result<String> {
+        let template = self.config.template.as_deref().unwrap_or(DEFAULT_TEMPLATE);
+        Ok(Tera::one_off(
+            template,
+            &Context::from_serialize(post)?,
+            false,
+        )?)
+    }
+
+    async fn toot(&self, text: &str) -> Result<()> {
+        self.client
+            .post(&format!(
+                "{}/api/v1/statuses",
+                self.config.instance.trim_end_matches('/')
+            ))
+            .bearer_auth(self.config.access_token.expose_secret())
+            .json(&json!({ "status": text }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Notifier for MastodonNotifier {
+    fn name(&self) -> &str {
+        "mastodon"
+    }
+
+    async fn notify(&self, state: &AppState, event: &RefreshEvent) -> Result<()> {
+        let record = storage::load_json::<f64>(&*state.storage, RECORD_KEY)?;
+        if record.is_none() {
+            // Nothing to beat yet, so the best now is the record to beat
+            let best = event
+                .snapshot
+                .catalog
+                .products()
+                .map(|drink| catalog::apk(drink).0)
+                .fold(0.0, f64::max);
+            storage::save_json(&*state.storage, RECORD_KEY, &best)?;
+        }
+
+        for post in posts(event, record) {
+            if !self.may_post() {
+                warn!("Not tooting about {}, tooted too recently", post.name);
+                continue;
+            }
+            self.toot(&self.text(&post)?).await?;
+            // Only once it's tooted, so a record that couldn't be is tooted next time
+            if post.kind == Kind::Record {
+                storage::save_json(&*state.storage, RECORD_KEY, &post.apk)?;
+            }
+        }
+        Ok(())
+    }
+}
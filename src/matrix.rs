@@ -0,0 +1,315 @@
+//! A Matrix client that posts a summary of each refresh to a room and answers `!`-commands there.
+
+use crate::catalog::{self, Category};
+use crate::config::MatrixConfig;
+use crate::error::{Error, Result};
+use crate::notify::{Notifier, RefreshEvent};
+use crate::server::Job;
+use crate::state::AppState;
+use crate::text;
+use async_trait::async_trait;
+use secrecy::ExposeSecret;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::error;
+
+/// In milliseconds, as Matrix wants it
+const SYNC_TIMEOUT: u64 = 30_000;
+const ERROR_DELAY: Duration = Duration::from_secs(10);
+const TOP: usize = 10;
+const SEARCH_RESULTS: usize = 5;
+const TOP_CHANGES: usize = 5;
+const HELP: &str = "Kommandon:\n\
+    !top <öl|vin|cider|sprit|annat> – bästa APK i en kategori\n\
+    !sok <namn> – sök efter dricka\n\
+    !apk <artikelnummer> – APK för en viss produkt";
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Top(Category),
+    Search(String),
+    Apk(String),
+    Help,
+}
+
+/// Parses a message. Returns `None` for anything that isn't a command.
+pub fn parse(text: &str) -> Option<Command> {
+    let text = text.trim();
+    if !text.starts_with('!') {
+        return None;
+    }
+    let mut parts = text.splitn(2, char::is_whitespace);
+    let command = parts.next()?;
+    let argument = parts.next().unwrap_or("").trim();
+    Some(match command {
+        "!top" => match Category::from_name(argument) {
+            Some(category) => Command::Top(category),
+            None => Command::Help,
+        },
+        "!sok" | "!sök" | "!search" if !argument.is_empty() => {
+            Command::Search(argument.to_string())
+        }
+        "!apk" if !argument.is_empty() => Command::Apk(argument.to_string()),
+        _ => Command::Help,
+    })
+}
+
+/// A short summary of a refresh, or `None` if nothing changed.
+pub fn summary(event: &RefreshEvent) -> Option<String> {
+    if event.diff.is_empty() {
+        return None;
+    }
+    let catalog = &event.snapshot.catalog;
+    let mut lines = vec![format!(
+        "APK-listan uppdaterad: {} nya, {} borttagna, {} prisändringar.",
+        event.diff.added.len(),
+        event.diff.removed.len(),
+        event.diff.price_changes.len()
+    )];
+    for change in event.diff.top_changes(TOP_CHANGES) {
+        if let Some(drink) = catalog.find(&change.id) {
+            lines.push(format!(
+                "{}: {} → {}",
+                catalog::name(drink),
+                change.old_price,
+                change.new_price
+            ));
+        }
+    }
+    Some(lines.join("\n"))
+}
+
+#[derive(Deserialize)]
+struct Sync {
+    next_batch: String,
+    #[serde(default)]
+    rooms: Rooms,
+}
+
+#[derive(Default, Deserialize)]
+struct Rooms {
+    #[serde(default)]
+    join: HashMap<String, JoinedRoom>,
+}
+
+#[derive(Deserialize)]
+struct JoinedRoom {
+    timeline: Timeline,
+}
+
+#[derive(Deserialize)]
+struct Timeline {
+    #[serde(default)]
+    events: Vec<Event>,
+}
+
+#[derive(Deserialize)]
+struct Event {
+    #[serde(rename = "type")]
+    kind: String,
+    sender: String,
+    #[serde(default)]
+    content: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct WhoAmI {
+    user_id: String,
+}
+
+/// Talks to the homeserver on behalf of the bot user.
+pub struct MatrixClient {
+    client: reqwest::Client,
+    config: MatrixConfig,
+    transaction: AtomicU64,
+}
+
+impl MatrixClient {
+    pub fn new(config: MatrixConfig) -> MatrixClient {
+        MatrixClient {
+            client: reqwest::Client::new(),
+            config,
+            transaction: AtomicU64::new(0),
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!(
+            "{}/_matrix/client/r0/{}",
+            self.config.homeserver.trim_end_matches('/'),
+            path
+        )
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<T> {
+        let response = self
+            .client
+            .get(&self.url(path))
+            .bearer_auth(self.config.access_token.expose_secret())
+            .query(query)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(Error::upstream(format!(
+                "Matrix {} failed: {}",
+                path,
+                response.status()
+            )));
+        }
+        Ok(response.json().await?)
+    }
+
+    /// Posts `text` to the configured room.
+    pub async fn send(&self, text: &str) -> Result<()> {
+        // Transaction ids only need to be unique for this access token and process
+        let transaction = format!(
+            "apk-{}-{}",
+            std::process::id(),
+            self.transaction.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = format!(
+            "rooms/{}/send/m.room.message/{}",
+            self.config.room, transaction
+        );
+        self.client
+            .put(&self.url(&path))
+            .bearer_auth(self.config.access_token.expose_secret())
+            .json(&json!({ "msgtype": "m.text", "body": text }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Posts a summary of each refresh that changed anything.
+pub struct MatrixNotifier {
+    client: Arc<MatrixClient>,
+}
+
+impl MatrixNotifier {
+    pub fn new(client: Arc<MatrixClient>) -> MatrixNotifier {
+        MatrixNotifier { client }
+    }
+}
+
+#[async_trait]
+impl Notifier for MatrixNotifier {
+    fn name(&self) -> &str {
+        "matrix"
+    }
+
+    async fn notify(&self, _: &AppState, event: &RefreshEvent) -> Result<()> {
+        match summary(event) {
+            Some(text) => self.client.send(&text).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Answers commands in the configured room.
+pub struct MatrixBot {
+    client: Arc<MatrixClient>,
+}
+
+impl MatrixBot {
+    pub fn new(client: Arc<MatrixClient>) -> MatrixBot {
+        MatrixBot { client }
+    }
+
+    fn reply(&self, state: &AppState, command: Command) -> String {
+        let snapshot = match state.snapshot.read().unwrap().clone() {
+            Some(snapshot) => snapshot,
+            None => return "Listan har inte laddats än, försök igen om en stund.".to_string(),
+        };
+        let catalog = &snapshot.catalog;
+        match command {
+            Command::Top(category) => text::top(catalog, category, TOP),
+            Command::Search(query) => match snapshot.index.search(catalog, &query).as_slice() {
+                [] => format!("Hittade inget som heter {}.", query),
+                matches => text::numbered(matches.iter().copied().take(SEARCH_RESULTS)),
+            },
+            Command::Apk(id) => match catalog.find(&id) {
+                Some(drink) => text::drink_line(drink),
+                None => format!("Hittade ingen produkt med nummer {}.", id),
+            },
+            Command::Help => HELP.to_string(),
+        }
+    }
+
+    /// The commands sent to the room by others since `since`, and the token to continue from.
+    async fn sync(&self, user: &str, since: &str) -> Result<(Vec<Command>, String)> {
+        let sync: Sync = self
+            .client
+            .get(
+                "sync",
+                &[
+                    ("since", since.to_string()),
+                    ("timeout", SYNC_TIMEOUT.to_string()),
+                ],
+            )
+            .await?;
+        let commands = sync
+            .rooms
+            .join
+            .get(&self.client.config.room)
+            .map(|room| {
+                room.timeline
+                    .events
+                    .iter()
+                    .filter(|event| event.kind == "m.room.message" && event.sender != user)
+                    .filter_map(|event| event.content.get("body")?.as_str())
+                    .filter_map(parse)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok((commands, sync.next_batch))
+    }
+
+    async fn poll(&self, state: &AppState) -> Result<()> {
+        let user = self
+            .client
+            .get::<WhoAmI>("account/whoami", &[])
+            .await?
+            .user_id;
+        // Skip whatever was said before we started
+        let mut since = self
+            .client
+            .get::<Sync>("sync", &[("timeout", "0".to_string())])
+            .await?
+            .next_batch;
+        loop {
+            let (commands, next) = match self.sync(&user, &since).await {
+                Ok(sync) => sync,
+                Err(err) => {
+                    error!("Syncing with Matrix failed: {}", err);
+                    tokio::time::delay_for(ERROR_DELAY).await;
+                    continue;
+                }
+            };
+            since = next;
+            for command in commands {
+                if let Err(err) = self.client.send(&self.reply(state, command)).await {
+                    error!("Replying on Matrix failed: {}", err);
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Job for MatrixBot {
+    async fn run(self: Box<Self>, state: AppState) {
+        loop {
+            if let Err(err) = self.poll(&state).await {
+                error!("Connecting to Matrix failed: {}", err);
+            }
+            tokio::time::delay_for(ERROR_DELAY).await;
+        }
+    }
+}
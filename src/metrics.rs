@@ -0,0 +1,113 @@
+use crate::catalog::CATEGORIES;
+use crate::error::Error;
+use crate::normalize;
+use crate::state::AppState;
+use std::fmt::Write;
+use std::sync::atomic::Ordering;
+
+/// Renders the Prometheus text exposition format.
+pub fn render(state: &AppState) -> String {
+    let status = state.status.read().unwrap().clone();
+    let snapshot = state.snapshot.read().unwrap().clone();
+    let mut out = String::new();
+
+    writeln!(
+        out,
+        "# HELP apk_refreshes_total Successful catalog refreshes."
+    )
+    .unwrap();
+    writeln!(out, "# TYPE apk_refreshes_total counter").unwrap();
+    writeln!(out, "apk_refreshes_total {}", status.refreshes).unwrap();
+
+    writeln!(
+        out,
+        "# HELP apk_refresh_errors_total Failed catalog refreshes."
+    )
+    .unwrap();
+    writeln!(out, "# TYPE apk_refresh_errors_total counter").unwrap();
+    for category in &Error::CATEGORIES {
+        let count = status.errors.get(category).copied().unwrap_or(0);
+        writeln!(
+            out,
+            "apk_refresh_errors_total{{category=\"{}\"}} {}",
+            category, count
+        )
+        .unwrap();
+    }
+
+    writeln!(
+        out,
+        "# HELP apk_refresh_failures Refreshes in a row that failed to fetch the products."
+    )
+    .unwrap();
+    writeln!(out, "# TYPE apk_refresh_failures gauge").unwrap();
+    writeln!(out, "apk_refresh_failures {}", status.failures).unwrap();
+
+    writeln!(
+        out,
+        "# HELP apk_refresh_backoff_seconds Wait before the next try, 0 if not backing off."
+    )
+    .unwrap();
+    writeln!(out, "# TYPE apk_refresh_backoff_seconds gauge").unwrap();
+    writeln!(
+        out,
+        "apk_refresh_backoff_seconds {}",
+        status.retry_in.unwrap_or(0)
+    )
+    .unwrap();
+
+    if let Some(last_success) = status.last_success {
+        writeln!(
+            out,
+            "# HELP apk_last_refresh_timestamp_seconds Time of the last successful refresh."
+        )
+        .unwrap();
+        writeln!(out, "# TYPE apk_last_refresh_timestamp_seconds gauge").unwrap();
+        writeln!(out, "apk_last_refresh_timestamp_seconds {}", last_success).unwrap();
+    }
+
+    writeln!(
+        out,
+        "# HELP apk_buy_redirects_total Visitors sent on to Systembolaget."
+    )
+    .unwrap();
+    writeln!(out, "# TYPE apk_buy_redirects_total counter").unwrap();
+    writeln!(
+        out,
+        "apk_buy_redirects_total {}",
+        state.buys.load(Ordering::Relaxed)
+    )
+    .unwrap();
+
+    writeln!(
+        out,
+        "# HELP apk_normalizations_total Upstream numbers fixed before parsing, by kind of fix."
+    )
+    .unwrap();
+    writeln!(out, "# TYPE apk_normalizations_total counter").unwrap();
+    for &fix in normalize::FIXES.iter() {
+        writeln!(
+            out,
+            "apk_normalizations_total{{fix=\"{}\"}} {}",
+            fix.name(),
+            normalize::count(fix)
+        )
+        .unwrap();
+    }
+
+    if let Some(snapshot) = snapshot {
+        writeln!(out, "# HELP apk_products Listed products per category.").unwrap();
+        writeln!(out, "# TYPE apk_products gauge").unwrap();
+        for &category in CATEGORIES.iter() {
+            let count = snapshot.catalog.get(category).len();
+            writeln!(
+                out,
+                "apk_products{{category=\"{}\"}} {}",
+                category.name(),
+                count
+            )
+            .unwrap();
+        }
+    }
+    out
+}
@@ -0,0 +1,111 @@
+//! The products whose APK changed the most over the last week, at `/movers` and for bots at
+//! `/api/movers`.
+
+use crate::catalog::{self, Catalog, Category};
+use crate::dates::DAY;
+use crate::error::Result;
+use crate::history::{self, Point};
+use crate::render;
+use crate::state::AppState;
+use crate::units::Apk;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use tracing::error;
+use warp::http::StatusCode;
+use warp::reply::{html, Response};
+use warp::{Filter, Rejection, Reply};
+
+const WEEK: u64 = 7 * DAY;
+/// How many gainers and losers are shown
+const MOVERS: usize = 10;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Mover {
+    pub id: String,
+    pub name: String,
+    pub category: Category,
+    pub old_apk: Apk,
+    pub new_apk: Apk,
+    /// How much the APK went up, or down if negative
+    pub delta: f64,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Movers {
+    /// Biggest increase first
+    pub gainers: Vec<Mover>,
+    /// Biggest decrease first
+    pub losers: Vec<Mover>,
+}
+
+/// The products in `catalog` whose APK changed the most in the week up to the newest point in
+/// `history`.
+pub fn movers(history: &[Point], catalog: &Catalog) -> Movers {
+    let since = match history.last() {
+        Some(last) => last.at.saturating_sub(WEEK),
+        None => return Movers::default(),
+    };
+    // From the APK before the first change in the week to the one after the last
+    let mut changes: HashMap<&str, (Apk, Apk)> = HashMap::new();
+    for point in history.iter().filter(|point| point.at > since) {
+        for change in &point.changes {
+            changes
+                .entry(&change.id)
+                .or_insert((change.old_apk, change.new_apk))
+                .1 = change.new_apk;
+        }
+    }
+    let mut all: Vec<Mover> = changes
+        .into_iter()
+        .filter_map(|(id, (old_apk, new_apk))| {
+            let drink = catalog.find(id)?;
+            Some(Mover {
+                id: id.to_string(),
+                name: catalog::name(drink).to_string(),
+                category: catalog::categorize(drink),
+                old_apk,
+                new_apk,
+                delta: new_apk.0 - old_apk.0,
+            })
+        })
+        .collect();
+    all.sort_by(|m1, m2| m2.delta.partial_cmp(&m1.delta).unwrap_or(Ordering::Equal));
+    let gainers = all
+        .iter()
+        .filter(|mover| mover.delta > 0.0)
+        .take(MOVERS)
+        .cloned()
+        .collect();
+    let losers = all
+        .iter()
+        .rev()
+        .filter(|mover| mover.delta < 0.0)
+        .take(MOVERS)
+        .cloned()
+        .collect();
+    Movers { gainers, losers }
+}
+
+/// The movers of the current catalog.
+pub fn current(state: &AppState) -> Result<Movers> {
+    let snapshot = match state.snapshot.read().unwrap().clone() {
+        Some(snapshot) => snapshot,
+        None => return Ok(Movers::default()),
+    };
+    Ok(movers(&history::load(&*state.storage)?, &snapshot.catalog))
+}
+
+fn page(state: &AppState) -> Result<String> {
+    Ok(render::render_movers(&state.tera(), &current(state)?)?)
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("movers").map(move || match page(&state) {
+        Ok(page) => html(page).into_response(),
+        Err(err) => {
+            error!("{}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    })
+}
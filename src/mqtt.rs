@@ -0,0 +1,105 @@
+//! Publishes the best product of each category and the time of the last refresh to an MQTT
+//! broker, as retained messages under `apk/`.
+
+use crate::catalog::{self, CATEGORIES};
+use crate::config::MqttConfig;
+use crate::error::{Error, Result};
+use crate::notify::{Notifier, RefreshEvent};
+use crate::server::Job;
+use crate::state::AppState;
+use crate::status::unix_time;
+use crate::units::Measures;
+use async_trait::async_trait;
+use rumqttc::{AsyncClient, EventLoop, MqttOptions, QoS};
+use secrecy::ExposeSecret;
+use serde_json::json;
+use std::time::Duration;
+use tracing::error;
+
+const CLIENT_ID: &str = "apk";
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+const CAPACITY: usize = 32;
+
+/// The topics and payloads to publish after a refresh.
+pub fn messages(prefix: &str, event: &RefreshEvent) -> Vec<(String, String)> {
+    let catalog = &event.snapshot.catalog;
+    let mut messages: Vec<(String, String)> = CATEGORIES
+        .iter()
+        .filter_map(|&category| {
+            let best = catalog.get(category).first()?;
+            let payload = json!({
+                "id": catalog::id(best),
+                "name": catalog::name(best),
+                "apk": catalog::apk(best),
+                "price": best.price_with_deposit(),
+            });
+            Some((
+                format!("{}/best/{}", prefix, category.slug()),
+                payload.to_string(),
+            ))
+        })
+        .collect();
+    messages.push((
+        format!("{}/last_update", prefix),
+        unix_time(event.snapshot.updated_at).to_string(),
+    ));
+    messages
+}
+
+/// Publishes after each refresh. Needs an [`MqttConnection`] running to actually send anything.
+pub struct MqttNotifier {
+    client: AsyncClient,
+    prefix: String,
+}
+
+/// Drives the connection to the broker, reconnecting when it drops.
+pub struct MqttConnection {
+    eventloop: EventLoop,
+}
+
+/// Sets up a client for the broker in `config`.
+pub fn connect(config: &MqttConfig) -> (MqttNotifier, MqttConnection) {
+    let mut options = MqttOptions::new(CLIENT_ID, &config.host, config.port);
+    options.set_keep_alive(KEEP_ALIVE.as_secs() as u16);
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        options.set_credentials(username, password.expose_secret());
+    }
+    let (client, eventloop) = AsyncClient::new(options, CAPACITY);
+    (
+        MqttNotifier {
+            client,
+            prefix: config.prefix.trim_end_matches('/').to_string(),
+        },
+        MqttConnection { eventloop },
+    )
+}
+
+#[async_trait]
+impl Notifier for MqttNotifier {
+    fn name(&self) -> &str {
+        "mqtt"
+    }
+
+    async fn notify(&self, _: &AppState, event: &RefreshEvent) -> Result<()> {
+        for (topic, payload) in messages(&self.prefix, event) {
+            self.client
+                .publish(topic, QoS::AtLeastOnce, true, payload)
+                .await
+                .map_err(Error::upstream)?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Job for MqttConnection {
+    async fn run(mut self: Box<Self>, _: AppState) {
+        loop {
+            if let Err(err) = self.eventloop.poll().await {
+                error!("MQTT connection failed: {}", err);
+                tokio::time::delay_for(RECONNECT_DELAY).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Cleaned up product names, for showing, and slugs made from them, for URLs. Years are left out,
+//! so that the vintages of a wine share a name, and volumes are written the same way, like `33 cl`
+//! for both `33CL` and `33cl`. Operators can replace words and set slugs in the config, see
+//! [`NamesConfig`]. Products with the same name but for the year and volume are grouped on the
+//! product pages, and slugs go in their permalinks, `/produkt/{id}/{slug}`.
+
+use crate::catalog::{self, Catalog};
+use crate::config::NamesConfig;
+use serde_json::Value;
+use std::collections::HashMap;
+use systemet::Product;
+
+enum Token {
+    Word(String),
+    Year(String),
+    /// As written, and standardized
+    Volume(String, String),
+}
+
+/// The punctuation before `text`, the rest of it, and the punctuation after it.
+fn punctuated(text: &str) -> (&str, &str, &str) {
+    let start = text.find(char::is_alphanumeric).unwrap_or(text.len());
+    let end = text
+        .rfind(char::is_alphanumeric)
+        .map_or(start, |i| i + text[i..].chars().next().unwrap().len_utf8());
+    (&text[..start], &text[start..end], &text[end..])
+}
+
+fn is_year(word: &str) -> bool {
+    word.len() == 4
+        && word.bytes().all(|b| b.is_ascii_digit())
+        && (1900..2100).contains(&word.parse::<u32>().unwrap())
+}
+
+/// `number` with a decimal comma, if it's a number.
+fn number(number: &str) -> Option<String> {
+    let digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    let mut parts = number.splitn(2, |c| c == ',' || c == '.');
+    let whole = parts.next().filter(|whole| digits(whole))?;
+    match parts.next() {
+        Some(fraction) if digits(fraction) => Some(format!("{},{}", whole, fraction)),
+        Some(_) => None,
+        None => Some(whole.to_string()),
+    }
+}
+
+fn unit(unit: &str) -> Option<&'static str> {
+    match unit.to_lowercase().as_str() {
+        "ml" => Some("ml"),
+        "cl" => Some("cl"),
+        "dl" => Some("dl"),
+        "l" | "liter" | "litre" => Some("l"),
+        _ => None,
+    }
+}
+
+/// `word` as a standardized volume, if it is one, like `33 cl` for `33CL`.
+fn volume(word: &str) -> Option<String> {
+    let split = word.find(char::is_alphabetic)?;
+    let (amount, unit) = (number(&word[..split])?, unit(&word[split..])?);
+    Some(format!("{} {}", amount, unit))
+}
+
+fn tokens(config: &NamesConfig, name: &str) -> Vec<Token> {
+    let replace: HashMap<String, &str> = config
+        .replace
+        .iter()
+        .map(|(from, to)| (from.to_lowercase(), to.as_str()))
+        .collect();
+    let words: Vec<String> = name
+        .split_whitespace()
+        .map(|word| {
+            let (before, core, after) = punctuated(word);
+            match replace.get(&core.to_lowercase()) {
+                Some(to) => format!("{}{}{}", before, to, after),
+                None => word.to_string(),
+            }
+        })
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        let word = &words[i];
+        let (before, core, after) = punctuated(word);
+        let next = words.get(i + 1).filter(|_| after.is_empty());
+        if is_year(core) {
+            tokens.push(Token::Year(word.clone()));
+        } else if let Some(volume) = volume(core) {
+            let standard = format!("{}{}{}", before, volume, after);
+            tokens.push(Token::Volume(word.clone(), standard));
+        } else if let Some((next, standard)) = next.and_then(|next| {
+            // Like `33 CL`
+            let (_, written, after) = punctuated(next);
+            let standard = format!("{}{} {}{}", before, number(core)?, unit(written)?, after);
+            Some((next, standard))
+        }) {
+            tokens.push(Token::Volume(format!("{} {}", word, next), standard));
+            i += 1;
+        } else {
+            tokens.push(Token::Word(word.clone()));
+        }
+        i += 1;
+    }
+    tokens
+}
+
+/// `words` joined, without punctuation left dangling at the ends by leaving some out.
+fn join(words: Vec<&str>) -> String {
+    words
+        .join(" ")
+        .trim_matches(|c: char| c.is_whitespace() || c == ',' || c == '-' || c == '–')
+        .to_string()
+}
+
+/// `name` cleaned up for showing, by `config`.
+pub fn clean(config: &NamesConfig, name: &str) -> String {
+    let tokens = tokens(config, name);
+    let words = tokens
+        .iter()
+        .filter_map(|token| match token {
+            Token::Word(word) => Some(word.as_str()),
+            Token::Year(_) if config.strip_vintage => None,
+            Token::Year(year) => Some(year.as_str()),
+            Token::Volume(_, standard) if config.volumes => Some(standard.as_str()),
+            Token::Volume(written, _) => Some(written.as_str()),
+        })
+        .collect();
+    let clean = join(words);
+    // A name that's just a year, like some beers, is still the name
+    if clean.is_empty() {
+        name.split_whitespace().collect::<Vec<_>>().join(" ")
+    } else {
+        clean
+    }
+}
+
+/// The cleaned up name of `drink`.
+pub fn display(config: &NamesConfig, drink: &Product) -> String {
+    clean(config, catalog::name(drink))
+}
+
+/// `text` with the letters with accents and dots, like `å`, `é` and `ü`, as they are without.
+pub fn fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            'å' | 'ä' | 'à' | 'á' | 'â' | 'ã' => folded.push('a'),
+            'Å' | 'Ä' | 'À' | 'Á' | 'Â' | 'Ã' => folded.push('A'),
+            'ö' | 'ø' | 'ò' | 'ó' | 'ô' | 'õ' => folded.push('o'),
+            'Ö' | 'Ø' | 'Ò' | 'Ó' | 'Ô' | 'Õ' => folded.push('O'),
+            'é' | 'è' | 'ê' | 'ë' => folded.push('e'),
+            'É' | 'È' | 'Ê' | 'Ë' => folded.push('E'),
+            'ü' | 'ú' | 'ù' | 'û' => folded.push('u'),
+            'Ü' | 'Ú' | 'Ù' | 'Û' => folded.push('U'),
+            'í' | 'ì' | 'î' | 'ï' => folded.push('i'),
+            'Í' | 'Ì' | 'Î' | 'Ï' => folded.push('I'),
+            'ç' => folded.push('c'),
+            'Ç' => folded.push('C'),
+            'ñ' => folded.push('n'),
+            'Ñ' => folded.push('N'),
+            'æ' => folded.push_str("ae"),
+            'Æ' => folded.push_str("AE"),
+            'ß' => folded.push_str("ss"),
+            _ => folded.push(c),
+        }
+    }
+    folded
+}
+
+/// `text` in lowercase ASCII letters and digits, with a dash between words, like
+/// `kopparbergs-paron` for `Kopparbergs Päron`.
+pub fn slugify(text: &str) -> String {
+    fold(&text.to_lowercase())
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// The slug of the product with `id` and `name`, as the config sets it or from the cleaned up
+/// name.
+fn slug_of(config: &NamesConfig, id: &str, name: &str) -> String {
+    match config.slugs.get(id) {
+        Some(slug) => slug.clone(),
+        None => slugify(&clean(config, name)),
+    }
+}
+
+pub fn slug(config: &NamesConfig, drink: &Product) -> String {
+    slug_of(config, catalog::id(drink), catalog::name(drink))
+}
+
+/// The permalink of `drink`, like `/produkt/1001/norrlands-guld`.
+pub fn path(config: &NamesConfig, drink: &Product) -> String {
+    format!("/produkt/{}/{}", catalog::id(drink), slug(config, drink))
+}
+
+/// What products with the same name but for the year and volume have in common.
+pub fn group(config: &NamesConfig, drink: &Product) -> String {
+    let tokens = tokens(config, catalog::name(drink));
+    let words = tokens
+        .iter()
+        .filter_map(|token| match token {
+            Token::Word(word) => Some(word.as_str()),
+            Token::Year(_) | Token::Volume(..) => None,
+        })
+        .collect();
+    slugify(&join(words))
+}
+
+/// The other products of `catalog` in the group of `drink`, best first.
+pub fn related<'a>(
+    config: &NamesConfig,
+    catalog: &'a Catalog,
+    drink: &Product,
+) -> Vec<&'a Product> {
+    let group = group(config, drink);
+    if group.is_empty() {
+        return Vec::new();
+    }
+    catalog
+        .products()
+        .filter(|other| catalog::id(other) != catalog::id(drink))
+        .filter(|other| self::group(config, other) == group)
+        .collect()
+}
+
+/// The `permalink` filter, giving the permalink of a product.
+pub fn filter(
+    config: NamesConfig,
+) -> impl Fn(&Value, &HashMap<String, Value>) -> tera::Result<Value> + Sync + Send {
+    move |value: &Value, _: &HashMap<String, Value>| {
+        let field = |name: &str| {
+            value
+                .get(name)
+                .and_then(Value::as_str)
+                .ok_or_else(|| tera::Error::msg(format!("No {} to make a permalink of", name)))
+        };
+        let id = field("ProductId")?;
+        let slug = slug_of(&config, id, field("ProductNameBold")?);
+        Ok(Value::String(format!("/produkt/{}/{}", id, slug)))
+    }
+}
@@ -0,0 +1,158 @@
+//! Evens out quirks in the numbers of upstream product records before they're parsed, so that a
+//! price like `"14,90"` or a negative deposit doesn't throw the filtering and scoring off. What to
+//! do with each field is in [`RULES`]. Every fix is logged, and counted in
+//! `apk_normalizations_total`.
+
+use serde_json::{Number, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::warn;
+
+/// What a field becomes when it isn't a usable number.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Fallback {
+    /// Null, which makes a required field skip the product
+    Null,
+    Zero,
+}
+
+impl Fallback {
+    fn value(self) -> Value {
+        match self {
+            Fallback::Null => Value::Null,
+            Fallback::Zero => Value::from(0.0),
+        }
+    }
+}
+
+struct Rule {
+    field: &'static str,
+    /// For null and text that isn't a number
+    missing: Fallback,
+    negative: Fallback,
+}
+
+/// The numeric fields the list depends on.
+const RULES: [Rule; 5] = [
+    Rule {
+        field: "Price",
+        missing: Fallback::Null,
+        negative: Fallback::Null,
+    },
+    // Most products have no deposit, so that's the safe guess
+    Rule {
+        field: "RecycleFee",
+        missing: Fallback::Zero,
+        negative: Fallback::Zero,
+    },
+    Rule {
+        field: "Volume",
+        missing: Fallback::Null,
+        negative: Fallback::Null,
+    },
+    Rule {
+        field: "AlcoholPercentage",
+        missing: Fallback::Null,
+        negative: Fallback::Null,
+    },
+    Rule {
+        field: "SugarContent",
+        missing: Fallback::Null,
+        negative: Fallback::Null,
+    },
+];
+
+/// A kind of fix.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fix {
+    /// A number as text with a decimal comma, like `"14,90"`
+    CommaDecimal,
+    /// A number as text, like `"14.90"`
+    Text,
+    /// Null where there should be a number, or text that isn't one
+    Missing,
+    Negative,
+}
+
+pub const FIXES: [Fix; 4] = [Fix::CommaDecimal, Fix::Text, Fix::Missing, Fix::Negative];
+
+impl Fix {
+    pub fn name(self) -> &'static str {
+        match self {
+            Fix::CommaDecimal => "comma_decimal",
+            Fix::Text => "text",
+            Fix::Missing => "missing",
+            Fix::Negative => "negative",
+        }
+    }
+
+    fn index(self) -> usize {
+        FIXES.iter().position(|&fix| fix == self).unwrap()
+    }
+}
+
+/// Fixes applied since the start, by [`Fix::index`]
+static COUNTS: [AtomicU64; 4] = [
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+];
+
+/// How many times `fix` has been applied since the start.
+pub fn count(fix: Fix) -> u64 {
+    COUNTS[fix.index()].load(Ordering::Relaxed)
+}
+
+fn number(value: f64) -> Value {
+    Number::from_f64(value).map_or(Value::Null, Value::Number)
+}
+
+/// The fix `value` needs by `rule`, and what it becomes.
+fn fix(rule: &Rule, value: &Value) -> Option<(Fix, Value)> {
+    let (parsed, kind) = match value {
+        Value::Number(n) => (n.as_f64(), None),
+        Value::String(text) => {
+            let text = text.trim();
+            if text.contains(',') {
+                (text.replace(',', ".").parse().ok(), Some(Fix::CommaDecimal))
+            } else {
+                (text.parse().ok(), Some(Fix::Text))
+            }
+        }
+        Value::Null if rule.missing != Fallback::Null => (None, None),
+        _ => return None,
+    };
+    match parsed.filter(|n: &f64| n.is_finite()) {
+        Some(n) if n < 0.0 => Some((Fix::Negative, rule.negative.value())),
+        Some(n) => kind.map(|kind| (kind, number(n))),
+        None => Some((Fix::Missing, rule.missing.value())),
+    }
+}
+
+/// Applies the [`RULES`] to a raw product record, logging and counting what it fixes.
+pub fn record(record: &mut Value) {
+    let id = record
+        .get("ProductId")
+        .and_then(Value::as_str)
+        .unwrap_or("?")
+        .to_string();
+    let fields = match record.as_object_mut() {
+        Some(fields) => fields,
+        None => return,
+    };
+    for rule in RULES.iter() {
+        let value = fields.get(rule.field).cloned().unwrap_or(Value::Null);
+        if let Some((fix, fixed)) = fix(rule, &value) {
+            warn!(
+                "Normalized {} of {} ({}): {} -> {}",
+                rule.field,
+                id,
+                fix.name(),
+                value,
+                fixed
+            );
+            COUNTS[fix.index()].fetch_add(1, Ordering::Relaxed);
+            fields.insert(rule.field.to_string(), fixed);
+        }
+    }
+}
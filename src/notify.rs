@@ -0,0 +1,59 @@
+use crate::diff::Diff;
+use crate::error::Result;
+use crate::refresh::Snapshot;
+use crate::state::AppState;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::error;
+
+/// What changed in a successful refresh.
+pub struct RefreshEvent {
+    pub snapshot: Arc<Snapshot>,
+    pub previous: Option<Arc<Snapshot>>,
+    pub diff: Diff,
+}
+
+/// Something that wants to hear about refreshes, like a webhook.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    fn name(&self) -> &str;
+    async fn notify(&self, state: &AppState, event: &RefreshEvent) -> Result<()>;
+
+    /// Hears about refreshes that were thrown away for looking wrong, see [`crate::anomaly`].
+    async fn anomaly(&self, _state: &AppState, _problems: &[String]) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Notifies all of `notifiers` in the background, logging failures.
+pub fn dispatch(notifiers: &[Arc<dyn Notifier>], state: &AppState, event: RefreshEvent) {
+    if notifiers.is_empty() {
+        return;
+    }
+    let event = Arc::new(event);
+    for notifier in notifiers {
+        let notifier = notifier.clone();
+        let event = event.clone();
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(err) = notifier.notify(&state, &event).await {
+                error!("Notifying {} failed: {}", notifier.name(), err);
+            }
+        });
+    }
+}
+
+/// Tells all of `notifiers` about a suspicious refresh in the background, logging failures.
+pub fn dispatch_anomaly(notifiers: &[Arc<dyn Notifier>], state: &AppState, problems: Vec<String>) {
+    let problems = Arc::new(problems);
+    for notifier in notifiers {
+        let notifier = notifier.clone();
+        let problems = problems.clone();
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(err) = notifier.anomaly(&state, &problems).await {
+                error!("Notifying {} failed: {}", notifier.name(), err);
+            }
+        });
+    }
+}
@@ -0,0 +1,69 @@
+//! Publishes alerts to an ntfy topic, on ntfy.sh or a self-hosted server.
+
+use crate::alerts::{self, Alert};
+use crate::config::NtfyConfig;
+use crate::error::Result;
+use crate::notify::{Notifier, RefreshEvent};
+use crate::state::AppState;
+use async_trait::async_trait;
+use secrecy::ExposeSecret;
+
+pub struct NtfyNotifier {
+    client: reqwest::Client,
+    config: NtfyConfig,
+}
+
+impl NtfyNotifier {
+    pub fn new(config: NtfyConfig) -> NtfyNotifier {
+        NtfyNotifier {
+            client: reqwest::Client::new(),
+            config,
+        }
+    }
+
+    /// The alerts to publish: about the watched products, or all of them if none are.
+    pub fn alerts<'a>(&'a self, alerts: &'a [Alert]) -> Vec<&'a Alert> {
+        if self.config.watch.is_empty() {
+            alerts.iter().collect()
+        } else {
+            alerts::watched(alerts, &self.config.watch).collect()
+        }
+    }
+
+    async fn publish(&self, text: String) -> Result<()> {
+        let mut request = self
+            .client
+            .post(&self.config.url)
+            .header("Title", "APK")
+            .header("Tags", "beer")
+            .body(text);
+        if let Some(token) = &self.config.token {
+            request = request.bearer_auth(token.expose_secret());
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Notifier for NtfyNotifier {
+    fn name(&self) -> &str {
+        "ntfy"
+    }
+
+    async fn notify(&self, _: &AppState, event: &RefreshEvent) -> Result<()> {
+        let alerts = alerts::alerts(event);
+        for alert in self.alerts(&alerts) {
+            self.publish(alert.text()).await?;
+        }
+        Ok(())
+    }
+
+    async fn anomaly(&self, _: &AppState, problems: &[String]) -> Result<()> {
+        let text = format!(
+            "Uppdateringen såg konstig ut och kastades: {}",
+            problems.join(", ")
+        );
+        self.publish(text).await
+    }
+}
@@ -0,0 +1,73 @@
+//! The pages rendered at the last refresh, kept in storage so that after a restart they're shown
+//! until the first refresh is done, instead of a blank page. Only the pages the refresh renders
+//! are kept, the rest wait for the catalog as before.
+
+use crate::catalog::Category;
+use crate::categories;
+use crate::error::Result;
+use crate::notify::{Notifier, RefreshEvent};
+use crate::state::AppState;
+use crate::status::unix_time;
+use crate::storage::{self, Storage};
+use crate::view::View;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const KEY: &str = "pages.json";
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Pages {
+    pub page: String,
+    pub category_pages: HashMap<Category, String>,
+    /// When they were rendered, in seconds since the epoch
+    pub updated_at: u64,
+}
+
+impl Pages {
+    /// The page for `view`, if it's one of the kept ones.
+    pub fn prerendered(&self, view: &View) -> Option<&str> {
+        prerendered(&self.page, &self.category_pages, view)
+    }
+}
+
+/// Of the main `page` and the `category_pages`, the one for `view`, if any.
+pub fn prerendered<'a>(
+    page: &'a str,
+    category_pages: &'a HashMap<Category, String>,
+    view: &View,
+) -> Option<&'a str> {
+    if view.is_default() {
+        return Some(page);
+    }
+    let category = view.category?;
+    if *view == categories::view(category) {
+        category_pages.get(&category).map(String::as_str)
+    } else {
+        None
+    }
+}
+
+/// The pages of the last refresh, if there's been one.
+pub fn load(storage: &dyn Storage) -> Result<Option<Pages>> {
+    storage::load_json(storage, KEY)
+}
+
+/// Keeps the pages of each refresh.
+pub struct PageCacher;
+
+#[async_trait]
+impl Notifier for PageCacher {
+    fn name(&self) -> &str {
+        "pages"
+    }
+
+    async fn notify(&self, state: &AppState, event: &RefreshEvent) -> Result<()> {
+        let pages = Pages {
+            page: event.snapshot.page.clone(),
+            category_pages: event.snapshot.category_pages.clone(),
+            updated_at: unix_time(event.snapshot.updated_at),
+        };
+        storage::save_json(&*state.storage, KEY, &pages)
+    }
+}
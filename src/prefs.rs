@@ -0,0 +1,136 @@
+//! A preferred default view, kept in a cookie and shown at `/` instead of the plain list. Or,
+//! without any client state, encoded in the `p` parameter, like `/?p=eyJrIjoiw5ZsIn0`, for
+//! bookmarks and displays that should always show the same thing.
+
+use crate::catalog::Category;
+use crate::units::{Percent, Sek};
+use crate::view::{Order, Sort, Tried, View};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use warp::http::{header, StatusCode};
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+pub const COOKIE: &str = "prefs";
+/// A year, in seconds
+const MAX_AGE: u64 = 365 * 24 * 60 * 60;
+const MAX_FORM: u64 = 4 * 1024;
+
+/// The query parameter holding an encoded view
+pub const PARAM: &str = "p";
+
+/// A view with short field names, to keep the encoding short.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Compact {
+    #[serde(rename = "k", skip_serializing_if = "Option::is_none")]
+    category: Option<Category>,
+    #[serde(rename = "s", skip_serializing_if = "Option::is_none")]
+    search: Option<String>,
+    #[serde(rename = "p", skip_serializing_if = "Option::is_none")]
+    max_price: Option<Sek>,
+    #[serde(rename = "a", skip_serializing_if = "Option::is_none")]
+    min_abv: Option<Percent>,
+    #[serde(rename = "g", skip_serializing_if = "Option::is_none")]
+    max_sugar: Option<f64>,
+    #[serde(rename = "l", skip_serializing_if = "Option::is_none")]
+    gluten_free: Option<bool>,
+    #[serde(rename = "b", skip_serializing_if = "Option::is_none")]
+    store: Option<String>,
+    #[serde(rename = "o", skip_serializing_if = "Option::is_none")]
+    sort: Option<Sort>,
+    #[serde(rename = "d", skip_serializing_if = "Option::is_none")]
+    order: Option<Order>,
+    #[serde(rename = "t", skip_serializing_if = "Option::is_none")]
+    tried: Option<Tried>,
+    #[serde(rename = "n", skip_serializing_if = "Option::is_none")]
+    per_page: Option<usize>,
+}
+
+/// `view` as a value for the `p` parameter: base64url of its compact JSON.
+pub fn encode(view: &View) -> String {
+    let compact = Compact {
+        category: view.category,
+        search: view.search.clone(),
+        max_price: view.max_price,
+        min_abv: view.min_abv,
+        max_sugar: view.max_sugar,
+        gluten_free: Some(view.gluten_free).filter(|&gluten_free| gluten_free),
+        store: view.store.clone(),
+        sort: Some(view.sort).filter(|&sort| sort != Sort::default()),
+        order: view.order,
+        tried: Some(view.tried).filter(|&tried| tried != Tried::default()),
+        per_page: view.per_page,
+    };
+    let json = serde_json::to_vec(&compact).unwrap_or_default();
+    base64::encode_config(json, base64::URL_SAFE_NO_PAD)
+}
+
+/// The view in a `p` parameter, if it's valid. Goes through the query string, so it's
+/// normalized just like a view given there.
+pub fn decode(value: &str) -> Option<View> {
+    let json = base64::decode_config(value, base64::URL_SAFE_NO_PAD).ok()?;
+    let compact: Compact = serde_json::from_slice(&json).ok()?;
+    let view = View {
+        category: compact.category,
+        search: compact.search,
+        max_price: compact.max_price,
+        min_abv: compact.min_abv,
+        max_sugar: compact.max_sugar,
+        gluten_free: compact.gluten_free.unwrap_or_default(),
+        store: compact.store,
+        sort: compact.sort.unwrap_or_default(),
+        order: compact.order,
+        tried: compact.tried.unwrap_or_default(),
+        page: None,
+        per_page: compact.per_page,
+    };
+    Some(View::from_query(&view.to_query()))
+}
+
+/// The preferred view in a prefs cookie, if any.
+pub fn view(cookie: Option<&str>) -> Option<View> {
+    // The query is encoded once more, to keep its `=` and `&` out of the cookie syntax
+    let view = View::from_query(&percent_decode_str(cookie?).decode_utf8_lossy());
+    if view.is_default() {
+        None
+    } else {
+        Some(view)
+    }
+}
+
+/// A `Set-Cookie` value saving `view` as the default, or clearing it if it's the plain list.
+pub fn cookie(view: &View) -> String {
+    if view.is_default() {
+        format!("{}=; Path=/; Max-Age=0", COOKIE)
+    } else {
+        format!(
+            "{}={}; Path=/; Max-Age={}; SameSite=Lax",
+            COOKIE,
+            utf8_percent_encode(&view.to_query(), NON_ALPHANUMERIC),
+            MAX_AGE
+        )
+    }
+}
+
+/// Saves the view in the `query` field, or clears the default if `action` is `clear`.
+fn update(form: HashMap<String, String>) -> Response {
+    let view = match form.get("action").map(String::as_str) {
+        Some("clear") => View::default(),
+        _ => {
+            let query = form.get("query").map_or("", String::as_str);
+            View::from_query(query.trim_start_matches('/').trim_start_matches('?'))
+        }
+    };
+    let redirect = warp::reply::with_header(StatusCode::SEE_OTHER, header::LOCATION, "/");
+    warp::reply::with_header(redirect, header::SET_COOKIE, cookie(&view)).into_response()
+}
+
+pub fn route() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("prefs")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(MAX_FORM))
+        .and(warp::body::form())
+        .map(update)
+}
@@ -0,0 +1,32 @@
+//! Curated views from the config, served at `/view/{slug}` like any other view of the list.
+
+use crate::session;
+use crate::state::AppState;
+use crate::tried;
+use crate::view::{self, View};
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+fn page(state: &AppState, slug: &str, tried: Option<&str>, session: Option<&str>) -> Response {
+    match state
+        .config
+        .presets
+        .iter()
+        .find(|preset| preset.slug == slug)
+    {
+        Some(preset) => view::show(state, &View::from_query(&preset.query), tried, session),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("view" / String)
+        .and(warp::cookie::optional(tried::COOKIE))
+        .and(warp::cookie::optional(session::COOKIE))
+        .map(
+            move |slug: String, tried: Option<String>, session: Option<String>| {
+                page(&state, &slug, tried.as_deref(), session.as_deref())
+            },
+        )
+}
@@ -0,0 +1,141 @@
+//! The price, volume and APK of every product at every refresh, in SQLite, served per product at
+//! `/history/{id}`.
+
+use crate::catalog;
+use crate::config::PriceHistoryConfig;
+use crate::error::Result;
+use crate::notify::{Notifier, RefreshEvent};
+use crate::state::AppState;
+use crate::status::unix_time;
+use crate::units::Measures;
+use async_trait::async_trait;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use tracing::error;
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// Each brings the schema up from the version before it, the first from an empty database. The
+/// number applied so far is kept in `user_version`.
+const MIGRATIONS: &[&str] = &["CREATE TABLE prices (
+        product_id TEXT NOT NULL,
+        at INTEGER NOT NULL,
+        price REAL NOT NULL,
+        volume REAL NOT NULL,
+        apk REAL NOT NULL,
+        PRIMARY KEY (product_id, at)
+    )"];
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PricePoint {
+    /// Unix timestamp of the refresh
+    pub at: u64,
+    pub price: f64,
+    /// In ml
+    pub volume: f64,
+    pub apk: f64,
+}
+
+pub struct PriceHistory {
+    connection: Mutex<Connection>,
+}
+
+pub type SharedPriceHistory = Option<Arc<PriceHistory>>;
+
+impl PriceHistory {
+    /// Opens the database, bringing its schema up to date.
+    pub fn open(config: &PriceHistoryConfig) -> Result<PriceHistory> {
+        let mut connection = Connection::open(&config.path)?;
+        migrate(&mut connection)?;
+        Ok(PriceHistory {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Records every product in the catalog of `event`.
+    pub fn record(&self, event: &RefreshEvent) -> Result<()> {
+        let at = unix_time(event.snapshot.updated_at) as i64;
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        {
+            let mut insert = transaction.prepare(
+                "INSERT OR REPLACE INTO prices (product_id, at, price, volume, apk)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for drink in event.snapshot.catalog.products() {
+                insert.execute(params![
+                    catalog::id(drink),
+                    at,
+                    drink.shelf_price().0,
+                    drink.volume().0,
+                    catalog::apk(drink).0,
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// The history of the product with `id`, oldest first.
+    pub fn product(&self, id: &str) -> Result<Vec<PricePoint>> {
+        let connection = self.connection.lock().unwrap();
+        let mut select = connection.prepare(
+            "SELECT at, price, volume, apk FROM prices WHERE product_id = ?1 ORDER BY at",
+        )?;
+        let points = select
+            .query_map(params![id], |row| {
+                Ok(PricePoint {
+                    at: row.get::<_, i64>(0)? as u64,
+                    price: row.get(1)?,
+                    volume: row.get(2)?,
+                    apk: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(points)
+    }
+}
+
+fn migrate(connection: &mut Connection) -> Result<()> {
+    let version: i64 = connection.query_row("PRAGMA user_version", params![], |row| row.get(0))?;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.execute_batch(&format!("PRAGMA user_version = {}", i + 1))?;
+        transaction.commit()?;
+    }
+    Ok(())
+}
+
+/// Records the prices of each refresh.
+pub struct PriceRecorder;
+
+#[async_trait]
+impl Notifier for PriceRecorder {
+    fn name(&self) -> &str {
+        "prices"
+    }
+
+    async fn notify(&self, state: &AppState, event: &RefreshEvent) -> Result<()> {
+        match &state.price_history {
+            Some(history) => history.record(event),
+            None => Ok(()),
+        }
+    }
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("history" / String).map(move |id: String| match &state.price_history {
+        Some(history) => match history.product(&id) {
+            Ok(points) if points.is_empty() => StatusCode::NOT_FOUND.into_response(),
+            Ok(points) => warp::reply::json(&points).into_response(),
+            Err(err) => {
+                error!("{}", err);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        },
+        None => StatusCode::NOT_FOUND.into_response(),
+    })
+}
@@ -0,0 +1,21 @@
+//! The raw products of the last successful fetch, kept in storage so that the list can be shown
+//! from them when upstream is unreachable, marked with when they're from, rather than not at all.
+//! See [`crate::refresh::Refresher::update`].
+
+use crate::archive::Archive;
+use crate::error::Result;
+use crate::storage::{self, Storage};
+use std::time::SystemTime;
+use systemet::Product;
+
+const KEY: &str = "products.json";
+
+/// Keeps `products`, fetched at `at`, replacing the ones kept before.
+pub fn save(storage: &dyn Storage, products: Vec<Product>, at: SystemTime) -> Result<()> {
+    storage::save_json(storage, KEY, &Archive::new(products, at))
+}
+
+/// The products of the last successful fetch, if any have been kept.
+pub fn load(storage: &dyn Storage) -> Result<Option<Archive>> {
+    storage::load_json(storage, KEY)
+}
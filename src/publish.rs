@@ -0,0 +1,141 @@
+//! Uploads the exports and the main page of each refresh to an S3-compatible bucket, both under
+//! the time of the refresh, like `apk/20201015T120000Z/products.csv`, to keep them, and under
+//! `latest/`, for hosting the site from the bucket when the server is down. Requests are signed
+//! with AWS Signature Version 4, which every S3-compatible store takes.
+
+use crate::config::PublishConfig;
+use crate::dates;
+use crate::error::{Error, Result};
+use crate::notify::{Notifier, RefreshEvent};
+use crate::signing;
+use crate::site;
+use crate::state::AppState;
+use async_trait::async_trait;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use secrecy::ExposeSecret;
+use std::time::SystemTime;
+
+/// Where the newest files are kept, under the prefix
+pub const LATEST: &str = "latest";
+
+/// Everything but the characters S3 leaves as they are, and the slashes between folders.
+const KEY: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~')
+    .remove(b'/');
+
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+pub struct Publisher {
+    client: reqwest::Client,
+    config: PublishConfig,
+}
+
+impl Publisher {
+    pub fn new(config: &PublishConfig) -> Publisher {
+        Publisher {
+            client: reqwest::Client::new(),
+            config: config.clone(),
+        }
+    }
+
+    /// Puts `body` at `key` in the bucket.
+    async fn put(&self, key: &str, body: String, content_type: &str) -> Result<()> {
+        let path = format!(
+            "/{}/{}{}",
+            self.config.bucket,
+            utf8_percent_encode(&self.config.prefix, KEY),
+            utf8_percent_encode(key, KEY)
+        );
+        let url = format!("{}{}", self.config.endpoint.trim_end_matches('/'), path);
+        let parsed = reqwest::Url::parse(&url)
+            .map_err(|err| Error::Config(format!("bad publish endpoint {}: {}", url, err)))?;
+        let host = match (parsed.host_str(), parsed.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(Error::Config(format!("no host in {}", url))),
+        };
+        let now = dates::basic_timestamp(SystemTime::now());
+        let payload_hash = signing::sha256(body.as_bytes());
+        let authorization = self.authorization(&path, &host, &now, &payload_hash);
+        self.client
+            .put(parsed)
+            .header("x-amz-date", &now)
+            .header("x-amz-content-sha256", &payload_hash)
+            .header("authorization", authorization)
+            .header("content-type", content_type)
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// The `Authorization` header of a PUT to `path` at `now`, see
+    /// <https://docs.aws.amazon.com/general/latest/gr/sigv4_signing.html>.
+    fn authorization(&self, path: &str, host: &str, now: &str, payload_hash: &str) -> String {
+        let canonical_request = format!(
+            "PUT\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            path, host, payload_hash, now, SIGNED_HEADERS, payload_hash
+        );
+        let day = &now[..8];
+        let scope = format!("{}/{}/s3/aws4_request", day, self.config.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            now,
+            scope,
+            signing::sha256(canonical_request.as_bytes())
+        );
+        let secret = format!("AWS4{}", self.config.secret_key.expose_secret());
+        let key = [day, self.config.region.as_str(), "s3", "aws4_request"]
+            .iter()
+            .fold(secret.into_bytes(), |key, part| {
+                signing::hmac_sha256_bytes(&key, part.as_bytes())
+            });
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.config.access_key,
+            scope,
+            SIGNED_HEADERS,
+            signing::hmac_sha256(&key, string_to_sign.as_bytes())
+        )
+    }
+}
+
+#[async_trait]
+impl Notifier for Publisher {
+    fn name(&self) -> &str {
+        "publish"
+    }
+
+    async fn notify(&self, state: &AppState, event: &RefreshEvent) -> Result<()> {
+        let snapshot = &event.snapshot;
+        let files = [
+            (
+                "index.html",
+                snapshot.page.clone(),
+                "text/html; charset=utf-8",
+            ),
+            (
+                site::JSON_EXPORT,
+                site::json(state, snapshot)?,
+                "application/json",
+            ),
+            (
+                site::CSV_EXPORT,
+                site::csv(snapshot, &state.config.display),
+                "text/csv; charset=utf-8",
+            ),
+        ];
+        let stamp = dates::basic_timestamp(snapshot.updated_at);
+        for (name, body, content_type) in files.iter() {
+            self.put(&format!("{}/{}", stamp, name), body.clone(), content_type)
+                .await?;
+            self.put(&format!("{}/{}", LATEST, name), body.clone(), content_type)
+                .await?;
+        }
+        Ok(())
+    }
+}
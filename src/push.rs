@@ -0,0 +1,255 @@
+//! Browser push notifications (VAPID) when a watched product gets cheaper or is back in stock.
+//! Subscriptions may only point at the push services in the config, since the server posts to
+//! them, and only the browser holding a subscription's `auth` secret may change or remove it.
+
+use crate::alerts;
+use crate::config::PushConfig;
+use crate::error::{Error, Result};
+use crate::notify::{Notifier, RefreshEvent};
+use crate::state::AppState;
+use crate::storage::{self, Storage};
+use async_trait::async_trait;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use tracing::{error, warn};
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+use web_push::{
+    ContentEncoding, SubscriptionInfo, VapidSignatureBuilder, WebPushClient, WebPushError,
+    WebPushMessageBuilder,
+};
+
+const SUBSCRIPTIONS_KEY: &str = "push-subscriptions.json";
+/// The largest subscription body accepted, in bytes
+const MAX_BODY: u64 = 16 * 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PushSubscription {
+    /// As given by the browser's `PushSubscription.toJSON()`
+    pub subscription: SubscriptionInfo,
+    /// Product ids to send alerts about
+    #[serde(default)]
+    pub watch: Vec<String>,
+}
+
+pub fn subscriptions(storage: &dyn Storage) -> Result<Vec<PushSubscription>> {
+    Ok(storage::load_json(storage, SUBSCRIPTIONS_KEY)?.unwrap_or_default())
+}
+
+/// Adds a subscription, replacing any earlier one with the same endpoint. A new one isn't added if
+/// there are `max` already. Returns whether it was added.
+pub fn subscribe(
+    storage: &dyn Storage,
+    subscription: PushSubscription,
+    max: usize,
+) -> Result<bool> {
+    let mut subscriptions = subscriptions(storage)?;
+    let count = subscriptions.len();
+    subscriptions.retain(|s| s.subscription.endpoint != subscription.subscription.endpoint);
+    if subscriptions.len() == count && count >= max {
+        return Ok(false);
+    }
+    subscriptions.push(subscription);
+    storage::save_json(storage, SUBSCRIPTIONS_KEY, &subscriptions)?;
+    Ok(true)
+}
+
+/// Removes the subscriptions with any of `endpoints`. Returns whether there were any.
+pub fn unsubscribe(storage: &dyn Storage, endpoints: &[String]) -> Result<bool> {
+    let mut subscriptions = subscriptions(storage)?;
+    let count = subscriptions.len();
+    subscriptions.retain(|s| !endpoints.contains(&s.subscription.endpoint));
+    storage::save_json(storage, SUBSCRIPTIONS_KEY, &subscriptions)?;
+    Ok(subscriptions.len() != count)
+}
+
+/// Whether `endpoint` is at one of the push services of `config`, over https.
+pub fn allowed(config: &PushConfig, endpoint: &str) -> bool {
+    let url = match Url::parse(endpoint) {
+        Ok(url) => url,
+        Err(_) => return false,
+    };
+    let host = match url.host_str() {
+        Some(host) => host.to_lowercase(),
+        None => return false,
+    };
+    url.scheme() == "https"
+        && url.port().is_none()
+        && config
+            .services
+            .iter()
+            .any(|service| host == *service || host.ends_with(&format!(".{}", service)))
+}
+
+/// Pushes alerts to the browsers watching the products.
+pub struct WebPushAlerts {
+    /// The VAPID private key, PEM encoded
+    private_key: Vec<u8>,
+    subject: String,
+    client: WebPushClient,
+}
+
+impl WebPushAlerts {
+    pub fn new(config: &PushConfig) -> Result<WebPushAlerts> {
+        let private_key = fs::read(&config.private_key)
+            .map_err(|err| Error::Config(format!("{}: {}", config.private_key.display(), err)))?;
+        Ok(WebPushAlerts {
+            private_key,
+            subject: config.subject.clone(),
+            client: WebPushClient::new(),
+        })
+    }
+
+    async fn push(
+        &self,
+        subscription: &SubscriptionInfo,
+        payload: &[u8],
+    ) -> std::result::Result<(), WebPushError> {
+        let mut signature = VapidSignatureBuilder::from_pem(&self.private_key[..], subscription)?;
+        signature.add_claim("sub", self.subject.as_str());
+        let mut message = WebPushMessageBuilder::new(subscription)?;
+        message.set_payload(ContentEncoding::AesGcm, payload);
+        message.set_vapid_signature(signature.build()?);
+        self.client.send(message.build()?).await
+    }
+}
+
+#[async_trait]
+impl Notifier for WebPushAlerts {
+    fn name(&self) -> &str {
+        "push"
+    }
+
+    async fn notify(&self, state: &AppState, event: &RefreshEvent) -> Result<()> {
+        let alerts = alerts::alerts(event);
+        if alerts.is_empty() {
+            return Ok(());
+        }
+        let mut gone = Vec::new();
+        for subscription in subscriptions(&*state.storage)? {
+            for alert in alerts::watched(&alerts, &subscription.watch) {
+                let payload = serde_json::to_vec(&json!({
+                    "title": "APK",
+                    "body": alert.text(),
+                    "id": alert.product_id(),
+                }))?;
+                match self.push(&subscription.subscription, &payload).await {
+                    Ok(()) => {}
+                    Err(WebPushError::EndpointNotValid) | Err(WebPushError::EndpointNotFound) => {
+                        gone.push(subscription.subscription.endpoint.clone());
+                        break;
+                    }
+                    Err(err) => error!(
+                        "Pushing to {} failed: {}",
+                        subscription.subscription.endpoint, err
+                    ),
+                }
+            }
+        }
+        if !gone.is_empty() {
+            warn!("Removing {} expired push subscriptions", gone.len());
+            unsubscribe(&*state.storage, &gone)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct Unsubscribe {
+    endpoint: String,
+    /// The subscription's `keys.auth`, which only its browser knows
+    auth: String,
+}
+
+fn enabled(state: AppState) -> impl Filter<Extract = (AppState,), Error = Rejection> + Clone {
+    warp::any().and_then(move || {
+        let state = state.clone();
+        async move {
+            if state.config.push.is_some() {
+                Ok(state)
+            } else {
+                Err(warp::reject::not_found())
+            }
+        }
+    })
+}
+
+fn respond(result: Result<StatusCode>) -> Response {
+    match result {
+        Ok(status) => status.into_response(),
+        Err(err) => {
+            error!("{}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// The auth secret of the subscription with `endpoint`, if there is one.
+fn auth(storage: &dyn Storage, endpoint: &str) -> Result<Option<String>> {
+    Ok(subscriptions(storage)?
+        .into_iter()
+        .find(|s| s.subscription.endpoint == endpoint)
+        .map(|s| s.subscription.keys.auth))
+}
+
+fn add(state: &AppState, subscription: PushSubscription) -> Result<StatusCode> {
+    let endpoint = &subscription.subscription.endpoint;
+    let config = match &state.config.push {
+        Some(config) if allowed(config, endpoint) => config,
+        _ => return Ok(StatusCode::BAD_REQUEST),
+    };
+    let _saving = state.saving.lock().unwrap();
+    let auth = auth(&*state.storage, endpoint)?;
+    if auth.map_or(false, |auth| auth != subscription.subscription.keys.auth) {
+        return Ok(StatusCode::FORBIDDEN);
+    }
+    let max = config.max_subscriptions;
+    Ok(if subscribe(&*state.storage, subscription, max)? {
+        StatusCode::CREATED
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+fn remove(state: &AppState, body: Unsubscribe) -> Result<StatusCode> {
+    let _saving = state.saving.lock().unwrap();
+    match auth(&*state.storage, &body.endpoint)? {
+        Some(auth) if auth == body.auth => {
+            unsubscribe(&*state.storage, &[body.endpoint]).map(|_| StatusCode::NO_CONTENT)
+        }
+        Some(_) => Ok(StatusCode::FORBIDDEN),
+        None => Ok(StatusCode::NOT_FOUND),
+    }
+}
+
+/// `GET /push/key` gives the public key for `pushManager.subscribe`, `POST /push/subscribe` and
+/// `POST /push/unsubscribe` take JSON, the latter the endpoint and its `auth` secret.
+pub fn routes(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let key = warp::path!("push" / "key")
+        .and(warp::get())
+        .and(enabled(state.clone()))
+        .map(|state: AppState| {
+            let key = state
+                .config
+                .push
+                .as_ref()
+                .map_or("", |push| &push.public_key);
+            key.to_string().into_response()
+        });
+    let subscribe = warp::path!("push" / "subscribe")
+        .and(warp::post())
+        .and(enabled(state.clone()))
+        .and(warp::body::content_length_limit(MAX_BODY))
+        .and(warp::body::json())
+        .map(|state: AppState, subscription| respond(add(&state, subscription)));
+    let unsubscribe = warp::path!("push" / "unsubscribe")
+        .and(warp::post())
+        .and(enabled(state))
+        .and(warp::body::content_length_limit(MAX_BODY))
+        .and(warp::body::json())
+        .map(|state: AppState, body| respond(remove(&state, body)));
+    key.or(subscribe).unify().or(unsubscribe).unify()
+}
@@ -0,0 +1,75 @@
+//! QR codes as PNGs, at `/qr/product/{id}.png` for a product's page on systembolaget.se and at
+//! `/qr?url=` for anything else, like a view, so printed lists can link back to the live ones.
+
+use crate::buy;
+use crate::error::{Error, Result};
+use crate::state::AppState;
+use image::{DynamicImage, ImageOutputFormat, Luma};
+use qrcode::QrCode;
+use std::collections::HashMap;
+use tracing::error;
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// Longer URLs make codes too dense to scan from paper anyway
+const MAX_URL_LENGTH: usize = 1000;
+/// Pixels per module
+const MODULE_SIZE: u32 = 8;
+
+/// A PNG of a QR code for `url`.
+pub fn png(url: &str) -> Result<Vec<u8>> {
+    let code = QrCode::new(url.as_bytes()).map_err(Error::image)?;
+    let image = code
+        .render::<Luma<u8>>()
+        .module_dimensions(MODULE_SIZE, MODULE_SIZE)
+        .build();
+    let mut png = Vec::new();
+    DynamicImage::ImageLuma8(image)
+        .write_to(&mut png, ImageOutputFormat::Png)
+        .map_err(Error::image)?;
+    Ok(png)
+}
+
+fn respond(url: &str) -> Response {
+    match png(url) {
+        Ok(png) => {
+            let reply = warp::reply::with_header(png, "Content-Type", "image/png");
+            // The same URL always gives the same code
+            warp::reply::with_header(reply, "Cache-Control", "public, max-age=86400")
+                .into_response()
+        }
+        Err(err) => {
+            error!("{}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+fn product(state: &AppState, file: &str) -> Option<Response> {
+    let id = file.strip_suffix(".png")?;
+    let snapshot = state.snapshot.read().unwrap().clone()?;
+    let drink = snapshot.catalog.find(id)?;
+    Some(respond(&buy::url(drink)))
+}
+
+fn url(query: HashMap<String, String>) -> Response {
+    match query.get("url") {
+        Some(url)
+            if (url.starts_with("https://") || url.starts_with("http://"))
+                && url.len() <= MAX_URL_LENGTH =>
+        {
+            respond(url)
+        }
+        _ => StatusCode::BAD_REQUEST.into_response(),
+    }
+}
+
+pub fn routes(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let product = warp::path!("qr" / "product" / String).and_then(move |file: String| {
+        let state = state.clone();
+        async move { product(&state, &file).ok_or_else(warp::reject::not_found) }
+    });
+    let url = warp::path!("qr").and(warp::query()).map(url);
+    product.or(url).unify()
+}
@@ -0,0 +1,183 @@
+//! Ratings from 1 to 5 per product and session. The average of everyone's ratings is available as
+//! the `betyg` scorer, so it can be shown and combined with the other scores.
+
+use crate::access;
+use crate::back;
+use crate::catalog;
+use crate::error::Result;
+use crate::limit::{self, Hourly};
+use crate::score::Scorer;
+use crate::session;
+use crate::state::AppState;
+use crate::status;
+use crate::storage::{self, Storage};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+use systemet::Product;
+use tracing::error;
+use warp::http::{header, StatusCode};
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+const RATINGS_KEY: &str = "ratings.json";
+pub const MIN_RATING: u8 = 1;
+pub const MAX_RATING: u8 = 5;
+/// Ratings each client may make per hour, so no one can sway the averages alone
+pub const MAX_PER_CLIENT: u32 = 30;
+/// Ratings of each product per hour, for when someone does it from many addresses
+pub const MAX_PER_PRODUCT: u32 = 20;
+const MAX_FORM: u64 = 4 * 1024;
+
+/// The average rating of each rated product
+pub type SharedRatings = Arc<RwLock<HashMap<String, f64>>>;
+
+/// Every rating, by product id and then session.
+fn all(storage: &dyn Storage) -> Result<HashMap<String, HashMap<String, u8>>> {
+    Ok(storage::load_json(storage, RATINGS_KEY)?.unwrap_or_default())
+}
+
+/// The average rating of each rated product.
+pub fn averages(storage: &dyn Storage) -> Result<HashMap<String, f64>> {
+    Ok(all(storage)?
+        .into_iter()
+        .filter(|(_, ratings)| !ratings.is_empty())
+        .map(|(id, ratings)| {
+            let sum: u32 = ratings.values().map(|&rating| rating as u32).sum();
+            (id, sum as f64 / ratings.len() as f64)
+        })
+        .collect())
+}
+
+/// The ratings made in `session`, by product id.
+pub fn mine(storage: &dyn Storage, session: &str) -> Result<HashMap<String, u8>> {
+    Ok(all(storage)?
+        .into_iter()
+        .filter_map(|(id, ratings)| Some((id, *ratings.get(session)?)))
+        .collect())
+}
+
+/// Rates a product for `session`. A rating outside 1–5 removes it.
+pub fn rate(storage: &dyn Storage, session: &str, id: &str, rating: u8) -> Result<()> {
+    let mut all = all(storage)?;
+    let ratings = all.entry(id.to_string()).or_default();
+    if (MIN_RATING..=MAX_RATING).contains(&rating) {
+        ratings.insert(session.to_string(), rating);
+    } else {
+        ratings.remove(session);
+    }
+    if ratings.is_empty() {
+        all.remove(id);
+    }
+    storage::save_json(storage, RATINGS_KEY, &all)
+}
+
+/// Scores products by their average rating, or 0 if they haven't been rated.
+pub struct RatingScorer {
+    ratings: SharedRatings,
+}
+
+impl RatingScorer {
+    pub fn new(ratings: SharedRatings) -> RatingScorer {
+        RatingScorer { ratings }
+    }
+}
+
+impl Scorer for RatingScorer {
+    fn name(&self) -> &str {
+        "betyg"
+    }
+
+    fn score(&self, drink: &Product) -> f64 {
+        let ratings = self.ratings.read().unwrap();
+        ratings.get(catalog::id(drink)).copied().unwrap_or(0.0)
+    }
+}
+
+fn catalog_has(state: &AppState, id: &str) -> bool {
+    let snapshot = state.snapshot.read().unwrap().clone();
+    snapshot.map_or(false, |snapshot| {
+        snapshot.catalog.find(id).map(catalog::id) == Some(id)
+    })
+}
+
+/// How many ratings have been made this hour, see [`MAX_PER_CLIENT`] and [`MAX_PER_PRODUCT`].
+struct Limits {
+    clients: Hourly<Option<IpAddr>>,
+    products: Hourly<String>,
+}
+
+/// Rates the product in `form`, and goes back to the page in `back`. Without a session, one is
+/// started instead, and the rating isn't counted, so that a session can't be had for every rating.
+fn update(
+    state: &AppState,
+    limits: &Limits,
+    client: Option<IpAddr>,
+    session: Option<String>,
+    form: HashMap<String, String>,
+) -> Result<Response> {
+    let id = match form.get("id") {
+        Some(id) if catalog_has(state, id) => id,
+        _ => return Ok(StatusCode::BAD_REQUEST.into_response()),
+    };
+    let rating = match form.get("rating").map(String::as_str) {
+        // An empty choice takes the rating back
+        Some("") => Some(0),
+        Some(rating) => rating
+            .parse::<u8>()
+            .ok()
+            .filter(|rating| (MIN_RATING..=MAX_RATING).contains(rating)),
+        None => None,
+    };
+    let rating = match rating {
+        Some(rating) => rating,
+        None => return Ok(StatusCode::BAD_REQUEST.into_response()),
+    };
+    let now = status::unix_time(SystemTime::now());
+    let (session, new) = match session {
+        Some(session) => (session, false),
+        None => (session::new_id(), true),
+    };
+    if !new {
+        if !limits.clients.allow(client, now) || !limits.products.allow(id.clone(), now) {
+            return Ok(limit::too_many(now));
+        }
+        let _saving = state.saving.lock().unwrap();
+        rate(&*state.storage, &session, id, rating)?;
+        *state.ratings.write().unwrap() = averages(&*state.storage)?;
+    }
+
+    let redirect =
+        warp::reply::with_header(StatusCode::SEE_OTHER, header::LOCATION, back::back(&form));
+    Ok(if new {
+        warp::reply::with_header(
+            redirect,
+            header::SET_COOKIE,
+            session::cookie(&state.cookie_key, &session),
+        )
+        .into_response()
+    } else {
+        redirect.into_response()
+    })
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let limits = Arc::new(Limits {
+        clients: Hourly::new(MAX_PER_CLIENT),
+        products: Hourly::new(MAX_PER_PRODUCT),
+    });
+    warp::path!("ratings")
+        .and(warp::post())
+        .and(access::client(&state))
+        .and(warp::cookie::optional(session::COOKIE))
+        .and(warp::body::content_length_limit(MAX_FORM))
+        .and(warp::body::form())
+        .map(move |client, cookie: Option<String>, form| {
+            let session = session::id(&state.cookie_key, cookie.as_deref());
+            update(&state, &limits, client, session, form).unwrap_or_else(|err| {
+                error!("{}", err);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            })
+        })
+}
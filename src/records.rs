@@ -0,0 +1,105 @@
+//! The best APK ever seen in each category, kept across refreshes so that a product matching or
+//! beating it can be pointed out.
+
+use crate::catalog::{self, Catalog, Category, CATEGORIES};
+use crate::error::Result;
+use crate::history;
+use crate::notify::{Notifier, RefreshEvent};
+use crate::state::AppState;
+use crate::status::unix_time;
+use crate::storage::{self, Storage};
+use crate::units::{Apk, Ml};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const RECORDS_KEY: &str = "records.json";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub apk: Apk,
+    /// What a standard drink cost at that APK
+    pub kr_per_standard_drink: f64,
+    /// The product that set the record, unknown for records taken from the history
+    pub id: Option<String>,
+    pub name: Option<String>,
+    /// Unix timestamp of the refresh that set the record
+    pub at: u64,
+}
+
+impl Record {
+    fn new(apk: Apk, at: u64) -> Record {
+        Record {
+            apk,
+            kr_per_standard_drink: 1.0 / Ml(apk.0).standard_drinks(),
+            id: None,
+            name: None,
+            at,
+        }
+    }
+}
+
+pub type Records = HashMap<Category, Record>;
+
+/// The records so far. The first time, they're worked out from the history.
+pub fn load(storage: &dyn Storage) -> Result<Records> {
+    if let Some(records) = storage::load_json(storage, RECORDS_KEY)? {
+        return Ok(records);
+    }
+    let mut records = Records::new();
+    for point in history::load(storage)? {
+        for (&category, &best) in &point.best {
+            if records
+                .get(&category)
+                .map_or(true, |record| best > record.apk.0)
+            {
+                records.insert(category, Record::new(Apk(best), point.at));
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// Raises the records beaten by the best product of each category in `catalog`.
+pub fn update(records: &mut Records, catalog: &Catalog, at: u64) {
+    for &category in CATEGORIES.iter() {
+        let best = catalog
+            .get(category)
+            .iter()
+            .min_by(|d1, d2| catalog::apk_comparator(d1, d2));
+        let best = match best {
+            Some(best) => best,
+            None => continue,
+        };
+        let apk = catalog::apk(best);
+        if records
+            .get(&category)
+            .map_or(true, |record| apk > record.apk)
+        {
+            let mut record = Record::new(apk, at);
+            record.id = Some(catalog::id(best).to_owned());
+            record.name = Some(catalog::name(best).to_owned());
+            records.insert(category, record);
+        }
+    }
+}
+
+/// Saves the new records after each refresh.
+pub struct RecordKeeper;
+
+#[async_trait]
+impl Notifier for RecordKeeper {
+    fn name(&self) -> &str {
+        "records"
+    }
+
+    async fn notify(&self, state: &AppState, event: &RefreshEvent) -> Result<()> {
+        let mut records = load(&*state.storage)?;
+        update(
+            &mut records,
+            &event.snapshot.catalog,
+            unix_time(event.snapshot.updated_at),
+        );
+        storage::save_json(&*state.storage, RECORDS_KEY, &records)
+    }
+}
@@ -0,0 +1,520 @@
+use crate::anomaly;
+use crate::audit::{self, Entry, Trigger};
+use crate::barcodes::{self, Barcodes};
+use crate::catalog::{self, AssortmentRules, Catalog, Category, CATEGORIES};
+use crate::categories;
+use crate::countries::{self, Country};
+use crate::dates;
+use crate::diff::{self, Diff};
+use crate::error::{Error, Result};
+use crate::notify::{self, Notifier, RefreshEvent};
+use crate::pagecache;
+use crate::productcache;
+use crate::records::{self, Records};
+use crate::render;
+use crate::score::{ApkScorer, Scorer};
+use crate::search::SearchIndex;
+use crate::signing;
+use crate::source::{Clock, ProductSource};
+use crate::state::{AppState, SharedTera};
+use crate::units::Apk;
+use crate::view::View;
+use crate::warm;
+use rand::Rng;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use systemet::Product;
+use tracing::{error, info, info_span, warn, Instrument};
+
+/// In seconds
+pub const UPDATE_INTERVAL: u64 = 7200;
+/// After the first failure to reach upstream, doubling with each one after it, see [`backoff`]
+pub const RETRY_INTERVAL: u64 = 5;
+
+pub struct Snapshot {
+    pub catalog: Catalog,
+    pub page: String,
+    /// When each category was last fetched, which differs between them while refreshing one at a
+    /// time
+    pub category_updated_at: HashMap<Category, SystemTime>,
+    /// The pages of each category on its own, see [`categories`]
+    pub category_pages: HashMap<Category, String>,
+    pub updated_at: SystemTime,
+    /// When the products were fetched, if that was at an earlier run because upstream is
+    /// unreachable, see [`productcache`]
+    pub data_from: Option<SystemTime>,
+    /// SHA-256 of the catalog contents
+    pub hash: String,
+    /// The average APK of boxed wine, see [`catalog::box_apk`]
+    pub box_apk: Option<Apk>,
+    /// The records from before this refresh, see [`records`]
+    pub records: Records,
+    /// Every country, see [`countries::leaderboard`]
+    pub countries: Vec<Country>,
+    /// For searching the catalog, see [`SearchIndex`]
+    pub index: SearchIndex,
+    /// The barcodes of the products, see [`barcodes`]
+    pub barcodes: Barcodes,
+}
+
+impl Snapshot {
+    /// The page rendered at refresh time for `view`, if there is one.
+    pub fn prerendered(&self, view: &View) -> Option<&str> {
+        pagecache::prerendered(&self.page, &self.category_pages, view)
+    }
+
+    /// Finds a product by id, product number or barcode.
+    pub fn find(&self, code: &str) -> Option<&Product> {
+        self.catalog
+            .find(code)
+            .or_else(|| self.barcodes.get(code).and_then(|id| self.catalog.find(id)))
+    }
+}
+
+/// Checks that `page`, rendered for `view` of `catalog`, has the first and last product of each
+/// category in it, so that a broken render is never published.
+fn validate(catalog: &Catalog, view: &View, page: &str) -> Result<()> {
+    if page.trim().is_empty() {
+        return Err(Error::Render("empty page".to_string()));
+    }
+    let (drinks, _) = view.paginate(view.apply(catalog, None, &[], &[]));
+    for (category, drinks) in drinks {
+        for drink in drinks.first().into_iter().chain(drinks.last()) {
+            if !page.contains(&tera::escape_html(catalog::name(drink))) {
+                return Err(Error::Render(format!(
+                    "{} is missing from {}",
+                    catalog::name(drink),
+                    category.name()
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+pub type SharedSnapshot = Arc<RwLock<Option<Arc<Snapshot>>>>;
+
+/// The IO shell around the pure catalog pipeline.
+pub struct Refresher {
+    source: Arc<dyn ProductSource>,
+    clock: Arc<dyn Clock>,
+    scorer: Arc<dyn Scorer>,
+    notifiers: Vec<Arc<dyn Notifier>>,
+    tera: SharedTera,
+    /// The products of the last successful fetch, for [`Refresher::recategorize`]
+    fetched: Mutex<Option<Vec<Product>>>,
+    staggered: bool,
+    /// The operator's barcode file, read at each refresh so that edits show up without a restart
+    barcodes: Option<PathBuf>,
+    /// Held while appending to the audit log
+    audit: Mutex<()>,
+    assortment: AssortmentRules,
+    /// Between refreshes, in seconds
+    interval: u64,
+}
+
+impl Refresher {
+    pub fn new(
+        source: Arc<dyn ProductSource>,
+        clock: Arc<dyn Clock>,
+        tera: SharedTera,
+    ) -> Refresher {
+        Refresher {
+            source,
+            clock,
+            scorer: Arc::new(ApkScorer),
+            notifiers: Vec::new(),
+            tera,
+            fetched: Mutex::new(None),
+            staggered: false,
+            barcodes: None,
+            audit: Mutex::new(()),
+            assortment: AssortmentRules::default(),
+            interval: UPDATE_INTERVAL,
+        }
+    }
+
+    /// Whether to fetch and show one category at a time, see [`Refresher::update`].
+    pub fn staggered(mut self, staggered: bool) -> Refresher {
+        self.staggered = staggered;
+        self
+    }
+
+    /// Reads barcodes from the file at `path` too, see [`barcodes`].
+    pub fn barcodes(mut self, path: Option<PathBuf>) -> Refresher {
+        self.barcodes = path;
+        self
+    }
+
+    /// Sets how many seconds to wait between refreshes. Defaults to [`UPDATE_INTERVAL`].
+    pub fn interval(mut self, seconds: u64) -> Refresher {
+        self.interval = seconds;
+        self
+    }
+
+    /// Shows only the assortments that `rules` allow.
+    pub fn assortment(mut self, rules: AssortmentRules) -> Refresher {
+        self.assortment = rules;
+        self
+    }
+
+    /// Sets the scorer used to rank the catalog.
+    pub fn scorer(mut self, scorer: Arc<dyn Scorer>) -> Refresher {
+        self.scorer = scorer;
+        self
+    }
+
+    /// Adds a notifier to tell about each successful refresh.
+    pub fn notifier(mut self, notifier: Arc<dyn Notifier>) -> Refresher {
+        self.notifiers.push(notifier);
+        self
+    }
+
+    /// Fetches and renders a new snapshot, pointing out products that tie or beat `records`.
+    pub async fn refresh(&self, records: Records) -> Result<Snapshot> {
+        info!("Fetching list of products...");
+        let products = self
+            .source
+            .fetch_products()
+            .instrument(info_span!("fetch"))
+            .await?;
+        info!(count = products.len(), "Fetched products");
+        *self.fetched.lock().unwrap() = Some(products.clone());
+        self.build(products, records)
+    }
+
+    /// Categorizes, scores and renders `products`, see [`Refresher::refresh`].
+    fn build(&self, products: Vec<Product>, records: Records) -> Result<Snapshot> {
+        self.build_from(products, records, None)
+    }
+
+    /// Like [`Refresher::build`], for products fetched at `data_from` instead of just now.
+    fn build_from(
+        &self,
+        products: Vec<Product>,
+        records: Records,
+        data_from: Option<SystemTime>,
+    ) -> Result<Snapshot> {
+        let catalog = {
+            let _span = info_span!("categorize").entered();
+            info!("Categorizing products...");
+            Catalog::build_with_rules(products, &*self.scorer, &self.assortment)
+        };
+        let _span = info_span!("render").entered();
+        info!("Rendering...");
+        let tera = self.tera.read().unwrap().clone();
+        let page = render::render_page(&tera, &catalog, &records, data_from)?;
+        validate(&catalog, &View::default(), &page)?;
+        let category_pages = CATEGORIES
+            .iter()
+            .map(|&category| {
+                let page =
+                    render::render_category_page(&tera, &catalog, &records, category, data_from)?;
+                validate(&catalog, &categories::view(category), &page)?;
+                Ok((category, page))
+            })
+            .collect::<Result<_>>()?;
+        let hash = signing::sha256(&serde_json::to_vec(
+            &catalog.products().collect::<Vec<_>>(),
+        )?);
+        let box_apk = catalog::box_apk(&catalog);
+        let countries = countries::leaderboard(&catalog);
+        let mapping = match &self.barcodes {
+            Some(path) => barcodes::read(path)?,
+            None => Vec::new(),
+        };
+        let barcodes = barcodes::build(&catalog, &mapping)?;
+        let index = SearchIndex::build(&catalog, &barcodes)?;
+        let updated_at = self.clock.now();
+        Ok(Snapshot {
+            catalog,
+            page,
+            category_updated_at: CATEGORIES
+                .iter()
+                .map(|&category| (category, updated_at))
+                .collect(),
+            category_pages,
+            updated_at,
+            data_from,
+            hash,
+            box_apk,
+            records,
+            countries,
+            index,
+            barcodes,
+        })
+    }
+
+    /// Like [`Refresher::refresh`], but showing each category in `state` as soon as it's fetched,
+    /// starting from the products of the last fetch. The in-between snapshots are only shown if
+    /// they look fine compared to `previous`, and nobody is notified about them.
+    async fn refresh_staggered(
+        &self,
+        state: &AppState,
+        previous: Option<&Snapshot>,
+        records: Records,
+    ) -> Result<Snapshot> {
+        let mut products = self.fetched.lock().unwrap().clone().unwrap_or_default();
+        let mut updated_at = previous
+            .map(|previous| previous.category_updated_at.clone())
+            .unwrap_or_default();
+        for (i, &category) in CATEGORIES.iter().enumerate() {
+            info!("Fetching {}...", category.name());
+            let fresh = self
+                .source
+                .fetch_category(category)
+                .instrument(info_span!("fetch", category = category.slug()))
+                .await?;
+            products.retain(|drink| catalog::categorize(drink) != category);
+            products.extend(fresh);
+            updated_at.insert(category, self.clock.now());
+            // The last one is published as usual
+            if i + 1 < CATEGORIES.len() {
+                let mut snapshot = self.build(products.clone(), records.clone())?;
+                snapshot.category_updated_at = updated_at.clone();
+                let fine = previous.map_or(true, |previous| {
+                    anomaly::check(&previous.catalog, &snapshot.catalog).is_empty()
+                });
+                if fine {
+                    self.swap(state, Arc::new(snapshot), Trigger::Stagger, None);
+                }
+            }
+        }
+        *self.fetched.lock().unwrap() = Some(products.clone());
+        let mut snapshot = self.build(products, records)?;
+        snapshot.category_updated_at = updated_at;
+        Ok(snapshot)
+    }
+
+    /// Refreshes once, publishing the new snapshot and the outcome to `state`. If staggered, the
+    /// categories are shown one at a time as they're fetched, and the notifiers are told once, at
+    /// the end. The fetched products are kept in storage, and if upstream can't be reached, the
+    /// list is shown from the ones kept last time, once, marked with when they're from.
+    pub async fn update(&self, state: &AppState) -> Result<()> {
+        info!("Updating APK list...");
+        let previous = state.snapshot.read().unwrap().clone();
+        let result = match records::load(&*state.storage) {
+            Ok(records) => {
+                let fetched = if self.staggered {
+                    self.refresh_staggered(state, previous.as_deref(), records.clone())
+                        .await
+                } else {
+                    self.refresh(records.clone()).await
+                };
+                let shown_fresh = previous
+                    .as_ref()
+                    .map_or(true, |previous| previous.data_from.is_none());
+                match fetched {
+                    Ok(snapshot) => {
+                        self.keep_fetched(state, snapshot.updated_at);
+                        Ok(snapshot)
+                    }
+                    Err(err @ Error::Upstream(_)) if shown_fresh => {
+                        self.from_cache(state, records, err)
+                    }
+                    Err(err) => Err(err),
+                }
+            }
+            Err(err) => Err(err),
+        };
+        let outcome = self.publish(state, previous.clone(), result, Trigger::Refresh, None);
+        if let (Err(Error::Anomaly(_)), Some(previous)) = (&outcome, previous) {
+            let shown = state.snapshot.read().unwrap().clone();
+            // Undo any categories shown on the way
+            if !shown.map_or(false, |shown| Arc::ptr_eq(&shown, &previous)) {
+                self.swap(state, previous, Trigger::Revert, None);
+            }
+        }
+        outcome
+    }
+
+    /// Keeps the last fetched products in storage, see [`productcache`].
+    fn keep_fetched(&self, state: &AppState, at: SystemTime) {
+        let products = match self.fetched.lock().unwrap().clone() {
+            Some(products) => products,
+            None => return,
+        };
+        if let Err(err) = productcache::save(&*state.storage, products, at) {
+            error!("Keeping the fetched products failed: {}", err);
+        }
+    }
+
+    /// A snapshot of the products kept at the last successful fetch, for when fetching failed
+    /// with `err`, which is returned if there are none.
+    fn from_cache(&self, state: &AppState, records: Records, err: Error) -> Result<Snapshot> {
+        let archive = match productcache::load(&*state.storage) {
+            Ok(Some(archive)) => archive,
+            Ok(None) => return Err(err),
+            Err(cache_err) => {
+                error!("Reading the kept products failed: {}", cache_err);
+                return Err(err);
+            }
+        };
+        let data_from = UNIX_EPOCH + Duration::from_secs(archive.exported_at);
+        warn!(
+            "Fetching failed ({}), showing the products from {}",
+            err,
+            dates::date_time(data_from)
+        );
+        *self.fetched.lock().unwrap() = Some(archive.products.clone());
+        self.build_from(archive.products, records, Some(data_from))
+    }
+
+    /// Goes through the last fetched products again, without fetching, and publishes the result
+    /// like [`Refresher::update`]. For after changing how products are categorized or scored, or
+    /// how they're rendered. `trigger` and `by` are what's written to the audit log.
+    pub fn recategorize(&self, state: &AppState, trigger: Trigger, by: Option<&str>) -> Result<()> {
+        info!("Recategorizing APK list...");
+        let products = self.fetched.lock().unwrap().clone();
+        let previous = state.snapshot.read().unwrap().clone();
+        // Still from the same fetch
+        let data_from = previous.as_ref().and_then(|previous| previous.data_from);
+        let result = match products {
+            Some(products) => records::load(&*state.storage)
+                .and_then(|records| self.build_from(products, records, data_from)),
+            None => Err(Error::Config("nothing has been fetched yet".to_string())),
+        };
+        self.publish(state, previous, result, trigger, by)
+    }
+
+    /// Shows `snapshot` in `state` and notes it in the audit log.
+    fn swap(&self, state: &AppState, snapshot: Arc<Snapshot>, trigger: Trigger, by: Option<&str>) {
+        let shown = state.snapshot.write().unwrap().replace(snapshot.clone());
+        let diff = match &shown {
+            Some(shown) => diff::diff(&shown.catalog, &snapshot.catalog),
+            None => Diff::default(),
+        };
+        let source = match trigger {
+            _ if snapshot.data_from.is_some() => "cache".to_string(),
+            Trigger::Refresh | Trigger::Stagger => self.source.name(),
+            // Built from products fetched earlier
+            Trigger::Recategorize | Trigger::Reload | Trigger::Revert => "cache".to_string(),
+        };
+        let entry = Entry::new(trigger, by, source, &snapshot, &diff);
+        let _guard = self.audit.lock().unwrap();
+        if let Err(err) = audit::append(&*state.storage, entry) {
+            error!("Writing the audit log failed: {}", err);
+        }
+    }
+
+    /// Publishes `result` of a refresh to `state`, unless it looks wrong compared to `previous`.
+    fn publish(
+        &self,
+        state: &AppState,
+        previous: Option<Arc<Snapshot>>,
+        result: Result<Snapshot>,
+        trigger: Trigger,
+        by: Option<&str>,
+    ) -> Result<()> {
+        match result {
+            Ok(snapshot) => {
+                let snapshot = Arc::new(snapshot);
+                if let Some(previous) = &previous {
+                    let problems = anomaly::check(&previous.catalog, &snapshot.catalog);
+                    if !problems.is_empty() {
+                        let err = Error::Anomaly(problems.clone());
+                        warn!("Keeping the current list: {}", err);
+                        state
+                            .status
+                            .write()
+                            .unwrap()
+                            .record_error(self.clock.now(), &err);
+                        notify::dispatch_anomaly(&self.notifiers, state, problems);
+                        return Err(err);
+                    }
+                }
+                let diff = match &previous {
+                    Some(previous) => {
+                        let diff = diff::diff(&previous.catalog, &snapshot.catalog);
+                        log_diff(&diff);
+                        diff
+                    }
+                    None => Diff::default(),
+                };
+                warm::warm(state, &snapshot);
+                self.swap(state, snapshot.clone(), trigger, by);
+                state
+                    .status
+                    .write()
+                    .unwrap()
+                    .record_success(self.clock.now());
+                info!("Succesfully updated APK list");
+                // Nothing's new about products from an earlier fetch, or the same products built
+                // again
+                let rebuilt = trigger == Trigger::Recategorize || trigger == Trigger::Reload;
+                if snapshot.data_from.is_none() && !rebuilt {
+                    let event = RefreshEvent {
+                        snapshot,
+                        previous,
+                        diff,
+                    };
+                    notify::dispatch(&self.notifiers, state, event);
+                }
+                Ok(())
+            }
+            Err(err) => {
+                error!(category = err.category(), "Update failed: {}", err);
+                state
+                    .status
+                    .write()
+                    .unwrap()
+                    .record_error(self.clock.now(), &err);
+                Err(err)
+            }
+        }
+    }
+
+    /// Refreshes forever, backing off while upstream can't be reached.
+    pub async fn run(self: Arc<Self>, state: AppState) {
+        loop {
+            let delay = match self.update(&state).await {
+                // The fetch worked, and fetching again right away would most likely get the same
+                // thing
+                Ok(()) | Err(Error::Anomaly(_)) | Err(Error::Render(_)) if !stale(&state) => {
+                    state.status.write().unwrap().reset_backoff();
+                    self.interval
+                }
+                // The fetch failed, whether or not an earlier run's products are shown meanwhile
+                _ => {
+                    let mut status = state.status.write().unwrap();
+                    let delay = jitter(backoff(status.failures + 1, self.interval));
+                    status.record_backoff(delay);
+                    delay
+                }
+            };
+            tokio::time::delay_for(Duration::new(delay, 0)).await;
+        }
+    }
+}
+
+/// Seconds to wait after `failures` failed refreshes in a row: [`RETRY_INTERVAL`], doubled for
+/// each failure after the first, but never longer than `max`.
+pub fn backoff(failures: u32, max: u64) -> u64 {
+    let doublings = failures.saturating_sub(1).min(32);
+    RETRY_INTERVAL.saturating_mul(1 << doublings).min(max)
+}
+
+/// `delay` shortened by up to half, so that instances that lost upstream together don't all come
+/// back at once.
+fn jitter(delay: u64) -> u64 {
+    delay - rand::thread_rng().gen_range(0, delay / 2 + 1)
+}
+
+/// Whether the list shown in `state` is from products fetched at an earlier run.
+fn stale(state: &AppState) -> bool {
+    let snapshot = state.snapshot.read().unwrap();
+    snapshot
+        .as_ref()
+        .map_or(false, |snapshot| snapshot.data_from.is_some())
+}
+
+fn log_diff(diff: &Diff) {
+    info!(
+        "{} new, {} removed, {} price changes",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.price_changes.len()
+    );
+}
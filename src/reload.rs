@@ -0,0 +1,78 @@
+//! Reloading the templates on SIGHUP, for deploying template tweaks without a restart. The config
+//! file is read again too, and the presets, icons and features the templates get from it are
+//! updated, before the cached pages are rendered again from the last fetched products.
+//!
+//! Everything else in the config, like the notifiers and the routes, is still only read at
+//! startup. So are the themes of the venues.
+
+use crate::audit::Trigger;
+use crate::config::{self, Config};
+use crate::error::Result;
+use crate::render;
+use crate::score::Scorer;
+use crate::server::Job;
+use crate::state::AppState;
+use async_trait::async_trait;
+use std::env;
+use std::sync::Arc;
+use tracing::{error, info};
+
+pub struct Reloader {
+    theme: String,
+    scorers: Vec<Arc<dyn Scorer>>,
+}
+
+impl Reloader {
+    /// Reloads the templates matching `theme`, with `scorers` available to them like at startup.
+    pub fn new(theme: impl Into<String>, scorers: Vec<Arc<dyn Scorer>>) -> Reloader {
+        Reloader {
+            theme: theme.into(),
+            scorers,
+        }
+    }
+
+    /// Parses the templates again, with the config file if there is one, and renders the cached
+    /// pages with them. Nothing changes if either is broken.
+    pub fn reload(&self, state: &AppState) -> Result<()> {
+        let config = match env::var(config::CONFIG_ENV_VAR) {
+            Ok(path) => Config::load(path)?,
+            Err(_) => (*state.config).clone(),
+        };
+        let tera = render::templates(&self.theme, &self.scorers, &config)?;
+        let previous = state.tera();
+        *state.tera.write().unwrap() = Arc::new(tera);
+        if let Some(refresher) = &state.refresher {
+            if state.snapshot.read().unwrap().is_some() {
+                if let Err(err) = refresher.recategorize(state, Trigger::Reload, None) {
+                    // Keep serving with templates that are known to work
+                    *state.tera.write().unwrap() = previous;
+                    return Err(err);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+#[async_trait]
+impl Job for Reloader {
+    async fn run(self: Box<Self>, state: AppState) {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangups = match signal(SignalKind::hangup()) {
+            Ok(hangups) => hangups,
+            Err(err) => {
+                error!("Can't listen for SIGHUP: {}", err);
+                return;
+            }
+        };
+        while hangups.recv().await.is_some() {
+            info!("Reloading templates...");
+            match self.reload(&state) {
+                Ok(()) => info!("Reloaded templates"),
+                Err(err) => error!("Reloading templates failed: {}", err),
+            }
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use crate::catalog::{self, Catalog, Category};
+use crate::categories;
+use crate::changes::Changes;
+use crate::config::Config;
+use crate::countries::Country;
+use crate::dates;
+use crate::display;
+use crate::movers::Movers;
+use crate::names;
+use crate::records::Records;
+use crate::refresh::Snapshot;
+use crate::score::{self, Scorer};
+use crate::shopping::{Line, Totals};
+use crate::view::{Page, View};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::SystemTime;
+use systemet::Product;
+use tera::{Context, Tera};
+
+pub const TEMPLATE_GLOB: &str = "templates/*";
+pub const TEMPLATE: &str = "apk.html";
+pub const MESSAGE_TEMPLATE: &str = "message.html";
+pub const FAVORITES_TEMPLATE: &str = "favorites.html";
+pub const SHOPPING_LIST_TEMPLATE: &str = "list.html";
+pub const AGE_GATE_TEMPLATE: &str = "age.html";
+pub const KIOSK_TEMPLATE: &str = "kiosk.html";
+pub const COMING_TEMPLATE: &str = "coming.html";
+pub const MOVERS_TEMPLATE: &str = "movers.html";
+pub const PRODUCT_TEMPLATE: &str = "product.html";
+pub const COUNTRIES_TEMPLATE: &str = "countries.html";
+pub const SEARCH_TEMPLATE: &str = "search.html";
+pub const CHANGES_TEMPLATE: &str = "changes.html";
+pub const DIGEST_TEMPLATE: &str = "digest.txt";
+
+/// The taste clocks Systembolaget gives some products, and what they're called on product pages
+const TASTE_CLOCKS: [(&str, &str); 7] = [
+    ("TasteClockBody", "Fyllighet"),
+    ("TasteClockSweetness", "Sötma"),
+    ("TasteClockFruitacid", "Fruktsyra"),
+    ("TasteClockBitter", "Beska"),
+    ("TasteClockRoughness", "Strävhet"),
+    ("TasteClockSmokiness", "Rökighet"),
+    ("TasteClockCasque", "Fatkaraktär"),
+];
+
+/// Loads the templates matching `glob`, making `scorers` available through the `score` filter, the
+/// decimals of `config` through the `display` filter, product permalinks through the `permalink`
+/// filter, and its presets, stores with known stock, category icons and features through the
+/// `presets`, `stores`, `icons` and `features` functions. `basen` is a feature if it isn't hidden and
+/// there's a basen APK scorer.
+pub fn templates(glob: &str, scorers: &[Arc<dyn Scorer>], config: &Config) -> tera::Result<Tera> {
+    let mut tera = Tera::new(glob)?;
+    tera.register_filter("apk", apk_filter);
+    tera.register_filter("price_per_75cl", price_per_75cl_filter);
+    tera.register_filter("price_per_liter", price_per_liter_filter);
+    tera.register_filter("is_box", is_box_filter);
+    tera.register_filter("format_float", format_float);
+    tera.register_filter("display", display::filter(config.display.clone()));
+    tera.register_filter("permalink", names::filter(config.names.clone()));
+    let basen = scorers.iter().any(|scorer| scorer.name() == "basen_apk");
+    let scorers = scorers.to_vec();
+    tera.register_filter(
+        "score",
+        move |value: &Value, args: &HashMap<String, Value>| -> tera::Result<Value> {
+            let name = args.get("by").and_then(Value::as_str).unwrap_or("apk");
+            let scorer = scorers
+                .iter()
+                .find(|scorer| scorer.name() == name)
+                .ok_or_else(|| tera::Error::msg(format!("No scorer named {}", name)))?;
+            let drink: Product = serde_json::from_value(value.clone())?;
+            Ok(serde_json::to_value(scorer.score(&drink))?)
+        },
+    );
+    let presets = serde_json::to_value(&config.presets)?;
+    tera.register_function("presets", move |_: &HashMap<String, Value>| {
+        Ok(presets.clone())
+    });
+    let stores =
+        serde_json::to_value(config.stock.as_ref().map_or(&[][..], |stock| &stock.stores))?;
+    tera.register_function("stores", move |_: &HashMap<String, Value>| {
+        Ok(stores.clone())
+    });
+    let icons = serde_json::to_value(config.icons())?;
+    tera.register_function("icons", move |_: &HashMap<String, Value>| Ok(icons.clone()));
+    let mut features = config.features();
+    features.insert("basen", features["basen"] && basen);
+    let features = serde_json::to_value(features)?;
+    tera.register_function("features", move |_: &HashMap<String, Value>| {
+        Ok(features.clone())
+    });
+    Ok(tera)
+}
+
+/// The context of `view`, which is just its pagination, of the whole catalog.
+fn page_context(catalog: &Catalog, records: &Records, view: &View) -> Context {
+    let (drinks, pages) = view.paginate(view.apply(catalog, None, &[], &[]));
+    let mut context = Context::new();
+    context.insert("drinks", &drinks);
+    context.insert("pages", &pages);
+    context.insert("view", &View::default());
+    context.insert("permalink", "/");
+    context.insert("pinned_link", &View::default().pinned_link());
+    context.insert("tried", &Vec::<String>::new());
+    context.insert("my_ratings", &HashMap::<String, u8>::new());
+    context.insert("box_apk", &catalog::box_apk(catalog));
+    context.insert("records", records);
+    context
+}
+
+/// Adds when the products are from, if they aren't fresh, see [`Snapshot::data_from`].
+fn insert_data_from(context: &mut Context, data_from: Option<SystemTime>) {
+    if let Some(data_from) = data_from {
+        context.insert("data_from", &dates::date_time(data_from));
+    }
+}
+
+/// The whole list, with a note about when the products are from if `data_from` is given.
+pub fn render_page(
+    tera: &Tera,
+    catalog: &Catalog,
+    records: &Records,
+    data_from: Option<SystemTime>,
+) -> tera::Result<String> {
+    let mut context = page_context(catalog, records, &View::default());
+    insert_data_from(&mut context, data_from);
+    tera.render(TEMPLATE, &context)
+}
+
+/// The list of just `category`, linking to its own page, see [`categories`].
+pub fn render_category_page(
+    tera: &Tera,
+    catalog: &Catalog,
+    records: &Records,
+    category: Category,
+    data_from: Option<SystemTime>,
+) -> tera::Result<String> {
+    let view = categories::view(category);
+    let mut context = page_context(catalog, records, &view);
+    insert_data_from(&mut context, data_from);
+    context.insert("view", &view);
+    context.insert("permalink", categories::path(category));
+    context.insert("pinned_link", &view.pinned_link());
+    tera.render(TEMPLATE, &context)
+}
+
+/// The list of a venue, with its own prices in `catalog`. The records are for shelf prices, so
+/// they aren't shown.
+pub fn render_venue_page(tera: &Tera, catalog: &Catalog, venue: &str) -> tera::Result<String> {
+    // All on one page, since the page links would lead to the main list
+    let view = View {
+        per_page: Some(usize::MAX),
+        ..View::default()
+    };
+    let mut context = page_context(catalog, &Records::new(), &view);
+    context.insert("venue", venue);
+    tera.render(TEMPLATE, &context)
+}
+
+/// The list with only the products in `view`, on the current `pages`, the ids of the products
+/// marked as tried, and the user's own ratings. The box wine APK and the records are those of the
+/// whole `snapshot`.
+pub fn render_view(
+    tera: &Tera,
+    drinks: &HashMap<Category, Vec<&Product>>,
+    pages: &HashMap<Category, Page>,
+    view: &View,
+    tried: &[String],
+    my_ratings: &HashMap<String, u8>,
+    snapshot: &Snapshot,
+) -> tera::Result<String> {
+    let mut context = Context::new();
+    context.insert("drinks", drinks);
+    context.insert("pages", pages);
+    context.insert("view", view);
+    context.insert("permalink", &view.link());
+    context.insert("pinned_link", &view.pinned_link());
+    context.insert("tried", tried);
+    context.insert("my_ratings", my_ratings);
+    context.insert("box_apk", &snapshot.box_apk);
+    context.insert("records", &snapshot.records);
+    insert_data_from(&mut context, snapshot.data_from);
+    tera.render(TEMPLATE, &context)
+}
+
+/// The plain text body of the email digest, with the `best` products and the `changes` since the
+/// last one, each already formatted as a line.
+pub fn render_digest(tera: &Tera, best: &[String], changes: &[String]) -> tera::Result<String> {
+    let mut context = Context::new();
+    context.insert("best", best);
+    context.insert("changes", changes);
+    tera.render(DIGEST_TEMPLATE, &context)
+}
+
+/// A page showing just a short message.
+pub fn render_message(tera: &Tera, message: &str) -> tera::Result<String> {
+    let mut context = Context::new();
+    context.insert("message", message);
+    tera.render(MESSAGE_TEMPLATE, &context)
+}
+
+/// Asks for the visitor's age, going to `back` once confirmed.
+pub fn render_age_gate(tera: &Tera, back: &str) -> tera::Result<String> {
+    let mut context = Context::new();
+    context.insert("back", back);
+    tera.render(AGE_GATE_TEMPLATE, &context)
+}
+
+/// The top of `category` in large type, moving on to `next` after `refresh` seconds.
+pub fn render_kiosk(
+    tera: &Tera,
+    category: Category,
+    drinks: &[Product],
+    refresh: u64,
+    next: &str,
+) -> tera::Result<String> {
+    let mut context = Context::new();
+    context.insert("category", category.name());
+    context.insert("drinks", drinks);
+    context.insert("refresh", &refresh);
+    context.insert("next", next);
+    tera.render(KIOSK_TEMPLATE, &context)
+}
+
+/// Upcoming launches, as (day, products) pairs.
+pub fn render_coming(tera: &Tera, launches: &[(&str, Vec<&Product>)]) -> tera::Result<String> {
+    let mut context = Context::new();
+    context.insert("launches", launches);
+    tera.render(COMING_TEMPLATE, &context)
+}
+
+/// The biggest APK changes of the last week.
+pub fn render_movers(tera: &Tera, movers: &Movers) -> tera::Result<String> {
+    let mut context = Context::new();
+    context.insert("gainers", &movers.gainers);
+    context.insert("losers", &movers.losers);
+    tera.render(MOVERS_TEMPLATE, &context)
+}
+
+/// The changes of the last refresh that changed anything.
+pub fn render_changes(tera: &Tera, changes: &Changes) -> tera::Result<String> {
+    let mut context = Context::new();
+    context.insert("changes", changes);
+    tera.render(CHANGES_TEMPLATE, &context)
+}
+
+/// The countries with at least `min` products, best first.
+pub fn render_countries(tera: &Tera, countries: &[&Country], min: usize) -> tera::Result<String> {
+    let mut context = Context::new();
+    context.insert("countries", countries);
+    context.insert("min", &min);
+    tera.render(COUNTRIES_TEMPLATE, &context)
+}
+
+/// The details of one product, with its allergen tags.
+/// The page of `drink`, named `name`, with links to the `related` products, see [`names`].
+pub fn render_product(
+    tera: &Tera,
+    drink: &Product,
+    name: &str,
+    tags: &[&str],
+    related: &[&Product],
+) -> tera::Result<String> {
+    let fields = serde_json::to_value(drink)?;
+    // Only the clocks the product has, out of 12
+    let taste: Vec<(&str, u64)> = TASTE_CLOCKS
+        .iter()
+        .filter_map(|&(key, label)| Some((label, fields.get(key)?.as_u64()?)))
+        .filter(|&(_, value)| value > 0)
+        .collect();
+    let mut context = Context::new();
+    context.insert("drink", drink);
+    context.insert("name", name);
+    context.insert("related", related);
+    context.insert("category", catalog::categorize(drink).name());
+    context.insert("tags", tags);
+    context.insert("basen_apk", &catalog::basen_apk(drink));
+    context.insert("price_per_liter", &catalog::price_per_liter(drink));
+    context.insert("country", &catalog::country(drink));
+    context.insert("taste", &taste);
+    context.insert("facts", &score::facts(drink));
+    tera.render(PRODUCT_TEMPLATE, &context)
+}
+
+pub fn render_search(tera: &Tera, query: &str, drinks: &[&Product]) -> tera::Result<String> {
+    let mut context = Context::new();
+    context.insert("query", query);
+    context.insert("drinks", drinks);
+    tera.render(SEARCH_TEMPLATE, &context)
+}
+
+pub fn render_favorites(tera: &Tera, drinks: &[&Product]) -> tera::Result<String> {
+    let mut context = Context::new();
+    context.insert("drinks", drinks);
+    tera.render(FAVORITES_TEMPLATE, &context)
+}
+
+pub fn render_shopping_list(tera: &Tera, lines: &[Line], totals: &Totals) -> tera::Result<String> {
+    let mut context = Context::new();
+    context.insert("lines", lines);
+    context.insert("totals", totals);
+    tera.render(SHOPPING_LIST_TEMPLATE, &context)
+}
+
+pub fn format_float(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let number: f64 = serde_json::from_value(value.clone())?;
+    let precision = serde_json::from_value(args.get("precision").unwrap().to_owned())?;
+    Ok(serde_json::to_value(format!("{:.*}", precision, number))?)
+}
+
+pub fn apk_filter(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let drink: Product = serde_json::from_value(value.clone())?;
+    Ok(serde_json::to_value(catalog::apk(&drink))?)
+}
+
+pub fn price_per_75cl_filter(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let drink: Product = serde_json::from_value(value.clone())?;
+    Ok(serde_json::to_value(catalog::price_per_75cl(&drink))?)
+}
+
+pub fn price_per_liter_filter(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let drink: Product = serde_json::from_value(value.clone())?;
+    Ok(serde_json::to_value(catalog::price_per_liter(&drink))?)
+}
+
+pub fn is_box_filter(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let drink: Product = serde_json::from_value(value.clone())?;
+    Ok(Value::Bool(catalog::is_box(&drink)))
+}
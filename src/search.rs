@@ -0,0 +1,287 @@
+//! Searching the catalog by name, producer and style, at `/search`, `/api/search` for JSON, and
+//! `/api/suggest` for a few suggestions while typing. The list's `?sok=` uses it too. The words of
+//! each product are indexed at refresh time, with the products having each trigram of them, so
+//! that a search only looks at products that share something with the query. A query matches a
+//! product if each of its words starts a word of the product, or is one letter off from one. The
+//! matches are ranked by how well they match, then by APK. Barcodes are indexed too, so a scanned
+//! EAN finds its product.
+
+use crate::apierror::{self, ApiError};
+use crate::barcodes::Barcodes;
+use crate::catalog::{self, Catalog, Category, CATEGORIES};
+use crate::display::{self, Field};
+use crate::error::Result;
+use crate::names;
+use crate::render;
+use crate::state::AppState;
+use futures::future;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use systemet::Product;
+use tracing::error;
+use warp::http::StatusCode;
+use warp::reply::{html, Response};
+use warp::{Filter, Rejection, Reply};
+
+/// How many products a search answers with
+pub const RESULTS: usize = 50;
+/// How many products `/api/suggest` answers with
+pub const SUGGESTIONS: usize = 10;
+/// Words shorter than this must be spelled right
+const MIN_FUZZY_LEN: usize = 4;
+
+/// The product fields that are searched.
+const FIELDS: [&str; 6] = [
+    "ProductNameBold",
+    "ProductNameThin",
+    "ProducerName",
+    "Style",
+    "Type",
+    "SubCategory",
+];
+
+#[derive(Default)]
+pub struct SearchIndex {
+    /// Where each indexed product is in the catalog
+    products: Vec<(Category, usize)>,
+    /// The normalized words of each product
+    words: Vec<Vec<String>>,
+    /// The products with each trigram of their words
+    trigrams: HashMap<String, Vec<usize>>,
+}
+
+/// Lowercase and without accents, see [`names::fold`], with everything but letters and digits as
+/// single spaces, and a space between digits and letters, so that `33cl` finds `33 cl`.
+pub fn normalize(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut last: Option<char> = None;
+    for c in names::fold(&text.to_lowercase()).chars() {
+        let c = if c.is_alphanumeric() { c } else { ' ' };
+        let boundary = last.map_or(false, |last| {
+            last.is_alphanumeric() && c.is_alphanumeric() && last.is_numeric() != c.is_numeric()
+        });
+        if boundary {
+            normalized.push(' ');
+        }
+        if c != ' ' || last.map_or(false, |last| last != ' ') {
+            normalized.push(c);
+        }
+        last = Some(c);
+    }
+    normalized.trim_end().to_string()
+}
+
+/// The trigrams of `word`, padded so that its start counts too. A word that's one letter off from
+/// another, or starts it, shares at least one of them.
+fn trigrams(word: &str) -> HashSet<String> {
+    let padded: Vec<char> = format!("  {} ", word).chars().collect();
+    padded
+        .windows(3)
+        .map(|window| window.iter().collect())
+        .collect()
+}
+
+/// The searchable words of `drink`, with `eans`, normalized.
+fn words(drink: &Product, eans: &[&str]) -> Result<Vec<String>> {
+    let fields = serde_json::to_value(drink)?;
+    let text: Vec<&str> = FIELDS
+        .iter()
+        .filter_map(|field| fields.get(field).and_then(Value::as_str))
+        .chain(eans.iter().copied())
+        .collect();
+    Ok(normalize(&text.join(" "))
+        .split(' ')
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Whether `a` becomes `b` by changing, adding or removing at most one letter.
+fn one_off(a: &[char], b: &[char]) -> bool {
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if long.len() - short.len() > 1 {
+        return false;
+    }
+    let start = short.iter().zip(long).take_while(|(a, b)| a == b).count();
+    if start == short.len() {
+        true
+    } else if short.len() == long.len() {
+        short[start + 1..] == long[start + 1..]
+    } else {
+        short[start..] == long[start + 1..]
+    }
+}
+
+/// How well the query word `wanted` matches the product word `word`: 2 if it starts it, 1 if it's
+/// one letter off from it, or from as much of it.
+fn similarity(wanted: &[char], word: &str) -> u32 {
+    let word: Vec<char> = word.chars().collect();
+    if word.starts_with(wanted) {
+        return 2;
+    }
+    if wanted.len() < MIN_FUZZY_LEN {
+        return 0;
+    }
+    let prefix = &word[..word.len().min(wanted.len())];
+    if one_off(wanted, &word) || one_off(wanted, prefix) {
+        1
+    } else {
+        0
+    }
+}
+
+impl SearchIndex {
+    pub fn build(catalog: &Catalog, barcodes: &Barcodes) -> Result<SearchIndex> {
+        let mut eans: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (ean, id) in barcodes {
+            eans.entry(id.as_str()).or_default().push(ean.as_str());
+        }
+        let mut index = SearchIndex::default();
+        for &category in CATEGORIES.iter() {
+            for (i, drink) in catalog.get(category).iter().enumerate() {
+                let eans = eans.get(catalog::id(drink)).map_or(&[][..], Vec::as_slice);
+                let words = words(drink, eans)?;
+                let trigrams: HashSet<String> =
+                    words.iter().flat_map(|word| trigrams(word)).collect();
+                for trigram in trigrams {
+                    index
+                        .trigrams
+                        .entry(trigram)
+                        .or_default()
+                        .push(index.products.len());
+                }
+                index.products.push((category, i));
+                index.words.push(words);
+            }
+        }
+        Ok(index)
+    }
+
+    /// The products sharing a trigram with `word`.
+    fn candidates(&self, word: &str) -> HashSet<usize> {
+        trigrams(word)
+            .iter()
+            .filter_map(|trigram| self.trigrams.get(trigram))
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    /// The products of `catalog`, which the index was built from, matching `query`, best first.
+    pub fn search<'a>(&self, catalog: &'a Catalog, query: &str) -> Vec<&'a Product> {
+        let query = normalize(query);
+        let wanted: Vec<&str> = query.split(' ').filter(|word| !word.is_empty()).collect();
+        // Only the products sharing something with every word can match
+        let mut candidates: Option<HashSet<usize>> = None;
+        for word in &wanted {
+            let found = self.candidates(word);
+            candidates = Some(match candidates {
+                Some(candidates) => candidates.intersection(&found).copied().collect(),
+                None => found,
+            });
+        }
+        let wanted: Vec<Vec<char>> = wanted.iter().map(|word| word.chars().collect()).collect();
+        let mut matches: Vec<(u32, &Product)> = candidates
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|product| {
+                let words = &self.words[product];
+                let mut score = 0;
+                for wanted in &wanted {
+                    match words.iter().map(|word| similarity(wanted, word)).max() {
+                        Some(similarity) if similarity > 0 => score += similarity,
+                        _ => return None,
+                    }
+                }
+                let (category, i) = self.products[product];
+                Some((score, catalog.get(category).get(i)?))
+            })
+            .collect();
+        matches.sort_by(|(s1, d1), (s2, d2)| {
+            Reverse(s1)
+                .cmp(&Reverse(s2))
+                .then_with(|| catalog::apk_comparator(d1, d2))
+        });
+        matches.into_iter().map(|(_, drink)| drink).collect()
+    }
+}
+
+/// The `n` best products matching `query`, if there's a catalog.
+fn results<'a>(
+    catalog: Option<(&'a Catalog, &SearchIndex)>,
+    query: &str,
+    n: usize,
+) -> Vec<&'a Product> {
+    match catalog {
+        Some((catalog, index)) => {
+            let mut results = index.search(catalog, query);
+            results.truncate(n);
+            results
+        }
+        None => Vec::new(),
+    }
+}
+
+/// The `n` best products matching `query` as JSON.
+fn answer(state: &AppState, query: &str, n: usize) -> std::result::Result<Response, Rejection> {
+    let snapshot = state
+        .snapshot
+        .read()
+        .unwrap()
+        .clone()
+        .ok_or_else(ApiError::not_ready)?;
+    let catalog = Some((&snapshot.catalog, &snapshot.index));
+    let results: Vec<Value> = results(catalog, query, n)
+        .into_iter()
+        .map(|drink| {
+            json!({
+                "id": catalog::id(drink),
+                "name": catalog::name(drink),
+                "category": catalog::categorize(drink),
+                "apk": display::round(&state.config.display, Field::Apk, catalog::apk(drink).0),
+            })
+        })
+        .collect();
+    Ok(warp::reply::json(&results).into_response())
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    #[serde(default)]
+    q: String,
+}
+
+pub fn routes(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let page = {
+        let state = state.clone();
+        warp::path!("search")
+            .and(warp::query::<SearchQuery>())
+            .map(move |query: SearchQuery| {
+                let snapshot = state.snapshot.read().unwrap().clone();
+                let catalog = snapshot
+                    .as_ref()
+                    .map(|snapshot| (&snapshot.catalog, &snapshot.index));
+                let results = results(catalog, &query.q, RESULTS);
+                match render::render_search(&state.tera(), &query.q, &results) {
+                    Ok(page) => html(page).into_response(),
+                    Err(err) => {
+                        error!("{}", err);
+                        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                    }
+                }
+            })
+    };
+    let api = {
+        let state = state.clone();
+        warp::path!("api" / "search")
+            .and(warp::query::<SearchQuery>())
+            .and_then(move |query: SearchQuery| future::ready(answer(&state, &query.q, RESULTS)))
+    };
+    let suggest = warp::path!("api" / "suggest")
+        .and(warp::query::<SearchQuery>())
+        .and_then(move |query: SearchQuery| future::ready(answer(&state, &query.q, SUGGESTIONS)));
+    let api = api.or(suggest).unify().recover(apierror::recover).unify();
+    page.or(api).unify()
+}
@@ -0,0 +1,76 @@
+//! Saved searches: a view plus an optional APK threshold, re-run after each refresh to alert about
+//! products that start matching.
+
+use crate::alerts::Alert;
+use crate::catalog::{self, Catalog};
+use crate::config::AllergenConfig;
+use crate::notify::RefreshEvent;
+use crate::view::View;
+use serde::{Deserialize, Serialize};
+use systemet::Product;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    /// The canonical query string of the view, see [`View::to_query`]
+    pub query: String,
+    pub min_apk: Option<f64>,
+}
+
+impl SavedSearch {
+    /// Canonicalizes `query`, which may also be a link like `/?sok=ipa`.
+    pub fn new(query: &str, min_apk: Option<f64>) -> SavedSearch {
+        SavedSearch {
+            query: View::from_query(query.trim_start_matches('/').trim_start_matches('?'))
+                .to_query(),
+            min_apk,
+        }
+    }
+
+    pub fn view(&self) -> View {
+        View::from_query(&self.query)
+    }
+
+    fn matches(&self, view: &View, drink: &Product, allergens: &[AllergenConfig]) -> bool {
+        view.matches(drink, allergens)
+            && self
+                .min_apk
+                .map_or(true, |min_apk| catalog::apk(drink).0 >= min_apk)
+    }
+
+    /// Products matching now that didn't before: new ones, or ones that got cheaper and the like.
+    /// Nothing on the first refresh, since everything would be new then.
+    pub fn new_matches<'a>(
+        &self,
+        event: &'a RefreshEvent,
+        allergens: &[AllergenConfig],
+    ) -> Vec<&'a Product> {
+        let previous: &Catalog = match &event.previous {
+            Some(previous) => &previous.catalog,
+            None => return Vec::new(),
+        };
+        let view = self.view();
+        event
+            .snapshot
+            .catalog
+            .products()
+            .filter(|drink| self.matches(&view, drink, allergens))
+            .filter(|drink| {
+                previous
+                    .find(catalog::id(drink))
+                    .map_or(true, |old| !self.matches(&view, old, allergens))
+            })
+            .collect()
+    }
+
+    pub fn alerts(&self, event: &RefreshEvent, allergens: &[AllergenConfig]) -> Vec<Alert> {
+        self.new_matches(event, allergens)
+            .into_iter()
+            .map(|drink| Alert::SearchMatch {
+                id: catalog::id(drink).to_string(),
+                name: catalog::name(drink).to_string(),
+                apk: catalog::apk(drink),
+                search: self.view().link(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,497 @@
+use crate::access;
+use crate::admin;
+use crate::agegate;
+use crate::allergens;
+use crate::api;
+use crate::apierror::{self, ApiError};
+use crate::atom::{self, AtomRecorder};
+use crate::audit::{self, Trigger};
+use crate::buy;
+use crate::categories;
+use crate::changes::{self, ChangesRecorder};
+use crate::config::Config;
+use crate::countries;
+use crate::crawl;
+use crate::email::{self, Mailer};
+use crate::error::{Error, Result};
+use crate::favorites;
+use crate::feed::{self, FeedRecorder};
+use crate::footprint;
+use crate::grafana;
+use crate::history::HistoryRecorder;
+use crate::homeassistant;
+use crate::ical;
+use crate::images;
+use crate::kiosk;
+use crate::launchplan::{self, LaunchPlanFetcher};
+use crate::metrics;
+use crate::movers;
+use crate::notify::Notifier;
+use crate::pagecache::{self, PageCacher};
+use crate::prefs;
+use crate::presets;
+use crate::prices::{self, PriceHistory, PriceRecorder};
+use crate::publish::Publisher;
+use crate::push;
+use crate::qr;
+use crate::ratings::{self, RatingScorer, SharedRatings};
+use crate::records::RecordKeeper;
+use crate::refresh::{self, Refresher};
+use crate::reload::Reloader;
+use crate::render;
+use crate::score::{self, Scorer};
+use crate::search;
+use crate::session;
+use crate::shed;
+use crate::shopping;
+use crate::shortlink;
+use crate::slack;
+use crate::source::{Clock, DumpSource, FallbackSource, ProductSource, SystemClock};
+pub use crate::state::AppState;
+use crate::state::SharedTera;
+use crate::stock::StockFetcher;
+use crate::storage::{MemoryStorage, Storage};
+use crate::stores::{self, StoreFetcher};
+use crate::sync::{self, VersionRecorder};
+use crate::tried;
+use crate::venue::{self, VenueRenderer};
+use crate::view::{self, View};
+use crate::warm;
+use async_trait::async_trait;
+use futures::future;
+use secrecy::ExposeSecret;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{info, warn};
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+pub const DEFAULT_ADDR: ([u8; 4], u16) = ([127, 0, 0, 1], 3030);
+
+/// A background task started together with the server.
+#[async_trait]
+pub trait Job: Send + 'static {
+    async fn run(self: Box<Self>, state: AppState);
+}
+
+pub struct ApkServer {
+    addrs: Vec<SocketAddr>,
+    refresher: Arc<Refresher>,
+    jobs: Vec<Box<dyn Job>>,
+    state: AppState,
+}
+
+#[derive(Default)]
+pub struct ApkServerBuilder {
+    addrs: Vec<SocketAddr>,
+    source: Option<Arc<dyn ProductSource>>,
+    clock: Option<Arc<dyn Clock>>,
+    scorers: Vec<Arc<dyn Scorer>>,
+    theme: Option<String>,
+    storage: Option<Arc<dyn Storage>>,
+    config: Config,
+    notifiers: Vec<Arc<dyn Notifier>>,
+    jobs: Vec<Box<dyn Job>>,
+    interval: Option<u64>,
+    mailer: Option<Arc<Mailer>>,
+}
+
+impl ApkServer {
+    pub fn builder() -> ApkServerBuilder {
+        ApkServerBuilder::default()
+    }
+
+    pub fn state(&self) -> &AppState {
+        &self.state
+    }
+
+    pub fn routes(&self) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+        routes(self.state.clone())
+    }
+
+    /// Runs a single refresh right away.
+    pub async fn update(&self) -> Result<()> {
+        self.refresher.update(&self.state).await
+    }
+
+    /// Starts the refresh job and any other jobs, and serves until the process is killed.
+    pub async fn run(self) -> Result<()> {
+        tokio::spawn(self.refresher.clone().run(self.state.clone()));
+        for job in self.jobs {
+            tokio::spawn(job.run(self.state.clone()));
+        }
+        let routes = routes(self.state);
+        let servers = self.addrs.into_iter().map(|addr| {
+            info!("Listening on {}...", addr);
+            warp::serve(routes.clone()).run(addr)
+        });
+        futures::future::join_all(servers).await;
+        Ok(())
+    }
+}
+
+impl ApkServerBuilder {
+    /// Adds an address to listen on. Defaults to 127.0.0.1:3030 if none are given.
+    pub fn bind(mut self, addr: SocketAddr) -> ApkServerBuilder {
+        self.addrs.push(addr);
+        self
+    }
+
+    pub fn source(mut self, source: impl ProductSource + 'static) -> ApkServerBuilder {
+        self.source = Some(Arc::new(source));
+        self
+    }
+
+    pub fn clock(mut self, clock: impl Clock + 'static) -> ApkServerBuilder {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Adds a scorer. The first one added ranks the lists; all of them are available to the
+    /// templates. Defaults to APK, basen APK unless `hide_basen` is set, and price per liter. The
+    /// `betyg` scorer of average ratings is always available too.
+    pub fn scorer(mut self, scorer: impl Scorer + 'static) -> ApkServerBuilder {
+        self.scorers.push(Arc::new(scorer));
+        self
+    }
+
+    /// Sets the glob matching the templates to render with.
+    pub fn theme(mut self, glob: impl Into<String>) -> ApkServerBuilder {
+        self.theme = Some(glob.into());
+        self
+    }
+
+    pub fn config(mut self, config: Config) -> ApkServerBuilder {
+        self.config = config;
+        self
+    }
+
+    pub fn storage(mut self, storage: impl Storage + 'static) -> ApkServerBuilder {
+        self.storage = Some(Arc::new(storage));
+        self
+    }
+
+    /// Adds a notifier to tell about each successful refresh.
+    pub fn notifier(mut self, notifier: impl Notifier + 'static) -> ApkServerBuilder {
+        self.notifiers.push(Arc::new(notifier));
+        self
+    }
+
+    /// Sets how many seconds to wait between refreshes, see [`Refresher::interval`].
+    pub fn interval(mut self, seconds: u64) -> ApkServerBuilder {
+        self.interval = Some(seconds);
+        self
+    }
+
+    pub fn job(mut self, job: impl Job) -> ApkServerBuilder {
+        self.jobs.push(Box::new(job));
+        self
+    }
+
+    /// Sets what mails confirmation links to email subscribers. The `/email` pages are only
+    /// served with one.
+    pub fn mailer(mut self, mailer: Arc<Mailer>) -> ApkServerBuilder {
+        self.mailer = Some(mailer);
+        self
+    }
+
+    pub fn build(self) -> Result<ApkServer> {
+        let source = self
+            .source
+            .ok_or_else(|| Error::Config("no product source configured".to_string()))?;
+        let clock = self.clock.unwrap_or_else(|| Arc::new(SystemClock));
+        let source: Arc<dyn ProductSource> = match &self.config.fallback {
+            Some(fallback) => Arc::new(FallbackSource::new(
+                source,
+                Arc::new(DumpSource::new(fallback.url.as_str())),
+                Duration::from_secs(fallback.after),
+                clock.clone(),
+            )),
+            None => source,
+        };
+        let storage = self
+            .storage
+            .unwrap_or_else(|| Arc::new(MemoryStorage::default()));
+        let ratings: SharedRatings = Arc::new(RwLock::new(ratings::averages(&*storage)?));
+        // Not worth refusing to start over
+        let cached_pages = pagecache::load(&*storage).unwrap_or_else(|err| {
+            warn!("Couldn't load the cached pages: {}", err);
+            None
+        });
+        let mut scorers = if self.scorers.is_empty() {
+            let hide_basen = self.config.hide_basen;
+            score::default_scorers()
+                .into_iter()
+                .filter(|scorer| !(hide_basen && scorer.name() == "basen_apk"))
+                .collect()
+        } else {
+            self.scorers
+        };
+        scorers.push(Arc::new(RatingScorer::new(ratings.clone())));
+        let theme = self.theme.as_deref().unwrap_or(render::TEMPLATE_GLOB);
+        let tera = Arc::new(render::templates(theme, &scorers, &self.config)?);
+        let shared_tera: SharedTera = Arc::new(RwLock::new(tera.clone()));
+        let mut notifiers = self.notifiers;
+        if !self.config.venues.is_empty() {
+            let venues = self
+                .config
+                .venues
+                .iter()
+                .map(|venue| {
+                    let tera = match &venue.theme {
+                        Some(theme) => Arc::new(render::templates(theme, &scorers, &self.config)?),
+                        None => tera.clone(),
+                    };
+                    Ok((venue.clone(), tera))
+                })
+                .collect::<Result<_>>()?;
+            notifiers.push(Arc::new(VenueRenderer::new(venues)));
+        }
+        if let Some(launch_plan) = &self.config.launch_plan {
+            notifiers.push(Arc::new(LaunchPlanFetcher::new(launch_plan)));
+        }
+        if let Some(stock) = &self.config.stock {
+            notifiers.push(Arc::new(StockFetcher::new(stock)));
+        }
+        if let Some(stores) = &self.config.stores {
+            notifiers.push(Arc::new(StoreFetcher::new(stores)));
+        }
+        if let Some(publish) = &self.config.publish {
+            notifiers.push(Arc::new(Publisher::new(publish)));
+        }
+        let price_history = match &self.config.price_history {
+            Some(config) => {
+                notifiers.push(Arc::new(PriceRecorder));
+                Some(Arc::new(PriceHistory::open(config)?))
+            }
+            None => None,
+        };
+        let refresher = notifiers.into_iter().fold(
+            Refresher::new(source, clock, shared_tera.clone())
+                .scorer(scorers[0].clone())
+                .staggered(self.config.stagger)
+                .barcodes(self.config.barcodes.clone())
+                .assortment(self.config.assortment.rules.clone())
+                .interval(self.interval.unwrap_or(refresh::UPDATE_INTERVAL))
+                .notifier(Arc::new(FeedRecorder))
+                .notifier(Arc::new(AtomRecorder))
+                .notifier(Arc::new(HistoryRecorder))
+                .notifier(Arc::new(RecordKeeper))
+                .notifier(Arc::new(ChangesRecorder))
+                .notifier(Arc::new(PageCacher))
+                .notifier(Arc::new(VersionRecorder)),
+            Refresher::notifier,
+        );
+
+        let mut jobs = self.jobs;
+        #[cfg(unix)]
+        jobs.push(Box::new(Reloader::new(theme, scorers.clone())));
+        let mut addrs = self.addrs;
+        if addrs.is_empty() {
+            addrs.push(DEFAULT_ADDR.into());
+        }
+        let refresher = Arc::new(refresher);
+        let defaults = AppState::default();
+        let state = AppState {
+            cookie_key: match &self.config.cookie_secret {
+                Some(secret) => Arc::new(secret.expose_secret().as_bytes().to_vec()),
+                None => defaults.cookie_key.clone(),
+            },
+            storage,
+            ratings,
+            config: Arc::new(self.config),
+            tera: shared_tera,
+            refresher: Some(refresher.clone()),
+            mailer: self.mailer,
+            price_history,
+            cached_pages: Arc::new(cached_pages),
+            ..defaults
+        };
+        Ok(ApkServer {
+            addrs,
+            refresher,
+            jobs,
+            state,
+        })
+    }
+}
+
+pub fn routes(state: AppState) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let status = {
+        let state = state.clone();
+        warp::path!("admin" / "status")
+            .map(move || warp::reply::json(&*state.status.read().unwrap()))
+    };
+    // Runs the last fetched products through the pipeline again, without fetching. That's a full
+    // build, so it's kept off the async threads.
+    let recategorize = {
+        let state = state.clone();
+        warp::path!("admin" / "recategorize")
+            .and(warp::post())
+            .and(admin::name(state.clone()))
+            .and_then(move |by: Option<String>| {
+                let state = state.clone();
+                async move {
+                    let refresher = match state.refresher.clone() {
+                        Some(refresher) => refresher,
+                        None => return Ok::<_, Rejection>(StatusCode::NOT_FOUND.into_response()),
+                    };
+                    let result = {
+                        let state = state.clone();
+                        tokio::task::spawn_blocking(move || {
+                            refresher.recategorize(&state, Trigger::Recategorize, by.as_deref())
+                        })
+                        .await
+                    };
+                    let result = result
+                        .map_err(|err| err.to_string())
+                        .and_then(|result| result.map_err(|err| err.to_string()));
+                    Ok(match result {
+                        Ok(()) => warp::reply::json(&*state.status.read().unwrap()).into_response(),
+                        Err(err) => {
+                            warp::reply::with_status(err, StatusCode::INTERNAL_SERVER_ERROR)
+                                .into_response()
+                        }
+                    })
+                }
+            })
+    };
+    let metrics = {
+        let state = state.clone();
+        warp::path!("metrics").map(move || metrics::render(&state))
+    };
+    // The whole catalog by category, best first, for anyone who'd rather not scrape the page
+    let drinks = {
+        let state = state.clone();
+        warp::path!("api" / "drinks")
+            .and_then(move || {
+                let snapshot = state.snapshot.read().unwrap().clone();
+                future::ready(match snapshot {
+                    Some(snapshot) => Ok(warp::reply::json(&snapshot.catalog).into_response()),
+                    None => Err(Rejection::from(ApiError::not_ready())),
+                })
+            })
+            .recover(apierror::recover)
+            .unify()
+    };
+    let icons = {
+        let state = state.clone();
+        warp::path!("api" / "icons").map(move || warp::reply::json(&state.config.icons()))
+    };
+    let sync = sync::route(state.clone());
+    let slack = slack::route(state.clone());
+    let email = email::routes(state.clone());
+    let push = push::routes(state.clone());
+    let favorites = favorites::routes(state.clone());
+    let shopping = shopping::routes(state.clone());
+    let tried = tried::route(state.clone());
+    let shortlinks = shortlink::routes(state.clone());
+    let ratings = ratings::route(state.clone());
+    let feed = feed::routes(state.clone());
+    let atom = atom::route(state.clone());
+    let grafana = grafana::routes(state.clone());
+    let releases = ical::route(state.clone());
+    let homeassistant = homeassistant::route(state.clone());
+    let api = api::routes(state.clone());
+    let venues = venue::routes(state.clone());
+    let search = search::routes(state.clone());
+    let qr = qr::routes(state.clone());
+    let images = images::route(state.clone());
+    let age_gate = agegate::gate(state.clone());
+    let kiosk = kiosk::route(state.clone());
+    let coming = launchplan::route(state.clone());
+    let movers = movers::route(state.clone());
+    let changes = changes::route(state.clone());
+    let products = allergens::route(state.clone());
+    let buy = buy::route(state.clone());
+    let presets = presets::route(state.clone());
+    let countries = countries::route(state.clone());
+    let crawl = crawl::routes(state.clone());
+    let footprint = footprint::route(state.clone());
+    let warm = warm::route(state.clone());
+    let stores = stores::routes(state.clone());
+    let prices = prices::route(state.clone());
+    let audit = audit::route(state.clone());
+    let categories = categories::route(state.clone());
+    let hints = state.config.crawl.clone();
+    let access_log = state.config.access_log;
+    let index = warp::query::raw()
+        .or(warp::any().map(String::new))
+        .unify()
+        .and(warp::cookie::optional(prefs::COOKIE))
+        .and(warp::cookie::optional(tried::COOKIE))
+        .and(warp::cookie::optional(session::COOKIE))
+        .and(warp::cookie::optional(stores::COOKIE))
+        .and_then(
+            move |query: String,
+                  prefs: Option<String>,
+                  tried: Option<String>,
+                  session: Option<String>,
+                  store: Option<String>| {
+                let state = state.clone();
+                async move {
+                    let view = View::from_query(&query);
+                    let response = shed::run(&state, "index", &view, move |state| {
+                        view::index(
+                            state,
+                            &query,
+                            prefs.as_deref(),
+                            tried.as_deref(),
+                            session.as_deref(),
+                            store.as_deref(),
+                        )
+                    })
+                    .await;
+                    Ok::<_, Rejection>(response)
+                }
+            },
+        );
+    let routes = admin::guard(state.clone())
+        .or(slack)
+        .or(email)
+        .or(push)
+        .or(favorites)
+        .or(shopping)
+        .or(tried)
+        .or(stores)
+        .or(api)
+        .or(ratings)
+        .or(shortlinks)
+        .or(prefs::route())
+        .or(agegate::route())
+        .or(grafana)
+        .or(recategorize)
+        .or(warp::get().and(
+            status
+                .or(footprint)
+                .or(metrics)
+                .or(feed)
+                .or(atom)
+                .or(releases)
+                .or(homeassistant)
+                .or(drinks)
+                .or(sync)
+                .or(icons)
+                .or(search)
+                .or(audit)
+                .or(qr)
+                .or(images)
+                .or(age_gate)
+                .or(kiosk)
+                .or(coming)
+                .or(movers)
+                .or(changes)
+                .or(products)
+                .or(buy)
+                .or(presets)
+                .or(countries)
+                .or(crawl)
+                .or(prices)
+                .or(venues)
+                .or(warm)
+                .or(categories)
+                .or(index),
+        ));
+    crawl::hints(hints, routes).with(access::log(access_log))
+}
@@ -0,0 +1,33 @@
+//! Anonymous sessions, identified by a random id in a signed cookie.
+
+use crate::signing;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+pub const COOKIE: &str = "session";
+/// A year, in seconds
+const MAX_AGE: u64 = 365 * 24 * 60 * 60;
+
+/// The session id in a session cookie, if it's valid.
+pub fn id(key: &[u8], cookie: Option<&str>) -> Option<String> {
+    cookie
+        .and_then(|cookie| signing::verify_signed(key, cookie))
+        .map(String::from)
+}
+
+pub fn new_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .collect()
+}
+
+/// A `Set-Cookie` value for the session `id`.
+pub fn cookie(key: &[u8], id: &str) -> String {
+    format!(
+        "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax",
+        COOKIE,
+        signing::sign(key, id),
+        MAX_AGE
+    )
+}
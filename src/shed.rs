@@ -0,0 +1,129 @@
+//! Keeps the site responsive when rendering views gets slow: a view that takes too long is given
+//! up on, and after one has taken longer than the budget, stale pages are served for a while
+//! without rendering anything. Stale pages are the ones rendered at refresh time, and come with a
+//! `Warning` header. Renders given up on can't be stopped, so only so many are run at once.
+
+use crate::config::LoadConfig;
+use crate::state::AppState;
+use crate::view::View;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{error, warn};
+use warp::http::{header, HeaderValue, StatusCode};
+use warp::reply::{html, Response};
+use warp::Reply;
+
+pub const STALE_WARNING: &str = "110 - \"Response is Stale\"";
+
+/// When rendering can start again, if it's been too slow, and how many views are being rendered.
+#[derive(Default)]
+pub struct Load {
+    shedding_until: Mutex<Option<Instant>>,
+    rendering: Arc<AtomicUsize>,
+}
+
+/// A render in progress, counted until it's dropped.
+struct Rendering(Arc<AtomicUsize>);
+
+impl Drop for Rendering {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub type SharedLoad = Arc<Load>;
+
+impl Load {
+    fn shedding(&self) -> bool {
+        let mut until = self.shedding_until.lock().unwrap();
+        match *until {
+            Some(instant) if Instant::now() < instant => true,
+            Some(_) => {
+                *until = None;
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Counts a render, unless `max` are running already.
+    fn start(&self, max: usize) -> Option<Rendering> {
+        self.rendering
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |rendering| {
+                Some(rendering + 1).filter(|&rendering| rendering <= max)
+            })
+            .ok()?;
+        Some(Rendering(self.rendering.clone()))
+    }
+
+    fn record(&self, config: &LoadConfig, elapsed: Duration) {
+        if let Some(budget) = config.budget {
+            if elapsed > Duration::from_millis(budget) {
+                warn!("Rendering took {:?}, shedding load", elapsed);
+                *self.shedding_until.lock().unwrap() =
+                    Some(Instant::now() + Duration::from_secs(config.cooldown));
+            }
+        }
+    }
+}
+
+/// Runs `render` on the blocking pool, unless shedding load or too many are running, within the
+/// timeout of `route`. The stale page for `view` is served instead if it can't be rendered in time.
+pub async fn run<F>(state: &AppState, route: &str, view: &View, render: F) -> Response
+where
+    F: FnOnce(&AppState) -> Response + Send + 'static,
+{
+    let config = &state.config.load;
+    if state.load.shedding() {
+        return stale(state, view);
+    }
+    let rendering = match state.load.start(config.max_renders) {
+        Some(rendering) => rendering,
+        None => {
+            warn!("Too many views rendering, serving {} stale", route);
+            return stale(state, view);
+        }
+    };
+    let timeout = config
+        .timeouts
+        .get(route)
+        .copied()
+        .unwrap_or(config.timeout);
+    let started = Instant::now();
+    let task = {
+        let state = state.clone();
+        tokio::task::spawn_blocking(move || {
+            let response = render(&state);
+            drop(rendering);
+            response
+        })
+    };
+    let result = tokio::time::timeout(Duration::from_millis(timeout), task).await;
+    state.load.record(config, started.elapsed());
+    match result {
+        Ok(Ok(response)) => response,
+        Ok(Err(err)) => {
+            error!("Rendering {} failed: {}", route, err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+        Err(_) => {
+            warn!("Rendering {} timed out", route);
+            stale(state, view)
+        }
+    }
+}
+
+/// The page rendered at refresh time for `view`, or the plain list if there's none.
+fn stale(state: &AppState, view: &View) -> Response {
+    let snapshot = match state.snapshot.read().unwrap().clone() {
+        Some(snapshot) => snapshot,
+        None => return StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    };
+    let page = snapshot.prerendered(view).unwrap_or(&snapshot.page);
+    let mut response = html(page.to_string()).into_response();
+    response
+        .headers_mut()
+        .insert(header::WARNING, HeaderValue::from_static(STALE_WARNING));
+    response
+}
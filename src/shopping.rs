@@ -0,0 +1,221 @@
+//! A shopping list per session at `/list`, with running totals for the trip to the store.
+
+use crate::access;
+use crate::catalog::{self, Catalog};
+use crate::error::Result;
+use crate::limit::{self, Hourly};
+use crate::render;
+use crate::session;
+use crate::state::AppState;
+use crate::status;
+use crate::storage::{self, Storage};
+use crate::units::{Measures, Ml, Sek};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::SystemTime;
+use systemet::Product;
+use tracing::error;
+use warp::http::{header, StatusCode};
+use warp::reply::{html, Response};
+use warp::{Filter, Rejection, Reply};
+
+const LISTS_KEY: &str = "shopping-lists.json";
+const MAX_QUANTITY: u32 = 99;
+const MAX_FORM: u64 = 4 * 1024;
+/// Lists kept at most. No new ones are started once there are this many.
+pub const MAX_LISTS: usize = 10_000;
+/// New lists each client may start per hour
+pub const MAX_NEW_PER_HOUR: u32 = 10;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub id: String,
+    pub quantity: u32,
+}
+
+/// A product on the list, with what it adds up to.
+#[derive(Serialize)]
+pub struct Line<'a> {
+    pub drink: &'a Product,
+    pub quantity: u32,
+    pub price: Sek,
+    pub standard_drinks: f64,
+}
+
+#[derive(Default, Serialize)]
+pub struct Totals {
+    pub price: Sek,
+    pub standard_drinks: f64,
+    /// Of everything together
+    pub apk: f64,
+}
+
+/// The lines of `entries` still in the catalog, and their totals.
+pub fn lines<'a>(catalog: &'a Catalog, entries: &[Entry]) -> (Vec<Line<'a>>, Totals) {
+    let lines: Vec<Line> = entries
+        .iter()
+        .filter_map(|entry| {
+            let drink = catalog.find(&entry.id)?;
+            let quantity = entry.quantity as f64;
+            Some(Line {
+                drink,
+                quantity: entry.quantity,
+                price: drink.price_with_deposit() * quantity,
+                standard_drinks: (drink.pure_alcohol() * quantity).standard_drinks(),
+            })
+        })
+        .collect();
+    let price: Sek = lines.iter().map(|line| line.price).sum();
+    let alcohol: Ml = lines
+        .iter()
+        .map(|line| line.drink.pure_alcohol() * line.quantity as f64)
+        .sum();
+    let totals = Totals {
+        price,
+        standard_drinks: alcohol.standard_drinks(),
+        apk: if price > Sek(0.0) {
+            (alcohol / price).0
+        } else {
+            0.0
+        },
+    };
+    (lines, totals)
+}
+
+fn lists(storage: &dyn Storage) -> Result<HashMap<String, Vec<Entry>>> {
+    Ok(storage::load_json(storage, LISTS_KEY)?.unwrap_or_default())
+}
+
+pub fn list(storage: &dyn Storage, session: &str) -> Result<Vec<Entry>> {
+    Ok(lists(storage)?.remove(session).unwrap_or_default())
+}
+
+/// Sets how many of a product are on the list. Zero removes it.
+pub fn set(storage: &dyn Storage, session: &str, id: &str, quantity: u32) -> Result<()> {
+    let mut lists = lists(storage)?;
+    let list = lists.entry(session.to_string()).or_default();
+    match list.iter_mut().find(|entry| entry.id == id) {
+        Some(entry) => entry.quantity = quantity,
+        None => list.push(Entry {
+            id: id.to_string(),
+            quantity,
+        }),
+    }
+    list.retain(|entry| entry.quantity > 0);
+    if list.is_empty() {
+        lists.remove(session);
+    }
+    storage::save_json(storage, LISTS_KEY, &lists)
+}
+
+fn page(state: &AppState, session: Option<String>) -> Result<Response> {
+    let entries = match &session {
+        Some(session) => list(&*state.storage, session)?,
+        None => Vec::new(),
+    };
+    let snapshot = state.snapshot.read().unwrap().clone();
+    let empty = Catalog::default();
+    let catalog = snapshot
+        .as_ref()
+        .map_or(&empty, |snapshot| &snapshot.catalog);
+    let (lines, totals) = lines(catalog, &entries);
+    Ok(html(render::render_shopping_list(
+        &state.tera(),
+        &lines,
+        &totals,
+    )?)
+    .into_response())
+}
+
+/// Adds to or changes the list, starting a session if needed. Without a `quantity`, one more of
+/// the product is added. Starting a list counts towards `started` of `client`.
+fn update(
+    state: &AppState,
+    started: &Hourly<Option<IpAddr>>,
+    client: Option<IpAddr>,
+    session: Option<String>,
+    form: HashMap<String, String>,
+) -> Result<Response> {
+    let id = match form.get("id") {
+        Some(id) if catalog_has(state, id) => id,
+        _ => return Ok(StatusCode::BAD_REQUEST.into_response()),
+    };
+    let (session, new) = match session {
+        Some(session) => (session, false),
+        None => (session::new_id(), true),
+    };
+    let _saving = state.saving.lock().unwrap();
+    let current = list(&*state.storage, &session)?;
+    if current.is_empty() {
+        let now = status::unix_time(SystemTime::now());
+        if !started.allow(client, now) {
+            return Ok(limit::too_many(now));
+        }
+        if lists(&*state.storage)?.len() >= MAX_LISTS {
+            return Ok(StatusCode::SERVICE_UNAVAILABLE.into_response());
+        }
+    }
+    let quantity = match form.get("quantity").and_then(|q| q.parse::<u32>().ok()) {
+        Some(quantity) => quantity,
+        None => {
+            let current = current
+                .into_iter()
+                .find(|entry| &entry.id == id)
+                .map_or(0, |entry| entry.quantity);
+            current + 1
+        }
+    };
+    set(&*state.storage, &session, id, quantity.min(MAX_QUANTITY))?;
+    let redirect = warp::reply::with_header(StatusCode::SEE_OTHER, header::LOCATION, "/list");
+    Ok(if new {
+        warp::reply::with_header(
+            redirect,
+            header::SET_COOKIE,
+            session::cookie(&state.cookie_key, &session),
+        )
+        .into_response()
+    } else {
+        redirect.into_response()
+    })
+}
+
+fn catalog_has(state: &AppState, id: &str) -> bool {
+    let snapshot = state.snapshot.read().unwrap().clone();
+    snapshot.map_or(false, |snapshot| {
+        snapshot.catalog.find(id).map(catalog::id) == Some(id)
+    })
+}
+
+fn respond(result: Result<Response>) -> Response {
+    result.unwrap_or_else(|err| {
+        error!("{}", err);
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    })
+}
+
+pub fn routes(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let show = {
+        let state = state.clone();
+        warp::path!("list")
+            .and(warp::get())
+            .and(warp::cookie::optional(session::COOKIE))
+            .map(move |cookie: Option<String>| {
+                let session = session::id(&state.cookie_key, cookie.as_deref());
+                respond(page(&state, session))
+            })
+    };
+    let started = Arc::new(Hourly::new(MAX_NEW_PER_HOUR));
+    let update = warp::path!("list")
+        .and(warp::post())
+        .and(access::client(&state))
+        .and(warp::cookie::optional(session::COOKIE))
+        .and(warp::body::content_length_limit(MAX_FORM))
+        .and(warp::body::form())
+        .map(move |client, cookie: Option<String>, form| {
+            let session = session::id(&state.cookie_key, cookie.as_deref());
+            respond(update(&state, &started, client, session, form))
+        });
+    show.or(update).unify()
+}
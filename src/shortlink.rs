@@ -0,0 +1,152 @@
+//! Short links like `/s/k3Xa9q`, made on demand for views and product pages and kept in storage,
+//! for when a full URL is too long for print or SMS.
+
+use crate::access;
+use crate::buy;
+use crate::error::Result;
+use crate::limit::{self, Hourly};
+use crate::render;
+use crate::session;
+use crate::state::AppState;
+use crate::status;
+use crate::storage::{self, Storage};
+use reqwest::Url;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tracing::error;
+use warp::http::{header, StatusCode};
+use warp::reply::{html, Response};
+use warp::{Filter, Rejection, Reply};
+
+const LINKS_KEY: &str = "short-links.json";
+const CODE_LENGTH: usize = 6;
+/// Longer ones aren't worth shortening, the links are for views and product pages
+const MAX_TARGET_LENGTH: usize = 1000;
+const MAX_FORM: u64 = 4 * 1024;
+/// Links made by each client per hour, since every new one rewrites all of them in storage
+pub const MAX_PER_HOUR: u32 = 20;
+/// Links kept at most. No more are made once there are this many.
+pub const MAX_LINKS: usize = 100_000;
+
+/// Local paths, and product pages at Systembolaget. Nothing else, so the links can't be used to
+/// hide where they go.
+pub fn is_allowed(target: &str) -> bool {
+    // Browsers read backslashes as slashes, so `/\evil.example` goes off the site
+    if target.len() > MAX_TARGET_LENGTH
+        || target.contains('\\')
+        || target.contains(char::is_control)
+    {
+        return false;
+    }
+    if target.starts_with(buy::PREFIX) {
+        return true;
+    }
+    let base = Url::parse("http://apk.invalid/").unwrap();
+    target.starts_with('/')
+        && base
+            .join(target)
+            .map_or(false, |url| url.host_str() == base.host_str())
+}
+
+/// Every short link, by code.
+fn links(storage: &dyn Storage) -> Result<HashMap<String, String>> {
+    Ok(storage::load_json(storage, LINKS_KEY)?.unwrap_or_default())
+}
+
+/// Where the link with `code` goes, if it exists.
+pub fn target(storage: &dyn Storage, code: &str) -> Result<Option<String>> {
+    Ok(links(storage)?.remove(code))
+}
+
+/// The code for `target`, making one if it doesn't have one yet, unless there are
+/// [`MAX_LINKS`] already.
+pub fn shorten(storage: &dyn Storage, target: &str) -> Result<Option<String>> {
+    let mut links = links(storage)?;
+    if let Some((code, _)) = links.iter().find(|(_, existing)| *existing == target) {
+        return Ok(Some(code.clone()));
+    }
+    if links.len() >= MAX_LINKS {
+        return Ok(None);
+    }
+    let code = loop {
+        let code: String = session::new_id().chars().take(CODE_LENGTH).collect();
+        if !links.contains_key(&code) {
+            break code;
+        }
+    };
+    links.insert(code.clone(), target.to_string());
+    storage::save_json(storage, LINKS_KEY, &links)?;
+    Ok(Some(code))
+}
+
+fn create(
+    state: &AppState,
+    lock: &Mutex<()>,
+    made: &Hourly<Option<IpAddr>>,
+    client: Option<IpAddr>,
+    form: HashMap<String, String>,
+) -> Result<Response> {
+    let target = match form.get("target") {
+        Some(target) if is_allowed(target) => target,
+        _ => return Ok(StatusCode::BAD_REQUEST.into_response()),
+    };
+    let now = status::unix_time(SystemTime::now());
+    if !made.allow(client, now) {
+        return Ok(limit::too_many(now));
+    }
+    let code = {
+        let _guard = lock.lock().unwrap();
+        shorten(&*state.storage, target)?
+    };
+    let code = match code {
+        Some(code) => code,
+        None => return Ok(StatusCode::SERVICE_UNAVAILABLE.into_response()),
+    };
+    let message = format!("Kortlänken är /s/{}", code);
+    let page = render::render_message(&state.tera(), &message)?;
+    Ok(warp::reply::with_status(html(page), StatusCode::CREATED).into_response())
+}
+
+fn follow(state: &AppState, code: &str) -> Result<Option<Response>> {
+    // Made before targets were checked as closely
+    let target = target(&*state.storage, code)?.filter(|target| is_allowed(target));
+    Ok(target.map(|target| {
+        warp::reply::with_header(StatusCode::FOUND, header::LOCATION, target).into_response()
+    }))
+}
+
+fn or_error(result: Result<Response>) -> Response {
+    result.unwrap_or_else(|err| {
+        error!("{}", err);
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    })
+}
+
+pub fn routes(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let lock = Arc::new(Mutex::new(()));
+    let made = Arc::new(Hourly::new(MAX_PER_HOUR));
+    let create = {
+        let state = state.clone();
+        warp::path!("s")
+            .and(warp::post())
+            .and(access::client(&state))
+            .and(warp::body::content_length_limit(MAX_FORM))
+            .and(warp::body::form())
+            .map(move |client, form| or_error(create(&state, &lock, &made, client, form)))
+    };
+    let follow = warp::path!("s" / String)
+        .and(warp::get())
+        .and_then(move |code: String| {
+            let state = state.clone();
+            async move {
+                match follow(&state, &code) {
+                    Ok(Some(response)) => Ok(response),
+                    Ok(None) => Err(warp::reject::not_found()),
+                    Err(err) => Ok(or_error(Err(err))),
+                }
+            }
+        });
+    create.or(follow).unify()
+}
@@ -0,0 +1,48 @@
+use hmac::{Hmac, Mac, NewMac};
+use sha2::{Digest, Sha256};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Hex-encoded HMAC-SHA256 of `message`.
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> String {
+    hex::encode(hmac_sha256_bytes(key, message))
+}
+
+/// HMAC-SHA256 of `message`, for chaining as the key of another.
+pub fn hmac_sha256_bytes(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_varkey(key).expect("HMAC can take keys of any size");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Hex-encoded SHA-256 of `data`.
+pub fn sha256(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Checks a hex-encoded HMAC-SHA256 of `message` in constant time.
+pub fn verify_hmac_sha256(key: &[u8], message: &[u8], signature: &str) -> bool {
+    let signature = match hex::decode(signature) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    let mut mac = HmacSha256::new_varkey(key).expect("HMAC can take keys of any size");
+    mac.update(message);
+    mac.verify(&signature).is_ok()
+}
+
+/// `value` with its HMAC-SHA256 appended, as `<value>.<hex>`.
+pub fn sign(key: &[u8], value: &str) -> String {
+    format!("{}.{}", value, hmac_sha256(key, value.as_bytes()))
+}
+
+/// The value of something made by [`sign`], if the signature is valid.
+pub fn verify_signed<'a>(key: &[u8], signed: &'a str) -> Option<&'a str> {
+    let dot = signed.rfind('.')?;
+    let (value, signature) = (&signed[..dot], &signed[dot + 1..]);
+    if verify_hmac_sha256(key, value.as_bytes(), signature) {
+        Some(value)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,103 @@
+//! Writing the site to a directory, for `apk render`, so that it can be hosted as static files and
+//! regenerated from cron. Each page goes in an `index.html` at its path, like `ol/index.html`, so
+//! that any static file host serves them at the same URLs as the server does. Product pages are at
+//! both their id and their permalink. Next to them are `products.json`, with the products as the
+//! API shows them, and `products.csv`, rounded the same way, see [`display`].
+
+use crate::allergens;
+use crate::api;
+use crate::catalog::{self, CATEGORIES};
+use crate::categories;
+use crate::config::DisplayConfig;
+use crate::countries::{self, Country};
+use crate::display::{self, Field};
+use crate::error::Result;
+use crate::names;
+use crate::refresh::Snapshot;
+use crate::render;
+use crate::state::AppState;
+use crate::units::Measures;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+pub const JSON_EXPORT: &str = "products.json";
+pub const CSV_EXPORT: &str = "products.csv";
+
+/// Writes the pages and exports of `snapshot` under `out`, creating it if needed.
+pub fn write(state: &AppState, snapshot: &Snapshot, out: &Path) -> Result<()> {
+    let tera = state.tera();
+    write_page(out, "/", &snapshot.page)?;
+    for &category in CATEGORIES.iter() {
+        if let Some(page) = snapshot.category_pages.get(&category) {
+            write_page(out, categories::path(category), page)?;
+        }
+    }
+    let config = &state.config.names;
+    for drink in snapshot.catalog.products() {
+        let tags = allergens::tags(&state.config.allergens, drink);
+        let name = names::display(config, drink);
+        let related = names::related(config, &snapshot.catalog, drink);
+        let page = render::render_product(&tera, drink, &name, &tags, &related)?;
+        write_page(out, &format!("/produkt/{}", catalog::id(drink)), &page)?;
+        write_page(out, &names::path(config, drink), &page)?;
+    }
+    let listed: Vec<&Country> = snapshot
+        .countries
+        .iter()
+        .filter(|country| country.products >= countries::DEFAULT_MIN_PRODUCTS)
+        .collect();
+    let page = render::render_countries(&tera, &listed, countries::DEFAULT_MIN_PRODUCTS)?;
+    write_page(out, "/countries", &page)?;
+
+    fs::write(out.join(JSON_EXPORT), json(state, snapshot)?)?;
+    fs::write(out.join(CSV_EXPORT), csv(snapshot, &state.config.display))?;
+    Ok(())
+}
+
+fn write_page(out: &Path, path: &str, page: &str) -> Result<()> {
+    let dir = out.join(path.trim_start_matches('/'));
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("index.html"), page)?;
+    Ok(())
+}
+
+/// The products of `snapshot` as the API shows them, best first within each category.
+pub fn json(state: &AppState, snapshot: &Snapshot) -> Result<String> {
+    let icons = state.config.icons();
+    let products: Vec<Value> = snapshot
+        .catalog
+        .products()
+        .map(|drink| api::product(drink, &icons, &state.config.display))
+        .collect();
+    Ok(serde_json::to_string(&products)?)
+}
+
+/// The products of `snapshot`, best first within each category, one per line.
+pub fn csv(snapshot: &Snapshot, config: &DisplayConfig) -> String {
+    let mut csv = String::from("id,name,category,price,abv,volume,apk\n");
+    for drink in snapshot.catalog.products() {
+        let fields = [
+            catalog::id(drink).to_string(),
+            catalog::name(drink).to_string(),
+            catalog::categorize(drink).name().to_string(),
+            display::format(config, Field::Price, drink.price_with_deposit().0),
+            display::format(config, Field::Abv, drink.abv().0),
+            drink.volume().0.to_string(),
+            display::format(config, Field::Apk, catalog::apk(drink).0),
+        ];
+        let fields: Vec<String> = fields.iter().map(|field| escape(field)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// `field` quoted if it needs to be, doubling any quotes in it.
+fn escape(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"' || c == '\n') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
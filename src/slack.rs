@@ -0,0 +1,119 @@
+//! A Slack slash command (`/apk <query>`) answering with the best matching products.
+//!
+//! Slack signs each request with the app's signing secret, see
+//! <https://api.slack.com/authentication/verifying-requests-from-slack>.
+
+use crate::error::Error;
+use crate::signing;
+use crate::state::AppState;
+use crate::status::unix_time;
+use crate::text;
+use bytes::Bytes;
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use serde_json::json;
+use std::time::SystemTime;
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// Requests older than this are rejected to prevent replays. In seconds
+const MAX_AGE: u64 = 5 * 60;
+/// Of a request body, in bytes, as slash commands are short
+const MAX_BODY: u64 = 64 * 1024;
+const RESULTS: usize = 5;
+
+#[derive(Deserialize)]
+struct Command {
+    text: String,
+}
+
+/// Whether a request was signed with `secret` in the last few minutes.
+pub fn verify(
+    secret: &[u8],
+    timestamp: &str,
+    signature: &str,
+    body: &[u8],
+    now: SystemTime,
+) -> bool {
+    let sent_at: u64 = match timestamp.parse() {
+        Ok(sent_at) => sent_at,
+        Err(_) => return false,
+    };
+    let now = unix_time(now);
+    if now.saturating_sub(sent_at) > MAX_AGE || sent_at.saturating_sub(now) > MAX_AGE {
+        return false;
+    }
+    let signature = match signature.strip_prefix("v0=") {
+        Some(signature) => signature,
+        None => return false,
+    };
+    let mut message = format!("v0:{}:", timestamp).into_bytes();
+    message.extend_from_slice(body);
+    signing::verify_hmac_sha256(secret, &message, signature)
+}
+
+fn answer(state: &AppState, query: &str) -> String {
+    let snapshot = match state.snapshot.read().unwrap().clone() {
+        Some(snapshot) => snapshot,
+        None => return "Listan har inte laddats än, försök igen om en stund.".to_string(),
+    };
+    let query = query.trim();
+    if query.is_empty() {
+        return "Användning: /apk <namn>".to_string();
+    }
+    match snapshot.index.search(&snapshot.catalog, query).as_slice() {
+        [] => format!("Hittade inget som heter {}.", query),
+        matches => text::numbered(matches.iter().copied().take(RESULTS)),
+    }
+}
+
+fn handle(
+    state: &AppState,
+    timestamp: &str,
+    signature: &str,
+    body: &[u8],
+) -> Result<Response, Rejection> {
+    let secret = match &state.config.slack {
+        Some(slack) => slack.signing_secret.expose_secret(),
+        None => return Err(warp::reject::not_found()),
+    };
+    if !verify(
+        secret.as_bytes(),
+        timestamp,
+        signature,
+        body,
+        SystemTime::now(),
+    ) {
+        return Ok(
+            warp::reply::with_status("Invalid signature", StatusCode::UNAUTHORIZED).into_response(),
+        );
+    }
+    let command: Command = match serde_urlencoded::from_bytes(body) {
+        Ok(command) => command,
+        Err(err) => {
+            let err = Error::Parse(err.into());
+            return Ok(
+                warp::reply::with_status(err.to_string(), StatusCode::BAD_REQUEST).into_response(),
+            );
+        }
+    };
+    Ok(warp::reply::json(&json!({
+        "response_type": "in_channel",
+        "text": answer(state, &command.text),
+    }))
+    .into_response())
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("slack" / "command")
+        .and(warp::post())
+        .and(warp::header::<String>("x-slack-request-timestamp"))
+        .and(warp::header::<String>("x-slack-signature"))
+        .and(warp::body::content_length_limit(MAX_BODY))
+        .and(warp::body::bytes())
+        .and_then(move |timestamp: String, signature: String, body: Bytes| {
+            let state = state.clone();
+            async move { handle(&state, &timestamp, &signature, &body) }
+        })
+}
@@ -0,0 +1,340 @@
+use crate::archive::Archive;
+use crate::catalog::{self, Category};
+use crate::error::{Error, Result};
+use crate::normalize;
+use crate::sync::Delta;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use systemet::{Product, Systemet};
+use tracing::warn;
+
+/// Where the raw product list comes from.
+#[async_trait]
+pub trait ProductSource: Send + Sync {
+    async fn fetch_products(&self) -> Result<Vec<Product>>;
+
+    /// What the source is, for the audit log.
+    fn name(&self) -> String {
+        "upstream".to_string()
+    }
+
+    /// Just the products in `category`, for refreshing one category at a time. Sources that can't
+    /// ask for a single category fetch everything and filter it.
+    async fn fetch_category(&self, category: Category) -> Result<Vec<Product>> {
+        Ok(in_category(self.fetch_products().await?, category))
+    }
+}
+
+fn in_category(products: Vec<Product>, category: Category) -> Vec<Product> {
+    products
+        .into_iter()
+        .filter(|drink| catalog::categorize(drink) == category)
+        .collect()
+}
+
+#[async_trait]
+impl ProductSource for Systemet {
+    async fn fetch_products(&self) -> Result<Vec<Product>> {
+        self.get_all_products().await.map_err(Error::upstream)
+    }
+
+    fn name(&self) -> String {
+        "systemet".to_string()
+    }
+}
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A source reading products as JSON from a plain HTTP endpoint, one page at a time (`?page=1`,
+/// `?page=2`, ...) until an empty page is returned. Records that can't be parsed are skipped. A
+/// single category is asked for with `category`, like `?category=beer&page=1`.
+pub struct HttpSource {
+    client: reqwest::Client,
+    url: String,
+    retries: u32,
+    retry_delay: Duration,
+}
+
+impl HttpSource {
+    pub fn new(url: impl Into<String>) -> HttpSource {
+        HttpSource {
+            client: reqwest::Client::new(),
+            url: url.into(),
+            retries: 3,
+            retry_delay: Duration::from_secs(1),
+        }
+    }
+
+    /// Sets how many times a failed page request is retried, and how long to wait in between.
+    pub fn retries(mut self, retries: u32, retry_delay: Duration) -> HttpSource {
+        self.retries = retries;
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    async fn fetch_page(&self, page: u32, category: Option<Category>) -> Result<Vec<Value>> {
+        let mut attempt = 0;
+        loop {
+            let mut request = self.client.get(&self.url);
+            if let Some(category) = category {
+                request = request.query(&[("category", category.slug())]);
+            }
+            let response = request
+                .query(&[("page", page)])
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            match response {
+                Ok(response) => return Ok(response.json().await?),
+                Err(err) if attempt < self.retries => {
+                    attempt += 1;
+                    warn!("Fetching page {} failed, retrying: {}", page, err);
+                    tokio::time::delay_for(self.retry_delay).await;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    async fn fetch_pages(&self, category: Option<Category>) -> Result<Vec<Product>> {
+        let mut products = Vec::new();
+        for page in 1.. {
+            let records = self.fetch_page(page, category).await?;
+            if records.is_empty() {
+                break;
+            }
+            products.extend(parse_records(records));
+        }
+        Ok(products)
+    }
+}
+
+#[async_trait]
+impl ProductSource for HttpSource {
+    async fn fetch_products(&self) -> Result<Vec<Product>> {
+        self.fetch_pages(None).await
+    }
+
+    fn name(&self) -> String {
+        format!("http {}", self.url)
+    }
+
+    async fn fetch_category(&self, category: Category) -> Result<Vec<Product>> {
+        // In case the endpoint doesn't know about categories
+        Ok(in_category(
+            self.fetch_pages(Some(category)).await?,
+            category,
+        ))
+    }
+}
+
+/// Parses raw product records, skipping the ones that don't look like products. The numbers are
+/// normalized first, see [`normalize`].
+pub fn parse_records(records: Vec<Value>) -> Vec<Product> {
+    records
+        .into_iter()
+        .map(|mut record| {
+            normalize::record(&mut record);
+            record
+        })
+        .filter_map(|record| match serde_json::from_value(record) {
+            Ok(product) => Some(product),
+            Err(err) => {
+                warn!("Skipping malformed product: {}", err);
+                None
+            }
+        })
+        .collect()
+}
+
+/// A source reading all products at once from a single JSON file, like a published nightly dump.
+pub struct DumpSource {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl DumpSource {
+    pub fn new(url: impl Into<String>) -> DumpSource {
+        DumpSource {
+            client: reqwest::Client::new(),
+            url: url.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl ProductSource for DumpSource {
+    async fn fetch_products(&self) -> Result<Vec<Product>> {
+        let response = self
+            .client
+            .get(&self.url)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(parse_records(response.json().await?))
+    }
+
+    fn name(&self) -> String {
+        format!("dump {}", self.url)
+    }
+}
+
+/// A source reading the catalog another instance, the primary, serves at `/api/sync`, so that
+/// read-only mirrors can be run without an API key or fetching everything from upstream. After the
+/// first fetch, only what changed since is fetched, see [`crate::sync`].
+pub struct MirrorSource {
+    client: reqwest::Client,
+    url: String,
+    /// The hash and products of the last sync, to ask for only what changed since
+    last: Mutex<Option<(String, Vec<Value>)>>,
+}
+
+impl MirrorSource {
+    /// Mirrors the instance at `url`, like `https://apk.example.com`.
+    pub fn new(url: &str) -> MirrorSource {
+        MirrorSource {
+            client: reqwest::Client::new(),
+            url: url.trim_end_matches('/').to_string(),
+            last: Mutex::new(None),
+        }
+    }
+
+    /// What changed since the snapshot with hash `since`, or every product if none.
+    async fn sync(&self, since: Option<&str>) -> Result<Delta> {
+        let mut request = self.client.get(&format!("{}/api/sync", self.url));
+        if let Some(since) = since {
+            request = request.query(&[("since", since)]);
+        }
+        Ok(request.send().await?.error_for_status()?.json().await?)
+    }
+}
+
+#[async_trait]
+impl ProductSource for MirrorSource {
+    async fn fetch_products(&self) -> Result<Vec<Product>> {
+        let since = self
+            .last
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|(hash, _)| hash.clone());
+        let mut delta = self.sync(since.as_deref()).await?;
+        let last = self.last.lock().unwrap().take();
+        let base = match last {
+            _ if delta.base.is_none() => Vec::new(),
+            Some((last, products)) if delta.base.as_ref() == Some(&last) => products,
+            // From a snapshot we don't have, so start over
+            _ => {
+                delta = self.sync(None).await?;
+                if delta.base.is_some() {
+                    return Err(Error::upstream(
+                        "the primary sent changes instead of a full sync",
+                    ));
+                }
+                Vec::new()
+            }
+        };
+        let hash = delta.hash.clone();
+        let products = delta.apply(base);
+        *self.last.lock().unwrap() = Some((hash, products.clone()));
+        Ok(parse_records(products))
+    }
+
+    fn name(&self) -> String {
+        format!("mirror {}", self.url)
+    }
+}
+
+/// Uses `fallback` once `primary` has been failing for `after`, and goes back as soon as
+/// `primary` works again.
+pub struct FallbackSource {
+    primary: Arc<dyn ProductSource>,
+    fallback: Arc<dyn ProductSource>,
+    after: Duration,
+    clock: Arc<dyn Clock>,
+    failing_since: Mutex<Option<SystemTime>>,
+}
+
+impl FallbackSource {
+    pub fn new(
+        primary: Arc<dyn ProductSource>,
+        fallback: Arc<dyn ProductSource>,
+        after: Duration,
+        clock: Arc<dyn Clock>,
+    ) -> FallbackSource {
+        FallbackSource {
+            primary,
+            fallback,
+            after,
+            clock,
+            failing_since: Mutex::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl ProductSource for FallbackSource {
+    async fn fetch_products(&self) -> Result<Vec<Product>> {
+        let err = match self.primary.fetch_products().await {
+            Ok(products) => {
+                *self.failing_since.lock().unwrap() = None;
+                return Ok(products);
+            }
+            Err(err) => err,
+        };
+        let now = self.clock.now();
+        let since = *self.failing_since.lock().unwrap().get_or_insert(now);
+        if now.duration_since(since).unwrap_or_default() < self.after {
+            return Err(err);
+        }
+        warn!("Primary source failing ({}), using the fallback", err);
+        self.fallback.fetch_products().await
+    }
+
+    /// The one used last.
+    fn name(&self) -> String {
+        let now = self.clock.now();
+        match *self.failing_since.lock().unwrap() {
+            Some(since) if now.duration_since(since).unwrap_or_default() >= self.after => {
+                self.fallback.name()
+            }
+            _ => self.primary.name(),
+        }
+    }
+}
+
+/// A source reading the products of an [`Archive`] file, every time, so that it can be swapped
+/// while running.
+pub struct ArchiveSource {
+    path: PathBuf,
+}
+
+impl ArchiveSource {
+    pub fn new(path: impl Into<PathBuf>) -> ArchiveSource {
+        ArchiveSource { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl ProductSource for ArchiveSource {
+    async fn fetch_products(&self) -> Result<Vec<Product>> {
+        Ok(Archive::read(&self.path)?.products)
+    }
+
+    fn name(&self) -> String {
+        format!("archive {}", self.path.display())
+    }
+}
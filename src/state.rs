@@ -0,0 +1,90 @@
+use crate::config::Config;
+use crate::email::Mailer;
+use crate::launchplan::SharedLaunchPlan;
+use crate::pagecache::Pages;
+use crate::prices::SharedPriceHistory;
+use crate::ratings::SharedRatings;
+use crate::refresh::{Refresher, SharedSnapshot};
+use crate::shed::SharedLoad;
+use crate::status::SharedStatus;
+use crate::stock::SharedStock;
+use crate::storage::{MemoryStorage, Storage};
+use crate::stores::SharedStores;
+use crate::sync::SharedVersions;
+use crate::venue::SharedVenuePages;
+use crate::warm::SharedWarm;
+use rand::Rng;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex, RwLock};
+use tera::Tera;
+
+/// The templates, which can be swapped for reloaded ones, see [`crate::reload`]
+pub type SharedTera = Arc<RwLock<Arc<Tera>>>;
+
+/// Everything the request handlers need, shared with the background jobs.
+#[derive(Clone)]
+pub struct AppState {
+    pub snapshot: SharedSnapshot,
+    pub status: SharedStatus,
+    pub storage: Arc<dyn Storage>,
+    pub config: Arc<Config>,
+    pub tera: SharedTera,
+    /// Signs cookies. Random unless configured, so cookies don't survive restarts then.
+    pub cookie_key: Arc<Vec<u8>>,
+    pub ratings: SharedRatings,
+    pub venue_pages: SharedVenuePages,
+    pub launch_plan: SharedLaunchPlan,
+    /// Redirects to Systembolaget, see [`crate::buy`]
+    pub buys: Arc<AtomicU64>,
+    /// The most visited views, rendered ahead, see [`crate::warm`]
+    pub warm: SharedWarm,
+    pub load: SharedLoad,
+    pub stock: SharedStock,
+    pub stores: SharedStores,
+    pub price_history: SharedPriceHistory,
+    /// The pages of the last run, shown until the first refresh is done, see [`crate::pagecache`]
+    pub cached_pages: Arc<Option<Pages>>,
+    /// The last few snapshots, for syncing from, see [`crate::sync`]
+    pub versions: SharedVersions,
+    /// For admin actions on the refresh job, set by [`crate::server::ApkServerBuilder::build`]
+    pub refresher: Option<Arc<Refresher>>,
+    /// Held by requests while they load, change and save something in storage, so they don't
+    /// undo each other's changes
+    pub saving: Arc<Mutex<()>>,
+    /// For confirmation links, set by [`crate::server::ApkServerBuilder::mailer`]
+    pub mailer: Option<Arc<Mailer>>,
+}
+
+impl AppState {
+    /// The current templates.
+    pub fn tera(&self) -> Arc<Tera> {
+        self.tera.read().unwrap().clone()
+    }
+}
+
+impl Default for AppState {
+    fn default() -> AppState {
+        AppState {
+            snapshot: Default::default(),
+            status: Default::default(),
+            storage: Arc::new(MemoryStorage::default()),
+            config: Default::default(),
+            tera: Default::default(),
+            cookie_key: Arc::new(rand::thread_rng().gen::<[u8; 32]>().to_vec()),
+            ratings: Default::default(),
+            venue_pages: Default::default(),
+            launch_plan: Default::default(),
+            buys: Default::default(),
+            warm: Default::default(),
+            load: Default::default(),
+            stock: Default::default(),
+            stores: Default::default(),
+            price_history: None,
+            cached_pages: Default::default(),
+            versions: Default::default(),
+            refresher: None,
+            saving: Default::default(),
+            mailer: None,
+        }
+    }
+}
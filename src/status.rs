@@ -0,0 +1,61 @@
+use crate::error::Error;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Health of the refresh job, as reported by `/admin/status` and `/metrics`.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Status {
+    pub refreshes: u64,
+    /// Unix timestamp
+    pub last_success: Option<u64>,
+    pub last_error: Option<LastError>,
+    /// Failed refreshes by error category
+    pub errors: BTreeMap<&'static str, u64>,
+    /// Refreshes in a row that failed to fetch the products
+    pub failures: u32,
+    /// Seconds until the next try while backing off, see [`crate::refresh::backoff`]
+    pub retry_in: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LastError {
+    /// Unix timestamp
+    pub at: u64,
+    pub category: &'static str,
+    pub message: String,
+}
+
+pub type SharedStatus = Arc<RwLock<Status>>;
+
+impl Status {
+    pub fn record_success(&mut self, at: SystemTime) {
+        self.refreshes += 1;
+        self.last_success = Some(unix_time(at));
+    }
+
+    pub fn record_error(&mut self, at: SystemTime, err: &Error) {
+        *self.errors.entry(err.category()).or_insert(0) += 1;
+        self.last_error = Some(LastError {
+            at: unix_time(at),
+            category: err.category(),
+            message: err.to_string(),
+        });
+    }
+
+    /// Counts another failed refresh, to try again in `delay` seconds.
+    pub fn record_backoff(&mut self, delay: u64) {
+        self.failures += 1;
+        self.retry_in = Some(delay);
+    }
+
+    pub fn reset_backoff(&mut self) {
+        self.failures = 0;
+        self.retry_in = None;
+    }
+}
+
+pub fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
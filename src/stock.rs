@@ -0,0 +1,126 @@
+//! What Systembolaget's stores have on their shelves. The stock of each store in the config is
+//! fetched after each refresh, and `?butik=<id>` (or `?store=`) narrows the list, and the API, to
+//! the products that store has. The stores in the config can be picked on the list.
+
+use crate::catalog::{self, Category};
+use crate::config::StockConfig;
+use crate::error::Result;
+use crate::notify::{Notifier, RefreshEvent};
+use crate::state::AppState;
+use crate::view::View;
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use systemet::Product;
+
+/// How many of each product a store has, by product id. Products it's out of aren't in it.
+pub type Levels = HashMap<String, u32>;
+
+/// The stock of each store, by store id, as of the last refresh
+pub type SharedStock = Arc<RwLock<HashMap<String, Levels>>>;
+
+/// A product's stock in a store, as Systembolaget's stock balance has it.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Balance {
+    product_id: String,
+    #[serde(default)]
+    stock: Option<i64>,
+}
+
+/// Only the products in `drinks` that the store of `view` has in stock, if it has picked one. A
+/// store without known stock has nothing.
+pub fn filter<'a>(
+    state: &AppState,
+    view: &View,
+    drinks: HashMap<Category, Vec<&'a Product>>,
+) -> HashMap<Category, Vec<&'a Product>> {
+    let store = match &view.store {
+        Some(store) => store,
+        None => return drinks,
+    };
+    let stock = state.stock.read().unwrap();
+    let levels = stock.get(store);
+    drinks
+        .into_iter()
+        .map(|(category, drinks)| {
+            let drinks = drinks
+                .into_iter()
+                .filter(|drink| {
+                    levels.map_or(false, |levels| levels.contains_key(catalog::id(drink)))
+                })
+                .collect();
+            (category, drinks)
+        })
+        .collect()
+}
+
+/// How many of the product with `id` the store with id `store` has, if its stock is known.
+pub fn level(state: &AppState, store: &str, id: &str) -> Option<u32> {
+    let stock = state.stock.read().unwrap();
+    let levels = stock.get(store)?;
+    Some(levels.get(id).copied().unwrap_or(0))
+}
+
+/// Fetches the stock of the configured stores after each refresh.
+pub struct StockFetcher {
+    client: reqwest::Client,
+    config: StockConfig,
+}
+
+impl StockFetcher {
+    pub fn new(config: &StockConfig) -> StockFetcher {
+        StockFetcher {
+            client: reqwest::Client::new(),
+            config: config.clone(),
+        }
+    }
+
+    async fn fetch(&self, store: &str) -> Result<Levels> {
+        let balances: Vec<Balance> = self
+            .client
+            .get(&self.config.url.replace("{store}", store))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(balances
+            .into_iter()
+            .filter_map(|balance| match balance.stock {
+                Some(stock) if stock > 0 => Some((balance.product_id, stock as u32)),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl Notifier for StockFetcher {
+    fn name(&self) -> &str {
+        "stock"
+    }
+
+    /// Keeps the previous stock of a store that can't be fetched, but still fails afterwards so
+    /// that it's logged.
+    async fn notify(&self, state: &AppState, _event: &RefreshEvent) -> Result<()> {
+        let mut failed = None;
+        for store in &self.config.stores {
+            match self.fetch(&store.id).await {
+                Ok(levels) => {
+                    state
+                        .stock
+                        .write()
+                        .unwrap()
+                        .insert(store.id.clone(), levels);
+                }
+                Err(err) => failed = Some(err),
+            }
+        }
+        match failed {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::error::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// A small key-value store for state that should survive restarts.
+pub trait Storage: Send + Sync {
+    fn load(&self, key: &str) -> Result<Option<Vec<u8>>>;
+    fn save(&self, key: &str, value: &[u8]) -> Result<()>;
+}
+
+/// Stores each key as a file in a directory.
+pub struct FileStorage {
+    dir: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<FileStorage> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(FileStorage { dir })
+    }
+}
+
+impl Storage for FileStorage {
+    fn load(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.dir.join(key)) {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn save(&self, key: &str, value: &[u8]) -> Result<()> {
+        // Write to a temporary file first so a crash never leaves a half-written value behind.
+        let tmp = self.dir.join(format!(".{}.tmp", key));
+        fs::write(&tmp, value)?;
+        fs::rename(&tmp, self.dir.join(key))?;
+        Ok(())
+    }
+}
+
+/// Keeps everything in memory. Used when no data directory is configured, and in tests.
+#[derive(Default)]
+pub struct MemoryStorage {
+    values: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl Storage for MemoryStorage {
+    fn load(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.values.lock().unwrap().get(key).cloned())
+    }
+
+    fn save(&self, key: &str, value: &[u8]) -> Result<()> {
+        self.values
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), value.to_vec());
+        Ok(())
+    }
+}
+
+pub fn load_json<T: DeserializeOwned>(storage: &dyn Storage, key: &str) -> Result<Option<T>> {
+    match storage.load(key)? {
+        Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn save_json<T: Serialize>(storage: &dyn Storage, key: &str, value: &T) -> Result<()> {
+    storage.save(key, &serde_json::to_vec(value)?)
+}
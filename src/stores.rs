@@ -0,0 +1,215 @@
+//! Systembolaget's stores, fetched from the store list after each refresh and served at
+//! `/stores`. Given `?stad=Umeå` (or `?city=`), only the stores in that city are listed, so a
+//! store can be picked by where it is rather than by its id. The same works on the list, where
+//! `?stad=Umeå` is redirected to the stock of a store there, see [`crate::stock`].
+//!
+//! A default store is kept in a signed cookie, set by posting `butik` (a store id) or `stad` to
+//! `/stores`. The list and `/api/products` show its stock, unless `butik` overrides it for a single
+//! request, see [`selected`].
+
+use crate::config::StoresConfig;
+use crate::error::Result;
+use crate::notify::{Notifier, RefreshEvent};
+use crate::signing;
+use crate::state::AppState;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use warp::http::{header, StatusCode};
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Store {
+    #[serde(alias = "siteId")]
+    pub id: String,
+    #[serde(alias = "alias", alias = "displayName")]
+    pub name: String,
+    pub city: String,
+}
+
+pub const COOKIE: &str = "butik";
+/// A year, in seconds
+const MAX_AGE: u64 = 365 * 24 * 60 * 60;
+const MAX_FORM: u64 = 4 * 1024;
+
+/// The stores, as of the last refresh
+pub type SharedStores = Arc<RwLock<Vec<Store>>>;
+
+/// The stores in `city`, ignoring case and surrounding whitespace.
+pub fn in_city<'a>(stores: &'a [Store], city: &str) -> Vec<&'a Store> {
+    let city = city.trim().to_lowercase();
+    stores
+        .iter()
+        .filter(|store| store.city.trim().to_lowercase() == city)
+        .collect()
+}
+
+/// The store picked by the `stad` (or `city`) parameter of `query`, preferring one with known stock.
+pub fn from_query(state: &AppState, query: &str) -> Option<String> {
+    let params: Vec<(String, String)> = serde_urlencoded::from_str(query).ok()?;
+    let (_, city) = params
+        .iter()
+        .find(|(key, _)| key == "stad" || key == "city")?;
+    let stores = state.stores.read().unwrap();
+    let stock = state.stock.read().unwrap();
+    let stores = in_city(&stores, city);
+    let store = stores
+        .iter()
+        .find(|store| stock.contains_key(&store.id))
+        .or_else(|| stores.first())?;
+    Some(store.id.clone())
+}
+
+/// The id of the store picked for this request: the `butik` (or `store`) query parameter, or else
+/// the default in the cookie, unless it's been tampered with.
+pub fn selected(
+    key: &[u8],
+    cookie: Option<&str>,
+    query: &HashMap<String, String>,
+) -> Option<String> {
+    match query.get("butik").or_else(|| query.get("store")) {
+        Some(id) if !id.is_empty() => Some(id.clone()),
+        _ => default(key, cookie),
+    }
+}
+
+/// The default store in `cookie`, unless it's been tampered with.
+pub fn default(key: &[u8], cookie: Option<&str>) -> Option<String> {
+    signing::verify_signed(key, cookie?).map(str::to_string)
+}
+
+/// The default store in `cookie`, if its stock is known. The stock of a store nothing is known
+/// about is empty, which would empty the list.
+pub fn default_with_stock(state: &AppState, cookie: Option<&str>) -> Option<String> {
+    let id = default(&state.cookie_key, cookie)?;
+    if state.stock.read().unwrap().contains_key(&id) {
+        Some(id)
+    } else {
+        None
+    }
+}
+
+/// A `Set-Cookie` value making `id` the default store, or forgetting it if there's none.
+pub fn cookie(key: &[u8], id: Option<&str>) -> String {
+    match id {
+        Some(id) => format!(
+            "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax",
+            COOKIE,
+            signing::sign(key, id),
+            MAX_AGE
+        ),
+        None => format!("{}=; Path=/; Max-Age=0", COOKIE),
+    }
+}
+
+/// Fetches the store list after each refresh. They rarely change, but it's a single small request.
+pub struct StoreFetcher {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl StoreFetcher {
+    pub fn new(config: &StoresConfig) -> StoreFetcher {
+        StoreFetcher {
+            client: reqwest::Client::new(),
+            url: config.url.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for StoreFetcher {
+    fn name(&self) -> &str {
+        "stores"
+    }
+
+    async fn notify(&self, state: &AppState, _event: &RefreshEvent) -> Result<()> {
+        let mut stores: Vec<Store> = self
+            .client
+            .get(&self.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        stores.sort_by(|s1, s2| (&s1.city, &s1.name).cmp(&(&s2.city, &s2.name)));
+        *state.stores.write().unwrap() = stores;
+        Ok(())
+    }
+}
+
+fn list(state: &AppState, query: &HashMap<String, String>) -> Response {
+    let stores = state.stores.read().unwrap();
+    match query.get("stad").or_else(|| query.get("city")) {
+        Some(city) => warp::reply::json(&in_city(&stores, city)).into_response(),
+        None => warp::reply::json(&*stores).into_response(),
+    }
+}
+
+/// The selected store, if it's a known one.
+fn show_selected(
+    state: &AppState,
+    cookie: Option<String>,
+    query: &HashMap<String, String>,
+) -> Response {
+    let id = selected(&state.cookie_key, cookie.as_deref(), query);
+    let stores = state.stores.read().unwrap();
+    match stores.iter().find(|store| Some(&store.id) == id.as_ref()) {
+        Some(store) => warp::reply::json(store).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Makes the store in `form` the default, by `butik` or, taking the first one there, by `stad`.
+/// With `action=clear`, forgets it.
+fn select(state: &AppState, form: HashMap<String, String>) -> Response {
+    let id = if form.get("action").map(String::as_str) == Some("clear") {
+        None
+    } else {
+        let stores = state.stores.read().unwrap();
+        let store = match (form.get("butik"), form.get("stad")) {
+            (Some(id), _) => stores.iter().find(|store| &store.id == id),
+            (None, Some(city)) => in_city(&stores, city).into_iter().next(),
+            (None, None) => None,
+        };
+        match store {
+            Some(store) => Some(store.id.clone()),
+            None => return StatusCode::BAD_REQUEST.into_response(),
+        }
+    };
+    let redirect = warp::reply::with_header(StatusCode::SEE_OTHER, header::LOCATION, "/stores");
+    warp::reply::with_header(
+        redirect,
+        header::SET_COOKIE,
+        cookie(&state.cookie_key, id.as_deref()),
+    )
+    .into_response()
+}
+
+pub fn routes(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let list = {
+        let state = state.clone();
+        warp::path!("stores")
+            .and(warp::get())
+            .and(warp::query::<HashMap<String, String>>())
+            .map(move |query: HashMap<String, String>| list(&state, &query))
+    };
+    let show_selected = {
+        let state = state.clone();
+        warp::path!("stores" / "selected")
+            .and(warp::get())
+            .and(warp::cookie::optional(COOKIE))
+            .and(warp::query::<HashMap<String, String>>())
+            .map(move |cookie, query: HashMap<String, String>| {
+                show_selected(&state, cookie, &query)
+            })
+    };
+    let select = warp::path!("stores")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(MAX_FORM))
+        .and(warp::body::form())
+        .map(move |form| select(&state, form));
+    list.or(show_selected).unify().or(select).unify()
+}
@@ -0,0 +1,180 @@
+//! Syncing the catalog by what changed since a version the client already has, for mirrors and
+//! bots that poll often. `GET /api/sync?since=<hash>` answers with the products that are new or
+//! changed since the snapshot with that hash, and the ids of the ones that are gone. For a hash
+//! that's too old, or none, every product is sent, with no `base`. The last [`MAX_VERSIONS`]
+//! snapshots can be synced from.
+
+use crate::apierror::{self, ApiError};
+use crate::catalog::{self, Catalog};
+use crate::error::Result;
+use crate::notify::{Notifier, RefreshEvent};
+use crate::signing;
+use crate::state::AppState;
+use async_trait::async_trait;
+use futures::future;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, RwLock};
+use tracing::error;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+pub const MAX_VERSIONS: usize = 8;
+
+/// A snapshot, by the SHA-256 of each product, by id.
+pub struct Version {
+    pub hash: String,
+    pub products: HashMap<String, String>,
+}
+
+/// The newest versions first
+pub type SharedVersions = Arc<RwLock<VecDeque<Version>>>;
+
+/// The products that changed between two snapshots.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Delta {
+    /// Of the snapshot this leads to
+    pub hash: String,
+    /// Of the snapshot this starts from, or none if `changed` is every product
+    pub base: Option<String>,
+    /// New or changed, as the upstream API has them
+    pub changed: Vec<Value>,
+    /// The ids of the ones that are gone
+    pub removed: Vec<String>,
+}
+
+impl Delta {
+    /// `products`, of the snapshot with hash [`Delta::base`], as they are in the one with
+    /// [`Delta::hash`].
+    pub fn apply(self, products: Vec<Value>) -> Vec<Value> {
+        if self.base.is_none() {
+            return self.changed;
+        }
+        let replaced: HashSet<&str> = self
+            .removed
+            .iter()
+            .map(String::as_str)
+            .chain(self.changed.iter().filter_map(id))
+            .collect();
+        let mut products: Vec<Value> = products
+            .into_iter()
+            .filter(|product| id(product).map_or(true, |id| !replaced.contains(id)))
+            .collect();
+        products.extend(self.changed);
+        products
+    }
+}
+
+fn id(product: &Value) -> Option<&str> {
+    product.get("ProductId").and_then(Value::as_str)
+}
+
+/// The SHA-256 of each product in `catalog`, by id.
+fn digests(catalog: &Catalog) -> Result<HashMap<String, String>> {
+    catalog
+        .products()
+        .map(|drink| {
+            let digest = signing::sha256(&serde_json::to_vec(drink)?);
+            Ok((catalog::id(drink).to_string(), digest))
+        })
+        .collect()
+}
+
+/// What changed in the current snapshot since the one with hash `since`, or none if there's no
+/// snapshot yet.
+pub fn delta(state: &AppState, since: Option<&str>) -> Result<Option<Delta>> {
+    let snapshot = match state.snapshot.read().unwrap().clone() {
+        Some(snapshot) => snapshot,
+        None => return Ok(None),
+    };
+    let versions = state.versions.read().unwrap();
+    let base = since.and_then(|since| versions.iter().find(|version| version.hash == since));
+    let base = match base {
+        Some(base) => base,
+        None => {
+            let changed = snapshot
+                .catalog
+                .products()
+                .map(serde_json::to_value)
+                .collect::<serde_json::Result<_>>()?;
+            return Ok(Some(Delta {
+                hash: snapshot.hash.clone(),
+                base: None,
+                changed,
+                removed: Vec::new(),
+            }));
+        }
+    };
+    let computed;
+    let current = match versions.front() {
+        Some(version) if version.hash == snapshot.hash => &version.products,
+        _ => {
+            computed = digests(&snapshot.catalog)?;
+            &computed
+        }
+    };
+    let mut changed = Vec::new();
+    for drink in snapshot.catalog.products() {
+        let id = catalog::id(drink);
+        if base.products.get(id) != current.get(id) {
+            changed.push(serde_json::to_value(drink)?);
+        }
+    }
+    let removed = base
+        .products
+        .keys()
+        .filter(|id| !current.contains_key(*id))
+        .cloned()
+        .collect();
+    Ok(Some(Delta {
+        hash: snapshot.hash.clone(),
+        base: Some(base.hash.clone()),
+        changed,
+        removed,
+    }))
+}
+
+/// Remembers the products of each refresh, for syncing from it later.
+pub struct VersionRecorder;
+
+#[async_trait]
+impl Notifier for VersionRecorder {
+    fn name(&self) -> &str {
+        "sync"
+    }
+
+    async fn notify(&self, state: &AppState, event: &RefreshEvent) -> Result<()> {
+        let version = Version {
+            hash: event.snapshot.hash.clone(),
+            products: digests(&event.snapshot.catalog)?,
+        };
+        let mut versions = state.versions.write().unwrap();
+        versions.retain(|other| other.hash != version.hash);
+        versions.push_front(version);
+        versions.truncate(MAX_VERSIONS);
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct SyncQuery {
+    since: Option<String>,
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("api" / "sync")
+        .and(warp::query::<SyncQuery>())
+        .and_then(move |query: SyncQuery| {
+            future::ready(match delta(&state, query.since.as_deref()) {
+                Ok(Some(delta)) => Ok(warp::reply::json(&delta).into_response()),
+                Ok(None) => Err(Rejection::from(ApiError::not_ready())),
+                Err(err) => {
+                    error!("{}", err);
+                    Err(Rejection::from(ApiError::internal()))
+                }
+            })
+        })
+        .recover(apierror::recover)
+        .unify()
+}
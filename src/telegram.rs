@@ -0,0 +1,265 @@
+//! A Telegram bot answering questions about the current snapshot, using long polling so it works
+//! without a public URL.
+
+use crate::catalog::Category;
+use crate::config::TelegramConfig;
+use crate::digest;
+use crate::error::{Error, Result};
+use crate::server::Job;
+use crate::state::AppState;
+use crate::storage;
+use crate::text;
+use async_trait::async_trait;
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::error;
+
+const POLL_TIMEOUT: u64 = 30;
+const ERROR_DELAY: Duration = Duration::from_secs(10);
+const DIGEST_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const SUBSCRIBERS_KEY: &str = "telegram-subscribers.json";
+const LAST_DIGEST_KEY: &str = "telegram-last-digest.json";
+const TOP: usize = 10;
+const SEARCH_RESULTS: usize = 5;
+/// The answer when something goes wrong, which isn't for chat users to know about
+const FAILED: &str = "Något gick fel, försök igen om en stund.";
+const HELP: &str = "Kommandon:\n\
+    /top <öl|vin|cider|sprit|annat> – bästa APK i en kategori\n\
+    /search <namn> – sök efter dricka\n\
+    /apk <artikelnummer> – APK för en viss produkt\n\
+    /prenumerera – få veckans APK varje vecka\n\
+    /avsluta – sluta få veckans APK";
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Top(Category),
+    Search(String),
+    Apk(String),
+    Subscribe,
+    Unsubscribe,
+    Help,
+}
+
+/// Parses a message. Returns `None` for anything that isn't a command.
+pub fn parse(text: &str) -> Option<Command> {
+    let text = text.trim();
+    if !text.starts_with('/') {
+        return None;
+    }
+    let mut parts = text.splitn(2, char::is_whitespace);
+    // Commands in groups look like /top@apk_bot
+    let command = parts.next()?.split('@').next()?;
+    let argument = parts.next().unwrap_or("").trim();
+    Some(match command {
+        "/top" => match Category::from_name(argument) {
+            Some(category) => Command::Top(category),
+            None => Command::Help,
+        },
+        "/search" | "/sok" if !argument.is_empty() => Command::Search(argument.to_string()),
+        "/apk" if !argument.is_empty() => Command::Apk(argument.to_string()),
+        "/prenumerera" | "/subscribe" => Command::Subscribe,
+        "/avsluta" | "/unsubscribe" => Command::Unsubscribe,
+        _ => Command::Help,
+    })
+}
+
+#[derive(Deserialize)]
+struct Response<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    chat: Chat,
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+pub struct TelegramBot {
+    client: reqwest::Client,
+    api: String,
+}
+
+impl TelegramBot {
+    pub fn new(config: TelegramConfig) -> TelegramBot {
+        TelegramBot {
+            client: reqwest::Client::new(),
+            api: format!(
+                "https://api.telegram.org/bot{}",
+                config.token.expose_secret()
+            ),
+        }
+    }
+
+    async fn call<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        body: serde_json::Value,
+    ) -> Result<T> {
+        let response: Response<T> = self
+            .client
+            .post(&format!("{}/{}", self.api, method))
+            .json(&body)
+            .send()
+            .await
+            .map_err(without_token)?
+            .json()
+            .await
+            .map_err(without_token)?;
+        match response.result {
+            Some(result) if response.ok => Ok(result),
+            _ => Err(Error::upstream(
+                response
+                    .description
+                    .unwrap_or_else(|| format!("Telegram {} failed", method)),
+            )),
+        }
+    }
+
+    async fn send(&self, chat: i64, text: &str) -> Result<()> {
+        self.call::<serde_json::Value>("sendMessage", json!({ "chat_id": chat, "text": text }))
+            .await?;
+        Ok(())
+    }
+
+    fn reply(&self, state: &AppState, chat: i64, command: Command) -> Result<String> {
+        let snapshot = match state.snapshot.read().unwrap().clone() {
+            Some(snapshot) => snapshot,
+            None => return Ok("Listan har inte laddats än, försök igen om en stund.".to_string()),
+        };
+        let catalog = &snapshot.catalog;
+        Ok(match command {
+            Command::Top(category) => text::top(catalog, category, TOP),
+            Command::Search(query) => match snapshot.index.search(catalog, &query).as_slice() {
+                [] => format!("Hittade inget som heter {}.", query),
+                matches => text::numbered(matches.iter().copied().take(SEARCH_RESULTS)),
+            },
+            Command::Apk(id) => match catalog.find(&id) {
+                Some(drink) => text::drink_line(drink),
+                None => format!("Hittade ingen produkt med nummer {}.", id),
+            },
+            Command::Subscribe => {
+                let mut subscribers = subscribers(state)?;
+                if !subscribers.contains(&chat) {
+                    subscribers.push(chat);
+                }
+                storage::save_json(&*state.storage, SUBSCRIBERS_KEY, &subscribers)?;
+                "Du får nu veckans APK varje vecka.".to_string()
+            }
+            Command::Unsubscribe => {
+                let mut subscribers = subscribers(state)?;
+                subscribers.retain(|&subscriber| subscriber != chat);
+                storage::save_json(&*state.storage, SUBSCRIBERS_KEY, &subscribers)?;
+                "Du får inte längre veckans APK.".to_string()
+            }
+            Command::Help => HELP.to_string(),
+        })
+    }
+
+    async fn poll(&self, state: &AppState) {
+        let mut offset = 0;
+        loop {
+            let updates: Vec<Update> = match self
+                .call(
+                    "getUpdates",
+                    json!({ "offset": offset, "timeout": POLL_TIMEOUT }),
+                )
+                .await
+            {
+                Ok(updates) => updates,
+                Err(err) => {
+                    error!("Polling Telegram failed: {}", err);
+                    tokio::time::delay_for(ERROR_DELAY).await;
+                    continue;
+                }
+            };
+            for update in updates {
+                offset = offset.max(update.update_id + 1);
+                let message = match update.message {
+                    Some(message) => message,
+                    None => continue,
+                };
+                let command = match message.text.as_deref().and_then(parse) {
+                    Some(command) => command,
+                    None => continue,
+                };
+                let reply = self
+                    .reply(state, message.chat.id, command)
+                    .unwrap_or_else(|err| {
+                        error!("Answering on Telegram failed: {}", err);
+                        FAILED.to_string()
+                    });
+                if let Err(err) = self.send(message.chat.id, &reply).await {
+                    error!("Replying on Telegram failed: {}", err);
+                }
+            }
+        }
+    }
+
+    async fn send_digest_if_due(&self, state: &AppState) -> Result<()> {
+        let now = SystemTime::now();
+        let last_sent = storage::load_json::<u64>(&*state.storage, LAST_DIGEST_KEY)?
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+        if !digest::is_due(last_sent, now) {
+            return Ok(());
+        }
+        let snapshot = match state.snapshot.read().unwrap().clone() {
+            Some(snapshot) => snapshot,
+            None => return Ok(()),
+        };
+        let text = digest::text(&snapshot.catalog);
+        for chat in subscribers(state)? {
+            if let Err(err) = self.send(chat, &text).await {
+                error!("Sending digest to {} failed: {}", chat, err);
+            }
+        }
+        let now = now.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        storage::save_json(&*state.storage, LAST_DIGEST_KEY, &now)
+    }
+
+    async fn send_digests(&self, state: &AppState) {
+        loop {
+            if let Err(err) = self.send_digest_if_due(state).await {
+                error!("Sending Telegram digest failed: {}", err);
+            }
+            tokio::time::delay_for(DIGEST_CHECK_INTERVAL).await;
+        }
+    }
+}
+
+/// `err` without the URL it was about, since that has the bot token in it.
+fn without_token(err: reqwest::Error) -> Error {
+    Error::from(err.without_url())
+}
+
+fn subscribers(state: &AppState) -> Result<Vec<i64>> {
+    Ok(storage::load_json(&*state.storage, SUBSCRIBERS_KEY)?.unwrap_or_default())
+}
+
+#[async_trait]
+impl Job for TelegramBot {
+    async fn run(self: Box<Self>, state: AppState) {
+        let bot: Arc<TelegramBot> = Arc::from(self);
+        {
+            let bot = bot.clone();
+            let state = state.clone();
+            tokio::spawn(async move { bot.send_digests(&state).await });
+        }
+        bot.poll(&state).await
+    }
+}
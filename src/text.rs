@@ -0,0 +1,80 @@
+//! Plain-text formatting of products for chat integrations.
+
+use crate::catalog::{self, Catalog, Category};
+use crate::config::DisplayConfig;
+use crate::display::{self, Field};
+use crate::units::Measures;
+use systemet::Product;
+
+pub fn drink_line(drink: &Product) -> String {
+    format!(
+        "{} – {}, {}, {} (APK {:.3})",
+        catalog::name(drink),
+        drink.abv(),
+        drink.volume(),
+        drink.price_with_deposit(),
+        catalog::apk(drink).0
+    )
+}
+
+/// A numbered list of `drinks`, one per line.
+pub fn numbered<'a>(drinks: impl IntoIterator<Item = &'a Product>) -> String {
+    drinks
+        .into_iter()
+        .enumerate()
+        .map(|(i, drink)| format!("{}. {}", i + 1, drink_line(drink)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn top(catalog: &Catalog, category: Category, n: usize) -> String {
+    format!(
+        "Topp {} {}:\n{}",
+        n,
+        category.name(),
+        numbered(catalog.get(category).iter().take(n))
+    )
+}
+
+/// The best `n` of `category` as a table with aligned columns, for reading in a terminal. The
+/// numbers are rounded like on the pages.
+pub fn table(catalog: &Catalog, category: Category, n: usize, config: &DisplayConfig) -> String {
+    let header = ["", "Namn", "Pris", "Alkohol", "Volym", "APK"];
+    let mut rows: Vec<Vec<String>> = vec![header.iter().map(|cell| cell.to_string()).collect()];
+    for (i, drink) in catalog.get(category).iter().take(n).enumerate() {
+        rows.push(vec![
+            format!("{}.", i + 1),
+            catalog::name(drink).to_string(),
+            format!(
+                "{} kr",
+                display::format(config, Field::Price, drink.price_with_deposit().0)
+            ),
+            format!("{}%", display::format(config, Field::Abv, drink.abv().0)),
+            drink.volume().to_string(),
+            display::format(config, Field::Apk, catalog::apk(drink).0),
+        ]);
+    }
+    let mut widths = vec![0; header.len()];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let lines: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(column, (cell, &width))| match column {
+                    // Only the name reads best to the left
+                    1 => format!("{:<width$}", cell, width = width),
+                    _ => format!("{:>width$}", cell, width = width),
+                })
+                .collect();
+            cells.join("  ").trim_end().to_string()
+        })
+        .collect();
+    format!("{}\n{}", category.name(), lines.join("\n"))
+}
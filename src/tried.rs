@@ -0,0 +1,64 @@
+//! Products the user has marked as tried, kept in a signed cookie, so views can hide or dim them
+//! for whoever is working their way down the list.
+
+use crate::back;
+use crate::favorites;
+use crate::signing;
+use crate::state::AppState;
+use std::collections::HashMap;
+use warp::http::{header, StatusCode};
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+pub const COOKIE: &str = "tried";
+/// Enough for a few years of weekly tasting
+const MAX_TRIED: usize = 500;
+/// A year, in seconds
+const MAX_AGE: u64 = 365 * 24 * 60 * 60;
+
+/// The product ids in a tried cookie, or none if it's missing or tampered with. Same format as
+/// the favorites cookie.
+pub fn parse(key: &[u8], cookie: Option<&str>) -> Vec<String> {
+    favorites::parse(key, cookie)
+}
+
+/// A `Set-Cookie` value holding `ids`.
+pub fn cookie(key: &[u8], ids: &[String]) -> String {
+    format!(
+        "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax",
+        COOKIE,
+        signing::sign(key, &ids.join(":")),
+        MAX_AGE
+    )
+}
+
+/// Marks or unmarks the product in `form`, depending on its `action`, and goes back to the page
+/// in `back`.
+fn update(state: &AppState, current: Option<String>, form: HashMap<String, String>) -> Response {
+    let mut ids = parse(&state.cookie_key, current.as_deref());
+    let id = match form.get("id") {
+        Some(id) if !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric()) => id,
+        _ => return StatusCode::BAD_REQUEST.into_response(),
+    };
+    ids.retain(|tried| tried != id);
+    if form.get("action").map(String::as_str) != Some("remove") {
+        ids.insert(0, id.clone());
+        ids.truncate(MAX_TRIED);
+    }
+    let redirect =
+        warp::reply::with_header(StatusCode::SEE_OTHER, header::LOCATION, back::back(&form));
+    warp::reply::with_header(
+        redirect,
+        header::SET_COOKIE,
+        cookie(&state.cookie_key, &ids),
+    )
+    .into_response()
+}
+
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("tried")
+        .and(warp::post())
+        .and(warp::cookie::optional(COOKIE))
+        .and(warp::body::form())
+        .map(move |cookie, form| update(&state, cookie, form))
+}
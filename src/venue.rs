@@ -0,0 +1,124 @@
+//! Lists for several venues, like student pubs, on one instance. Each has its own prices,
+//! blocklist and theme, and is served at `/v/{slug}`, or at `/` for requests to its own host.
+
+use crate::catalog::{self, Catalog};
+use crate::config::VenueConfig;
+use crate::error::Result;
+use crate::notify::{Notifier, RefreshEvent};
+use crate::render;
+use crate::state::AppState;
+use crate::units::{Measures, Sek};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use systemet::Product;
+use tera::Tera;
+use warp::reply::{html, Response};
+use warp::{Filter, Rejection, Reply};
+
+/// The rendered list of each venue, by slug
+pub type SharedVenuePages = Arc<RwLock<HashMap<String, String>>>;
+
+/// What `drink` costs at `venue`.
+pub fn price(venue: &VenueConfig, drink: &Product) -> Sek {
+    let price = drink.shelf_price() * venue.markup;
+    match venue.rounding {
+        Some(rounding) if rounding > 0.0 => Sek((price.0 / rounding).ceil() * rounding),
+        _ => price,
+    }
+}
+
+/// The products `venue` serves, at its prices.
+pub fn catalog(venue: &VenueConfig, catalog: &Catalog) -> Catalog {
+    let products = catalog
+        .products()
+        .filter(|drink| {
+            !venue.blocklist.iter().any(|blocked| {
+                blocked == catalog::id(drink) || Some(blocked.as_str()) == catalog::number(drink)
+            })
+        })
+        .map(|drink| {
+            let mut served = drink.clone();
+            served.price = price(venue, drink).0;
+            served.recycle_fee = 0.0;
+            served
+        })
+        .collect();
+    Catalog::build(products)
+}
+
+/// The venue a request to `host` is for, if any. Ports are ignored.
+pub fn resolve<'a>(venues: &'a [VenueConfig], host: &str) -> Option<&'a VenueConfig> {
+    let host = host.splitn(2, ':').next().unwrap_or(host);
+    venues.iter().find(|venue| {
+        venue
+            .host
+            .as_deref()
+            .map_or(false, |h| h.eq_ignore_ascii_case(host))
+    })
+}
+
+/// Renders the list of every venue after each refresh.
+pub struct VenueRenderer {
+    venues: Vec<(VenueConfig, Arc<Tera>)>,
+}
+
+impl VenueRenderer {
+    /// `venues` with the templates to render each with.
+    pub fn new(venues: Vec<(VenueConfig, Arc<Tera>)>) -> VenueRenderer {
+        VenueRenderer { venues }
+    }
+}
+
+#[async_trait]
+impl Notifier for VenueRenderer {
+    fn name(&self) -> &str {
+        "venues"
+    }
+
+    async fn notify(&self, state: &AppState, event: &RefreshEvent) -> Result<()> {
+        for (venue, tera) in &self.venues {
+            let catalog = catalog(venue, &event.snapshot.catalog);
+            let page = render::render_venue_page(tera, &catalog, &venue.name)?;
+            state
+                .venue_pages
+                .write()
+                .unwrap()
+                .insert(venue.slug.clone(), page);
+        }
+        Ok(())
+    }
+}
+
+fn page(state: &AppState, slug: &str) -> Response {
+    let page = state.venue_pages.read().unwrap().get(slug).cloned();
+    html(page.unwrap_or_default()).into_response()
+}
+
+pub fn routes(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let by_path = {
+        let state = state.clone();
+        warp::path!("v" / String).and_then(move |slug: String| {
+            let state = state.clone();
+            async move {
+                if state.config.venues.iter().any(|venue| venue.slug == slug) {
+                    Ok(page(&state, &slug))
+                } else {
+                    Err(warp::reject::not_found())
+                }
+            }
+        })
+    };
+    let by_host = warp::path::end()
+        .and(warp::header::<String>("host"))
+        .and_then(move |host: String| {
+            let state = state.clone();
+            async move {
+                match resolve(&state.config.venues, &host) {
+                    Some(venue) => Ok(page(&state, &venue.slug)),
+                    None => Err(warp::reject::not_found()),
+                }
+            }
+        });
+    by_path.or(by_host).unify()
+}
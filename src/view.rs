@@ -0,0 +1,548 @@
+//! Filtered and sorted views of the list, encoded canonically in the query string so they can be
+//! shared, like `/?kategori=öl&maxpris=20&sortera=pris`.
+
+use crate::allergens;
+use crate::catalog::{self, Catalog, Category, SortKey, CATEGORIES};
+use crate::config::{AllergenConfig, AssortmentConfig};
+use crate::names;
+use crate::prefs;
+use crate::ratings;
+use crate::render;
+use crate::search::SearchIndex;
+use crate::session;
+use crate::state::AppState;
+use crate::stock;
+use crate::stores;
+use crate::tried;
+use crate::units::{Measures, Percent, Sek};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use systemet::Product;
+use tracing::error;
+use warp::http::{header, StatusCode};
+use warp::reply::{html, Response};
+use warp::Reply;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Sort {
+    #[serde(rename = "apk")]
+    Apk,
+    #[serde(rename = "basen")]
+    BasenApk,
+    #[serde(rename = "pris")]
+    Price,
+    #[serde(rename = "alkohol")]
+    Abv,
+    #[serde(rename = "volym")]
+    Volume,
+    /// Kronor per liter, whatever the strength, see [`crate::score::LiterPriceScorer`]
+    #[serde(rename = "literpris")]
+    LiterPrice,
+    #[serde(rename = "namn")]
+    Name,
+}
+
+const SORTS: [Sort; 7] = [
+    Sort::Apk,
+    Sort::BasenApk,
+    Sort::Price,
+    Sort::Abv,
+    Sort::Volume,
+    Sort::LiterPrice,
+    Sort::Name,
+];
+
+impl Sort {
+    pub fn name(self) -> &'static str {
+        match self {
+            Sort::Apk => "apk",
+            Sort::BasenApk => "basen",
+            Sort::Price => "pris",
+            Sort::Abv => "alkohol",
+            Sort::Volume => "volym",
+            Sort::LiterPrice => "literpris",
+            Sort::Name => "namn",
+        }
+    }
+
+    fn english_name(self) -> &'static str {
+        match self {
+            Sort::Apk => "apk",
+            Sort::BasenApk => "basen",
+            Sort::Price => "price",
+            Sort::Abv => "abv",
+            Sort::Volume => "volume",
+            Sort::LiterPrice => "price_per_liter",
+            Sort::Name => "name",
+        }
+    }
+
+    /// The sort by its name, in Swedish or English.
+    pub fn from_name(name: &str) -> Option<Sort> {
+        SORTS
+            .iter()
+            .copied()
+            .find(|sort| sort.name() == name || sort.english_name() == name)
+    }
+
+    pub fn key(self) -> SortKey {
+        match self {
+            Sort::Apk => SortKey::Apk,
+            Sort::BasenApk => SortKey::BasenApk,
+            Sort::Price => SortKey::Price,
+            Sort::Abv => SortKey::Abv,
+            Sort::Volume => SortKey::Volume,
+            Sort::LiterPrice => SortKey::LiterPrice,
+            Sort::Name => SortKey::Name,
+        }
+    }
+}
+
+impl Default for Sort {
+    fn default() -> Sort {
+        Sort::Apk
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Order {
+    #[serde(rename = "stigande")]
+    Ascending,
+    #[serde(rename = "fallande")]
+    Descending,
+}
+
+impl Order {
+    pub fn name(self) -> &'static str {
+        match self {
+            Order::Ascending => "stigande",
+            Order::Descending => "fallande",
+        }
+    }
+
+    /// The order by its name, in Swedish or English.
+    pub fn from_name(name: &str) -> Option<Order> {
+        match name {
+            "stigande" | "asc" => Some(Order::Ascending),
+            "fallande" | "desc" => Some(Order::Descending),
+            _ => None,
+        }
+    }
+
+    /// The order a list sorted by `sort` has unless asked otherwise, the best first.
+    pub fn of(sort: Sort) -> Order {
+        if sort.key().descending() {
+            Order::Descending
+        } else {
+            Order::Ascending
+        }
+    }
+}
+
+/// What to do with products marked as tried.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Tried {
+    #[serde(rename = "visa")]
+    Show,
+    #[serde(rename = "tona")]
+    Dim,
+    #[serde(rename = "dolj")]
+    Hide,
+}
+
+impl Tried {
+    pub fn name(self) -> &'static str {
+        match self {
+            Tried::Show => "visa",
+            Tried::Dim => "tona",
+            Tried::Hide => "dolj",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Tried> {
+        [Tried::Show, Tried::Dim, Tried::Hide]
+            .iter()
+            .copied()
+            .find(|tried| tried.name() == name)
+    }
+}
+
+impl Default for Tried {
+    fn default() -> Tried {
+        Tried::Show
+    }
+}
+
+/// Products shown per category and page, unless the view says otherwise
+pub const DEFAULT_PER_PAGE: usize = 100;
+
+/// Where a category's list is cut, see [`View::paginate`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Page {
+    /// Counting from 1
+    pub page: usize,
+    pub pages: usize,
+    /// Products in the category, on all pages
+    pub total: usize,
+    /// Products on the pages before this one
+    pub offset: usize,
+    pub previous: Option<String>,
+    pub next: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct View {
+    pub category: Option<Category>,
+    /// Lowercase, since searching ignores case anyway
+    pub search: Option<String>,
+    pub max_price: Option<Sek>,
+    pub min_abv: Option<Percent>,
+    /// In grams per liter. Products without sugar data don't match.
+    pub max_sugar: Option<f64>,
+    /// Only beer without gluten, by the allergen rules
+    pub gluten_free: bool,
+    /// Only what the store with this id has in stock, see [`crate::stock`]
+    pub store: Option<String>,
+    pub sort: Sort,
+    /// Only set if it isn't the usual one of the sort, see [`Order::of`]
+    pub order: Option<Order>,
+    pub tried: Tried,
+    /// The page of each category to show, the first if not set
+    pub page: Option<usize>,
+    /// Defaults to [`DEFAULT_PER_PAGE`]
+    pub per_page: Option<usize>,
+}
+
+/// A count above `min`.
+fn count(value: &str, min: usize) -> Option<usize> {
+    value.parse::<usize>().ok().filter(|&count| count > min)
+}
+
+fn positive(value: &str) -> Option<f64> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite() && *value > 0.0)
+}
+
+impl View {
+    /// Reads the view from query parameters, ignoring anything unknown or invalid. The main filters
+    /// and the sort are also understood by their English names, for scripts, like
+    /// `sort=price&order=desc`.
+    pub fn from_query(query: &str) -> View {
+        let params: Vec<(String, String)> = serde_urlencoded::from_str(query).unwrap_or_default();
+        let mut view = View::default();
+        for (key, value) in params {
+            let value = value.trim();
+            match key.as_str() {
+                "kategori" | "category" => view.category = Category::from_name(value),
+                "sok" if !value.is_empty() => view.search = Some(value.to_lowercase()),
+                "maxpris" | "max_price" => view.max_price = positive(value).map(Sek),
+                "minalkohol" | "min_abv" => view.min_abv = positive(value).map(Percent),
+                "maxsocker" => view.max_sugar = positive(value),
+                "glutenfri" => view.gluten_free = value == "ja",
+                "butik" | "store" if !value.is_empty() => view.store = Some(value.to_string()),
+                "sortera" | "sort" => view.sort = Sort::from_name(value).unwrap_or_default(),
+                "ordning" | "order" => view.order = Order::from_name(value),
+                "provade" => view.tried = Tried::from_name(value).unwrap_or_default(),
+                "sida" | "page" => view.page = count(value, 1),
+                "per_sida" | "per_page" => {
+                    view.per_page = count(value, 0).filter(|&n| n != DEFAULT_PER_PAGE)
+                }
+                _ => {}
+            }
+        }
+        if view.order == Some(Order::of(view.sort)) {
+            view.order = None;
+        }
+        view
+    }
+
+    /// The order the list is sorted in.
+    pub fn order(&self) -> Order {
+        self.order.unwrap_or_else(|| Order::of(self.sort))
+    }
+
+    /// The canonical query string, without defaults. Empty for the plain list.
+    pub fn to_query(&self) -> String {
+        let mut params: Vec<(&str, String)> = Vec::new();
+        if let Some(category) = self.category {
+            params.push(("kategori", category.name().to_lowercase()));
+        }
+        if let Some(search) = &self.search {
+            params.push(("sok", search.clone()));
+        }
+        if let Some(max_price) = self.max_price {
+            params.push(("maxpris", max_price.0.to_string()));
+        }
+        if let Some(min_abv) = self.min_abv {
+            params.push(("minalkohol", min_abv.0.to_string()));
+        }
+        if let Some(max_sugar) = self.max_sugar {
+            params.push(("maxsocker", max_sugar.to_string()));
+        }
+        if self.gluten_free {
+            params.push(("glutenfri", "ja".to_string()));
+        }
+        if let Some(store) = &self.store {
+            params.push(("butik", store.clone()));
+        }
+        if self.sort != Sort::default() {
+            params.push(("sortera", self.sort.name().to_string()));
+        }
+        if let Some(order) = self.order.filter(|&order| order != Order::of(self.sort)) {
+            params.push(("ordning", order.name().to_string()));
+        }
+        if self.tried != Tried::default() {
+            params.push(("provade", self.tried.name().to_string()));
+        }
+        if let Some(page) = self.page {
+            params.push(("sida", page.to_string()));
+        }
+        if let Some(per_page) = self.per_page {
+            params.push(("per_sida", per_page.to_string()));
+        }
+        serde_urlencoded::to_string(params).unwrap_or_default()
+    }
+
+    /// The path and query to link to this view.
+    pub fn link(&self) -> String {
+        match self.to_query().as_str() {
+            "" => "/".to_string(),
+            query => format!("/?{}", query),
+        }
+    }
+
+    /// A link to this view with everything in a single opaque parameter, see [`prefs::encode`].
+    pub fn pinned_link(&self) -> String {
+        format!("/?{}={}", prefs::PARAM, prefs::encode(self))
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == View::default()
+    }
+
+    /// Whether `drink` is in the view, with `allergens` telling which products have gluten.
+    pub fn matches(&self, drink: &Product, allergens: &[AllergenConfig]) -> bool {
+        self.search.as_ref().map_or(true, |search| {
+            names::fold(&catalog::name(drink).to_lowercase()).contains(&names::fold(search))
+        }) && self.matches_filters(drink, allergens)
+    }
+
+    /// Like [`View::matches`], but ignoring the search.
+    fn matches_filters(&self, drink: &Product, allergens: &[AllergenConfig]) -> bool {
+        self.category
+            .map_or(true, |category| catalog::categorize(drink) == category)
+            && self
+                .max_price
+                .map_or(true, |max_price| drink.price_with_deposit() <= max_price)
+            && self.min_abv.map_or(true, |min_abv| drink.abv() >= min_abv)
+            && self.max_sugar.map_or(true, |max_sugar| {
+                catalog::sugar(drink).map_or(false, |sugar| sugar <= max_sugar)
+            })
+            && (!self.gluten_free
+                || catalog::categorize(drink) == Category::Beer
+                    && !allergens::has(allergens, drink, allergens::GLUTEN))
+    }
+
+    /// The matching products of each category, sorted. Categories that aren't shown are empty.
+    /// The products in `tried` are left out if the view hides them. With the `index` of `catalog`,
+    /// the search tolerates typos and looks at more than the name.
+    pub fn apply<'a>(
+        &self,
+        catalog: &'a Catalog,
+        index: Option<&SearchIndex>,
+        tried: &[String],
+        allergens: &[AllergenConfig],
+    ) -> HashMap<Category, Vec<&'a Product>> {
+        let found: Option<HashSet<&str>> = match (&self.search, index) {
+            (Some(search), Some(index)) => Some(
+                index
+                    .search(catalog, search)
+                    .into_iter()
+                    .map(catalog::id)
+                    .collect(),
+            ),
+            _ => None,
+        };
+        let matches = |drink: &Product| match &found {
+            Some(found) => {
+                found.contains(catalog::id(drink)) && self.matches_filters(drink, allergens)
+            }
+            None => self.matches(drink, allergens),
+        };
+        CATEGORIES
+            .iter()
+            .map(|&category| {
+                let mut drinks: Vec<&Product> = if self.category.map_or(true, |c| c == category) {
+                    catalog
+                        .get(category)
+                        .iter()
+                        .filter(|drink| matches(drink))
+                        .filter(|drink| {
+                            self.tried != Tried::Hide
+                                || !tried.iter().any(|id| id == catalog::id(drink))
+                        })
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                let key = self.sort.key();
+                match self.order() {
+                    // The catalog is already sorted by APK
+                    Order::Descending if self.sort == Sort::Apk => {}
+                    Order::Descending => drinks.sort_by(|d1, d2| key.compare(d2, d1)),
+                    Order::Ascending => drinks.sort_by(|d1, d2| key.compare(d1, d2)),
+                }
+                (category, drinks)
+            })
+            .collect()
+    }
+
+    /// Cuts each category of `drinks`, as returned by [`View::apply`], down to the view's page.
+    pub fn paginate<'a>(
+        &self,
+        mut drinks: HashMap<Category, Vec<&'a Product>>,
+    ) -> (HashMap<Category, Vec<&'a Product>>, HashMap<Category, Page>) {
+        let page = self.page.unwrap_or(1);
+        let per_page = self.per_page.unwrap_or(DEFAULT_PER_PAGE);
+        let link = |page: usize, category: Category| {
+            let view = View {
+                page: Some(page).filter(|&page| page > 1),
+                ..self.clone()
+            };
+            format!("{}#{}", view.link(), category.name())
+        };
+        let mut pages = HashMap::new();
+        for (&category, drinks) in drinks.iter_mut() {
+            let total = drinks.len();
+            let offset = (page - 1).saturating_mul(per_page).min(total);
+            let last = total.saturating_sub(1) / per_page + 1;
+            drinks.drain(..offset);
+            drinks.truncate(per_page);
+            pages.insert(
+                category,
+                Page {
+                    page,
+                    pages: last,
+                    total,
+                    offset,
+                    previous: Some(page - 1)
+                        .filter(|&previous| previous >= 1 && previous <= last)
+                        .map(|previous| link(previous, category)),
+                    next: page
+                        .checked_add(1)
+                        .filter(|&next| next <= last)
+                        .map(|next| link(next, category)),
+                },
+            );
+        }
+        (drinks, pages)
+    }
+}
+
+/// The view in a query consisting of just a `p` parameter.
+fn pinned(query: &str) -> Option<View> {
+    let params: Vec<(String, String)> = serde_urlencoded::from_str(query).ok()?;
+    match params.as_slice() {
+        [(key, value)] if key == prefs::PARAM => prefs::decode(value),
+        _ => None,
+    }
+}
+
+/// The list, filtered by the query string. Non-canonical queries are redirected to the canonical
+/// one, so every view has exactly one URL, except for views pinned with the `p` parameter. Without
+/// a query, the preferred view from the prefs cookie is shown, if there is one. A store picked by
+/// city is redirected to by its id.
+pub fn index(
+    state: &AppState,
+    query: &str,
+    prefs: Option<&str>,
+    tried: Option<&str>,
+    session: Option<&str>,
+    store: Option<&str>,
+) -> Response {
+    let pinned = pinned(query);
+    let mut view = pinned.clone().unwrap_or_else(|| View::from_query(query));
+    if let Some(store) = stores::from_query(state, query) {
+        view.store = Some(store);
+    }
+    if pinned.is_none() && view.to_query() != query {
+        return warp::reply::with_header(
+            StatusCode::MOVED_PERMANENTLY,
+            header::LOCATION,
+            view.link(),
+        )
+        .into_response();
+    }
+    if query.is_empty() {
+        view = prefs::view(prefs).unwrap_or_default();
+    }
+    if view.store.is_none() {
+        view.store = stores::default_with_stock(state, store);
+    }
+    show(state, &view, tried, session)
+}
+
+/// The list in `view`, with the visitor's tried products and ratings.
+pub fn show(state: &AppState, view: &View, tried: Option<&str>, session: Option<&str>) -> Response {
+    let assortment = state.config.assortment.view(AssortmentConfig::HTML);
+    let snapshot = match state.snapshot.read().unwrap().clone() {
+        Some(snapshot) => snapshot,
+        None => {
+            // The cached pages have everything in the catalog, like the ones rendered at refresh
+            // time
+            let page = match state.cached_pages.as_ref() {
+                Some(pages) if assortment.is_none() => pages.prerendered(view).unwrap_or(""),
+                _ => "",
+            };
+            return html(page.to_string()).into_response();
+        }
+    };
+    let ratings = match session::id(&state.cookie_key, session) {
+        Some(session) => ratings::mine(&*state.storage, &session),
+        None => Ok(HashMap::new()),
+    };
+    let ratings = match ratings {
+        Ok(ratings) => ratings,
+        Err(err) => {
+            error!("{}", err);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let tried = tried::parse(&state.cookie_key, tried);
+    // The page rendered at refresh time has nothing personal in it, but has everything in the
+    // catalog
+    if ratings.is_empty() && tried.is_empty() && assortment.is_none() {
+        if let Some(page) = snapshot.prerendered(view) {
+            return html(page.to_string()).into_response();
+        }
+    }
+    let mut drinks = view.apply(
+        &snapshot.catalog,
+        Some(&snapshot.index),
+        &tried,
+        &state.config.allergens,
+    );
+    if let Some(rules) = assortment {
+        for drinks in drinks.values_mut() {
+            drinks.retain(|drink| rules.allows(drink));
+        }
+    }
+    let (drinks, pages) = view.paginate(stock::filter(state, view, drinks));
+    match render::render_view(
+        &state.tera(),
+        &drinks,
+        &pages,
+        view,
+        &tried,
+        &ratings,
+        &snapshot,
+    ) {
+        Ok(page) => html(page).into_response(),
+        Err(err) => {
+            error!("{}", err);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
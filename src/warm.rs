@@ -0,0 +1,237 @@
+//! Keeps the most popular views of the list rendered and gzipped, so that the first visitor after
+//! a refresh doesn't wait for them. Visits to views are counted between refreshes, and each refresh
+//! renders the [`WARM_VIEWS`] most visited ones for the new catalog before it's shown. The warm
+//! pages are only served to visitors without anything personal in their cookies.
+
+use crate::config::AssortmentConfig;
+use crate::error::Result;
+use crate::refresh::Snapshot;
+use crate::render;
+use crate::session;
+use crate::state::AppState;
+use crate::stores;
+use crate::tried;
+use crate::view::View;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tracing::warn;
+use warp::http::header;
+use warp::reply::{html, Response};
+use warp::{Filter, Rejection, Reply};
+
+/// How many of the most visited views each refresh renders
+pub const WARM_VIEWS: usize = 20;
+/// Views counted between refreshes at most. Visits to others aren't counted once there are this
+/// many, so crawling every search can't grow the counts without end.
+pub const MAX_COUNTED: usize = 1000;
+
+/// A view rendered ahead of time.
+pub struct Page {
+    pub html: String,
+    pub gzip: Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct Warm {
+    /// Visits to each view since the last refresh, by query
+    hits: Mutex<HashMap<String, u64>>,
+    /// The hash of the snapshot the pages are of, and the pages by query
+    pages: RwLock<Option<(String, HashMap<String, Arc<Page>>)>>,
+    /// Visits served a warm page
+    served: AtomicU64,
+    /// Visits to views that weren't warm
+    missed: AtomicU64,
+}
+
+pub type SharedWarm = Arc<Warm>;
+
+impl Warm {
+    /// The warm page of the view with `query` in the snapshot with `hash`, if there is one.
+    fn page(&self, hash: &str, query: &str) -> Option<Arc<Page>> {
+        match &*self.pages.read().unwrap() {
+            Some((warm, pages)) if warm == hash => pages.get(query).cloned(),
+            _ => None,
+        }
+    }
+
+    /// Counts a visit to the view with `query`, unless there are [`MAX_COUNTED`] others.
+    pub fn visit(&self, query: &str) {
+        let mut hits = self.hits.lock().unwrap();
+        match hits.get_mut(query) {
+            Some(visits) => *visits += 1,
+            None if hits.len() < MAX_COUNTED => {
+                hits.insert(query.to_string(), 1);
+            }
+            None => {}
+        }
+    }
+
+    /// How many views have been counted since the last refresh.
+    pub fn counted(&self) -> usize {
+        self.hits.lock().unwrap().len()
+    }
+
+    /// The queries of the `n` most visited views, the most visited first, forgetting the visits.
+    fn popular(&self, n: usize) -> Vec<String> {
+        let hits = std::mem::take(&mut *self.hits.lock().unwrap());
+        let mut hits: Vec<(String, u64)> = hits.into_iter().collect();
+        hits.sort_by(|(q1, h1), (q2, h2)| h2.cmp(h1).then_with(|| q1.cmp(q2)));
+        hits.into_iter().take(n).map(|(query, _)| query).collect()
+    }
+
+    /// How many pages are warm and how many bytes they take, plain and gzipped.
+    pub fn sizes(&self) -> (usize, usize, usize) {
+        match &*self.pages.read().unwrap() {
+            Some((_, pages)) => pages.values().fold((0, 0, 0), |(n, plain, gzip), page| {
+                (n + 1, plain + page.html.len(), gzip + page.gzip.len())
+            }),
+            None => (0, 0, 0),
+        }
+    }
+
+    /// Visits that were served a warm page, and ones that weren't.
+    pub fn hits(&self) -> (u64, u64) {
+        (
+            self.served.load(Ordering::Relaxed),
+            self.missed.load(Ordering::Relaxed),
+        )
+    }
+}
+
+fn gzip(html: &str) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(html.as_bytes())?;
+    Ok(encoder.finish()?)
+}
+
+/// The page of `view` in `snapshot` for anyone, without tried products or ratings.
+fn render(state: &AppState, snapshot: &Snapshot, view: &View) -> Result<Page> {
+    let mut drinks = view.apply(
+        &snapshot.catalog,
+        Some(&snapshot.index),
+        &[],
+        &state.config.allergens,
+    );
+    if let Some(rules) = state.config.assortment.view(AssortmentConfig::HTML) {
+        for drinks in drinks.values_mut() {
+            drinks.retain(|drink| rules.allows(drink));
+        }
+    }
+    let (drinks, pages) = view.paginate(drinks);
+    let html = render::render_view(
+        &state.tera(),
+        &drinks,
+        &pages,
+        view,
+        &[],
+        &HashMap::new(),
+        snapshot,
+    )?;
+    let gzip = gzip(&html)?;
+    Ok(Page { html, gzip })
+}
+
+/// Renders the most visited views for `snapshot`, before it's shown. A view that fails is left
+/// cold, to be rendered on demand as usual.
+pub fn warm(state: &AppState, snapshot: &Snapshot) {
+    let mut pages = HashMap::new();
+    for query in state.warm.popular(WARM_VIEWS) {
+        let view = View::from_query(&query);
+        // These are rendered with the snapshot anyway
+        if snapshot.prerendered(&view).is_some() {
+            continue;
+        }
+        match render(state, snapshot, &view) {
+            Ok(page) => {
+                pages.insert(query, Arc::new(page));
+            }
+            Err(err) => warn!("Couldn't warm /?{}: {}", query, err),
+        }
+    }
+    *state.warm.pages.write().unwrap() = Some((snapshot.hash.clone(), pages));
+}
+
+/// Whether an `Accept-Encoding` of `encoding` takes gzip, which it doesn't with `q=0`.
+fn accepts_gzip(encoding: &str) -> bool {
+    let quality = |coding: &str| {
+        encoding.split(',').find_map(|accepted| {
+            let mut parts = accepted.split(';').map(str::trim);
+            if !parts.next()?.eq_ignore_ascii_case(coding) {
+                return None;
+            }
+            let q = parts
+                .filter_map(|param| param.strip_prefix("q="))
+                .next()
+                .map_or(Some(1.0), |q| q.parse::<f64>().ok());
+            Some(q.unwrap_or(0.0))
+        })
+    };
+    quality("gzip")
+        .or_else(|| quality("x-gzip"))
+        .or_else(|| quality("*"))
+        .map_or(false, |q| q > 0.0)
+}
+
+fn respond(page: &Page, encoding: Option<&str>) -> Response {
+    let reply = match encoding {
+        Some(encoding) if accepts_gzip(encoding) => {
+            let reply =
+                warp::reply::with_header(page.gzip.clone(), header::CONTENT_ENCODING, "gzip");
+            warp::reply::with_header(reply, header::CONTENT_TYPE, "text/html; charset=utf-8")
+                .into_response()
+        }
+        _ => html(page.html.clone()).into_response(),
+    };
+    warp::reply::with_header(reply, header::VARY, "Accept-Encoding").into_response()
+}
+
+/// Counts visits to views of the list and serves the warm ones, leaving the rest to
+/// [`crate::view::index`].
+pub fn route(state: AppState) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path::end()
+        .and(warp::query::raw())
+        .and(warp::header::optional::<String>("accept-encoding"))
+        .and(warp::cookie::optional(tried::COOKIE))
+        .and(warp::cookie::optional(session::COOKIE))
+        .and(warp::cookie::optional(stores::COOKIE))
+        .and_then(
+            move |query: String,
+                  encoding: Option<String>,
+                  tried: Option<String>,
+                  session: Option<String>,
+                  store: Option<String>| {
+                let state = state.clone();
+                async move {
+                    // Only canonical queries are shown, the rest are redirected, and without a
+                    // query the page rendered at refresh time or the preferred view is shown.
+                    // Stock changes between refreshes, so views of a store aren't kept.
+                    let view = View::from_query(&query);
+                    if query.is_empty() || view.to_query() != query || view.store.is_some() {
+                        return Err(warp::reject::not_found());
+                    }
+                    state.warm.visit(&query);
+                    let snapshot = state.snapshot.read().unwrap().clone();
+                    let page = match (snapshot, tried, session, store) {
+                        (Some(snapshot), None, None, None) => {
+                            state.warm.page(&snapshot.hash, &query)
+                        }
+                        _ => None,
+                    };
+                    match page {
+                        Some(page) => {
+                            state.warm.served.fetch_add(1, Ordering::Relaxed);
+                            Ok(respond(&page, encoding.as_deref()))
+                        }
+                        None => {
+                            state.warm.missed.fetch_add(1, Ordering::Relaxed);
+                            Err(warp::reject::not_found())
+                        }
+                    }
+                }
+            },
+        )
+}
@@ -0,0 +1,220 @@
+//! POSTs a JSON summary of each refresh to a configured URL.
+//!
+//! If a secret is configured, the body is signed with HMAC-SHA256 and the signature is sent in the
+//! `X-Apk-Signature` header as `sha256=<hex>`.
+//!
+//! With a template configured, the body is rendered from it instead, so the same webhook can feed
+//! services that want some other format.
+//!
+//! With `min_apk` or `top` configured, only refreshes with notable products are posted: new ones
+//! with at least that APK, and ones entering the top of their category. They're in `notable`.
+
+use crate::catalog::{self, Catalog, CATEGORIES};
+use crate::config::WebhookConfig;
+use crate::diff::{self, PriceChange};
+use crate::error::Result;
+use crate::notify::{Notifier, RefreshEvent};
+use crate::signing;
+use crate::state::AppState;
+use crate::status::unix_time;
+use crate::units::{Apk, Sek};
+use async_trait::async_trait;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Serialize;
+use std::time::Duration;
+use systemet::Product;
+use tera::{Context, Tera};
+use tracing::warn;
+
+pub const SIGNATURE_HEADER: &str = "X-Apk-Signature";
+const TOP_CHANGES: usize = 10;
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    /// Unix timestamp of the refresh
+    pub timestamp: u64,
+    pub snapshot: String,
+    pub products: usize,
+    pub new_products: Vec<NewProduct>,
+    pub top_changes: Vec<Change>,
+    /// The products that beat the thresholds, if there are any
+    pub notable: Vec<NewProduct>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NewProduct {
+    pub id: String,
+    pub name: String,
+    pub apk: Apk,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Change {
+    pub id: String,
+    pub name: String,
+    pub old_price: Sek,
+    pub new_price: Sek,
+    pub old_apk: Apk,
+    pub new_apk: Apk,
+}
+
+impl NewProduct {
+    fn new(drink: &Product) -> NewProduct {
+        NewProduct {
+            id: catalog::id(drink).to_string(),
+            name: catalog::name(drink).to_string(),
+            apk: catalog::apk(drink),
+        }
+    }
+}
+
+impl Summary {
+    pub fn new(event: &RefreshEvent, notable: Vec<NewProduct>) -> Summary {
+        let catalog = &event.snapshot.catalog;
+        Summary {
+            timestamp: unix_time(event.snapshot.updated_at),
+            snapshot: event.snapshot.hash.clone(),
+            products: catalog.len(),
+            new_products: event
+                .diff
+                .added
+                .iter()
+                .filter_map(|id| catalog.find(id))
+                .map(NewProduct::new)
+                .collect(),
+            top_changes: event
+                .diff
+                .top_changes(TOP_CHANGES)
+                .into_iter()
+                .map(|change| Change::new(catalog, change))
+                .collect(),
+            notable,
+        }
+    }
+}
+
+impl Change {
+    fn new(catalog: &Catalog, change: &PriceChange) -> Change {
+        Change {
+            id: change.id.clone(),
+            name: catalog
+                .find(&change.id)
+                .map_or("", catalog::name)
+                .to_string(),
+            old_price: change.old_price,
+            new_price: change.new_price,
+            old_apk: change.old_apk,
+            new_apk: change.new_apk,
+        }
+    }
+}
+
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: String,
+    secret: Option<SecretString>,
+    retries: u32,
+    template: Option<String>,
+    content_type: String,
+    min_apk: Option<f64>,
+    top: Option<usize>,
+}
+
+impl WebhookNotifier {
+    pub fn new(config: WebhookConfig) -> WebhookNotifier {
+        WebhookNotifier {
+            client: reqwest::Client::new(),
+            url: config.url,
+            secret: config.secret,
+            retries: config.retries,
+            template: config.template,
+            content_type: config
+                .content_type
+                .unwrap_or_else(|| "application/json".to_string()),
+            min_apk: config.min_apk,
+            top: config.top,
+        }
+    }
+
+    /// The new products with at least `min_apk`, and the products that entered the top `top` of
+    /// their category.
+    pub fn notable<'a>(&self, event: &'a RefreshEvent) -> Vec<&'a Product> {
+        let catalog = &event.snapshot.catalog;
+        let mut notable: Vec<&Product> = match self.min_apk {
+            Some(min_apk) => event
+                .diff
+                .added
+                .iter()
+                .filter_map(|id| catalog.find(id))
+                .filter(|drink| catalog::apk(drink).0 >= min_apk)
+                .collect(),
+            None => Vec::new(),
+        };
+        if let (Some(top), Some(previous)) = (self.top, &event.previous) {
+            for &category in CATEGORIES.iter() {
+                for drink in diff::entered_top(&previous.catalog, catalog, category, top) {
+                    if !notable.iter().any(|d| catalog::id(d) == catalog::id(drink)) {
+                        notable.push(drink);
+                    }
+                }
+            }
+        }
+        notable
+    }
+
+    /// The summary as JSON, or rendered with the configured template.
+    pub fn body(&self, event: &RefreshEvent) -> Result<Vec<u8>> {
+        let notable = self.notable(event).into_iter().map(NewProduct::new);
+        let summary = Summary::new(event, notable.collect());
+        match &self.template {
+            Some(template) => {
+                let mut context = Context::from_serialize(&summary)?;
+                context.insert("diff", &event.diff);
+                Ok(Tera::one_off(template, &context, false)?.into_bytes())
+            }
+            None => Ok(serde_json::to_vec(&summary)?),
+        }
+    }
+
+    async fn post(&self, body: &[u8]) -> Result<()> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, &self.content_type)
+            .body(body.to_vec());
+        if let Some(secret) = &self.secret {
+            let signature = signing::hmac_sha256(secret.expose_secret().as_bytes(), body);
+            request = request.header(SIGNATURE_HEADER, format!("sha256={}", signature));
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    async fn notify(&self, _: &AppState, event: &RefreshEvent) -> Result<()> {
+        let thresholds = self.min_apk.is_some() || self.top.is_some();
+        if thresholds && self.notable(event).is_empty() {
+            return Ok(());
+        }
+        let body = self.body(event)?;
+        let mut attempt = 0;
+        loop {
+            match self.post(&body).await {
+                Ok(()) => return Ok(()),
+                Err(err) if attempt < self.retries => {
+                    attempt += 1;
+                    warn!("Webhook failed, retrying: {}", err);
+                    tokio::time::delay_for(RETRY_DELAY).await;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
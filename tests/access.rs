@@ -0,0 +1,34 @@
+use apk::access::{client_address, remote};
+use std::net::IpAddr;
+use warp::http::{HeaderMap, HeaderValue};
+
+#[test]
+fn honors_forwarded_for() {
+    let peer = "10.0.0.2:51234".parse().ok();
+    let mut headers = HeaderMap::new();
+    assert_eq!(remote(&headers, peer).as_deref(), Some("10.0.0.2"));
+    assert_eq!(remote(&headers, None), None);
+
+    headers.insert(
+        "x-forwarded-for",
+        HeaderValue::from_static("203.0.113.7, 10.0.0.1"),
+    );
+    assert_eq!(remote(&headers, peer).as_deref(), Some("203.0.113.7"));
+}
+
+#[test]
+fn trusts_forwarded_for_only_from_proxies() {
+    let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "x-forwarded-for",
+        HeaderValue::from_static("198.51.100.1, 203.0.113.7"),
+    );
+    let client = |peer: &str| {
+        let peer = format!("{}:51234", peer).parse().ok();
+        client_address(&[proxy], &headers, peer).map(|client| client.to_string())
+    };
+    assert_eq!(client("10.0.0.2").as_deref(), Some("10.0.0.2"));
+    assert_eq!(client("10.0.0.1").as_deref(), Some("203.0.113.7"));
+    assert_eq!(client_address(&[proxy], &headers, None), None);
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use apk::admin::authorize;
+use apk::config::{AdminConfig, AdminToken};
+use common::{get, get_admin, with_admin};
+use secrecy::SecretString;
+
+#[test]
+fn authorizes_bearer_tokens() {
+    let config = AdminConfig {
+        tokens: vec![AdminToken {
+            name: "falk".to_string(),
+            token: SecretString::new("hemligt".to_string()),
+        }],
+    };
+    assert_eq!(
+        authorize(&config, Some("Bearer hemligt")).map(|token| token.name.as_str()),
+        Some("falk")
+    );
+    assert!(authorize(&config, Some("Bearer fel")).is_none());
+    assert!(authorize(&config, Some("hemligt")).is_none());
+    assert!(authorize(&config, None).is_none());
+}
+
+#[tokio::test]
+async fn hides_admin_pages_without_tokens() {
+    let (status, _) = get(Default::default(), "/admin/status").await;
+    assert_eq!(status, 404);
+    let (status, _) = get(Default::default(), "/metrics").await;
+    assert_eq!(status, 200);
+}
+
+#[tokio::test]
+async fn requires_an_admin_token() {
+    let state = with_admin(Default::default());
+    let (status, _) = get(state.clone(), "/admin/status").await;
+    assert_eq!(status, 401);
+    let (status, _) = get(state.clone(), "/admin/audit").await;
+    assert_eq!(status, 401);
+    let (status, _) = get(state.clone(), "/metrics").await;
+    assert_eq!(status, 200);
+
+    let routes = apk::server::routes(state.clone());
+    let response = warp::test::request()
+        .method("POST")
+        .path("/admin/recategorize")
+        .header("authorization", "Bearer fel")
+        .header("x-forwarded-user", "falk")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(response.headers()["www-authenticate"], "Bearer");
+
+    let (status, _) = get_admin(state, "/admin/status").await;
+    assert_eq!(status, 200);
+}
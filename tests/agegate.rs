@@ -0,0 +1,49 @@
+mod common;
+
+use apk::config::Config;
+use apk::server::ApkServer;
+use common::{fixture, source, upstream};
+
+#[tokio::test]
+async fn asks_for_age_before_showing_list() {
+    let upstream = upstream(vec![fixture()]).await;
+    let config = Config {
+        age_gate: true,
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let routes = server.routes();
+
+    let response = warp::test::request()
+        .path("/?kategori=sprit")
+        .reply(&routes)
+        .await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains("Jag har fyllt 20"));
+    assert!(body.contains("kategori=sprit"));
+    assert!(!body.contains("Explorer Vodka"));
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/age")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body("back=%2F%3Fkategori%3Dsprit")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 303);
+    assert_eq!(response.headers()["location"], "/?kategori=sprit");
+    let set_cookie = response.headers()["set-cookie"].to_str().unwrap();
+
+    let response = warp::test::request()
+        .path("/?kategori=sprit")
+        .header("cookie", set_cookie.split(';').next().unwrap())
+        .reply(&routes)
+        .await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains("Explorer Vodka"));
+}
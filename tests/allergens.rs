@@ -0,0 +1,73 @@
+mod common;
+
+use apk::allergens::tags;
+use apk::catalog::{Catalog, Category};
+use apk::config::{AllergenConfig, Config};
+use apk::server::ApkServer;
+use common::{fixture, get, source, upstream};
+use serde_json::json;
+
+fn rules() -> Vec<AllergenConfig> {
+    vec![
+        AllergenConfig {
+            tag: "Gluten".to_string(),
+            categories: Some(vec![Category::Beer]),
+            words: Vec::new(),
+            except: vec!["Glutenfri".to_string()],
+        },
+        AllergenConfig {
+            tag: "Sulfiter".to_string(),
+            categories: Some(vec![Category::Wine, Category::Cider]),
+            words: Vec::new(),
+            except: Vec::new(),
+        },
+    ]
+}
+
+fn with_gluten_free() -> Vec<serde_json::Value> {
+    let mut products = fixture();
+    let mut gluten_free = products[0].clone();
+    gluten_free["ProductId"] = json!("1003");
+    gluten_free["ProductNumber"] = json!("1003");
+    gluten_free["ProductNameBold"] = json!("Glutenfri Lager");
+    products.push(gluten_free);
+    products
+}
+
+#[test]
+fn tags_by_rules() {
+    let rules = rules();
+    let catalog = Catalog::build(serde_json::from_value(with_gluten_free().into()).unwrap());
+    let tags_of = |id: &str| tags(&rules, catalog.find(id).unwrap());
+    assert_eq!(tags_of("1001"), vec!["Gluten"]);
+    assert!(tags_of("1003").is_empty());
+    assert_eq!(tags_of("2001"), vec!["Sulfiter"]);
+    assert!(tags_of("4001").is_empty());
+}
+
+#[tokio::test]
+async fn filters_gluten_free_beer_and_shows_tags() {
+    let upstream = upstream(vec![with_gluten_free()]).await;
+    let config = Config {
+        allergens: rules(),
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let state = server.state().clone();
+
+    let (_, body) = get(state.clone(), "/?glutenfri=ja").await;
+    assert!(body.contains("Glutenfri Lager"));
+    assert!(!body.contains("Norrlands Guld"));
+    assert!(!body.contains("Castillo de Gredos"));
+
+    let (status, body) = get(state.clone(), "/produkt/2001").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Sulfiter"));
+    let (status, _) = get(state, "/produkt/9999").await;
+    assert_eq!(status, 404);
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use apk::anomaly::check;
+use apk::catalog::Catalog;
+use apk::server::ApkServer;
+use apk::source::ProductSource;
+use common::fixture;
+use serde_json::Value;
+use std::sync::Mutex;
+use systemet::Product;
+
+fn products(records: Vec<Value>) -> Vec<Product> {
+    serde_json::from_value(records.into()).unwrap()
+}
+
+#[test]
+fn accepts_ordinary_changes() {
+    let current = Catalog::build(products(fixture()));
+    let mut records = fixture();
+    records[0]["Price"] = 12.9.into();
+    assert!(check(&current, &Catalog::build(products(records))).is_empty());
+}
+
+#[test]
+fn flags_shrinking_and_price_jumps() {
+    let current = Catalog::build(products(fixture()));
+    let next = Catalog::build(products(fixture()[..2].to_vec()));
+    let problems = check(&current, &next);
+    assert!(problems.iter().any(|p| p.starts_with("antalet produkter")));
+    assert!(problems.contains(&"Sprit blev tom".to_string()));
+
+    let mut records = fixture();
+    for record in &mut records {
+        let price = record["Price"].as_f64().unwrap();
+        record["Price"] = (price * 10.0).into();
+    }
+    let problems = check(&current, &Catalog::build(products(records)));
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].starts_with("medianpriset"));
+}
+
+/// Hands out the given product lists in order.
+struct Sequence(Mutex<Vec<Vec<Product>>>);
+
+#[async_trait::async_trait]
+impl ProductSource for Sequence {
+    async fn fetch_products(&self) -> apk::Result<Vec<Product>> {
+        Ok(self.0.lock().unwrap().remove(0))
+    }
+}
+
+#[tokio::test]
+async fn keeps_current_list_on_suspicious_refresh() {
+    let source = Sequence(Mutex::new(vec![
+        products(fixture()),
+        products(fixture()[..1].to_vec()),
+    ]));
+    let server = ApkServer::builder().source(source).build().unwrap();
+    server.update().await.unwrap();
+
+    let err = server.update().await.unwrap_err();
+    assert_eq!(err.category(), "anomaly");
+    let snapshot = server.state().snapshot.read().unwrap().clone().unwrap();
+    assert_eq!(snapshot.catalog.len(), 5);
+    assert_eq!(server.state().status.read().unwrap().errors["anomaly"], 1);
+}
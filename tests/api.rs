@@ -0,0 +1,201 @@
+mod common;
+
+use apk::api::{authorize, record, usage};
+use apk::config::{AdminConfig, AdminToken, ApiConfig, ApiToken, Config};
+use apk::server::ApkServer;
+use apk::storage::MemoryStorage;
+use common::{fixture, source, upstream};
+use secrecy::SecretString;
+use std::time::{Duration, UNIX_EPOCH};
+
+fn token(name: &str, token: &str, quota: Option<u64>) -> ApiToken {
+    ApiToken {
+        name: name.to_string(),
+        token: SecretString::new(token.to_string()),
+        quota,
+    }
+}
+
+#[test]
+fn counts_requests_per_day() {
+    let storage = MemoryStorage::default();
+    let token = token("krogen", "hemligt", Some(2));
+    let day = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+
+    assert!(record(&storage, &token, day).unwrap());
+    assert!(record(&storage, &token, day).unwrap());
+    assert!(!record(&storage, &token, day).unwrap());
+    let next_day = day + Duration::from_secs(24 * 60 * 60);
+    assert!(record(&storage, &token, next_day).unwrap());
+
+    let usage = &usage(&storage).unwrap()["krogen"];
+    assert_eq!(usage.day, "2020-09-14");
+    assert_eq!((usage.today, usage.total, usage.rejected), (1, 3, 1));
+}
+
+#[test]
+fn authorizes_bearer_tokens() {
+    let config = ApiConfig {
+        tokens: vec![token("krogen", "hemligt", None)],
+    };
+    assert_eq!(
+        authorize(&config, Some("Bearer hemligt")).map(|token| token.name.as_str()),
+        Some("krogen")
+    );
+    assert!(authorize(&config, Some("Bearer fel")).is_none());
+    assert!(authorize(&config, Some("hemligt")).is_none());
+    assert!(authorize(&config, None).is_none());
+}
+
+#[tokio::test]
+async fn enforces_quota() {
+    let upstream = upstream(vec![fixture()]).await;
+    let config = Config {
+        api: Some(ApiConfig {
+            tokens: vec![token("krogen", "hemligt", Some(1))],
+        }),
+        admin: Some(AdminConfig {
+            tokens: vec![AdminToken {
+                name: "falk".to_string(),
+                token: SecretString::new("admin-hemligt".to_string()),
+            }],
+        }),
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .storage(MemoryStorage::default())
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let routes = server.routes();
+
+    let request = || {
+        warp::test::request()
+            .path("/api/products?kategori=%C3%B6l")
+            .header("authorization", "Bearer hemligt")
+    };
+    let response = request().reply(&routes).await;
+    assert_eq!(response.status(), 200);
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains("Norrlands Guld"));
+    assert!(!body.contains("Explorer Vodka"));
+
+    let response = request().reply(&routes).await;
+    assert_eq!(response.status(), 429);
+    assert!(response.headers().contains_key("retry-after"));
+
+    let response = warp::test::request()
+        .path("/api/products")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 401);
+
+    let response = warp::test::request()
+        .path("/admin/api")
+        .header("authorization", "Bearer admin-hemligt")
+        .reply(&routes)
+        .await;
+    let stats: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(stats["krogen"]["today"], 1);
+    assert_eq!(stats["krogen"]["rejected"], 1);
+    assert_eq!(stats["krogen"]["quota"], 1);
+}
+
+#[tokio::test]
+async fn answers_errors_in_an_envelope() {
+    let upstream = upstream(vec![fixture()]).await;
+    let config = Config {
+        api: Some(ApiConfig {
+            tokens: vec![token("krogen", "hemligt", None)],
+        }),
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    let routes = server.routes();
+    let request = |path: &str| {
+        warp::test::request()
+            .path(path)
+            .header("authorization", "Bearer hemligt")
+    };
+    let error = |body: &[u8]| {
+        let body: serde_json::Value = serde_json::from_slice(body).unwrap();
+        body["error"].clone()
+    };
+
+    let response = request("/api/products").reply(&routes).await;
+    assert_eq!(response.status(), 503);
+    let not_ready = error(response.body());
+    assert_eq!(not_ready["code"], "not_ready");
+    assert_eq!(
+        response.headers()["x-request-id"].to_str().unwrap(),
+        not_ready["request_id"]
+    );
+    let response = request("/api/drinks").reply(&routes).await;
+    assert_eq!(response.status(), 503);
+    assert_eq!(error(response.body())["code"], "not_ready");
+
+    server.update().await.unwrap();
+    let response = request("/api/cost?standard_drinks=flera")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(error(response.body())["code"], "invalid_request");
+    let response = warp::test::request()
+        .path("/api/movers")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 401);
+    let unauthorized = error(response.body());
+    assert_eq!(unauthorized["code"], "unauthorized");
+    assert!(unauthorized["message"].is_string());
+}
+
+#[tokio::test]
+async fn looks_up_many_products() {
+    let upstream = upstream(vec![fixture()]).await;
+    let config = Config {
+        api: Some(ApiConfig {
+            tokens: vec![token("skannern", "hemligt", None)],
+        }),
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let routes = server.routes();
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/api/products/lookup")
+        .header("authorization", "Bearer hemligt")
+        .json(&serde_json::json!({ "ids": ["100203", "4001", "9999"] }))
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+    let products = body["products"].as_array().unwrap();
+    assert_eq!(products.len(), 2);
+    assert_eq!(products[0]["name"], "Mariestads");
+    assert_eq!(products[0]["rank"], 2);
+    assert_eq!(products[1]["name"], "Explorer Vodka");
+    assert_eq!(products[1]["rank"], 1);
+    assert!(products[1]["basen_apk"].is_number());
+    assert_eq!(body["missing"], serde_json::json!(["9999"]));
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/api/products/lookup")
+        .json(&serde_json::json!({ "ids": ["1001"] }))
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 401);
+}
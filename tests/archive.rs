@@ -0,0 +1,31 @@
+mod common;
+
+use apk::archive::{Archive, VERSION};
+use apk::server::ApkServer;
+use apk::source::{parse_records, ArchiveSource};
+use common::{fixture, get};
+use std::time::{Duration, UNIX_EPOCH};
+
+#[tokio::test]
+async fn serves_archives() {
+    let path = std::env::temp_dir().join(format!("apk-archive-{}.json", std::process::id()));
+    let at = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    Archive::new(parse_records(fixture()), at)
+        .write(&path)
+        .unwrap();
+
+    let archive = Archive::read(&path).unwrap();
+    assert_eq!(archive.version, VERSION);
+    assert_eq!(archive.exported_at, 1_600_000_000);
+    assert_eq!(archive.products.len(), parse_records(fixture()).len());
+
+    let server = ApkServer::builder()
+        .source(ArchiveSource::new(&path))
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let (_, body) = get(server.state().clone(), "/").await;
+    assert!(body.contains("Norrlands Guld"));
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use apk::catalog::AssortmentRules;
+use apk::config::{ApiConfig, ApiToken, AssortmentConfig, Config};
+use apk::server::ApkServer;
+use common::{fixture, get, source, upstream};
+use secrecy::SecretString;
+
+#[test]
+fn reads_rules_from_config() {
+    let config: Config = toml::from_str(
+        r#"
+        [assortment]
+        exclude = ["TSLS"]
+
+        [assortment.views.api]
+        include = ["FS"]
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.assortment.rules.exclude, ["TSLS"]);
+    assert!(config.assortment.rules.include.is_empty());
+    let api = config.assortment.view(AssortmentConfig::API).unwrap();
+    assert_eq!(api.include, ["FS"]);
+    assert_eq!(
+        Config::default().assortment.rules,
+        AssortmentRules::default()
+    );
+}
+
+#[tokio::test]
+async fn views_can_leave_out_more() {
+    let upstream = upstream(vec![fixture()]).await;
+    let mut assortment = AssortmentConfig {
+        rules: AssortmentRules {
+            include: Vec::new(),
+            exclude: Vec::new(),
+        },
+        ..AssortmentConfig::default()
+    };
+    assortment.views.insert(
+        AssortmentConfig::API.to_string(),
+        AssortmentRules::default(),
+    );
+    let config = Config {
+        assortment,
+        api: Some(ApiConfig {
+            tokens: vec![ApiToken {
+                name: "krogen".to_string(),
+                token: SecretString::new("hemligt".to_string()),
+                quota: None,
+            }],
+        }),
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+
+    let (_, body) = get(server.state().clone(), "/").await;
+    assert!(body.contains("Lokal Lager"));
+
+    let response = warp::test::request()
+        .path("/api/products")
+        .header("authorization", "Bearer hemligt")
+        .reply(&server.routes())
+        .await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains("Norrlands Guld"));
+    assert!(!body.contains("Lokal Lager"));
+}
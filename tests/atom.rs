@@ -0,0 +1,40 @@
+mod common;
+
+use apk::server::ApkServer;
+use common::{fixture, get, mount_page, source, upstream};
+use serde_json::json;
+use std::time::Duration;
+
+#[tokio::test]
+async fn follows_the_top() {
+    let mut before = fixture();
+    let castillo = before.remove(2);
+    let upstream = upstream(vec![before.clone()]).await;
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+
+    let (status, body) = get(server.state().clone(), "/feed.atom").await;
+    assert_eq!(status, 200);
+    assert!(!body.contains("<entry>"));
+
+    let mut after = before;
+    after[0]["Price"] = json!(12.9);
+    after[1]["Price"] = json!(99.0);
+    after.push(castillo);
+    upstream.reset().await;
+    mount_page(&upstream, 1, after).await;
+    mount_page(&upstream, 2, Vec::new()).await;
+    server.update().await.unwrap();
+    // The entries are recorded by a notifier, in the background
+    tokio::time::delay_for(Duration::from_millis(200)).await;
+
+    let (status, body) = get(server.state().clone(), "/feed.atom").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("<feed xmlns=\"http://www.w3.org/2005/Atom\">"));
+    assert!(body.contains("Ny i topp 20: Castillo de Gredos"));
+    assert!(body.contains("Billigare: Norrlands Guld: 14.90 kr → 12.90 kr"));
+    assert!(!body.contains("Mariestads"));
+}
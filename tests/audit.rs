@@ -0,0 +1,48 @@
+mod common;
+
+use apk::audit::Trigger;
+use apk::server::ApkServer;
+use common::{fixture, source, upstream, with_admin, ADMIN_TOKEN};
+use serde_json::Value;
+
+#[tokio::test]
+async fn logs_snapshot_swaps() {
+    let upstream = upstream(vec![fixture()]).await;
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .build()
+        .unwrap();
+    let routes = apk::server::routes(with_admin(server.state().clone()));
+    server.update().await.unwrap();
+    server.update().await.unwrap();
+    let response = warp::test::request()
+        .method("POST")
+        .path("/admin/recategorize")
+        .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 200);
+
+    let response = warp::test::request()
+        .path("/admin/audit")
+        .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 200);
+    let entries: Vec<Value> = serde_json::from_slice(response.body()).unwrap();
+    let triggers: Vec<&str> = entries
+        .iter()
+        .map(|entry| entry["trigger"].as_str().unwrap())
+        .collect();
+    assert_eq!(triggers, ["refresh", "refresh", "recategorize"]);
+    assert_eq!(entries[0]["source"], format!("http {}", upstream.uri()));
+    assert_eq!(entries[0]["products"], 5);
+    assert_eq!(entries[0]["by"], Value::Null);
+    assert_eq!(entries[2]["source"], "cache");
+    assert_eq!(entries[2]["by"], "falk");
+    assert_eq!(entries[2]["hash"], entries[0]["hash"]);
+
+    let entries = apk::audit::entries(&*server.state().storage).unwrap();
+    assert_eq!(entries[1].trigger, Trigger::Refresh);
+    assert_eq!(entries[1].added, 0);
+}
@@ -0,0 +1,14 @@
+use apk::back::is_local;
+
+#[test]
+fn only_goes_back_on_the_site() {
+    assert!(is_local("/"));
+    assert!(is_local("/?kategori=sprit"));
+    assert!(is_local("/produkt/1001"));
+    assert!(!is_local(""));
+    assert!(!is_local("//evil.example"));
+    assert!(!is_local("/\\evil.example"));
+    assert!(!is_local("/ol\\..\\\\evil.example"));
+    assert!(!is_local("/\t/evil.example"));
+    assert!(!is_local("https://evil.example/"));
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use apk::refresh::{backoff, RETRY_INTERVAL, UPDATE_INTERVAL};
+use apk::ApkServer;
+use common::{fixture, get, mount_page, source};
+use std::time::Duration;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[test]
+fn doubles_up_to_the_interval() {
+    let delays: Vec<u64> = (1..=4).map(|n| backoff(n, UPDATE_INTERVAL)).collect();
+    assert_eq!(
+        delays,
+        vec![
+            RETRY_INTERVAL,
+            RETRY_INTERVAL * 2,
+            RETRY_INTERVAL * 4,
+            RETRY_INTERVAL * 8
+        ]
+    );
+    assert_eq!(backoff(20, UPDATE_INTERVAL), UPDATE_INTERVAL);
+    assert_eq!(backoff(u32::MAX, UPDATE_INTERVAL), UPDATE_INTERVAL);
+}
+
+#[tokio::test]
+async fn reports_backoff_in_metrics_until_upstream_is_back() {
+    let upstream = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&upstream)
+        .await;
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .interval(1)
+        .build()
+        .unwrap();
+    let state = server.state().clone();
+    tokio::spawn(state.refresher.clone().unwrap().run(state.clone()));
+    tokio::time::delay_for(Duration::from_millis(300)).await;
+
+    let (_, body) = get(state.clone(), "/metrics").await;
+    assert!(body.contains("apk_refresh_failures 1\n"));
+    assert!(body.contains("apk_refresh_backoff_seconds 1\n"));
+
+    upstream.reset().await;
+    mount_page(&upstream, 1, fixture()).await;
+    mount_page(&upstream, 2, Vec::new()).await;
+    tokio::time::delay_for(Duration::from_millis(1200)).await;
+
+    let (_, body) = get(state, "/metrics").await;
+    assert!(body.contains("apk_refresh_failures 0\n"));
+    assert!(body.contains("apk_refresh_backoff_seconds 0\n"));
+}
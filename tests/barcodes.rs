@@ -0,0 +1,52 @@
+mod common;
+
+use apk::barcodes;
+use apk::config::Config;
+use apk::server::ApkServer;
+use common::{fixture, get, source, upstream};
+use std::fs;
+
+#[tokio::test]
+async fn finds_products_by_barcode() {
+    let path = std::env::temp_dir().join(format!("apk-barcodes-{}.csv", std::process::id()));
+    fs::write(
+        &path,
+        "# EAN, produkt\n7310401000103,100103\n7311100002003, 4001\n7300000000000,9999\n",
+    )
+    .unwrap();
+    assert_eq!(barcodes::read(&path).unwrap().len(), 3);
+
+    let upstream = upstream(vec![fixture()]).await;
+    let config = Config {
+        barcodes: Some(path.clone()),
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let snapshot = server.state().snapshot.read().unwrap().clone().unwrap();
+    let find = |code| snapshot.find(code).map(apk::catalog::name);
+    assert_eq!(find("7310401000103"), Some("Norrlands Guld"));
+    assert_eq!(find("7311100002003"), Some("Explorer Vodka"));
+    // Not in the catalog
+    assert_eq!(find("7300000000000"), None);
+
+    let (status, body) = get(server.state().clone(), "/api/suggest?q=7311100002003").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Explorer Vodka"));
+    assert!(!body.contains("Norrlands Guld"));
+}
+
+#[test]
+fn rejects_malformed_lines() {
+    let path = std::env::temp_dir().join(format!("apk-bad-barcodes-{}.csv", std::process::id()));
+    fs::write(&path, "7310401000103,100103\nnorrlands,100103\n").unwrap();
+    let result = barcodes::read(&path);
+    fs::remove_file(&path).unwrap();
+    assert!(result.is_err());
+}
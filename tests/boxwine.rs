@@ -0,0 +1,34 @@
+mod common;
+
+use apk::catalog::{box_apk, price_per_75cl};
+use apk::units::Sek;
+use common::{fixture, get, refresh, upstream};
+use serde_json::json;
+
+fn with_box() -> Vec<serde_json::Value> {
+    let mut products = fixture();
+    let mut boxed = products[2].clone();
+    boxed["ProductId"] = json!("2002");
+    boxed["ProductNumber"] = json!("2002");
+    boxed["ProductNameBold"] = json!("Lådvin Rött");
+    boxed["BottleTextShort"] = json!("Bag in box");
+    boxed["Price"] = json!(299.0);
+    products.push(boxed);
+    products
+}
+
+#[tokio::test]
+async fn compares_bottles_with_boxes() {
+    let upstream = upstream(vec![with_box()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let snapshot = state.snapshot.read().unwrap().clone().unwrap();
+
+    let average = box_apk(&snapshot.catalog).unwrap();
+    assert!((average.0 - 3000.0 * 0.13 / 299.0).abs() < 1e-9);
+    let castillo = snapshot.catalog.find("2001").unwrap();
+    assert_eq!(price_per_75cl(castillo), Sek(49.75));
+
+    let (_, body) = get(state, "/").await;
+    assert!(body.contains("49.75 kr/75 cl"));
+    assert_eq!(body.matches("Slår boxen!").count(), 1);
+}
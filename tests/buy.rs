@@ -0,0 +1,36 @@
+mod common;
+
+use common::{fixture, get, refresh, upstream};
+
+#[tokio::test]
+async fn redirects_to_systembolaget_and_counts() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+
+    let response = warp::test::request()
+        .path("/buy/1001")
+        .reply(&apk::server::routes(state.clone()))
+        .await;
+    assert_eq!(response.status(), 302);
+    assert_eq!(
+        response.headers()["location"],
+        "https://www.systembolaget.se/100103/"
+    );
+    // Coming products aren't listed yet, so they're linked by number
+    let response = warp::test::request()
+        .path("/buy/123456")
+        .reply(&apk::server::routes(state.clone()))
+        .await;
+    assert_eq!(
+        response.headers()["location"],
+        "https://www.systembolaget.se/123456/"
+    );
+    let (status, _) = get(state.clone(), "/buy/evil.example").await;
+    assert_eq!(status, 404);
+
+    let (_, body) = get(state.clone(), "/").await;
+    assert!(body.contains("href=\"/buy/1001\""));
+    assert!(!body.contains("systembolaget.se/100103"));
+    let (_, body) = get(state, "/metrics").await;
+    assert!(body.contains("apk_buy_redirects_total 2\n"));
+}
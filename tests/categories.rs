@@ -0,0 +1,23 @@
+mod common;
+
+use common::{fixture, get, refresh, upstream};
+
+#[tokio::test]
+async fn serves_category_pages() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+
+    let (status, body) = get(state.clone(), "/ol").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Norrlands Guld"));
+    assert!(body.contains("Mariestads"));
+    assert!(!body.contains("Explorer Vodka"));
+
+    let (status, body) = get(state.clone(), "/sprit").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Explorer Vodka"));
+    assert!(!body.contains("Norrlands Guld"));
+
+    let snapshot = state.snapshot.read().unwrap().clone().unwrap();
+    assert_eq!(snapshot.category_pages.len(), 5);
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use apk::server::ApkServer;
+use common::{fixture, get, mount_page, source, upstream};
+use serde_json::json;
+use std::time::Duration;
+
+#[tokio::test]
+async fn shows_changes() {
+    let mut before = fixture();
+    let castillo = before.remove(2);
+    let upstream = upstream(vec![before.clone()]).await;
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+
+    let (status, body) = get(server.state().clone(), "/changes").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Inga ändringar än"));
+
+    let mut after = before;
+    after[0]["Price"] = json!(12.9);
+    after.remove(1);
+    after.push(castillo);
+    upstream.reset().await;
+    mount_page(&upstream, 1, after).await;
+    mount_page(&upstream, 2, Vec::new()).await;
+    server.update().await.unwrap();
+    // The changes are recorded by a notifier, in the background
+    tokio::time::delay_for(Duration::from_millis(200)).await;
+
+    let (status, body) = get(server.state().clone(), "/changes").await;
+    assert_eq!(status, 200);
+    let added = body.find("Castillo de Gredos").unwrap();
+    let removed = body.find("Mariestads").unwrap();
+    let changed = body.find("Norrlands Guld").unwrap();
+    assert!(added < removed && removed < changed);
+    assert!(body.contains("14.90 kr"));
+    assert!(body.contains("12.90 kr"));
+}
@@ -0,0 +1,276 @@
+//! Synthetic but realistic catalogs, for exercising the pipeline at scale without real API data.
+//! The same seed always gives the same catalog, so a failing test can be rerun as is.
+
+use rand::distributions::WeightedIndex;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde_json::{json, Value};
+
+/// The kinds of broken or unusual products mixed in with the realistic ones.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EdgeCase {
+    ZeroPrice,
+    NoAlcohol,
+    /// Without a name, which upstream sometimes forgets
+    MissingName,
+    /// With nulls wherever upstream allows them
+    Nulls,
+    OutOfStock,
+    /// Only sold to order
+    OrderOnly,
+    /// Far outside what's usual, like a 20 l keg
+    Huge,
+}
+
+pub const EDGE_CASES: [EdgeCase; 7] = [
+    EdgeCase::ZeroPrice,
+    EdgeCase::NoAlcohol,
+    EdgeCase::MissingName,
+    EdgeCase::Nulls,
+    EdgeCase::OutOfStock,
+    EdgeCase::OrderOnly,
+    EdgeCase::Huge,
+];
+
+/// What a category looks like upstream, and what's usual for it.
+struct Kind {
+    category: &'static str,
+    sub_categories: &'static [&'static str],
+    abv: (f64, f64),
+    volumes: &'static [f64],
+    price: (f64, f64),
+    deposit: f64,
+    packaging: &'static [&'static str],
+}
+
+/// Beer, wine, cider, liquor and the rest, in the order of `Generator::mix`.
+const KINDS: [Kind; 5] = [
+    Kind {
+        category: "Öl",
+        sub_categories: &["Ljus lager", "Mörk lager", "Ale", "IPA", "Porter och stout"],
+        abv: (3.5, 9.0),
+        volumes: &[330.0, 500.0],
+        price: (11.0, 45.0),
+        deposit: 1.0,
+        packaging: &["Burk", "Flaska"],
+    },
+    Kind {
+        category: "Röda viner",
+        sub_categories: &[
+            "Fruktigt & Smakrikt",
+            "Kryddigt & Mustigt",
+            "Lätt & Fruktigt",
+        ],
+        abv: (11.0, 15.0),
+        volumes: &[750.0, 3000.0],
+        price: (69.0, 350.0),
+        deposit: 0.0,
+        packaging: &["Flaska", "Box"],
+    },
+    Kind {
+        category: "Cider och blanddrycker",
+        sub_categories: &["Cider"],
+        abv: (4.0, 7.0),
+        volumes: &[330.0, 500.0],
+        price: (13.0, 30.0),
+        deposit: 1.0,
+        packaging: &["Burk", "Flaska"],
+    },
+    Kind {
+        category: "Sprit",
+        sub_categories: &["Vodka", "Whisky", "Rom", "Gin"],
+        abv: (37.5, 46.0),
+        volumes: &[350.0, 500.0, 700.0],
+        price: (199.0, 600.0),
+        deposit: 0.0,
+        packaging: &["Flaska"],
+    },
+    Kind {
+        category: "Cider och blanddrycker",
+        sub_categories: &["Blanddrycker"],
+        abv: (4.0, 10.0),
+        volumes: &[330.0, 750.0],
+        price: (15.0, 120.0),
+        deposit: 1.0,
+        packaging: &["Burk", "Flaska"],
+    },
+];
+
+const FIRST: [&str; 8] = [
+    "Norrlands",
+    "Gamla",
+    "Röda",
+    "Kalla",
+    "Fjällens",
+    "Sankt",
+    "Höga",
+    "Blå",
+];
+const LAST: [&str; 8] = [
+    "Guld", "Kusten", "Tornet", "Bryggan", "Ängen", "Älven", "Hästen", "Stugan",
+];
+const COUNTRIES: [&str; 6] = [
+    "Sverige",
+    "Tyskland",
+    "Italien",
+    "Frankrike",
+    "Spanien",
+    "Chile",
+];
+
+pub struct Generator {
+    size: usize,
+    mix: [u32; 5],
+    edge_cases: f64,
+    seed: u64,
+}
+
+impl Generator {
+    /// `size` products, mostly beer and wine like the real catalog, without edge cases.
+    pub fn new(size: usize) -> Generator {
+        Generator {
+            size,
+            mix: [40, 30, 10, 15, 5],
+            edge_cases: 0.0,
+            seed: 0,
+        }
+    }
+
+    /// How many beers, wines, ciders, liquors and others there are, relative to each other.
+    pub fn mix(mut self, mix: [u32; 5]) -> Generator {
+        self.mix = mix;
+        self
+    }
+
+    /// The share of products that are one of the [`EDGE_CASES`].
+    pub fn edge_cases(mut self, share: f64) -> Generator {
+        self.edge_cases = share;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Generator {
+        self.seed = seed;
+        self
+    }
+
+    pub fn generate(&self) -> Vec<Value> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let kinds = WeightedIndex::new(&self.mix).unwrap();
+        (0..self.size)
+            .map(|i| {
+                let kind = &KINDS[rng.sample(&kinds)];
+                let mut product = product(&mut rng, i, kind);
+                if rng.gen_bool(self.edge_cases) {
+                    edge_case(&mut product, *EDGE_CASES.choose(&mut rng).unwrap());
+                }
+                product
+            })
+            .collect()
+    }
+
+    /// The catalog split into pages of `per_page`, for [`super::upstream`].
+    pub fn pages(&self, per_page: usize) -> Vec<Vec<Value>> {
+        self.generate()
+            .chunks(per_page)
+            .map(<[Value]>::to_vec)
+            .collect()
+    }
+}
+
+fn product(rng: &mut StdRng, i: usize, kind: &Kind) -> Value {
+    let id = format!("{}", 100_000 + i);
+    let sub_category = *kind.sub_categories.choose(rng).unwrap();
+    let volume = *kind.volumes.choose(rng).unwrap();
+    // Bigger packages are cheaper per liter, but not by that much
+    let price = rng.gen_range(kind.price.0, kind.price.1) * (volume / kind.volumes[0]).powf(0.8);
+    let name = format!(
+        "{} {}",
+        FIRST.choose(rng).unwrap(),
+        LAST.choose(rng).unwrap()
+    );
+    let thin_name = if rng.gen_bool(0.3) {
+        Value::from(format!("Nr {}", i))
+    } else {
+        Value::Null
+    };
+    let sugar = if rng.gen_bool(0.5) {
+        Value::from(rng.gen_range(0.0, 60.0_f64).round())
+    } else {
+        Value::Null
+    };
+    let sell_start = format!(
+        "20{:02}-{:02}-01T00:00:00",
+        rng.gen_range(10, 21),
+        rng.gen_range(1, 13)
+    );
+    json!({
+        "ProductId": id,
+        "ProductNumber": format!("{}01", id),
+        "ProductNumberShort": id,
+        "ProductNameBold": name,
+        "ProductNameThin": thin_name,
+        "Category": kind.category,
+        "SubCategory": sub_category,
+        "Type": null,
+        "Style": sub_category,
+        "BottleTextShort": kind.packaging.choose(rng).unwrap(),
+        "ProducerName": format!("{} bryggeri", LAST.choose(rng).unwrap()),
+        "SupplierName": "Grossisten",
+        "Country": COUNTRIES.choose(rng).unwrap(),
+        "AlcoholPercentage": (rng.gen_range(kind.abv.0, kind.abv.1) * 10.0).round() / 10.0,
+        "Volume": volume,
+        "SugarContent": sugar,
+        "Price": (price * 10.0).round() / 10.0,
+        "RecycleFee": kind.deposit,
+        "Assortment": "FS",
+        "AssortmentText": "Fast sortiment",
+        "IsCompletelyOutOfStock": false,
+        "IsTemporaryOutOfStock": rng.gen_bool(0.05),
+        "IsOrganic": rng.gen_bool(0.1),
+        "IsKosher": false,
+        "IsEthical": rng.gen_bool(0.05),
+        "IsNews": rng.gen_bool(0.05),
+        "IsWebLaunch": false,
+        "SellStartDate": sell_start,
+        "Vintage": null,
+    })
+}
+
+fn edge_case(product: &mut Value, case: EdgeCase) {
+    let fields = product.as_object_mut().unwrap();
+    match case {
+        EdgeCase::ZeroPrice => {
+            fields.insert("Price".to_string(), json!(0.0));
+            fields.insert("RecycleFee".to_string(), json!(0.0));
+        }
+        EdgeCase::NoAlcohol => {
+            fields.insert("AlcoholPercentage".to_string(), json!(0.0));
+        }
+        EdgeCase::MissingName => {
+            fields.remove("ProductNameBold");
+        }
+        EdgeCase::Nulls => {
+            for field in &[
+                "ProductNumber",
+                "SubCategory",
+                "Style",
+                "Country",
+                "SugarContent",
+            ] {
+                fields.insert(field.to_string(), Value::Null);
+            }
+        }
+        EdgeCase::OutOfStock => {
+            fields.insert("IsCompletelyOutOfStock".to_string(), json!(true));
+        }
+        EdgeCase::OrderOnly => {
+            fields.insert("Assortment".to_string(), json!("BS"));
+            fields.insert("AssortmentText".to_string(), json!("Beställningssortiment"));
+        }
+        EdgeCase::Huge => {
+            fields.insert("Volume".to_string(), json!(20000.0));
+            fields.insert("Price".to_string(), json!(1899.0));
+        }
+    }
+}
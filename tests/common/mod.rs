@@ -0,0 +1,87 @@
+#![allow(dead_code)]
+
+pub mod generate;
+
+use apk::config::{AdminConfig, AdminToken, Config};
+use apk::server::{ApkServer, AppState};
+use apk::source::HttpSource;
+use secrecy::SecretString;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use wiremock::matchers::{method, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+pub fn fixture() -> Vec<Value> {
+    serde_json::from_str(include_str!("../fixtures/products.json")).unwrap()
+}
+
+/// Mounts `page` as page number `number` of the upstream product list.
+pub async fn mount_page(upstream: &MockServer, number: u32, page: Vec<Value>) {
+    Mock::given(method("GET"))
+        .and(query_param("page", number.to_string()))
+        .respond_with(ResponseTemplate::new(200).set_body_json(page))
+        .mount(upstream)
+        .await;
+}
+
+/// Serves `pages` as a paginated upstream product list.
+pub async fn upstream(pages: Vec<Vec<Value>>) -> MockServer {
+    let upstream = MockServer::start().await;
+    let count = pages.len() as u32;
+    for (i, page) in pages.into_iter().enumerate() {
+        mount_page(&upstream, i as u32 + 1, page).await;
+    }
+    mount_page(&upstream, count + 1, Vec::new()).await;
+    upstream
+}
+
+pub fn source(upstream: &MockServer) -> HttpSource {
+    HttpSource::new(upstream.uri()).retries(2, Duration::from_millis(10))
+}
+
+/// Runs a single refresh against `upstream` and publishes the result.
+pub async fn refresh(upstream: &MockServer) -> apk::Result<AppState> {
+    let server = ApkServer::builder().source(source(upstream)).build()?;
+    server.update().await?;
+    Ok(server.state().clone())
+}
+
+pub async fn get(state: AppState, path: &str) -> (u16, String) {
+    let response = warp::test::request()
+        .path(path)
+        .reply(&apk::server::routes(state))
+        .await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    (response.status().as_u16(), body)
+}
+
+pub const ADMIN_TOKEN: &str = "admin-hemligt";
+
+/// `state` with [`ADMIN_TOKEN`] issued to `falk`, for the admin pages.
+pub fn with_admin(state: AppState) -> AppState {
+    let admin = AdminConfig {
+        tokens: vec![AdminToken {
+            name: "falk".to_string(),
+            token: SecretString::new(ADMIN_TOKEN.to_string()),
+        }],
+    };
+    AppState {
+        config: Arc::new(Config {
+            admin: Some(admin),
+            ..(*state.config).clone()
+        }),
+        ..state
+    }
+}
+
+/// Like [`get`], with [`ADMIN_TOKEN`].
+pub async fn get_admin(state: AppState, path: &str) -> (u16, String) {
+    let response = warp::test::request()
+        .path(path)
+        .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+        .reply(&apk::server::routes(with_admin(state)))
+        .await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    (response.status().as_u16(), body)
+}
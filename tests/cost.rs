@@ -0,0 +1,86 @@
+mod common;
+
+use apk::catalog::{self, Catalog, Category};
+use apk::config::{ApiConfig, ApiToken, Config};
+use apk::cost::cheapest;
+use apk::server::ApkServer;
+use apk::units::Sek;
+use common::{fixture, source, upstream};
+use secrecy::SecretString;
+
+#[test]
+fn finds_cheapest_per_category() {
+    let catalog = Catalog::build(serde_json::from_value(fixture().into()).unwrap());
+    let purchases = cheapest(&catalog, 6.0);
+
+    let beer = purchases
+        .iter()
+        .find(|purchase| purchase.category == Category::Beer)
+        .unwrap();
+    assert_eq!(catalog::name(beer.drink), "Norrlands Guld");
+    assert_eq!(beer.units, 4);
+    assert!((beer.price.0 - 63.6).abs() < 1e-9);
+    assert!(beer.standard_drinks >= 6.0);
+
+    let cider = purchases
+        .iter()
+        .find(|purchase| purchase.category == Category::Cider)
+        .unwrap();
+    assert_eq!(cider.units, 7);
+
+    let liquor = purchases
+        .iter()
+        .find(|purchase| purchase.category == Category::Liquor)
+        .unwrap();
+    assert_eq!((liquor.units, liquor.price), (1, Sek(249.0)));
+
+    // More cans than can be counted
+    assert!(cheapest(&catalog, 1e12).is_empty());
+}
+
+#[tokio::test]
+async fn serves_cost_to_tokens() {
+    let upstream = upstream(vec![fixture()]).await;
+    let config = Config {
+        api: Some(ApiConfig {
+            tokens: vec![ApiToken {
+                name: "krogen".to_string(),
+                token: SecretString::new("hemligt".to_string()),
+                quota: None,
+            }],
+        }),
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let routes = server.routes();
+
+    let response = warp::test::request()
+        .path("/api/cost?standard_drinks=6")
+        .header("authorization", "Bearer hemligt")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 200);
+    let purchases: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(purchases[0]["category"], "Öl");
+    assert_eq!(purchases[0]["name"], "Norrlands Guld");
+    assert_eq!(purchases[0]["units"], 4);
+
+    let response = warp::test::request()
+        .path("/api/cost?standard_drinks=-1")
+        .header("authorization", "Bearer hemligt")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 400);
+
+    let response = warp::test::request()
+        .path("/api/cost?standard_drinks=1e12")
+        .header("authorization", "Bearer hemligt")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 400);
+}
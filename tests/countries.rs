@@ -0,0 +1,34 @@
+mod common;
+
+use apk::catalog::Catalog;
+use apk::countries::leaderboard;
+use common::{fixture, get, refresh, upstream};
+
+#[test]
+fn ranks_by_median_apk() {
+    let catalog = Catalog::build(serde_json::from_value(fixture().into()).unwrap());
+    let countries = leaderboard(&catalog);
+
+    assert_eq!(countries.len(), 2);
+    let sverige = countries.iter().find(|c| c.name == "Sverige").unwrap();
+    assert_eq!(sverige.products, 4);
+    assert_eq!(sverige.best_name, "Norrlands Guld");
+    // Explorer and Mariestads are in the middle
+    let explorer = 700.0 * 0.375 / 249.0;
+    let mariestads = 500.0 * 0.053 / 18.9;
+    assert!((sverige.median_apk.0 - (explorer + mariestads) / 2.0).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn applies_minimum_count() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+
+    let (status, body) = get(state.clone(), "/countries").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Inga länder"));
+
+    let (_, body) = get(state, "/countries?min=1").await;
+    assert!(body.contains("Sverige"));
+    assert!(body.contains("Spanien"));
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use apk::config::{Config, CrawlConfig};
+use apk::crawl::{canonical, noindex};
+use apk::server::ApkServer;
+use common::{fixture, source, upstream};
+
+#[test]
+fn indexes_only_clean_pages() {
+    let config = CrawlConfig {
+        noindex: vec!["/v/".to_string()],
+        ..CrawlConfig::default()
+    };
+    assert!(!noindex(&config, "/", ""));
+    assert!(!noindex(&config, "/", "kategori=%C3%B6l"));
+    assert!(noindex(&config, "/", "kategori=%C3%B6l&sortera=pris"));
+    assert!(noindex(&config, "/api/products", ""));
+    assert!(noindex(&config, "/v/karhuset", ""));
+    assert!(!noindex(&config, "/movers", ""));
+}
+
+#[test]
+fn points_filtered_views_at_category_pages() {
+    let config = CrawlConfig {
+        base_url: Some("https://apk.example.com/".to_string()),
+        ..CrawlConfig::default()
+    };
+    assert_eq!(
+        canonical(&config, "kategori=sprit&maxpris=300"),
+        "https://apk.example.com/?kategori=sprit"
+    );
+    assert_eq!(canonical(&config, "sok=guld"), "https://apk.example.com/");
+}
+
+#[tokio::test]
+async fn adds_headers_and_sitemap() {
+    let upstream = upstream(vec![fixture()]).await;
+    let config = Config {
+        crawl: CrawlConfig {
+            base_url: Some("https://apk.example.com".to_string()),
+            ..CrawlConfig::default()
+        },
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let routes = server.routes();
+
+    let response = warp::test::request()
+        .path("/?sok=guld")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.headers()["x-robots-tag"], "noindex");
+    assert_eq!(
+        response.headers()["link"],
+        "<https://apk.example.com/>; rel=\"canonical\""
+    );
+
+    let response = warp::test::request().path("/").reply(&routes).await;
+    assert!(!response.headers().contains_key("x-robots-tag"));
+
+    let response = warp::test::request()
+        .path("/sitemap.xml")
+        .reply(&routes)
+        .await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(
+        body.contains("<loc>https://apk.example.com/?kategori=sprit</loc><priority>0.8</priority>")
+    );
+    assert!(!body.contains("/api"));
+
+    let response = warp::test::request()
+        .path("/robots.txt")
+        .reply(&routes)
+        .await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains("Disallow: /api"));
+    assert!(body.contains("Sitemap: https://apk.example.com/sitemap.xml"));
+}
@@ -0,0 +1,29 @@
+use apk::dates::{basic_timestamp, date, parse_timestamp, timestamp};
+use std::time::{Duration, UNIX_EPOCH};
+
+#[test]
+fn formats_dates() {
+    assert_eq!(date(UNIX_EPOCH), "1970-01-01");
+    assert_eq!(
+        date(UNIX_EPOCH + Duration::from_secs(1_582_934_400)),
+        "2020-02-29"
+    );
+    assert_eq!(
+        basic_timestamp(UNIX_EPOCH + Duration::from_secs(1_600_000_000)),
+        "20200913T122640Z"
+    );
+    assert_eq!(
+        timestamp(UNIX_EPOCH + Duration::from_secs(1_600_000_000)),
+        "2020-09-13T12:26:40Z"
+    );
+}
+
+#[test]
+fn parses_timestamps() {
+    assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), Some(0));
+    assert_eq!(
+        parse_timestamp("2020-09-13T12:26:40.123Z"),
+        Some(1_600_000_000)
+    );
+    assert_eq!(parse_timestamp("igår"), None);
+}
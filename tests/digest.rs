@@ -0,0 +1,30 @@
+mod common;
+
+use apk::catalog::{self, Catalog};
+use apk::config::Config;
+use apk::digest::{best, EMAIL_TOP};
+use apk::render::{self, TEMPLATE_GLOB};
+use apk::score::default_scorers;
+use apk::source::parse_records;
+use apk::text::drink_line;
+use common::fixture;
+
+#[test]
+fn writes_email_digests() {
+    let catalog = Catalog::build(parse_records(fixture()));
+    let best = best(&catalog, EMAIL_TOP);
+    assert_eq!(best.len(), catalog.len());
+    assert_eq!(catalog::name(best[0]), "Norrlands Guld");
+    assert_eq!(catalog::name(best[1]), "Mariestads");
+
+    let tera = render::templates(TEMPLATE_GLOB, &default_scorers(), &Config::default()).unwrap();
+    let lines: Vec<String> = best.into_iter().map(drink_line).collect();
+    let changes = vec!["Ny: Explorer Vodka (APK 1.054)".to_string()];
+    let text = render::render_digest(&tera, &lines, &changes).unwrap();
+    assert!(text.starts_with("Veckans APK!"));
+    assert!(text.contains(&format!("1. {}\n", lines[0])));
+    assert!(text.contains("Sedan förra veckan:\n- Ny: Explorer Vodka (APK 1.054)"));
+
+    let text = render::render_digest(&tera, &lines, &[]).unwrap();
+    assert!(!text.contains("Sedan förra veckan"));
+}
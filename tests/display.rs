@@ -0,0 +1,58 @@
+mod common;
+
+use apk::config::{ApiConfig, ApiToken, Config, DisplayConfig};
+use apk::display::{self, Field};
+use apk::server::ApkServer;
+use common::{fixture, source, upstream};
+use secrecy::SecretString;
+
+#[test]
+fn rounds_half_away_from_zero() {
+    let config = DisplayConfig::default();
+    assert_eq!(display::format(&config, Field::Price, 14.906), "14.91");
+    assert_eq!(display::format(&config, Field::Apk, 1.666666), "1.66667");
+    assert_eq!(display::format(&config, Field::Kcal, 146.3595), "146");
+    assert_eq!(display::round(&config, Field::Sugar, 12.34), 12.3);
+}
+
+#[tokio::test]
+async fn pages_and_api_agree() {
+    let upstream = upstream(vec![fixture()]).await;
+    let config = Config {
+        api: Some(ApiConfig {
+            tokens: vec![ApiToken {
+                name: "krogen".to_string(),
+                token: SecretString::new("hemligt".to_string()),
+                quota: None,
+            }],
+        }),
+        display: DisplayConfig {
+            apk: 3,
+            ..DisplayConfig::default()
+        },
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let routes = server.routes();
+
+    let response = warp::test::request().path("/").reply(&routes).await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    // Norrlands Guld, 26.5 ml of alcohol for 15.90 kr
+    assert!(body.contains("1.667"));
+    assert!(!body.contains("1.66667"));
+
+    let response = warp::test::request()
+        .path("/api/products?kategori=%C3%B6l")
+        .header("authorization", "Bearer hemligt")
+        .reply(&routes)
+        .await;
+    let products: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(products[0]["name"], "Norrlands Guld");
+    assert_eq!(products[0]["apk"], 1.667);
+    assert_eq!(products[0]["price"], 15.9);
+}
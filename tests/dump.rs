@@ -0,0 +1,36 @@
+mod common;
+
+use apk::catalog::Category;
+use apk::config::DisplayConfig;
+use apk::text;
+use common::{fixture, refresh, upstream};
+
+#[tokio::test]
+async fn aligns_the_table() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let snapshot = state.snapshot.read().unwrap().clone().unwrap();
+    let table = text::table(
+        &snapshot.catalog,
+        Category::Beer,
+        20,
+        &DisplayConfig::default(),
+    );
+    assert_eq!(
+        table,
+        "Öl\n    Namn                Pris  Alkohol   Volym      APK\n\
+         1.  Norrlands Guld  15.90 kr     5.3%  500 ml  1.66667\n\
+         2.  Mariestads      18.90 kr     5.3%  500 ml  1.40212"
+    );
+    assert_eq!(
+        text::table(
+            &snapshot.catalog,
+            Category::Beer,
+            1,
+            &DisplayConfig::default()
+        )
+        .lines()
+        .count(),
+        3
+    );
+}
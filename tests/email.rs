@@ -0,0 +1,108 @@
+use apk::email::{
+    confirm, request, subscribe, subscribers, unsubscribe, Change, CONFIRM_WITHIN, MAX_PENDING,
+    RESEND_AFTER,
+};
+use apk::storage::MemoryStorage;
+
+#[test]
+fn subscribes_and_unsubscribes() {
+    let storage = MemoryStorage::default();
+    let subscriber = subscribe(&storage, "kalle@example.com", vec!["1001".to_string()]).unwrap();
+    subscribe(&storage, "lisa@example.com", Vec::new()).unwrap();
+
+    assert_eq!(subscribers(&storage).unwrap().len(), 2);
+    assert!(unsubscribe(&storage, &subscriber.token).unwrap());
+    assert!(!unsubscribe(&storage, &subscriber.token).unwrap());
+    let remaining = subscribers(&storage).unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].email, "lisa@example.com");
+}
+
+#[test]
+fn resubscribing_updates_watched_products() {
+    let storage = MemoryStorage::default();
+    let first = subscribe(&storage, "kalle@example.com", Vec::new()).unwrap();
+    let second = subscribe(&storage, "kalle@example.com", vec!["1002".to_string()]).unwrap();
+
+    assert_eq!(first.token, second.token);
+    assert_eq!(
+        subscribers(&storage).unwrap()[0].watch,
+        vec!["1002".to_string()]
+    );
+}
+
+#[test]
+fn waits_for_confirmation() {
+    let storage = MemoryStorage::default();
+    let now = 1_600_000_000;
+    let watch = Change::Watch(vec!["1001".to_string()]);
+    let pending = request(&storage, "kalle@example.com", watch.clone(), now)
+        .unwrap()
+        .unwrap();
+
+    assert!(subscribers(&storage).unwrap().is_empty());
+    // No more mail to the same address for a while
+    assert!(
+        request(&storage, "kalle@example.com", watch.clone(), now + 60)
+            .unwrap()
+            .is_none()
+    );
+    assert!(confirm(&storage, "gissning", now).unwrap().is_none());
+    assert_eq!(
+        confirm(&storage, &pending.id, now + 60)
+            .unwrap()
+            .unwrap()
+            .change,
+        watch
+    );
+    assert!(confirm(&storage, &pending.id, now + 60).unwrap().is_none());
+    assert_eq!(
+        subscribers(&storage).unwrap()[0].watch,
+        vec!["1001".to_string()]
+    );
+
+    let later = now + RESEND_AFTER;
+    let pending = request(
+        &storage,
+        "kalle@example.com",
+        Change::Watch(Vec::new()),
+        later,
+    )
+    .unwrap()
+    .unwrap();
+    assert!(confirm(&storage, &pending.id, later + CONFIRM_WITHIN)
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        subscribers(&storage).unwrap()[0].watch,
+        vec!["1001".to_string()]
+    );
+}
+
+#[test]
+fn keeps_only_so_many_waiting() {
+    let storage = MemoryStorage::default();
+    let now = 1_600_000_000;
+    for n in 0..MAX_PENDING {
+        let email = format!("{}@example.com", n);
+        assert!(request(&storage, &email, Change::Watch(Vec::new()), now)
+            .unwrap()
+            .is_some());
+    }
+    let more = request(
+        &storage,
+        "kalle@example.com",
+        Change::Watch(Vec::new()),
+        now,
+    );
+    assert!(more.unwrap().is_none());
+    // Until they expire
+    let later = now + CONFIRM_WITHIN;
+    let more = request(
+        &storage,
+        "kalle@example.com",
+        Change::Watch(Vec::new()),
+        later,
+    );
+    assert!(more.unwrap().is_some());
+}
@@ -0,0 +1,26 @@
+mod common;
+
+use apk::score;
+use common::{fixture, get, refresh, upstream};
+
+#[test]
+fn computes_facts_per_package() {
+    // Norrlands Guld, 50 cl of 5.3% for 14.90 kr plus 1 kr deposit
+    let drink = serde_json::from_value(fixture().remove(0)).unwrap();
+    let facts = score::facts(&drink);
+    assert!((facts.alcohol_grams - 20.9085).abs() < 1e-6);
+    assert!((facts.standard_drinks - 1.742375).abs() < 1e-6);
+    assert!((facts.kr_per_standard_drink * facts.standard_drinks - 15.9).abs() < 1e-9);
+    assert!((facts.kcal - 146.3595).abs() < 1e-6);
+}
+
+#[tokio::test]
+async fn shows_facts_on_product_pages() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let (status, body) = get(state, "/produkt/1001").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("20.9 g"));
+    assert!(body.contains("1.7, 9.13 kr styck"));
+    assert!(body.contains("cirka 146 kcal"));
+}
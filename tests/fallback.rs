@@ -0,0 +1,59 @@
+mod common;
+
+use apk::config::{Config, FallbackConfig};
+use apk::server::ApkServer;
+use common::{fixture, source};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn servers() -> (MockServer, MockServer) {
+    let primary = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&primary)
+        .await;
+    let dump = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/products.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(fixture()))
+        .mount(&dump)
+        .await;
+    (primary, dump)
+}
+
+fn config(dump: &MockServer, after: u64) -> Config {
+    Config {
+        fallback: Some(FallbackConfig {
+            url: format!("{}/products.json", dump.uri()),
+            after,
+        }),
+        ..Config::default()
+    }
+}
+
+#[tokio::test]
+async fn falls_back_when_primary_is_down() {
+    let (primary, dump) = servers().await;
+    let server = ApkServer::builder()
+        .source(source(&primary))
+        .config(config(&dump, 0))
+        .build()
+        .unwrap();
+
+    server.update().await.unwrap();
+    let snapshot = server.state().snapshot.read().unwrap().clone().unwrap();
+    assert_eq!(snapshot.catalog.len(), 5);
+}
+
+#[tokio::test]
+async fn waits_before_falling_back() {
+    let (primary, dump) = servers().await;
+    let server = ApkServer::builder()
+        .source(source(&primary))
+        .config(config(&dump, 3600))
+        .build()
+        .unwrap();
+
+    assert!(server.update().await.is_err());
+    assert!(server.state().snapshot.read().unwrap().is_none());
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use apk::favorites::{cookie, parse};
+use common::{fixture, refresh, upstream};
+
+const KEY: &[u8] = b"nyckel";
+
+#[test]
+fn round_trips_signed_cookie() {
+    let ids = vec!["1001".to_string(), "1002".to_string()];
+    let set_cookie = cookie(KEY, &ids);
+    let value = set_cookie
+        .trim_start_matches("favorites=")
+        .split(';')
+        .next()
+        .unwrap();
+
+    assert_eq!(parse(KEY, Some(value)), ids);
+    assert!(parse(b"annan nyckel", Some(value)).is_empty());
+    assert!(parse(KEY, Some(&value.replace("1002", "1003"))).is_empty());
+    assert!(parse(KEY, None).is_empty());
+}
+
+#[tokio::test]
+async fn stars_and_shows_favorites() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let routes = apk::server::routes(state);
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/favorites")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body("id=1001")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 303);
+    let set_cookie = response.headers()["set-cookie"].to_str().unwrap();
+    let value = set_cookie.split(';').next().unwrap().to_string();
+
+    let response = warp::test::request()
+        .path("/favorites")
+        .header("cookie", value)
+        .reply(&routes)
+        .await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains("Norrlands Guld"));
+    assert!(!body.contains("Mariestads"));
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use apk::config::{Config, LaunchPlanConfig};
+use apk::server::ApkServer;
+use common::{fixture, get, refresh, source, upstream};
+
+#[tokio::test]
+async fn leaves_out_disabled_features() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let (_, body) = get(state.clone(), "/").await;
+    assert!(!body.contains("/kommande"));
+    assert!(body.contains("Basen"));
+    let (_, body) = get(state, "/?kategori=%C3%B6l").await;
+    assert!(!body.contains("Bevaka sökningen"));
+
+    let config = Config {
+        launch_plan: Some(LaunchPlanConfig {
+            url: format!("{}/plan", upstream.uri()),
+        }),
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let (_, body) = get(server.state().clone(), "/").await;
+    assert!(body.contains("/kommande"));
+    assert!(body.contains("Basen"));
+
+    let config = Config {
+        hide_basen: true,
+        ..Config::default()
+    };
+    assert_eq!(config.features()["basen"], false);
+    assert_eq!(Config::default().features()["basen"], true);
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let (_, body) = get(server.state().clone(), "/").await;
+    assert!(!body.contains("Basen"));
+}
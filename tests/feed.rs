@@ -0,0 +1,33 @@
+use apk::catalog::Category;
+use apk::feed::{render, Item};
+
+fn item(category: Category, title: &str) -> Item {
+    Item {
+        at: 1_600_000_000,
+        category,
+        id: "1001".to_string(),
+        title: title.to_string(),
+        link: "https://www.systembolaget.se/1001/".to_string(),
+    }
+}
+
+#[test]
+fn filters_by_category() {
+    let items = vec![
+        item(Category::Beer, "Ny: Norrlands Guld"),
+        item(Category::Wine, "Ny: Rödtjut"),
+    ];
+
+    let beer = render(&items, Some(Category::Beer));
+    assert!(beer.contains("Norrlands Guld"));
+    assert!(!beer.contains("Rödtjut"));
+    let all = render(&items, None);
+    assert!(all.contains("Norrlands Guld") && all.contains("Rödtjut"));
+}
+
+#[test]
+fn escapes_titles() {
+    let feed = render(&[item(Category::Liquor, "Gin & <tonic>")], None);
+    assert!(feed.contains("Gin &amp; &lt;tonic&gt;"));
+    assert!(feed.contains("<pubDate>Sun, 13 Sep 2020 12:26:40 GMT</pubDate>"));
+}
@@ -0,0 +1,35 @@
+mod common;
+
+use common::{fixture, get, get_admin, refresh, upstream};
+use serde_json::Value;
+
+#[tokio::test]
+async fn reports_snapshot_size_and_warm_pages() {
+    let (status, body) = get_admin(Default::default(), "/admin/snapshot").await;
+    assert_eq!(status, 200);
+    let report: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(report["snapshot"], Value::Null);
+    assert_eq!(report["warm"]["hit_rate"], Value::Null);
+
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    get(state.clone(), "/?sortera=pris").await;
+
+    let (_, body) = get_admin(state.clone(), "/admin/snapshot").await;
+    let report: Value = serde_json::from_str(&body).unwrap();
+    let snapshot = &report["snapshot"];
+    let products = snapshot["products"].as_u64().unwrap();
+    assert!(products > 0);
+    let counted: u64 = snapshot["categories"]
+        .as_object()
+        .unwrap()
+        .values()
+        .map(|count| count.as_u64().unwrap())
+        .sum();
+    assert_eq!(counted, products);
+    assert!(snapshot["bytes"]["catalog"].as_u64().unwrap() > 0);
+    assert!(snapshot["bytes"]["total"].as_u64() > snapshot["bytes"]["pages"].as_u64());
+    assert_eq!(report["warm"]["pages"], 0);
+    assert_eq!(report["warm"]["missed"], 1);
+    assert_eq!(report["warm"]["hit_rate"], 0.0);
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use apk::catalog::{self, CATEGORIES};
+use apk::units::{Measures, Percent, Sek};
+use common::generate::Generator;
+use common::{get, refresh, upstream};
+
+#[test]
+fn generates_the_same_catalog_from_the_same_seed() {
+    let generator = Generator::new(100).edge_cases(0.2).seed(7);
+    assert_eq!(generator.generate(), generator.generate());
+    assert_ne!(generator.generate(), Generator::new(100).seed(8).generate());
+    assert_eq!(generator.pages(30).len(), 4);
+}
+
+#[tokio::test]
+async fn survives_a_large_catalog_with_edge_cases() {
+    let generator = Generator::new(3000).edge_cases(0.1).seed(1);
+    let upstream = upstream(generator.pages(500)).await;
+    let state = refresh(&upstream).await.unwrap();
+
+    let snapshot = state.snapshot.read().unwrap().clone().unwrap();
+    assert!(snapshot.catalog.len() > 2500);
+    assert!(snapshot.catalog.len() < 3000);
+    for &category in CATEGORIES.iter() {
+        assert!(!snapshot.catalog.get(category).is_empty());
+    }
+    for drink in snapshot.catalog.products() {
+        assert!(drink.price_with_deposit() > Sek(0.0));
+        assert!(drink.abv() > Percent(0.0));
+        assert!(catalog::apk(drink).0.is_finite());
+    }
+
+    let (status, body) = get(state, "/").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Sprit!"));
+}
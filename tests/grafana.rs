@@ -0,0 +1,41 @@
+use apk::catalog::Category;
+use apk::grafana::{query, targets, Query};
+use apk::history::Point;
+use serde_json::json;
+
+fn point(at: u64, best_beer: f64) -> Point {
+    Point {
+        at,
+        best: vec![(Category::Beer, best_beer)].into_iter().collect(),
+        products: vec![(Category::Beer, 3), (Category::Wine, 2)]
+            .into_iter()
+            .collect(),
+        errors: 1,
+        changes: Vec::new(),
+    }
+}
+
+#[test]
+fn lists_targets() {
+    let targets = targets();
+    assert!(targets.contains(&"best_apk.Öl".to_string()));
+    assert!(targets.contains(&"refresh_errors".to_string()));
+}
+
+#[test]
+fn queries_within_range() {
+    let history = vec![
+        point(1_600_000_000, 0.5),
+        point(1_600_007_200, 0.6),
+        point(1_600_014_400, 0.7),
+    ];
+    let body: Query = serde_json::from_value(json!({
+        "range": { "from": "2020-09-13T13:00:00.000Z", "to": "2020-09-13T16:00:00.000Z" },
+        "targets": [{ "target": "best_apk.Öl" }, { "target": "products" }],
+    }))
+    .unwrap();
+
+    let series = query(&history, &body);
+    assert_eq!(series[0].datapoints, vec![(0.6, 1_600_007_200_000)]);
+    assert_eq!(series[1].datapoints, vec![(5.0, 1_600_007_200_000)]);
+}
@@ -0,0 +1,18 @@
+mod common;
+
+use common::{fixture, get, refresh, upstream};
+use serde_json::Value;
+
+#[tokio::test]
+async fn serves_best_per_category() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+
+    let (status, body) = get(state, "/homeassistant").await;
+    assert_eq!(status, 200);
+    let sensors: Value = serde_json::from_str(&body).unwrap();
+    assert!(sensors["best"]["beer"]["name"].is_string());
+    assert!(sensors["best"]["beer"]["apk"].is_f64());
+    assert!(sensors["best"].get("other").is_none());
+    assert!(sensors["last_update_age"].is_u64());
+}
@@ -0,0 +1,26 @@
+mod common;
+
+use apk::ical::calendar;
+use common::{fixture, refresh, upstream};
+use std::time::{Duration, UNIX_EPOCH};
+
+#[tokio::test]
+async fn lists_upcoming_launches() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let snapshot = state.snapshot.read().unwrap().clone().unwrap();
+
+    let before = calendar(
+        &snapshot.catalog,
+        UNIX_EPOCH + Duration::from_secs(1_570_000_000),
+    );
+    assert_eq!(before.matches("BEGIN:VEVENT").count(), 1);
+    assert!(before.contains("DTSTART;VALUE=DATE:20200101\r\n"));
+    assert!(before.contains("SUMMARY:5 nya drycker på Systemet\r\n"));
+
+    let after = calendar(
+        &snapshot.catalog,
+        UNIX_EPOCH + Duration::from_secs(1_600_000_000),
+    );
+    assert!(!after.contains("BEGIN:VEVENT"));
+}
@@ -0,0 +1,29 @@
+mod common;
+
+use apk::config::Config;
+use apk::server::ApkServer;
+use common::{fixture, get, source, upstream};
+
+#[tokio::test]
+async fn uses_configured_icons() {
+    let upstream = upstream(vec![fixture()]).await;
+    let mut config = Config::default();
+    config.icons.insert("sprit".to_string(), "🍸".to_string());
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let state = server.state().clone();
+
+    let (_, body) = get(state.clone(), "/").await;
+    assert!(body.contains("🍺 Öl!"));
+    assert!(body.contains("🍸 Sprit!"));
+
+    let (status, body) = get(state, "/api/icons").await;
+    assert_eq!(status, 200);
+    let icons: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(icons["Sprit"], "🍸");
+    assert_eq!(icons["Vin"], "🍷");
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use apk::config::{Config, ImagesConfig};
+use apk::images::{thumbnail, width};
+use apk::server::ApkServer;
+use common::{fixture, source, upstream};
+use image::{DynamicImage, ImageBuffer, ImageOutputFormat, Rgb};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn bottle() -> Vec<u8> {
+    let image = ImageBuffer::from_pixel(400, 1000, Rgb([0u8, 34, 68]));
+    let mut png = Vec::new();
+    DynamicImage::ImageRgb8(image)
+        .write_to(&mut png, ImageOutputFormat::Png)
+        .unwrap();
+    png
+}
+
+#[test]
+fn rounds_widths_up() {
+    assert_eq!(width(None), 120);
+    assert_eq!(width(Some(101)), 120);
+    assert_eq!(width(Some(0)), 20);
+    assert_eq!(width(Some(5000)), 400);
+}
+
+#[test]
+fn keeps_aspect_ratio() {
+    let thumbnail = image::load_from_memory(&thumbnail(&bottle(), 120).unwrap()).unwrap();
+    assert_eq!((thumbnail.width(), thumbnail.height()), (120, 300));
+}
+
+#[tokio::test]
+async fn resizes_and_caches_images() {
+    let cdn = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/1001.png"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(bottle()))
+        .expect(1)
+        .mount(&cdn)
+        .await;
+    let cache_dir = std::env::temp_dir().join(format!("apk-images-{}", std::process::id()));
+    let config = Config {
+        images: Some(ImagesConfig {
+            cache_dir: cache_dir.clone(),
+            url: format!("{}/{{id}}.png", cdn.uri()),
+        }),
+        ..Config::default()
+    };
+    let upstream = upstream(vec![fixture()]).await;
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let routes = server.routes();
+
+    for _ in 0..2 {
+        let response = warp::test::request()
+            .path("/img/1001?w=60")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 200);
+        let image = image::load_from_memory(response.body()).unwrap();
+        assert_eq!(image.width(), 60);
+    }
+    std::fs::remove_dir_all(cache_dir).unwrap();
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use apk::catalog::Category;
+use apk::kiosk::{next, parse_duration};
+use common::{fixture, get, refresh, upstream};
+
+#[test]
+fn parses_durations() {
+    assert_eq!(parse_duration("30s"), Some(30));
+    assert_eq!(parse_duration("2m"), Some(120));
+    assert_eq!(parse_duration("45"), Some(45));
+    assert_eq!(parse_duration("snart"), None);
+}
+
+#[test]
+fn rotates_through_all_categories() {
+    assert_eq!(next(Category::Beer), Category::Wine);
+    assert_eq!(next(Category::Other), Category::Beer);
+}
+
+#[tokio::test]
+async fn shows_category_and_rotates() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+
+    let (status, body) = get(state.clone(), "/kiosk?category=beer&rotate=30s").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Norrlands Guld"));
+    assert!(!body.contains("Explorer Vodka"));
+    assert!(body.contains("content=\"30; url=&#x2F;kiosk?category=wine&amp;rotate=30s\""));
+
+    let (status, _) = get(state, "/kiosk?category=mjod").await;
+    assert_eq!(status, 400);
+}
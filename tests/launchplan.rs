@@ -0,0 +1,38 @@
+mod common;
+
+use apk::config::{Config, LaunchPlanConfig};
+use apk::server::ApkServer;
+use common::{fixture, get, source, upstream};
+use serde_json::json;
+use std::time::Duration;
+
+#[tokio::test]
+async fn shows_upcoming_launches() {
+    let mut plan = fixture();
+    plan[0]["SellStartDate"] = json!("2099-06-01T00:00:00");
+    plan[4]["SellStartDate"] = json!("2099-06-01T00:00:00");
+    // Already launched, so not coming anymore
+    plan[1]["SellStartDate"] = json!("2020-01-01T00:00:00");
+    let plan = upstream(vec![plan]).await;
+    let upstream = upstream(vec![fixture()]).await;
+    let config = Config {
+        launch_plan: Some(LaunchPlanConfig { url: plan.uri() }),
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    // The plan is fetched by a notifier, in the background
+    tokio::time::delay_for(Duration::from_millis(200)).await;
+
+    let (status, body) = get(server.state().clone(), "/kommande").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("2099-06-01"));
+    let norrlands = body.find("Norrlands Guld").unwrap();
+    let vodka = body.find("Explorer Vodka").unwrap();
+    assert!(norrlands < vodka);
+    assert!(!body.contains("Mariestads"));
+}
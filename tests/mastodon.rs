@@ -0,0 +1,86 @@
+mod common;
+
+use apk::config::MastodonConfig;
+use apk::diff::Diff;
+use apk::mastodon::{posts, Kind, MastodonNotifier};
+use apk::notify::{Notifier, RefreshEvent};
+use apk::server::AppState;
+use apk::storage;
+use common::{fixture, refresh, upstream};
+use secrecy::SecretString;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn event_of(state: &AppState) -> RefreshEvent {
+    let snapshot = state.snapshot.read().unwrap().clone().unwrap();
+    RefreshEvent {
+        previous: Some(snapshot.clone()),
+        snapshot,
+        diff: Diff::default(),
+    }
+}
+
+async fn event() -> RefreshEvent {
+    let upstream = upstream(vec![fixture()]).await;
+    event_of(&refresh(&upstream).await.unwrap())
+}
+
+fn notifier(instance: &str) -> MastodonNotifier {
+    MastodonNotifier::new(MastodonConfig {
+        instance: instance.to_string(),
+        access_token: SecretString::new("token".to_string()),
+        template: Some("{{ kind }}: {{ name }}".to_string()),
+        min_interval: 0,
+    })
+}
+
+#[tokio::test]
+async fn toots_about_new_records_only() {
+    let event = event().await;
+
+    assert!(posts(&event, None).is_empty());
+    assert!(posts(&event, Some(1000.0)).is_empty());
+    let posts = posts(&event, Some(0.0));
+    assert_eq!(posts.len(), 1);
+    assert_eq!(posts[0].kind, Kind::Record);
+}
+
+#[tokio::test]
+async fn renders_template() {
+    let event = event().await;
+    let notifier = notifier("https://mastodon.example.com");
+
+    let post = &posts(&event, Some(0.0))[0];
+    assert_eq!(
+        notifier.text(post).unwrap(),
+        format!("record: {}", post.name)
+    );
+}
+
+#[tokio::test]
+async fn keeps_records_once_tooted() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let event = event_of(&state);
+    let record = || storage::load_json::<f64>(&*state.storage, "mastodon-record.json").unwrap();
+    storage::save_json(&*state.storage, "mastodon-record.json", &0.0).unwrap();
+    let instance = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/statuses"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&instance)
+        .await;
+
+    let notifier = notifier(&instance.uri());
+    assert!(notifier.notify(&state, &event).await.is_err());
+    assert_eq!(record(), Some(0.0));
+
+    instance.reset().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/statuses"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&instance)
+        .await;
+    notifier.notify(&state, &event).await.unwrap();
+    assert_eq!(record(), Some(posts(&event, Some(0.0))[0].apk));
+}
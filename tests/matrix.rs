@@ -0,0 +1,22 @@
+use apk::catalog::Category;
+use apk::matrix::{parse, Command};
+
+#[test]
+fn parses_commands() {
+    assert_eq!(parse("!top cider"), Some(Command::Top(Category::Cider)));
+    assert_eq!(
+        parse("!sök mariestads"),
+        Some(Command::Search("mariestads".to_string()))
+    );
+    assert_eq!(
+        parse("!apk 1234567"),
+        Some(Command::Apk("1234567".to_string()))
+    );
+    assert_eq!(parse("!top läsk"), Some(Command::Help));
+}
+
+#[test]
+fn ignores_chatter() {
+    assert_eq!(parse("vad har bäst apk?"), None);
+    assert_eq!(parse("/top öl"), None);
+}
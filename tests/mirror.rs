@@ -0,0 +1,77 @@
+mod common;
+
+use apk::catalog;
+use apk::server::ApkServer;
+use apk::source::{MirrorSource, ProductSource};
+use apk::sync::Delta;
+use common::{fixture, get, refresh, upstream};
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn copies_the_primary() {
+    let upstream = upstream(vec![fixture()]).await;
+    let primary = refresh(&upstream).await.unwrap();
+    let (_, delta) = get(primary.clone(), "/api/sync").await;
+    let served = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/sync"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(delta))
+        .mount(&served)
+        .await;
+
+    let source = MirrorSource::new(&format!("{}/", served.uri()));
+    assert_eq!(source.name(), format!("mirror {}", served.uri()));
+    let mirror = ApkServer::builder().source(source).build().unwrap();
+    mirror.update().await.unwrap();
+
+    let primary = primary.snapshot.read().unwrap().clone().unwrap();
+    let mirrored = mirror.state().snapshot.read().unwrap().clone().unwrap();
+    assert_eq!(mirrored.hash, primary.hash);
+    let ids = |snapshot: &apk::refresh::Snapshot| -> Vec<String> {
+        snapshot
+            .catalog
+            .products()
+            .map(|drink| catalog::id(drink).to_string())
+            .collect()
+    };
+    assert_eq!(ids(&mirrored), ids(&primary));
+}
+
+#[tokio::test]
+async fn syncs_fully_when_the_base_is_unknown() {
+    let upstream = upstream(vec![fixture()]).await;
+    let primary = refresh(&upstream).await.unwrap();
+    let (_, full) = get(primary.clone(), "/api/sync").await;
+    let snapshot = primary.snapshot.read().unwrap().clone().unwrap();
+    let hash = snapshot.hash.clone();
+    let served = MockServer::start().await;
+    let elsewhere = Delta {
+        hash: "ny".to_string(),
+        base: Some("någon annan".to_string()),
+        ..Delta::default()
+    };
+    Mock::given(method("GET"))
+        .and(path("/api/sync"))
+        .and(query_param("since", hash.as_str()))
+        .respond_with(ResponseTemplate::new(200).set_body_json(elsewhere))
+        .mount(&served)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/sync"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(full))
+        .expect(2)
+        .mount(&served)
+        .await;
+
+    let mirror = ApkServer::builder()
+        .source(MirrorSource::new(&served.uri()))
+        .build()
+        .unwrap();
+    mirror.update().await.unwrap();
+    mirror.update().await.unwrap();
+
+    let mirrored = mirror.state().snapshot.read().unwrap().clone().unwrap();
+    assert_eq!(mirrored.hash, hash);
+    assert!(mirrored.catalog.products().count() > 0);
+}
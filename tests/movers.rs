@@ -0,0 +1,85 @@
+mod common;
+
+use apk::catalog::Catalog;
+use apk::diff::PriceChange;
+use apk::history::Point;
+use apk::movers::movers;
+use apk::server::ApkServer;
+use apk::storage::{self, MemoryStorage};
+use apk::units::{Apk, Sek};
+use common::{fixture, get, source, upstream};
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+fn change(id: &str, old_apk: f64, new_apk: f64) -> PriceChange {
+    PriceChange {
+        id: id.to_string(),
+        old_price: Sek(0.0),
+        new_price: Sek(0.0),
+        old_apk: Apk(old_apk),
+        new_apk: Apk(new_apk),
+    }
+}
+
+fn point(at: u64, changes: Vec<PriceChange>) -> Point {
+    Point {
+        at,
+        best: HashMap::new(),
+        products: HashMap::new(),
+        errors: 0,
+        changes,
+    }
+}
+
+fn catalog() -> Catalog {
+    Catalog::build(serde_json::from_value(fixture().into()).unwrap())
+}
+
+#[test]
+fn sums_up_the_last_week() {
+    let week = 7 * 24 * 60 * 60;
+    let history = vec![
+        point(1_000, vec![change("1001", 0.1, 0.9)]),
+        point(
+            1_000 + week,
+            vec![change("1001", 0.2, 0.3), change("4001", 1.0, 0.8)],
+        ),
+        point(
+            2_000 + week,
+            vec![change("1001", 0.3, 0.25), change("9999", 0.1, 0.5)],
+        ),
+        point(3_000 + week, vec![change("2001", 0.2, 0.2)]),
+    ];
+
+    let movers = movers(&history, &catalog());
+    assert_eq!(movers.gainers.len(), 1);
+    assert_eq!(movers.gainers[0].id, "1001");
+    assert_eq!(movers.gainers[0].name, "Norrlands Guld");
+    assert_eq!(movers.gainers[0].old_apk, Apk(0.2));
+    assert_eq!(movers.gainers[0].new_apk, Apk(0.25));
+    assert_eq!(movers.losers.len(), 1);
+    assert_eq!(movers.losers[0].id, "4001");
+}
+
+#[tokio::test]
+async fn shows_movers() {
+    let upstream = upstream(vec![fixture()]).await;
+    let storage = MemoryStorage::default();
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let history = vec![point(now - 3600, vec![change("4001", 0.9, 1.0)])];
+    storage::save_json(&storage, "history.json", &history).unwrap();
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .storage(storage)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+
+    let (status, body) = get(server.state().clone(), "/movers").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Explorer Vodka"));
+    assert!(body.contains("+0.10000"));
+}
@@ -0,0 +1,32 @@
+mod common;
+
+use apk::diff::Diff;
+use apk::mqtt::messages;
+use apk::notify::RefreshEvent;
+use common::{fixture, refresh, upstream};
+
+#[tokio::test]
+async fn publishes_best_per_category() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let event = RefreshEvent {
+        snapshot: state.snapshot.read().unwrap().clone().unwrap(),
+        previous: None,
+        diff: Diff::default(),
+    };
+
+    let topics: Vec<String> = messages("apk", &event)
+        .into_iter()
+        .map(|(topic, _)| topic)
+        .collect();
+    assert_eq!(
+        topics,
+        vec![
+            "apk/best/beer",
+            "apk/best/wine",
+            "apk/best/cider",
+            "apk/best/liquor",
+            "apk/last_update",
+        ]
+    );
+}
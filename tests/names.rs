@@ -0,0 +1,98 @@
+mod common;
+
+use apk::config::NamesConfig;
+use apk::names::{clean, slugify};
+use apk::ApkServer;
+use common::{fixture, get, source, upstream};
+use serde_json::json;
+
+#[test]
+fn cleans_names() {
+    let config = NamesConfig::default();
+    assert_eq!(
+        clean(&config, "Castillo de Gredos  2018"),
+        "Castillo de Gredos"
+    );
+    assert_eq!(clean(&config, "Rioja Reserva, 2015"), "Rioja Reserva");
+    assert_eq!(clean(&config, "Kronenbourg 1664"), "Kronenbourg 1664");
+    assert_eq!(
+        clean(&config, "Norrlands Guld 33CL"),
+        "Norrlands Guld 33 cl"
+    );
+    assert_eq!(
+        clean(&config, "Norrlands Guld 50 Cl"),
+        "Norrlands Guld 50 cl"
+    );
+    assert_eq!(clean(&config, "Explorer (0.5L)"), "Explorer (0,5 l)");
+    assert_eq!(clean(&config, "2020"), "2020");
+
+    let config = NamesConfig {
+        strip_vintage: false,
+        volumes: false,
+        replace: vec![
+            ("ipa".to_string(), "IPA".to_string()),
+            ("Limited".to_string(), String::new()),
+        ]
+        .into_iter()
+        .collect(),
+        ..NamesConfig::default()
+    };
+    assert_eq!(
+        clean(&config, "Alkoholfri Ipa Limited 2019 33CL"),
+        "Alkoholfri IPA 2019 33CL"
+    );
+}
+
+#[test]
+fn makes_slugs() {
+    assert_eq!(slugify("Kopparbergs Päron"), "kopparbergs-paron");
+    assert_eq!(slugify("Château Côte-Rôtie"), "chateau-cote-rotie");
+    assert_eq!(slugify("Öl & Ål, 33 cl"), "ol-al-33-cl");
+}
+
+#[tokio::test]
+async fn links_permalinks_and_groups_vintages() {
+    let mut products = fixture();
+    products[2]["ProductNameBold"] = json!("Castillo de Gredos 2018");
+    let mut vintage = products[2].clone();
+    vintage["ProductId"] = json!("2002");
+    vintage["ProductNumber"] = json!("2002");
+    vintage["ProductNameBold"] = json!("Castillo de Gredos 2019 75cl");
+    vintage["Volume"] = json!(750.0);
+    products.push(vintage);
+    let upstream = upstream(vec![products]).await;
+    let config = apk::config::Config {
+        names: NamesConfig {
+            slugs: vec![("1002".to_string(), "mariestads-export".to_string())]
+                .into_iter()
+                .collect(),
+            ..NamesConfig::default()
+        },
+        ..Default::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let state = server.state().clone();
+
+    let (_, body) = get(state.clone(), "/").await;
+    assert!(body.contains("href=\"/produkt/1001/norrlands-guld\""));
+    assert!(body.contains("href=\"/produkt/1002/mariestads-export\""));
+    assert!(body.contains("href=\"/produkt/3001/kopparbergs-paron\""));
+
+    let (status, body) = get(state.clone(), "/produkt/2001/castillo-de-gredos").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("<h1>Castillo de Gredos</h1>"));
+    assert!(body.contains("href=\"/produkt/2002/castillo-de-gredos-75-cl\""));
+    let (status, _) = get(state.clone(), "/produkt/2001/old-slug").await;
+    assert_eq!(status, 200);
+    let (_, body) = get(state.clone(), "/produkt/1001").await;
+    assert!(!body.contains("Finns också som"));
+
+    let (_, body) = get(state, "/?sok=paron").await;
+    assert!(body.contains("Kopparbergs Päron"));
+    assert!(!body.contains("Norrlands Guld"));
+}
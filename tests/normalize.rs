@@ -0,0 +1,39 @@
+mod common;
+
+use apk::catalog;
+use apk::normalize::{self, Fix};
+use apk::source::parse_records;
+use apk::units::{Measures, Ml, Sek};
+use common::{fixture, get, refresh, upstream};
+use serde_json::json;
+
+#[tokio::test]
+async fn fixes_upstream_numbers() {
+    let mut products = fixture();
+    products[0]["Price"] = json!("13,90");
+    products[0]["RecycleFee"] = json!(-1.0);
+    products[0]["Volume"] = json!(" 500 ");
+    products[0]["SugarContent"] = json!("okänt");
+    products[1]["RecycleFee"] = json!(null);
+    // Without a price, there's no APK, so it's left out
+    products[2]["Price"] = json!(null);
+
+    let parsed = parse_records(products.clone());
+    assert_eq!(parsed.len(), products.len() - 1);
+    let norrlands = &parsed[0];
+    assert_eq!(norrlands.price_with_deposit(), Sek(13.9));
+    assert_eq!(norrlands.volume(), Ml(500.0));
+    assert_eq!(catalog::sugar(norrlands), None);
+    assert_eq!(parsed[1].price_with_deposit(), Sek(17.9));
+    assert!(parsed.iter().all(|drink| catalog::id(drink) != "2001"));
+
+    assert_eq!(normalize::count(Fix::CommaDecimal), 1);
+    assert_eq!(normalize::count(Fix::Text), 1);
+    assert_eq!(normalize::count(Fix::Negative), 1);
+    assert_eq!(normalize::count(Fix::Missing), 2);
+
+    let upstream = upstream(vec![products]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let (_, body) = get(state, "/metrics").await;
+    assert!(body.contains("apk_normalizations_total{fix=\"comma_decimal\"} 2\n"));
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use apk::config::NtfyConfig;
+use apk::diff::Diff;
+use apk::notify::{Notifier, RefreshEvent};
+use apk::ntfy::NtfyNotifier;
+use common::{fixture, refresh, upstream};
+use wiremock::matchers::{body_string_contains, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn publishes_alerts_to_topic() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let event = RefreshEvent {
+        snapshot: state.snapshot.read().unwrap().clone().unwrap(),
+        previous: None,
+        diff: Diff {
+            restocked: vec!["1001".to_string()],
+            ..Diff::default()
+        },
+    };
+    let ntfy = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/apk"))
+        .and(header("authorization", "Bearer hemligt"))
+        .and(body_string_contains("Norrlands Guld finns i lager igen"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&ntfy)
+        .await;
+
+    let notifier = NtfyNotifier::new(NtfyConfig {
+        url: format!("{}/apk", ntfy.uri()),
+        token: Some(secrecy::SecretString::new("hemligt".to_string())),
+        watch: Vec::new(),
+    });
+    notifier.notify(&state, &event).await.unwrap();
+}
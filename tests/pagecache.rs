@@ -0,0 +1,44 @@
+mod common;
+
+use apk::server::ApkServer;
+use apk::storage::FileStorage;
+use common::{fixture, get, source, upstream};
+use std::time::Duration;
+use wiremock::MockServer;
+
+#[tokio::test]
+async fn serves_the_last_pages_after_a_restart() {
+    let dir = std::env::temp_dir().join(format!("apk-pagecache-{}", std::process::id()));
+    let upstream = upstream(vec![fixture()]).await;
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .storage(FileStorage::new(&dir).unwrap())
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    tokio::time::delay_for(Duration::from_millis(200)).await;
+    let page = server
+        .state()
+        .snapshot
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap()
+        .page
+        .clone();
+
+    // Restarted, with nothing to fetch from yet
+    let down = MockServer::start().await;
+    let restarted = ApkServer::builder()
+        .source(source(&down))
+        .storage(FileStorage::new(&dir).unwrap())
+        .build()
+        .unwrap();
+    let (status, body) = get(restarted.state().clone(), "/").await;
+    assert_eq!(status, 200);
+    assert_eq!(body, page);
+    let (_, body) = get(restarted.state().clone(), "/ol").await;
+    assert!(body.contains("Norrlands Guld"));
+    assert!(!body.contains("Explorer Vodka"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
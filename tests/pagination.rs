@@ -0,0 +1,29 @@
+mod common;
+
+use common::{fixture, get, refresh, upstream};
+
+#[tokio::test]
+async fn paginates_categories() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+
+    let (status, body) = get(state.clone(), "/?kategori=%C3%B6l&per_sida=1").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Norrlands Guld"));
+    assert!(!body.contains("Mariestads"));
+    assert!(body.contains("Sida 1 av 2"));
+    assert!(body.contains("/?kategori=%C3%B6l&amp;sida=2&amp;per_sida=1#"));
+
+    let (status, body) = get(state.clone(), "/?kategori=%C3%B6l&sida=2&per_sida=1").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Mariestads"));
+    assert!(!body.contains("Norrlands Guld"));
+    assert!(body.contains("Föregående"));
+
+    let response = warp::test::request()
+        .path("/?page=2&per_page=1")
+        .reply(&apk::server::routes(state))
+        .await;
+    assert_eq!(response.status(), 301);
+    assert_eq!(response.headers()["location"], "/?sida=2&per_sida=1");
+}
@@ -0,0 +1,25 @@
+mod common;
+
+use apk::prefs::{decode, encode};
+use apk::view::View;
+use common::{fixture, get, refresh, upstream};
+
+#[test]
+fn round_trips_encoded_view() {
+    let view = View::from_query("kategori=%C3%B6l&sok=guld&maxpris=20&sortera=pris&provade=dolj");
+    assert_eq!(decode(&encode(&view)), Some(view));
+    assert_eq!(encode(&View::default()), "e30");
+    assert_eq!(decode("inte base64!"), None);
+}
+
+#[tokio::test]
+async fn shows_pinned_view_without_redirecting() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let link = View::from_query("kategori=sprit").pinned_link();
+
+    let (status, body) = get(state, &link).await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Explorer Vodka"));
+    assert!(!body.contains("Norrlands Guld"));
+}
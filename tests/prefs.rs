@@ -0,0 +1,36 @@
+mod common;
+
+use apk::prefs::{cookie, view};
+use apk::view::View;
+use common::{fixture, refresh, upstream};
+
+#[test]
+fn round_trips_preferred_view() {
+    let preferred = View::from_query("kategori=%C3%B6l&sortera=pris");
+    let set_cookie = cookie(&preferred);
+    let value = set_cookie
+        .trim_start_matches("prefs=")
+        .split(';')
+        .next()
+        .unwrap();
+
+    assert!(!value.contains('='));
+    assert_eq!(view(Some(value)), Some(preferred));
+    assert!(cookie(&View::default()).contains("Max-Age=0"));
+}
+
+#[tokio::test]
+async fn shows_preferred_view_at_root() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let set_cookie = cookie(&View::from_query("kategori=sprit"));
+
+    let response = warp::test::request()
+        .path("/")
+        .header("cookie", set_cookie.split(';').next().unwrap())
+        .reply(&apk::server::routes(state))
+        .await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains("Explorer Vodka"));
+    assert!(!body.contains("Norrlands Guld"));
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use apk::config::{Config, PresetConfig};
+use apk::server::ApkServer;
+use common::{fixture, get, source, upstream};
+
+#[tokio::test]
+async fn serves_presets() {
+    let upstream = upstream(vec![fixture()]).await;
+    let config = Config {
+        presets: vec![PresetConfig {
+            slug: "fredagsol".to_string(),
+            name: "Fredagsöl".to_string(),
+            query: "kategori=öl&maxpris=16&minalkohol=5".to_string(),
+        }],
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let state = server.state().clone();
+
+    let (_, body) = get(state.clone(), "/").await;
+    assert!(body.contains("Fredagsöl"));
+
+    let (status, body) = get(state.clone(), "/view/fredagsol").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Norrlands Guld"));
+    assert!(!body.contains("Mariestads"));
+    assert!(!body.contains("Explorer Vodka"));
+
+    let (status, _) = get(state, "/view/lordagsvin").await;
+    assert_eq!(status, 404);
+}
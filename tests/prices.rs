@@ -0,0 +1,62 @@
+mod common;
+
+use apk::config::{Config, PriceHistoryConfig};
+use apk::server::ApkServer;
+use apk::source::Clock;
+use common::{fixture, get, mount_page, source, upstream};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+struct TestClock(Arc<AtomicU64>);
+
+impl Clock for TestClock {
+    fn now(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.0.load(Ordering::SeqCst))
+    }
+}
+
+#[tokio::test]
+async fn records_price_history() {
+    let path = std::env::temp_dir().join(format!("apk-prices-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let upstream = upstream(vec![fixture()]).await;
+    let time = Arc::new(AtomicU64::new(1_600_000_000));
+    let config = Config {
+        price_history: Some(PriceHistoryConfig { path: path.clone() }),
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .clock(TestClock(time.clone()))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+
+    let mut cheaper = fixture();
+    cheaper[0]["Price"] = json!(12.9);
+    upstream.reset().await;
+    mount_page(&upstream, 1, cheaper).await;
+    mount_page(&upstream, 2, Vec::new()).await;
+    time.store(1_600_007_200, Ordering::SeqCst);
+    server.update().await.unwrap();
+    // The prices are recorded by a notifier, in the background
+    tokio::time::delay_for(Duration::from_millis(200)).await;
+
+    let (status, body) = get(server.state().clone(), "/history/1001").await;
+    assert_eq!(status, 200);
+    let points: Vec<Value> = serde_json::from_str(&body).unwrap();
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[0]["at"], 1_600_000_000);
+    assert_eq!(points[0]["price"], 14.9);
+    assert_eq!(points[1]["price"], 12.9);
+    assert_eq!(points[1]["volume"], 500.0);
+    assert!(points[1]["apk"].as_f64().unwrap() > points[0]["apk"].as_f64().unwrap());
+
+    let (status, _) = get(server.state().clone(), "/history/9999").await;
+    assert_eq!(status, 404);
+
+    std::fs::remove_file(&path).unwrap();
+}
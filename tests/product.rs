@@ -0,0 +1,30 @@
+mod common;
+
+use common::{fixture, get, refresh, upstream};
+
+#[tokio::test]
+async fn shows_a_products_details() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+
+    let (status, body) = get(state.clone(), "/product/1001").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Norrlands Guld"));
+    assert!(body.contains("APK på Basen"));
+    // 14.90 kr and 1 kr deposit for half a liter
+    assert!(body.contains("31.80 kr/l"));
+    assert!(body.contains("Fast sortiment"));
+    assert!(body.contains("Bryggeri"));
+    assert!(body.contains("Sverige"));
+    assert!(body.contains("href=\"/buy/1001\""));
+
+    // The same product, by product number and by its Swedish path
+    let (status, by_number) = get(state.clone(), "/product/100103").await;
+    assert_eq!(status, 200);
+    assert_eq!(by_number, body);
+    let (_, swedish) = get(state.clone(), "/produkt/1001").await;
+    assert_eq!(swedish, body);
+
+    let (status, _) = get(state, "/product/999999").await;
+    assert_eq!(status, 404);
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use apk::server::ApkServer;
+use apk::storage::FileStorage;
+use common::{fixture, get, source, upstream};
+use std::time::{SystemTime, UNIX_EPOCH};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn shows_the_kept_products_when_upstream_is_down() {
+    let dir = std::env::temp_dir().join(format!("apk-productcache-{}", std::process::id()));
+    let upstream = upstream(vec![fixture()]).await;
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .storage(FileStorage::new(&dir).unwrap())
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let fetched = server.state().snapshot.read().unwrap().clone().unwrap();
+    assert!(fetched.data_from.is_none());
+    assert!(!fetched.page.contains("svarar inte"));
+
+    let down = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&down)
+        .await;
+    let restarted = ApkServer::builder()
+        .source(source(&down))
+        .storage(FileStorage::new(&dir).unwrap())
+        .build()
+        .unwrap();
+    restarted.update().await.unwrap();
+    let snapshot = restarted.state().snapshot.read().unwrap().clone().unwrap();
+    let secs = |time: SystemTime| time.duration_since(UNIX_EPOCH).unwrap().as_secs();
+    assert_eq!(snapshot.data_from.map(secs), Some(secs(fetched.updated_at)));
+    assert_eq!(snapshot.catalog.len(), fetched.catalog.len());
+    let (_, body) = get(restarted.state().clone(), "/").await;
+    assert!(body.contains("Systemets API svarar inte just nu"));
+    assert!(body.contains("Norrlands Guld"));
+
+    // Already showing them, so there's nothing to fall back to
+    assert!(restarted.update().await.is_err());
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use apk::config::PublishConfig;
+use apk::dates;
+use apk::diff::Diff;
+use apk::notify::{Notifier, RefreshEvent};
+use apk::publish::Publisher;
+use common::{fixture, refresh, upstream};
+use wiremock::matchers::{body_string_contains, header, header_exists, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn uploads_exports_by_time_and_as_latest() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let snapshot = state.snapshot.read().unwrap().clone().unwrap();
+    let stamp = dates::basic_timestamp(snapshot.updated_at);
+    let event = RefreshEvent {
+        snapshot,
+        previous: None,
+        diff: Diff::default(),
+    };
+    let bucket = MockServer::start().await;
+    for folder in &[stamp.as_str(), "latest"] {
+        Mock::given(method("PUT"))
+            .and(path(format!("/apk-bucket/arkiv/{}/products.csv", folder)))
+            .and(header("content-type", "text/csv; charset=utf-8"))
+            .and(header_exists("x-amz-date"))
+            .and(header_exists("authorization"))
+            .and(body_string_contains("1001,Norrlands Guld,Öl,15.90"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&bucket)
+            .await;
+    }
+    Mock::given(method("PUT"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(4)
+        .mount(&bucket)
+        .await;
+
+    let publisher = Publisher::new(&PublishConfig {
+        endpoint: bucket.uri(),
+        bucket: "apk-bucket".to_string(),
+        region: "eu-north-1".to_string(),
+        access_key: "AKID".to_string(),
+        secret_key: secrecy::SecretString::new("hemligt".to_string()),
+        prefix: "arkiv/".to_string(),
+    });
+    publisher.notify(&state, &event).await.unwrap();
+}
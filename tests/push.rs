@@ -0,0 +1,140 @@
+use apk::config::{Config, PushConfig};
+use apk::push::{allowed, subscribe, subscriptions, unsubscribe, PushSubscription};
+use apk::server::{self, AppState};
+use apk::storage::MemoryStorage;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+fn subscription(endpoint: &str, watch: &[&str]) -> PushSubscription {
+    serde_json::from_value(json!({
+        "subscription": {
+            "endpoint": endpoint,
+            "keys": { "p256dh": "BPubKey", "auth": "secret" },
+        },
+        "watch": watch,
+    }))
+    .unwrap()
+}
+
+#[test]
+fn resubscribing_replaces_the_endpoint() {
+    let storage = MemoryStorage::default();
+    let add = |endpoint: &str, watch: &[&str]| {
+        subscribe(&storage, subscription(endpoint, watch), 2).unwrap()
+    };
+    assert!(add("https://push.example.com/a", &[]));
+    assert!(add("https://push.example.com/a", &["1001"]));
+    assert!(add("https://push.example.com/b", &[]));
+    // No more than the most kept, but the ones kept can still change
+    assert!(!add("https://push.example.com/c", &[]));
+    assert!(add("https://push.example.com/b", &["1002"]));
+
+    let stored = subscriptions(&storage).unwrap();
+    assert_eq!(stored.len(), 2);
+    assert_eq!(stored[0].watch, vec!["1001".to_string()]);
+}
+
+#[test]
+fn unsubscribes_by_endpoint() {
+    let storage = MemoryStorage::default();
+    subscribe(
+        &storage,
+        subscription("https://push.example.com/a", &[]),
+        10,
+    )
+    .unwrap();
+
+    let endpoints = ["https://push.example.com/a".to_string()];
+    assert!(unsubscribe(&storage, &endpoints).unwrap());
+    assert!(!unsubscribe(&storage, &endpoints).unwrap());
+    assert!(subscriptions(&storage).unwrap().is_empty());
+}
+
+fn config() -> PushConfig {
+    serde_json::from_value(json!({
+        "private_key": "vapid.pem",
+        "public_key": "BPublic",
+        "subject": "mailto:apk@example.com",
+    }))
+    .unwrap()
+}
+
+#[test]
+fn allows_only_push_services() {
+    let config = config();
+
+    assert!(allowed(&config, "https://fcm.googleapis.com/fcm/send/abc"));
+    assert!(allowed(
+        &config,
+        "https://updates.push.services.mozilla.com/wpush/v2/abc"
+    ));
+    assert!(!allowed(&config, "http://fcm.googleapis.com/fcm/send/abc"));
+    assert!(!allowed(&config, "https://fcm.googleapis.com:8443/abc"));
+    assert!(!allowed(
+        &config,
+        "https://evilfcm.googleapis.com.example/abc"
+    ));
+    assert!(!allowed(&config, "https://127.0.0.1/abc"));
+    assert!(!allowed(&config, "https://localhost/abc"));
+    assert!(!allowed(&config, "https://fcm.googleapis.com@10.0.0.1/abc"));
+}
+
+async fn post(state: &AppState, path: &str, body: Value) -> u16 {
+    warp::test::request()
+        .method("POST")
+        .path(path)
+        .json(&body)
+        .reply(&server::routes(state.clone()))
+        .await
+        .status()
+        .as_u16()
+}
+
+#[tokio::test]
+async fn only_the_browser_can_change_a_subscription() {
+    let state = AppState {
+        config: Arc::new(Config {
+            push: Some(config()),
+            ..Config::default()
+        }),
+        ..AppState::default()
+    };
+    let endpoint = "https://fcm.googleapis.com/fcm/send/abc";
+    let body = |endpoint: &str, auth: &str| {
+        json!({
+            "subscription": {
+                "endpoint": endpoint,
+                "keys": { "p256dh": "BPubKey", "auth": auth },
+            },
+            "watch": ["1001"],
+        })
+    };
+
+    assert_eq!(
+        post(
+            &state,
+            "/push/subscribe",
+            body("http://10.0.0.1/", "secret")
+        )
+        .await,
+        400
+    );
+    assert_eq!(
+        post(&state, "/push/subscribe", body(endpoint, "secret")).await,
+        201
+    );
+    assert_eq!(
+        post(&state, "/push/subscribe", body(endpoint, "guess")).await,
+        403
+    );
+    let unsubscribe = |auth: &str| json!({ "endpoint": endpoint, "auth": auth });
+    assert_eq!(
+        post(&state, "/push/unsubscribe", unsubscribe("guess")).await,
+        403
+    );
+    assert_eq!(
+        post(&state, "/push/unsubscribe", unsubscribe("secret")).await,
+        204
+    );
+    assert!(subscriptions(&*state.storage).unwrap().is_empty());
+}
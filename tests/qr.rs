@@ -0,0 +1,39 @@
+mod common;
+
+use common::{fixture, refresh, upstream};
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+#[tokio::test]
+async fn renders_product_and_url_codes() {
+    let upstream = upstream(vec![fixture()]).await;
+    let routes = apk::server::routes(refresh(&upstream).await.unwrap());
+
+    for path in &[
+        "/qr/product/1001.png",
+        "/qr?url=https%3A%2F%2Fapk.example.com%2F%3Fkategori%3Dsprit",
+    ] {
+        let response = warp::test::request().path(path).reply(&routes).await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], "image/png");
+        assert!(response.body().starts_with(PNG_SIGNATURE));
+    }
+}
+
+#[tokio::test]
+async fn rejects_unknown_products_and_urls() {
+    let upstream = upstream(vec![fixture()]).await;
+    let routes = apk::server::routes(refresh(&upstream).await.unwrap());
+
+    let response = warp::test::request()
+        .path("/qr/product/9999.png")
+        .reply(&routes)
+        .await;
+    // Falls through to the list, like any other unknown path
+    assert_ne!(response.headers()["content-type"], "image/png");
+    let response = warp::test::request()
+        .path("/qr?url=javascript%3Aalert(1)")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 400);
+}
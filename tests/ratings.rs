@@ -0,0 +1,109 @@
+mod common;
+
+use apk::ratings::{averages, mine, rate};
+use apk::ratings::{MAX_PER_CLIENT, MAX_PER_PRODUCT};
+use apk::session;
+use apk::storage::MemoryStorage;
+use common::{fixture, refresh, upstream};
+use std::net::SocketAddr;
+
+#[test]
+fn averages_ratings_of_all_sessions() {
+    let storage = MemoryStorage::default();
+    rate(&storage, "a", "1001", 5).unwrap();
+    rate(&storage, "b", "1001", 2).unwrap();
+    rate(&storage, "b", "1002", 4).unwrap();
+    rate(&storage, "b", "1002", 0).unwrap();
+
+    let averages = averages(&storage).unwrap();
+    assert_eq!(averages.get("1001"), Some(&3.5));
+    assert_eq!(averages.get("1002"), None);
+    assert_eq!(mine(&storage, "a").unwrap().get("1001"), Some(&5));
+    assert!(mine(&storage, "c").unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn shows_ratings_in_list() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let routes = apk::server::routes(state.clone());
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/ratings")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body("id=1001&rating=4&back=%2F")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 303);
+    let set_cookie = response.headers()["set-cookie"].to_str().unwrap();
+    let value = set_cookie.split(';').next().unwrap().to_string();
+    // Only counted from a session that was there before
+    assert!(state.ratings.read().unwrap().is_empty());
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/ratings")
+        .header("cookie", &value)
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body("id=1001&rating=4&back=%2F")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 303);
+    assert!(response.headers().get("set-cookie").is_none());
+    assert_eq!(state.ratings.read().unwrap().get("1001"), Some(&4.0));
+
+    let response = warp::test::request()
+        .path("/")
+        .header("cookie", value)
+        .reply(&routes)
+        .await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains("4.0"));
+    assert!(body.contains("<option value=\"4\" selected>"));
+}
+
+#[tokio::test]
+async fn rejects_invalid_ratings() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/ratings")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body("id=1001&rating=bra")
+        .reply(&apk::server::routes(state))
+        .await;
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn limits_ratings_per_client_and_product() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let routes = apk::server::routes(state.clone());
+    let cookie = session::cookie(&state.cookie_key, "a");
+    let cookie = cookie.split(';').next().unwrap();
+    let rate = |peer: u8, id: &str| {
+        warp::test::request()
+            .method("POST")
+            .path("/ratings")
+            .remote_addr(SocketAddr::from(([10, 0, 0, peer], 4711)))
+            .header("cookie", cookie)
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(format!("id={}&rating=4", id))
+    };
+
+    for peer in 0..MAX_PER_PRODUCT as u8 {
+        assert_eq!(rate(peer, "1001").reply(&routes).await.status(), 303);
+    }
+    assert_eq!(rate(100, "1001").reply(&routes).await.status(), 429);
+
+    let ids = ["1002", "2001", "3001"];
+    for n in 0..MAX_PER_CLIENT as usize {
+        let id = ids[n % ids.len()];
+        assert_eq!(rate(200, id).reply(&routes).await.status(), 303);
+    }
+    assert_eq!(rate(200, "4001").reply(&routes).await.status(), 429);
+}
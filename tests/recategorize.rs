@@ -0,0 +1,39 @@
+mod common;
+
+use apk::server::ApkServer;
+use common::{fixture, source, upstream, with_admin, ADMIN_TOKEN};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn recategorizes_without_fetching() {
+    let upstream = upstream(vec![fixture()]).await;
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .build()
+        .unwrap();
+    let routes = apk::server::routes(with_admin(server.state().clone()));
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/admin/recategorize")
+        .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 500);
+
+    server.update().await.unwrap();
+    let before = server.state().snapshot.read().unwrap().clone().unwrap();
+    // Fetching again would fail from now on
+    upstream.reset().await;
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/admin/recategorize")
+        .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 200);
+    let after = server.state().snapshot.read().unwrap().clone().unwrap();
+    assert!(!Arc::ptr_eq(&before, &after));
+    assert_eq!(before.hash, after.hash);
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use apk::catalog::Category;
+use apk::records::{self, Record};
+use apk::server::ApkServer;
+use apk::storage::{self, MemoryStorage};
+use apk::units::Apk;
+use common::{fixture, get, upstream};
+use serde_json::json;
+
+#[test]
+fn starts_from_the_history() {
+    let storage = MemoryStorage::default();
+    let history = json!([
+        {"at": 1, "best": {"Sprit": 1.0, "Öl": 0.3}, "products": {}, "errors": 0},
+        {"at": 2, "best": {"Sprit": 1.2, "Öl": 0.2}, "products": {}, "errors": 0},
+    ]);
+    storage::save_json(&storage, "history.json", &history).unwrap();
+
+    let records = records::load(&storage).unwrap();
+    assert_eq!(records[&Category::Liquor].apk, Apk(1.2));
+    assert_eq!(records[&Category::Liquor].at, 2);
+    assert_eq!(records[&Category::Beer].apk, Apk(0.3));
+    assert_eq!(records[&Category::Liquor].id, None);
+}
+
+#[tokio::test]
+async fn marks_products_that_beat_the_record() {
+    let storage = MemoryStorage::default();
+    let record = Record {
+        apk: Apk(1.0),
+        kr_per_standard_drink: 15.21,
+        id: None,
+        name: None,
+        at: 1,
+    };
+    let records = vec![(Category::Liquor, record)]
+        .into_iter()
+        .collect::<records::Records>();
+    storage::save_json(&storage, "records.json", &records).unwrap();
+    let upstream = upstream(vec![fixture()]).await;
+    let server = ApkServer::builder()
+        .source(common::source(&upstream))
+        .storage(storage)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+
+    let (_, body) = get(server.state().clone(), "/").await;
+    assert_eq!(body.matches("Rekord!").count(), 1);
+    assert!(body.contains("15.21 kr per standardglas"));
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use apk::reload::Reloader;
+use apk::score;
+use apk::server::ApkServer;
+use common::{fixture, get, source, upstream};
+use std::fs;
+
+fn theme(version: u32) -> String {
+    format!(
+        "Version {}:{{% for category, list in drinks %}}{{% for drink in list %}} {{{{drink.ProductNameBold}}}}{{% endfor %}}{{% endfor %}}",
+        version
+    )
+}
+
+#[tokio::test]
+async fn reloads_templates() {
+    let dir = std::env::temp_dir().join(format!("apk-theme-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("apk.html"), theme(1)).unwrap();
+    let glob = format!("{}/*", dir.display());
+
+    let upstream = upstream(vec![fixture()]).await;
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .theme(glob.clone())
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let state = server.state().clone();
+    let (_, body) = get(state.clone(), "/").await;
+    assert!(body.starts_with("Version 1:"));
+
+    let reloader = Reloader::new(glob, score::default_scorers());
+    fs::write(dir.join("apk.html"), theme(2)).unwrap();
+    reloader.reload(&state).unwrap();
+    let (_, body) = get(state.clone(), "/").await;
+    assert!(body.starts_with("Version 2:"));
+    assert!(body.contains("Norrlands Guld"));
+
+    // A broken template is left out, and the working one kept
+    fs::write(dir.join("apk.html"), "{% for").unwrap();
+    assert!(reloader.reload(&state).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+    let (_, body) = get(state, "/").await;
+    assert!(body.starts_with("Version 2:"));
+}
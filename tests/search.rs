@@ -0,0 +1,73 @@
+mod common;
+
+use common::{fixture, get, refresh, upstream};
+use serde_json::Value;
+
+#[tokio::test]
+async fn finds_products_despite_typos() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+
+    let (status, body) = get(state.clone(), "/api/search?q=NORRLANDS").await;
+    assert_eq!(status, 200);
+    let results: Vec<Value> = serde_json::from_str(&body).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["id"], "1001");
+
+    // A letter off, or missing, and the start of a word
+    for query in &["norlands", "kopparbegs", "castilo gred", "expl vodka"] {
+        let (_, body) = get(state.clone(), &format!("/api/search?q={}", query)).await;
+        let results: Vec<Value> = serde_json::from_str(&body).unwrap();
+        assert_eq!(results.len(), 1, "{}", query);
+    }
+    // Short words must be spelled right
+    let (_, body) = get(state.clone(), "/api/search?q=ipo").await;
+    assert_eq!(body, "[]");
+
+    // By producer, which they all share
+    let (_, body) = get(state.clone(), "/api/search?q=bryggeri").await;
+    let results: Vec<Value> = serde_json::from_str(&body).unwrap();
+    let listed = state
+        .snapshot
+        .read()
+        .unwrap()
+        .as_ref()
+        .unwrap()
+        .catalog
+        .len();
+    assert_eq!(results.len(), listed);
+
+    let (status, body) = get(state.clone(), "/search?q=guld").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("href=\"/produkt/1001\""));
+    assert!(!body.contains("Mariestads"));
+    let (status, body) = get(state, "/search").await;
+    assert_eq!(status, 200);
+    assert!(!body.contains("Hittade inget"));
+}
+
+#[tokio::test]
+async fn tolerates_typos() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+
+    let (status, body) = get(state.clone(), "/?sok=norlands").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Norrlands Guld"));
+    assert!(!body.contains("Mariestads"));
+
+    let (status, body) = get(state.clone(), "/api/suggest?q=ljus+lagr").await;
+    assert_eq!(status, 200);
+    let suggestions: Value = serde_json::from_str(&body).unwrap();
+    let names: Vec<&str> = suggestions
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|drink| drink["name"].as_str().unwrap())
+        .collect();
+    // Both are light lagers, the cheaper one first
+    assert_eq!(names, ["Norrlands Guld", "Mariestads"]);
+
+    let (_, body) = get(state, "/api/suggest?q=zzzz").await;
+    assert_eq!(body, "[]");
+}
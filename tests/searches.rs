@@ -0,0 +1,50 @@
+mod common;
+
+use apk::diff::Diff;
+use apk::email::{save_search, subscribers};
+use apk::notify::RefreshEvent;
+use apk::searches::SavedSearch;
+use apk::storage::MemoryStorage;
+use common::{fixture, refresh, upstream};
+
+#[test]
+fn canonicalizes_queries() {
+    let search = SavedSearch::new("/?sok=Guld&kategori=", Some(0.1));
+    assert_eq!(search.query, "sok=guld");
+}
+
+#[test]
+fn saves_searches_for_new_subscribers() {
+    let storage = MemoryStorage::default();
+    let search = SavedSearch::new("sok=ipa&maxpris=25", None);
+    save_search(&storage, "kalle@example.com", search.clone()).unwrap();
+    save_search(&storage, "kalle@example.com", search.clone()).unwrap();
+
+    let subscribers = subscribers(&storage).unwrap();
+    assert_eq!(subscribers.len(), 1);
+    assert_eq!(subscribers[0].searches, vec![search]);
+}
+
+#[tokio::test]
+async fn alerts_only_about_new_matches() {
+    let old = refresh(&upstream(vec![fixture()[..1].to_vec()]).await)
+        .await
+        .unwrap();
+    let new = refresh(&upstream(vec![fixture()]).await).await.unwrap();
+    let event = RefreshEvent {
+        snapshot: new.snapshot.read().unwrap().clone().unwrap(),
+        previous: old.snapshot.read().unwrap().clone(),
+        diff: Diff::default(),
+    };
+
+    let beer = SavedSearch::new("kategori=öl", None);
+    let names: Vec<_> = beer
+        .new_matches(&event, &[])
+        .into_iter()
+        .map(apk::catalog::name)
+        .collect();
+    assert_eq!(names, vec!["Mariestads"]);
+    assert!(SavedSearch::new("sok=vodka", Some(1000.0))
+        .new_matches(&event, &[])
+        .is_empty());
+}
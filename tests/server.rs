@@ -0,0 +1,164 @@
+mod common;
+
+use apk::notify::{Notifier, RefreshEvent};
+use apk::server::AppState;
+use apk::ApkServer;
+use common::{fixture, get, get_admin, mount_page, refresh, upstream};
+use serde_json::json;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn renders_listed_products_by_apk() {
+    let upstream = upstream(vec![fixture()]).await;
+    let (status, body) = get(refresh(&upstream).await.unwrap(), "/").await;
+
+    assert_eq!(status, 200);
+    let norrlands = body.find("Norrlands Guld").unwrap();
+    let mariestads = body.find("Mariestads").unwrap();
+    assert!(norrlands < mariestads);
+    assert!(body.contains("Castillo de Gredos"));
+    assert!(body.contains("Kopparbergs Päron"));
+    assert!(body.contains("Explorer Vodka"));
+    assert!(!body.contains("Lokal Lager"));
+    assert!(!body.contains("Alkoholfri IPA"));
+}
+
+#[tokio::test]
+async fn follows_pagination() {
+    let mut products = fixture();
+    let second = products.split_off(3);
+    let upstream = upstream(vec![products, second]).await;
+    let (_, body) = get(refresh(&upstream).await.unwrap(), "/").await;
+
+    assert!(body.contains("Norrlands Guld"));
+    assert!(body.contains("Explorer Vodka"));
+}
+
+#[tokio::test]
+async fn retries_server_errors() {
+    let upstream = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .mount(&upstream)
+        .await;
+    mount_page(&upstream, 1, fixture()).await;
+    mount_page(&upstream, 2, Vec::new()).await;
+    let (_, body) = get(refresh(&upstream).await.unwrap(), "/").await;
+
+    assert!(body.contains("Norrlands Guld"));
+}
+
+#[tokio::test]
+async fn gives_up_after_retries() {
+    let upstream = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(3)
+        .mount(&upstream)
+        .await;
+
+    assert!(refresh(&upstream).await.is_err());
+}
+
+#[tokio::test]
+async fn skips_malformed_records() {
+    let mut products = fixture();
+    products.push(json!({ "ProductNameBold": "Trasig" }));
+    products.push(json!("not a product"));
+    products[1]["AlcoholPercentage"] = json!("mycket");
+    let upstream = upstream(vec![products]).await;
+    let (_, body) = get(refresh(&upstream).await.unwrap(), "/").await;
+
+    assert!(body.contains("Norrlands Guld"));
+    assert!(!body.contains("Mariestads"));
+    assert!(!body.contains("Trasig"));
+}
+
+#[tokio::test]
+async fn serves_empty_page_before_first_refresh() {
+    let (status, body) = get(Default::default(), "/").await;
+
+    assert_eq!(status, 200);
+    assert!(body.is_empty());
+}
+
+#[tokio::test]
+async fn reports_catalog_size_in_metrics() {
+    let upstream = upstream(vec![fixture()]).await;
+    let (status, body) = get(refresh(&upstream).await.unwrap(), "/metrics").await;
+
+    assert_eq!(status, 200);
+    assert!(body.contains("apk_products{category=\"Öl\"} 2"));
+    assert!(body.contains("apk_refresh_errors_total{category=\"upstream\"} 0"));
+    assert!(body.contains("apk_refresh_errors_total{category=\"render\"} 0"));
+}
+
+#[tokio::test]
+async fn reports_status_as_json() {
+    let (status, body) = get_admin(Default::default(), "/admin/status").await;
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(status, 200);
+    assert_eq!(body["refreshes"], 0);
+    assert!(body["last_error"].is_null());
+}
+
+#[tokio::test]
+async fn reports_failed_refreshes_by_category() {
+    let upstream = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&upstream)
+        .await;
+    let server = ApkServer::builder()
+        .source(common::source(&upstream))
+        .build()
+        .unwrap();
+    assert!(server.update().await.is_err());
+    let (_, body) = get_admin(server.state().clone(), "/admin/status").await;
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(body["errors"]["upstream"], 1);
+    assert_eq!(body["last_error"]["category"], "upstream");
+}
+
+struct Forward(tokio::sync::mpsc::UnboundedSender<usize>);
+
+#[async_trait::async_trait]
+impl Notifier for Forward {
+    fn name(&self) -> &str {
+        "forward"
+    }
+
+    async fn notify(&self, _: &AppState, event: &RefreshEvent) -> apk::Result<()> {
+        self.0.send(event.snapshot.catalog.len()).unwrap();
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn notifies_after_refresh() {
+    let upstream = upstream(vec![fixture()]).await;
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let server = ApkServer::builder()
+        .source(common::source(&upstream))
+        .notifier(Forward(sender))
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+
+    assert_eq!(receiver.recv().await, Some(5));
+}
+
+#[tokio::test]
+async fn serves_drinks_as_json() {
+    let upstream = upstream(vec![fixture()]).await;
+    let (status, body) = get(refresh(&upstream).await.unwrap(), "/api/drinks").await;
+    assert_eq!(status, 200);
+    let drinks: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(drinks["Öl"][0]["ProductNameBold"], "Norrlands Guld");
+    assert_eq!(drinks["Öl"][1]["ProductNameBold"], "Mariestads");
+    assert_eq!(drinks["Sprit"].as_array().unwrap().len(), 1);
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use apk::config::{Config, LoadConfig};
+use apk::server::ApkServer;
+use apk::shed::STALE_WARNING;
+use common::{fixture, source, upstream};
+
+#[tokio::test]
+async fn sheds_load() {
+    let upstream = upstream(vec![fixture()]).await;
+    let config = Config {
+        load: LoadConfig {
+            // Everything is over budget
+            budget: Some(0),
+            ..LoadConfig::default()
+        },
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let routes = apk::server::routes(server.state().clone());
+
+    let response = warp::test::request()
+        .path("/?sok=guld")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 200);
+    assert!(response.headers().get("warning").is_none());
+    let body = String::from_utf8_lossy(response.body());
+    assert!(!body.contains("Explorer Vodka"));
+
+    // The plain list, since there's no stale page of the search
+    let response = warp::test::request()
+        .path("/?sok=guld")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["warning"], STALE_WARNING);
+    let body = String::from_utf8_lossy(response.body());
+    assert!(body.contains("Explorer Vodka"));
+
+    let response = warp::test::request().path("/sprit").reply(&routes).await;
+    assert_eq!(response.headers()["warning"], STALE_WARNING);
+    let body = String::from_utf8_lossy(response.body());
+    assert!(body.contains("Explorer Vodka"));
+    assert!(!body.contains("Norrlands Guld"));
+}
+
+#[tokio::test]
+async fn sheds_renders_beyond_the_limit() {
+    let upstream = upstream(vec![fixture()]).await;
+    let config = Config {
+        load: LoadConfig {
+            max_renders: 0,
+            ..LoadConfig::default()
+        },
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let routes = apk::server::routes(server.state().clone());
+
+    let response = warp::test::request()
+        .path("/?sok=guld")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["warning"], STALE_WARNING);
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use apk::shopping::{lines, list, set, Entry, MAX_NEW_PER_HOUR};
+use apk::storage::MemoryStorage;
+use apk::units::Sek;
+use common::{fixture, refresh, upstream};
+use std::net::SocketAddr;
+
+#[test]
+fn sets_quantities() {
+    let storage = MemoryStorage::default();
+    set(&storage, "abc", "1001", 2).unwrap();
+    set(&storage, "abc", "1002", 1).unwrap();
+    set(&storage, "abc", "1002", 0).unwrap();
+
+    assert_eq!(
+        list(&storage, "abc").unwrap(),
+        vec![Entry {
+            id: "1001".to_string(),
+            quantity: 2
+        }]
+    );
+    assert!(list(&storage, "def").unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn sums_up_the_list() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let snapshot = state.snapshot.read().unwrap().clone().unwrap();
+    let entries = vec![
+        Entry {
+            id: "1001".to_string(),
+            quantity: 2,
+        },
+        Entry {
+            id: "9999".to_string(),
+            quantity: 1,
+        },
+    ];
+
+    let (lines, totals) = lines(&snapshot.catalog, &entries);
+    assert_eq!(lines.len(), 1);
+    // Norrlands Guld: 14.90 kr plus 1 kr deposit, 500 ml at 5.3%
+    assert!((totals.price.0 - 31.8).abs() < 1e-9);
+    assert!((totals.apk - 53.0 / 31.8).abs() < 1e-9);
+    assert!((totals.standard_drinks - 53.0 * 0.789 / 12.0).abs() < 1e-9);
+    assert!(lines[0].price > Sek(0.0));
+}
+
+#[tokio::test]
+async fn adds_to_the_list_of_a_new_session() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let routes = apk::server::routes(state);
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/list")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body("id=1001")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 303);
+    let cookie = response.headers()["set-cookie"].to_str().unwrap();
+    let cookie = cookie.split(';').next().unwrap().to_string();
+
+    let response = warp::test::request()
+        .path("/list")
+        .header("cookie", cookie)
+        .reply(&routes)
+        .await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains("Norrlands Guld"));
+    assert!(body.contains("31.80 kr"));
+}
+
+#[tokio::test]
+async fn limits_new_lists_per_client() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let routes = apk::server::routes(state.clone());
+    let add = |peer: u8| {
+        warp::test::request()
+            .method("POST")
+            .path("/list")
+            .remote_addr(SocketAddr::from(([10, 0, 0, peer], 4711)))
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body("id=1001")
+    };
+
+    for _ in 0..MAX_NEW_PER_HOUR {
+        assert_eq!(add(1).reply(&routes).await.status(), 303);
+    }
+    let response = add(1).reply(&routes).await;
+    assert_eq!(response.status(), 429);
+    assert!(response.headers().get("set-cookie").is_none());
+    assert_eq!(add(2).reply(&routes).await.status(), 303);
+}
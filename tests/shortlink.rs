@@ -0,0 +1,116 @@
+mod common;
+
+use apk::config::Config;
+use apk::server::AppState;
+use apk::shortlink::{is_allowed, shorten, target, MAX_PER_HOUR};
+use apk::storage::MemoryStorage;
+use common::{fixture, refresh, upstream};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+#[test]
+fn reuses_codes_for_the_same_target() {
+    let storage = MemoryStorage::default();
+    let code = shorten(&storage, "/?kategori=sprit").unwrap().unwrap();
+    assert_eq!(code.len(), 6);
+    assert_eq!(
+        shorten(&storage, "/?kategori=sprit").unwrap(),
+        Some(code.clone())
+    );
+    assert_ne!(
+        shorten(&storage, "/?kategori=vin").unwrap(),
+        Some(code.clone())
+    );
+    assert_eq!(
+        target(&storage, &code).unwrap().as_deref(),
+        Some("/?kategori=sprit")
+    );
+}
+
+#[test]
+fn only_allows_local_and_product_targets() {
+    assert!(is_allowed("/favorites"));
+    assert!(is_allowed("https://www.systembolaget.se/1001/"));
+    assert!(!is_allowed("//example.com"));
+    assert!(!is_allowed("https://example.com/"));
+    assert!(!is_allowed("/\\evil.example"));
+    assert!(!is_allowed("/\\/evil.example"));
+    assert!(!is_allowed("/\t/evil.example"));
+    assert!(!is_allowed(&format!("/?q={}", "x".repeat(1000))));
+}
+
+#[tokio::test]
+async fn creates_and_follows_links() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let routes = apk::server::routes(state.clone());
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/s")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body("target=%2F%3Fkategori%3Dsprit")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 201);
+    let code = shorten(&*state.storage, "/?kategori=sprit")
+        .unwrap()
+        .unwrap();
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains(&format!("/s/{}", code)));
+
+    let response = warp::test::request()
+        .path(&format!("/s/{}", code))
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 302);
+    assert_eq!(response.headers()["location"], "/?kategori=sprit");
+}
+
+#[tokio::test]
+async fn limits_links_per_client() {
+    let upstream = upstream(vec![fixture()]).await;
+    let proxy: IpAddr = "10.0.0.9".parse().unwrap();
+    let state = refresh(&upstream).await.unwrap();
+    let state = AppState {
+        config: Arc::new(Config {
+            trusted_proxies: vec![proxy],
+            ..Config::default()
+        }),
+        ..state
+    };
+    let routes = apk::server::routes(state);
+    let create = |peer: &str, forwarded: &str, n: u32| {
+        warp::test::request()
+            .method("POST")
+            .path("/s")
+            .remote_addr(SocketAddr::new(peer.parse().unwrap(), 4711))
+            .header("x-forwarded-for", forwarded)
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(format!("target=%2F%3Fq%3D{}", n))
+    };
+
+    // Made up forwarded addresses don't make a client someone else
+    for n in 0..MAX_PER_HOUR {
+        let forwarded = format!("192.0.2.{}", n);
+        let response = create("10.0.0.1", &forwarded, n).reply(&routes).await;
+        assert_eq!(response.status(), 201);
+    }
+    let response = create("10.0.0.1", "192.0.2.99", MAX_PER_HOUR)
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 429);
+    assert!(response.headers().contains_key("retry-after"));
+    let response = create("10.0.0.2", "", MAX_PER_HOUR).reply(&routes).await;
+    assert_eq!(response.status(), 201);
+
+    // Unless they're from a trusted proxy
+    let response = create("10.0.0.9", "10.0.0.1, 10.0.0.3", MAX_PER_HOUR + 1)
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 201);
+    let response = create("10.0.0.9", "10.0.0.3, 10.0.0.1", MAX_PER_HOUR + 2)
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 429);
+}
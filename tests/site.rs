@@ -0,0 +1,36 @@
+mod common;
+
+use apk::site;
+use common::{fixture, refresh, upstream};
+use std::fs;
+
+#[tokio::test]
+async fn writes_pages_and_exports() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let snapshot = state.snapshot.read().unwrap().clone().unwrap();
+    let out = std::env::temp_dir().join(format!("apk-site-{}", std::process::id()));
+
+    site::write(&state, &snapshot, &out).unwrap();
+    let index = fs::read_to_string(out.join("index.html")).unwrap();
+    assert_eq!(index, snapshot.page);
+    let beer = fs::read_to_string(out.join("ol/index.html")).unwrap();
+    assert!(beer.contains("Norrlands Guld"));
+    assert!(!beer.contains("Explorer Vodka"));
+    let product = fs::read_to_string(out.join("produkt/1001/index.html")).unwrap();
+    assert!(product.contains("Norrlands Guld"));
+    assert!(out.join("countries/index.html").exists());
+
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(out.join(site::JSON_EXPORT)).unwrap()).unwrap();
+    assert_eq!(json.as_array().unwrap().len(), snapshot.catalog.len());
+    let csv = fs::read_to_string(out.join(site::CSV_EXPORT)).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("id,name,category,price,abv,volume,apk"));
+    assert_eq!(
+        lines.next(),
+        Some("1001,Norrlands Guld,Öl,15.90,5.3,500,1.66667")
+    );
+    assert_eq!(lines.count(), snapshot.catalog.len() - 1);
+    fs::remove_dir_all(out).unwrap();
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use apk::config::{Config, SlackConfig};
+use apk::server::{self, AppState};
+use apk::signing;
+use apk::slack::verify;
+use apk::status::unix_time;
+use common::{fixture, refresh, upstream};
+use secrecy::SecretString;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+const SECRET: &str = "8f742231b10e8888abcd99yyyzzz85a5";
+
+fn sign(timestamp: u64, body: &str) -> String {
+    let message = format!("v0:{}:{}", timestamp, body);
+    format!(
+        "v0={}",
+        signing::hmac_sha256(SECRET.as_bytes(), message.as_bytes())
+    )
+}
+
+#[test]
+fn accepts_fresh_signed_requests() {
+    let now = SystemTime::now();
+    let timestamp = unix_time(now);
+    let body = "command=%2Fapk&text=guld";
+
+    assert!(verify(
+        SECRET.as_bytes(),
+        &timestamp.to_string(),
+        &sign(timestamp, body),
+        body.as_bytes(),
+        now
+    ));
+}
+
+#[test]
+fn rejects_tampered_and_stale_requests() {
+    let now = SystemTime::now();
+    let timestamp = unix_time(now);
+    let body = "command=%2Fapk&text=guld";
+    let signature = sign(timestamp, body);
+
+    assert!(!verify(
+        SECRET.as_bytes(),
+        &timestamp.to_string(),
+        &signature,
+        b"command=%2Fapk&text=vodka",
+        now
+    ));
+    assert!(!verify(
+        b"fel nyckel",
+        &timestamp.to_string(),
+        &signature,
+        body.as_bytes(),
+        now
+    ));
+    let later = now + Duration::from_secs(10 * 60);
+    assert!(!verify(
+        SECRET.as_bytes(),
+        &timestamp.to_string(),
+        &signature,
+        body.as_bytes(),
+        later
+    ));
+}
+
+#[tokio::test]
+async fn answers_slash_commands() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = AppState {
+        config: Arc::new(Config {
+            slack: Some(SlackConfig {
+                signing_secret: SecretString::new(SECRET.to_string()),
+            }),
+            ..Config::default()
+        }),
+        ..refresh(&upstream).await.unwrap()
+    };
+    let timestamp = unix_time(SystemTime::now());
+    let body = "command=%2Fapk&text=guld";
+    let response = warp::test::request()
+        .method("POST")
+        .path("/slack/command")
+        .header("x-slack-request-timestamp", timestamp.to_string())
+        .header("x-slack-signature", sign(timestamp, body))
+        .body(body)
+        .reply(&server::routes(state.clone()))
+        .await;
+    let reply: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert!(reply["text"].as_str().unwrap().contains("Norrlands Guld"));
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/slack/command")
+        .header("x-slack-request-timestamp", timestamp.to_string())
+        .header("x-slack-signature", "v0=00")
+        .body(body)
+        .reply(&server::routes(state))
+        .await;
+    assert_eq!(response.status(), 401);
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use apk::catalog::CATEGORIES;
+use apk::config::Config;
+use apk::server::ApkServer;
+use common::{fixture, get, refresh, source, upstream};
+
+#[tokio::test]
+async fn refreshes_one_category_at_a_time() {
+    let upstream = upstream(vec![fixture()]).await;
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(Config {
+            stagger: true,
+            ..Config::default()
+        })
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    server.update().await.unwrap();
+
+    let staggered = server.state().snapshot.read().unwrap().clone().unwrap();
+    let whole = refresh(&upstream).await.unwrap();
+    let whole = whole.snapshot.read().unwrap().clone().unwrap();
+    assert_eq!(staggered.hash, whole.hash);
+    for category in CATEGORIES.iter() {
+        assert!(staggered.category_updated_at.contains_key(category));
+    }
+
+    let (status, body) = get(server.state().clone(), "/").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Norrlands Guld"));
+    assert!(body.contains("Explorer Vodka"));
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use apk::config::{Config, StockConfig, StockStore};
+use apk::server::ApkServer;
+use common::{fixture, get, source, upstream};
+use serde_json::{json, Value};
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn narrows_the_list_to_a_stores_stock() {
+    let stock = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/stock/0611"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {"productId": "1002", "stock": 12},
+            {"productId": "3001", "stock": 0},
+            {"productId": "4001", "stock": null},
+        ])))
+        .mount(&stock)
+        .await;
+    let upstream = upstream(vec![fixture()]).await;
+    let config = Config {
+        stock: Some(StockConfig {
+            url: format!("{}/stock/{{store}}", stock.uri()),
+            stores: vec![StockStore {
+                id: "0611".to_string(),
+                name: "Umeå Avion".to_string(),
+            }],
+        }),
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    // The stock is fetched by a notifier, in the background
+    tokio::time::delay_for(Duration::from_millis(200)).await;
+    let state = server.state().clone();
+
+    let (_, body) = get(state.clone(), "/").await;
+    assert!(body.contains("<option value=\"0611\">Umeå Avion</option>"));
+
+    let (status, body) = get(state.clone(), "/?butik=0611").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Mariestads"));
+    assert!(!body.contains("Norrlands Guld"));
+    assert!(!body.contains("Kopparbergs"));
+    assert!(!body.contains("Explorer Vodka"));
+    assert!(body.contains("<option value=\"0611\" selected>"));
+
+    // In English, it's redirected to the canonical view
+    let (status, _) = get(state.clone(), "/?store=0611").await;
+    assert_eq!(status, 301);
+
+    // A store without known stock has nothing
+    let (_, body) = get(state.clone(), "/?butik=0999").await;
+    assert!(!body.contains("Mariestads"));
+
+    let products: Value = serde_json::to_value(apk::api::products(
+        &state,
+        &apk::view::View::from_query("butik=0611"),
+    ))
+    .unwrap();
+    assert_eq!(products.as_array().unwrap().len(), 1);
+    assert_eq!(products[0]["id"], "1002");
+    assert_eq!(products[0]["stock"], 12);
+
+    // A default store in the cookie is used when the query has none
+    let routes = apk::server::routes(state.clone());
+    let cookie = |store| {
+        let cookie = apk::stores::cookie(&state.cookie_key, Some(store));
+        cookie.split(';').next().unwrap().to_string()
+    };
+    let response = warp::test::request()
+        .path("/")
+        .header("cookie", cookie("0611"))
+        .reply(&routes)
+        .await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains("Mariestads"));
+    assert!(!body.contains("Norrlands Guld"));
+    // Unless nothing is known about its stock
+    let response = warp::test::request()
+        .path("/")
+        .header("cookie", cookie("0999"))
+        .reply(&routes)
+        .await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains("Norrlands Guld"));
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use apk::config::{Config, StoresConfig};
+use apk::server::ApkServer;
+use common::{fixture, get, source, upstream};
+use serde_json::{json, Value};
+use std::time::Duration;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn lists_stores() {
+    let stores = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            { "siteId": "2504", "alias": "Umeå Utopia", "city": "UMEÅ" },
+            { "siteId": "0102", "alias": "Stockholm Klarabergsgatan", "city": "STOCKHOLM" },
+            { "siteId": "2501", "alias": "Umeå Avion", "city": "UMEÅ" },
+        ])))
+        .mount(&stores)
+        .await;
+    let upstream = upstream(vec![fixture()]).await;
+    let config = Config {
+        stores: Some(StoresConfig { url: stores.uri() }),
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    // The stores are fetched by a notifier, in the background
+    tokio::time::delay_for(Duration::from_millis(200)).await;
+
+    let (status, body) = get(server.state().clone(), "/stores").await;
+    assert_eq!(status, 200);
+    let all: Vec<Value> = serde_json::from_str(&body).unwrap();
+    assert_eq!(all.len(), 3);
+    assert_eq!(all[0]["name"], "Stockholm Klarabergsgatan");
+
+    let (_, body) = get(server.state().clone(), "/stores?stad=ume%C3%A5").await;
+    let umea: Vec<Value> = serde_json::from_str(&body).unwrap();
+    let ids: Vec<&str> = umea
+        .iter()
+        .map(|store| store["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, vec!["2501", "2504"]);
+
+    // On the list, a city is taken to a store's stock
+    let response = warp::test::request()
+        .path("/?stad=ume%C3%A5&kategori=%C3%B6l")
+        .reply(&apk::server::routes(server.state().clone()))
+        .await;
+    assert_eq!(response.status(), 301);
+    assert_eq!(
+        response.headers()["location"],
+        "/?kategori=%C3%B6l&butik=2501"
+    );
+}
+
+#[tokio::test]
+async fn remembers_the_default_store() {
+    let stores = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            { "siteId": "2504", "alias": "Umeå Utopia", "city": "UMEÅ" },
+            { "siteId": "0102", "alias": "Stockholm Klarabergsgatan", "city": "STOCKHOLM" },
+        ])))
+        .mount(&stores)
+        .await;
+    let upstream = upstream(vec![fixture()]).await;
+    let config = Config {
+        stores: Some(StoresConfig { url: stores.uri() }),
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    tokio::time::delay_for(Duration::from_millis(200)).await;
+    let routes = apk::server::routes(server.state().clone());
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/stores")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body("stad=Ume%C3%A5")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 303);
+    let cookie = response.headers()["set-cookie"].to_str().unwrap();
+    let cookie = cookie.split(';').next().unwrap().to_string();
+
+    let response = warp::test::request()
+        .path("/stores/selected")
+        .header("cookie", &cookie)
+        .reply(&routes)
+        .await;
+    let store: Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(store["id"], "2504");
+
+    let response = warp::test::request()
+        .path("/stores/selected?butik=0102")
+        .header("cookie", &cookie)
+        .reply(&routes)
+        .await;
+    let store: Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(store["id"], "0102");
+
+    let response = warp::test::request()
+        .path("/stores/selected")
+        .header("cookie", "butik=0102.forged")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 404);
+}
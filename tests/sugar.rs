@@ -0,0 +1,29 @@
+mod common;
+
+use apk::view::View;
+use common::{fixture, get, refresh, upstream};
+
+#[test]
+fn reads_max_sugar() {
+    let view = View::from_query("kategori=cider&maxsocker=15");
+    assert_eq!(view.max_sugar, Some(15.0));
+    assert_eq!(view.to_query(), "kategori=cider&maxsocker=15");
+    assert_eq!(View::from_query("maxsocker=mycket").max_sugar, None);
+}
+
+#[tokio::test]
+async fn filters_by_sugar() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+
+    let (status, body) = get(state.clone(), "/?maxsocker=50").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Castillo de Gredos"));
+    assert!(body.contains("3.0 g/l"));
+    assert!(!body.contains("Kopparbergs"));
+    // Without sugar data
+    assert!(!body.contains("Norrlands Guld"));
+
+    let (_, body) = get(state, "/?kategori=cider&maxsocker=15").await;
+    assert!(!body.contains("Kopparbergs"));
+}
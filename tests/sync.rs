@@ -0,0 +1,94 @@
+mod common;
+
+use apk::server::ApkServer;
+use apk::sync::Delta;
+use common::{fixture, get, mount_page, source, upstream};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+fn ids(products: &[Value]) -> Vec<&str> {
+    let mut ids: Vec<&str> = products
+        .iter()
+        .map(|product| product["ProductId"].as_str().unwrap())
+        .collect();
+    ids.sort();
+    ids
+}
+
+#[tokio::test]
+async fn sends_only_what_changed() {
+    let mut before = fixture();
+    let castillo = before.remove(2);
+    let upstream = upstream(vec![before.clone()]).await;
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    // Versions are recorded by a notifier, in the background
+    tokio::time::delay_for(Duration::from_millis(200)).await;
+
+    let (status, body) = get(server.state().clone(), "/api/sync").await;
+    assert_eq!(status, 200);
+    let full: Delta = serde_json::from_str(&body).unwrap();
+    assert_eq!(full.base, None);
+    assert_eq!(full.changed.len(), before.len());
+
+    let (_, body) = get(
+        server.state().clone(),
+        &format!("/api/sync?since={}", full.hash),
+    )
+    .await;
+    let same: Delta = serde_json::from_str(&body).unwrap();
+    assert_eq!(same.hash, full.hash);
+    assert!(same.changed.is_empty() && same.removed.is_empty());
+
+    let mut after = before.clone();
+    after[0]["Price"] = json!(12.9);
+    let gone = after.remove(1);
+    after.push(castillo.clone());
+    upstream.reset().await;
+    mount_page(&upstream, 1, after.clone()).await;
+    mount_page(&upstream, 2, Vec::new()).await;
+    server.update().await.unwrap();
+    tokio::time::delay_for(Duration::from_millis(200)).await;
+
+    let (_, body) = get(
+        server.state().clone(),
+        &format!("/api/sync?since={}", full.hash),
+    )
+    .await;
+    let delta: Delta = serde_json::from_str(&body).unwrap();
+    assert_eq!(delta.base.as_ref(), Some(&full.hash));
+    assert_ne!(delta.hash, full.hash);
+    assert_eq!(
+        ids(&delta.changed),
+        ids(&[after[0].clone(), castillo.clone()])
+    );
+    assert_eq!(delta.removed, vec![gone["ProductId"].as_str().unwrap()]);
+
+    let hash = delta.hash.clone();
+    let synced = delta.apply(full.changed);
+    let (_, body) = get(server.state().clone(), "/api/sync?since=unknown").await;
+    let current: Delta = serde_json::from_str(&body).unwrap();
+    assert_eq!(current.base, None);
+    assert_eq!(current.hash, hash);
+    assert_eq!(ids(&synced), ids(&current.changed));
+    let price = |products: &[Value]| {
+        products
+            .iter()
+            .find(|product| product["ProductId"] == after[0]["ProductId"])
+            .map(|product| product["Price"].clone())
+    };
+    assert_eq!(price(&synced), Some(json!(12.9)));
+}
+
+#[tokio::test]
+async fn unavailable_before_the_first_refresh() {
+    let server = ApkServer::builder()
+        .source(source(&upstream(vec![fixture()]).await))
+        .build()
+        .unwrap();
+    let (status, _) = get(server.state().clone(), "/api/sync").await;
+    assert_eq!(status, 503);
+}
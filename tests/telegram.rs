@@ -0,0 +1,32 @@
+use apk::catalog::Category;
+use apk::telegram::{parse, Command};
+
+#[test]
+fn parses_commands() {
+    assert_eq!(parse("/top öl"), Some(Command::Top(Category::Beer)));
+    assert_eq!(
+        parse("/top@apk_bot Sprit"),
+        Some(Command::Top(Category::Liquor))
+    );
+    assert_eq!(
+        parse("/search norrlands guld"),
+        Some(Command::Search("norrlands guld".to_string()))
+    );
+    assert_eq!(
+        parse("/apk 1234567"),
+        Some(Command::Apk("1234567".to_string()))
+    );
+    assert_eq!(parse("/prenumerera"), Some(Command::Subscribe));
+}
+
+#[test]
+fn answers_unknown_commands_with_help() {
+    assert_eq!(parse("/top läsk"), Some(Command::Help));
+    assert_eq!(parse("/search"), Some(Command::Help));
+    assert_eq!(parse("/hej"), Some(Command::Help));
+}
+
+#[test]
+fn ignores_chatter() {
+    assert_eq!(parse("skål!"), None);
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use apk::view::{Tried, View};
+use common::{fixture, refresh, upstream};
+
+#[test]
+fn reads_tried_mode_from_query() {
+    let view = View::from_query("provade=dolj");
+    assert_eq!(view.tried, Tried::Hide);
+    assert_eq!(view.to_query(), "provade=dolj");
+    assert!(View::from_query("provade=visa").is_default());
+}
+
+#[tokio::test]
+async fn hides_and_dims_tried_products() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let routes = apk::server::routes(state);
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/tried")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body("id=1001&back=%2F%3Fprovade%3Ddolj")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 303);
+    assert_eq!(response.headers()["location"], "/?provade=dolj");
+    let set_cookie = response.headers()["set-cookie"].to_str().unwrap();
+    let value = set_cookie.split(';').next().unwrap().to_string();
+
+    let response = warp::test::request()
+        .path("/?provade=dolj")
+        .header("cookie", value.clone())
+        .reply(&routes)
+        .await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(!body.contains("Norrlands Guld"));
+    assert!(body.contains("Mariestads"));
+
+    let response = warp::test::request()
+        .path("/?provade=tona")
+        .header("cookie", value.clone())
+        .reply(&routes)
+        .await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains("Norrlands Guld"));
+    assert!(body.contains("class=\"tried\""));
+
+    // Not the page rendered at refresh time, which has nothing marked
+    let response = warp::test::request()
+        .path("/")
+        .header("cookie", value)
+        .reply(&routes)
+        .await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains("Markera som oprovad"));
+}
+
+#[tokio::test]
+async fn only_redirects_to_local_pages() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/tried")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body("id=1001&back=%2F%2Fexample.com")
+        .reply(&apk::server::routes(state))
+        .await;
+    assert_eq!(response.headers()["location"], "/");
+}
+
+#[tokio::test]
+async fn goes_back_only_on_the_site() {
+    let upstream = upstream(vec![fixture()]).await;
+    let routes = apk::server::routes(refresh(&upstream).await.unwrap());
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/tried")
+        .header("content-type", "application/x-www-form-urlencoded")
+        .body("id=1001&back=%2F%5Cevil.example")
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), 303);
+    assert_eq!(response.headers()["location"], "/");
+}
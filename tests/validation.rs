@@ -0,0 +1,20 @@
+mod common;
+
+use apk::server::ApkServer;
+use common::{fixture, source, upstream};
+
+#[tokio::test]
+async fn refuses_broken_renders() {
+    let upstream = upstream(vec![fixture()]).await;
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .theme("tests/fixtures/blank/*")
+        .build()
+        .unwrap();
+
+    let err = server.update().await.unwrap_err();
+    assert_eq!(err.category(), "render");
+    assert!(server.state().snapshot.read().unwrap().is_none());
+    let status = server.state().status.read().unwrap();
+    assert_eq!(status.errors["render"], 1);
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use apk::config::{Config, VenueConfig};
+use apk::server::ApkServer;
+use apk::units::Sek;
+use apk::venue::{price, resolve};
+use common::{fixture, source, upstream};
+use std::time::Duration;
+
+fn karhuset() -> VenueConfig {
+    VenueConfig {
+        slug: "karhuset".to_string(),
+        name: "Kårhuset".to_string(),
+        host: Some("karhuset.example.com".to_string()),
+        markup: 3.0,
+        rounding: Some(5.0),
+        blocklist: vec!["1002".to_string()],
+        theme: None,
+    }
+}
+
+#[test]
+fn resolves_venue_by_host() {
+    let venues = vec![karhuset()];
+    assert!(resolve(&venues, "Karhuset.example.com:3030").is_some());
+    assert!(resolve(&venues, "example.com").is_none());
+}
+
+#[tokio::test]
+async fn prices_with_markup_and_rounding() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = common::refresh(&upstream).await.unwrap();
+    let snapshot = state.snapshot.read().unwrap().clone().unwrap();
+    let norrlands = snapshot.catalog.find("1001").unwrap();
+
+    assert_eq!(price(&karhuset(), norrlands), Sek(45.0));
+}
+
+#[tokio::test]
+async fn serves_venue_lists() {
+    let upstream = upstream(vec![fixture()]).await;
+    let config = Config {
+        venues: vec![karhuset()],
+        ..Config::default()
+    };
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .config(config)
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    // The pages are rendered by a notifier, in the background
+    tokio::time::delay_for(Duration::from_millis(100)).await;
+    let routes = server.routes();
+
+    for request in vec![
+        warp::test::request().path("/v/karhuset"),
+        warp::test::request()
+            .path("/")
+            .header("host", "karhuset.example.com"),
+    ] {
+        let response = request.reply(&routes).await;
+        let body = String::from_utf8(response.body().to_vec()).unwrap();
+        assert!(body.contains("Priser hos Kårhuset"));
+        assert!(body.contains("45.00 kr"));
+        assert!(!body.contains("Mariestads"));
+    }
+
+    let response = warp::test::request()
+        .path("/")
+        .header("host", "example.com")
+        .reply(&routes)
+        .await;
+    let body = String::from_utf8(response.body().to_vec()).unwrap();
+    assert!(body.contains("Mariestads"));
+    assert!(!body.contains("Kårhuset"));
+}
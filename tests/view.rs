@@ -0,0 +1,124 @@
+mod common;
+
+use apk::catalog::{self, Catalog, Category};
+use apk::score::{LiterPriceScorer, Scorer};
+use apk::view::{Order, Sort, View};
+use common::{fixture, get, refresh, upstream};
+use systemet::Product;
+
+#[test]
+fn normalizes_queries() {
+    let view = View::from_query("sortera=pris&kategori=%C3%96L&sok=+Guld+&maxpris=&foo=bar");
+    assert_eq!(view.category, Some(Category::Beer));
+    assert_eq!(view.search.as_deref(), Some("guld"));
+    assert_eq!(view.max_price, None);
+    assert_eq!(view.sort, Sort::Price);
+    assert_eq!(view.to_query(), "kategori=%C3%B6l&sok=guld&sortera=pris");
+    assert_eq!(View::from_query(&view.to_query()), view);
+}
+
+#[test]
+fn links_default_view_to_root() {
+    assert_eq!(View::from_query("sortera=apk&maxpris=-3").link(), "/");
+}
+
+#[tokio::test]
+async fn redirects_to_canonical_url() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+
+    let response = warp::test::request()
+        .path("/?sok=Guld&kategori=")
+        .reply(&apk::server::routes(state))
+        .await;
+    assert_eq!(response.status(), 301);
+    assert_eq!(response.headers()["location"], "/?sok=guld");
+}
+
+#[tokio::test]
+async fn shows_only_matching_products() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+
+    let (status, body) = get(state, "/?sok=guld").await;
+    assert_eq!(status, 200);
+    assert!(body.contains("Norrlands Guld"));
+    assert!(!body.contains("Mariestads"));
+    assert!(!body.contains("Explorer Vodka"));
+}
+
+#[tokio::test]
+async fn understands_english_filters() {
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+
+    let response = warp::test::request()
+        .path("/?max_price=100&min_abv=4&category=%C3%96l")
+        .reply(&apk::server::routes(state))
+        .await;
+    assert_eq!(response.status(), 301);
+    assert_eq!(
+        response.headers()["location"],
+        "/?kategori=%C3%B6l&maxpris=100&minalkohol=4"
+    );
+}
+
+#[test]
+fn sorts_by_any_key_in_either_order() {
+    let view = View::from_query("sort=name&order=desc");
+    assert_eq!(view.sort, Sort::Name);
+    assert_eq!(view.order(), Order::Descending);
+    assert_eq!(view.to_query(), "sortera=namn&ordning=fallande");
+    // Cheapest first is how prices are sorted anyway
+    assert_eq!(
+        View::from_query("sortera=pris&ordning=stigande").to_query(),
+        "sortera=pris"
+    );
+
+    let catalog = Catalog::build(serde_json::from_value(fixture().into()).unwrap());
+    let names = |query: &str| -> Vec<&str> {
+        View::from_query(query).apply(&catalog, &[], &[])[&Category::Beer]
+            .iter()
+            .map(|drink| catalog::name(drink))
+            .collect()
+    };
+    let mut sorted = names("");
+    sorted.sort_by_key(|name| name.to_lowercase());
+    assert_eq!(names("sortera=namn"), sorted);
+    sorted.reverse();
+    assert_eq!(names("sortera=namn&ordning=fallande"), sorted);
+    let mut worst_first = names("");
+    worst_first.reverse();
+    assert_eq!(names("ordning=stigande"), worst_first);
+}
+
+#[tokio::test]
+async fn ranks_by_price_per_liter() {
+    assert_eq!(
+        View::from_query("sort=price_per_liter").to_query(),
+        "sortera=literpris"
+    );
+    let catalog = Catalog::build(serde_json::from_value(fixture().into()).unwrap());
+    let ranked = View::from_query("sortera=literpris").apply(&catalog, &[], &[]);
+    let mut scored: Vec<&Product> = catalog.get(Category::Beer).iter().collect();
+    scored.sort_by(|d1, d2| {
+        LiterPriceScorer
+            .score(d2)
+            .partial_cmp(&LiterPriceScorer.score(d1))
+            .unwrap()
+    });
+    let ids = |drinks: &[&Product]| -> Vec<String> {
+        drinks
+            .iter()
+            .map(|drink| catalog::id(drink).to_string())
+            .collect()
+    };
+    assert_eq!(ids(&ranked[&Category::Beer]), ids(&scored));
+
+    let upstream = upstream(vec![fixture()]).await;
+    let state = refresh(&upstream).await.unwrap();
+    let (status, body) = get(state, "/?sortera=literpris").await;
+    assert_eq!(status, 200);
+    // Norrlands Guld: 14.90 kr plus 1 kr deposit for half a liter
+    assert!(body.contains("31.80 kr/l"));
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use apk::server::ApkServer;
+use apk::warm::{Warm, MAX_COUNTED};
+use common::{fixture, get, source, upstream};
+use flate2::read::GzDecoder;
+use std::io::Read;
+
+#[tokio::test]
+async fn renders_popular_views_ahead() {
+    let upstream = upstream(vec![fixture()]).await;
+    let server = ApkServer::builder()
+        .source(source(&upstream))
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let state = server.state().clone();
+
+    // Nothing is warm before the first refresh has seen any visits
+    let (status, cold) = get(state.clone(), "/?sortera=pris").await;
+    assert_eq!(status, 200);
+    assert_eq!(state.warm.hits(), (0, 1));
+    assert_eq!(state.warm.sizes().0, 0);
+
+    server.update().await.unwrap();
+    assert_eq!(state.warm.sizes().0, 1);
+
+    let response = warp::test::request()
+        .path("/?sortera=pris")
+        .header("accept-encoding", "gzip, deflate")
+        .reply(&apk::server::routes(state.clone()))
+        .await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-encoding"], "gzip");
+    assert_eq!(response.headers()["vary"], "Accept-Encoding");
+    let mut warm = String::new();
+    GzDecoder::new(&response.body()[..])
+        .read_to_string(&mut warm)
+        .unwrap();
+    assert_eq!(warm, cold);
+
+    let (_, plain) = get(state.clone(), "/?sortera=pris").await;
+    assert_eq!(plain, cold);
+    assert_eq!(state.warm.hits(), (2, 1));
+
+    let response = warp::test::request()
+        .path("/?sortera=pris")
+        .header("accept-encoding", "gzip;q=0, deflate")
+        .reply(&apk::server::routes(state.clone()))
+        .await;
+    assert!(response.headers().get("content-encoding").is_none());
+    assert_eq!(String::from_utf8_lossy(response.body()), cold);
+    assert_eq!(state.warm.hits(), (3, 1));
+
+    // Anything personal is rendered on demand
+    let response = warp::test::request()
+        .path("/?sortera=pris")
+        .header("accept-encoding", "gzip")
+        .header("cookie", "tried=1001")
+        .reply(&apk::server::routes(state.clone()))
+        .await;
+    assert_eq!(response.status(), 200);
+    assert!(response.headers().get("content-encoding").is_none());
+    assert_eq!(state.warm.hits(), (3, 2));
+}
+
+#[test]
+fn counts_only_so_many_views() {
+    let warm = Warm::default();
+    for n in 0..MAX_COUNTED + 10 {
+        warm.visit(&format!("sok={}", n));
+    }
+    warm.visit("sok=0");
+    assert_eq!(warm.counted(), MAX_COUNTED);
+}
@@ -0,0 +1,201 @@
+mod common;
+
+use apk::config::WebhookConfig;
+use apk::diff::Diff;
+use apk::notify::{Notifier, RefreshEvent};
+use apk::server::AppState;
+use apk::signing;
+use apk::webhook::WebhookNotifier;
+use apk::ApkServer;
+use common::{fixture, upstream};
+use secrecy::SecretString;
+use wiremock::matchers::{body_string, header, method};
+use wiremock::{Match, Mock, MockServer, Request, ResponseTemplate};
+
+const SECRET: &str = "hemligt";
+
+struct SignedWith(&'static str);
+
+impl Match for SignedWith {
+    fn matches(&self, request: &Request) -> bool {
+        let expected = format!(
+            "sha256={}",
+            signing::hmac_sha256(self.0.as_bytes(), &request.body)
+        );
+        request
+            .headers
+            .iter()
+            .find(|(name, _)| name.as_str() == "x-apk-signature")
+            .map_or(false, |(_, values)| values.last().as_str() == expected)
+    }
+}
+
+struct Summarizes(usize);
+
+impl Match for Summarizes {
+    fn matches(&self, request: &Request) -> bool {
+        let body: serde_json::Value = match serde_json::from_slice(&request.body) {
+            Ok(body) => body,
+            Err(_) => return false,
+        };
+        body["products"] == self.0 && body["snapshot"].is_string() && body["timestamp"].is_u64()
+    }
+}
+
+async fn event() -> (AppState, RefreshEvent) {
+    let upstream = upstream(vec![fixture()]).await;
+    let server = ApkServer::builder()
+        .source(common::source(&upstream))
+        .build()
+        .unwrap();
+    server.update().await.unwrap();
+    let snapshot = server.state().snapshot.read().unwrap().clone().unwrap();
+    let event = RefreshEvent {
+        snapshot,
+        previous: None,
+        diff: Diff::default(),
+    };
+    (server.state().clone(), event)
+}
+
+fn notifier(receiver: &MockServer, retries: u32) -> WebhookNotifier {
+    WebhookNotifier::new(WebhookConfig {
+        url: receiver.uri(),
+        secret: Some(SecretString::new(SECRET.to_string())),
+        retries,
+        template: None,
+        content_type: None,
+        min_apk: None,
+        top: None,
+    })
+}
+
+#[tokio::test]
+async fn posts_signed_summary() {
+    let receiver = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(SignedWith(SECRET))
+        .and(Summarizes(5))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&receiver)
+        .await;
+
+    let (state, event) = event().await;
+    notifier(&receiver, 0).notify(&state, &event).await.unwrap();
+}
+
+#[tokio::test]
+async fn fails_after_retries() {
+    let receiver = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(3)
+        .mount(&receiver)
+        .await;
+
+    let (state, event) = event().await;
+    assert!(notifier(&receiver, 2).notify(&state, &event).await.is_err());
+}
+
+#[tokio::test]
+async fn renders_body_template() {
+    let receiver = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(header("content-type", "text/plain"))
+        .and(body_string("5 produkter, 0 nya"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&receiver)
+        .await;
+
+    let (state, event) = event().await;
+    let notifier = WebhookNotifier::new(WebhookConfig {
+        url: receiver.uri(),
+        secret: None,
+        retries: 0,
+        template: Some("{{ products }} produkter, {{ diff.added | length }} nya".to_string()),
+        content_type: Some("text/plain".to_string()),
+        min_apk: None,
+        top: None,
+    });
+    notifier.notify(&state, &event).await.unwrap();
+}
+
+fn with_thresholds(
+    receiver: &MockServer,
+    min_apk: Option<f64>,
+    top: Option<usize>,
+) -> WebhookNotifier {
+    WebhookNotifier::new(WebhookConfig {
+        url: receiver.uri(),
+        secret: None,
+        retries: 0,
+        template: None,
+        content_type: None,
+        min_apk,
+        top,
+    })
+}
+
+struct Notable(&'static str);
+
+impl Match for Notable {
+    fn matches(&self, request: &Request) -> bool {
+        let body: serde_json::Value = match serde_json::from_slice(&request.body) {
+            Ok(body) => body,
+            Err(_) => return false,
+        };
+        body["notable"]
+            .as_array()
+            .map_or(false, |notable| notable.iter().any(|p| p["name"] == self.0))
+    }
+}
+
+#[tokio::test]
+async fn posts_products_beating_the_threshold() {
+    let receiver = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(Notable("Norrlands Guld"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&receiver)
+        .await;
+
+    let (state, mut event) = event().await;
+    let norrlands = event.snapshot.catalog.search("Norrlands Guld")[0];
+    event
+        .diff
+        .added
+        .push(apk::catalog::id(norrlands).to_string());
+    with_thresholds(&receiver, Some(0.1), None)
+        .notify(&state, &event)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn skips_refreshes_without_notable_products() {
+    let receiver = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&receiver)
+        .await;
+
+    let (state, mut event) = event().await;
+    let norrlands = event.snapshot.catalog.search("Norrlands Guld")[0];
+    event
+        .diff
+        .added
+        .push(apk::catalog::id(norrlands).to_string());
+    with_thresholds(&receiver, Some(100.0), None)
+        .notify(&state, &event)
+        .await
+        .unwrap();
+    // Without a previous catalog, nothing can enter the top
+    with_thresholds(&receiver, None, Some(20))
+        .notify(&state, &event)
+        .await
+        .unwrap();
+}